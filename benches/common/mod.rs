@@ -0,0 +1,72 @@
+use std::{fs::File, io::BufWriter, path::Path};
+
+use twsnap::{
+    compat::ddnet::{DemoKind, DemoMapHash, DemoWriter},
+    enums::{ActiveWeapon, Direction, HookState},
+    items::{Player, Tee},
+    time::Instant,
+    uid::UidGenerator,
+    Position, PositionPrecision, Snap,
+};
+
+pub const TICK_RATE: i32 = 50;
+
+/// Writes a demo where every player runs back and forth, hooks and hammers in a fixed
+/// rhythm, so every analysis has something to work with.
+pub fn write_demo(path: &Path, players: u32, seconds: i32) {
+    let file = BufWriter::new(File::create(path).unwrap());
+    let mut writer = DemoWriter::new(
+        file,
+        DemoKind::Server,
+        "2024-01-01_12-00-00",
+        "0.6 626fce9a778df4d4",
+        "bench",
+        None,
+        DemoMapHash::Sha256([0; 32]),
+        seconds,
+    )
+    .unwrap();
+
+    let uids = UidGenerator::new();
+    let mut snap = Snap::default();
+    for id in 0..players {
+        let uid = uids.next_player(id);
+        let mut player = Player {
+            uid,
+            tee: Some(Tee::default()),
+            ..Default::default()
+        };
+        player.name.push_str(&format!("player {id}"));
+        snap.players.insert(uid.sort_id(), player);
+    }
+
+    let mut now = Instant::zero();
+    for tick in 0..seconds * TICK_RATE {
+        for (i, player) in snap.players.values_mut().enumerate() {
+            let tee = player.tee.as_mut().unwrap();
+            let phase = tick + i as i32 * 7;
+            tee.tick = now;
+            tee.direction = if phase / 40 % 2 == 0 {
+                Direction::Left
+            } else {
+                Direction::Right
+            };
+            tee.hook_state = if phase % 30 < 12 {
+                HookState::Grabbed
+            } else {
+                HookState::Idle
+            };
+            tee.weapon = ActiveWeapon::Hammer;
+            if phase % 13 == 0 {
+                tee.attack_tick = now;
+            }
+            let x = (phase % 80 - 40).abs() as f32 / 4.0;
+            tee.pos = Position::new(
+                PositionPrecision::from_num(10.0 + x + tick as f32 / 200.0),
+                PositionPrecision::from_num(20.0 + i as f32),
+            );
+        }
+        writer.write_snapshot(tick, &snap).unwrap();
+        now = now.advance();
+    }
+}
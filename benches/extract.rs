@@ -0,0 +1,44 @@
+//! Times extraction on a synthetic demo with many players over a long session.
+//!
+//! Run with `cargo bench --bench extract`.
+
+use std::{
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+
+mod common;
+
+const PLAYERS: u32 = 32;
+/// Half an hour
+const SECONDS: i32 = 30 * 60;
+const RUNS: u32 = 5;
+
+fn main() {
+    let dir = std::env::temp_dir().join("demo_analyzer_bench");
+    std::fs::create_dir_all(&dir).unwrap();
+    let demo = dir.join("long.demo");
+    if !demo.exists() {
+        common::write_demo(&demo, PLAYERS, SECONDS);
+    }
+
+    let mut times = Vec::new();
+    for _ in 0..RUNS {
+        let start = Instant::now();
+        let status = Command::new(env!("CARGO_BIN_EXE_demo_analyzer"))
+            .args(["--no-cache", "extract", "--format", "bincode"])
+            .arg(&demo)
+            .stdout(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(status.success());
+        times.push(start.elapsed());
+    }
+    times.sort();
+    let mean = times.iter().sum::<Duration>() / RUNS;
+    println!(
+        "extract, {PLAYERS} players, {SECONDS} seconds: mean {mean:?}, min {:?}, max {:?}",
+        times[0],
+        times[times.len() - 1]
+    );
+}
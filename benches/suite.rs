@@ -0,0 +1,91 @@
+//! Benchmarks of the commands on the demos in `benches/fixtures`, which are written by
+//! `common::write_demo` if they are missing.
+//!
+//! Run with `cargo bench --bench suite`. Criterion compares each run against the previous
+//! one, to check a change against a fixed state use `-- --save-baseline main` before and
+//! `-- --baseline main` after it.
+//!
+//! Every iteration runs the binary, so the numbers include process startup, which is a few
+//! milliseconds and the same for all of them.
+
+use std::{
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+mod common;
+
+/// Name, players and length in seconds.
+const FIXTURES: [(&str, u32, i32); 2] = [("solo", 1, 120), ("team", 4, 60)];
+
+fn fixtures() -> Vec<(&'static str, PathBuf)> {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("benches/fixtures");
+    FIXTURES
+        .iter()
+        .map(|(name, players, seconds)| {
+            let path = dir.join(format!("{name}.demo"));
+            if !path.exists() {
+                common::write_demo(&path, *players, *seconds);
+            }
+            (*name, path)
+        })
+        .collect()
+}
+
+fn run(args: &[&str], demo: &Path) {
+    let status = Command::new(env!("CARGO_BIN_EXE_demo_analyzer"))
+        .args(args)
+        .arg(demo)
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "{args:?} failed on {demo:?}");
+}
+
+fn extraction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("extraction");
+    for (name, demo) in fixtures() {
+        group.bench_with_input(BenchmarkId::new("uncached", name), &demo, |b, demo| {
+            b.iter(|| run(&["--no-cache", "extract", "--format", "bincode"], demo))
+        });
+
+        // The cache is written next to the demo, keep it out of the fixtures
+        let dir = std::env::temp_dir().join("demo_analyzer_bench");
+        std::fs::create_dir_all(&dir).unwrap();
+        let cached = dir.join(format!("{name}.demo"));
+        std::fs::copy(&demo, &cached).unwrap();
+        run(&["extract", "--format", "bincode"], &cached);
+        group.bench_with_input(BenchmarkId::new("cached", name), &cached, |b, demo| {
+            b.iter(|| run(&["extract", "--format", "bincode"], demo))
+        });
+    }
+    group.finish();
+}
+
+fn analysis(c: &mut Criterion) {
+    let mut group = c.benchmark_group("analysis");
+    for (name, demo) in fixtures() {
+        for format in ["plain", "json"] {
+            group.bench_with_input(BenchmarkId::new(format, name), &demo, |b, demo| {
+                b.iter(|| run(&["--no-cache", "analyze", "--format", format], demo))
+            });
+        }
+    }
+    group.finish();
+}
+
+fn serialization(c: &mut Criterion) {
+    let mut group = c.benchmark_group("serialization");
+    let (name, demo) = fixtures().remove(1);
+    for format in ["json", "yaml", "toml", "rsn", "msgpack", "bincode"] {
+        group.bench_with_input(BenchmarkId::new(format, name), &demo, |b, demo| {
+            b.iter(|| run(&["--no-cache", "extract", "--format", format], demo))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, extraction, analysis, serialization);
+criterion_main!(benches);
@@ -0,0 +1,99 @@
+# The plain analyze report, the numbers come formatted
+insufficient-sample = Zu wenig Daten, nur { $seconds }s
+end = ENDE
+
+duration = Dauer ......................... : { $seconds }s
+time-played = Spielzeit ..................... : { $seconds }s in { $stretches } Abschnitten
+afk = AFK ........................... : { $percent }% ({ $seconds }s)
+bot-probability = Bot-Wahrscheinlichkeit (Modell) : { $percent }%
+input-changes = Eingabewechsel insgesamt ...... : { $changes }
+direction-changes = Richtungswechsel .............. : { $changes } ({ $raw } roh)
+hook-changes = Hakenwechsel .................. : { $changes } ({ $raw } roh)
+
+direction-change-rate = Richtungswechselrate
+hook-state-change-rate = Hakenwechselrate
+rate-average = Durchschnitt : { $rate } pro Sekunde
+rate-median = Median ..... : { $rate } pro Sekunde
+rate-max = Maximum .... : { $rate } pro Sekunde
+rate-interval = 95% KI ..... : { $low } - { $high } über { $windows } Fenster
+
+aim = Zielen
+aim-angular-speed = Winkelgeschwindigkeit : { $speed } rad/s
+aim-angular-jerk = Winkelruck .......... : { $jerk } rad/s³
+aim-linear = Lineares Zielen ..... : { $percent }%
+
+attacks = Angriffe
+attack-count = Angriffe ... : { $attacks }
+attack-fast-fire = Schnellfeuer : { $count }
+attack-double-clicks = Doppelklicks : { $count } ({ $alternating } abwechselnd)
+attack-at = bei { $times }
+
+target-distance = Zielentfernung
+distance-average = Durchschnitt : { $tiles } Kacheln
+distance-median = Median ..... : { $tiles } Kacheln
+distance-p90 = P90 ........ : { $tiles } Kacheln
+distance-max = Maximum .... : { $tiles } Kacheln
+distance-beyond-default = Über Standardreichweite . : { $percent }%
+distance-beyond-dyncam = Über Dyncam-Reichweite .. : { $percent }%
+distance-constant-long = Konstant große Reichweite : { $percent }%
+probable-dyncam = Wahrscheinlich Dyncam ... : { $probable }
+probable-zoom = Wahrscheinlich Zoom ..... : { $probable }
+
+aim-offset = Zielabweichung zum nächsten Tee
+offset-samples = Stichproben : { $samples }
+offset-average = Durchschnitt : { $degrees }°
+offset-median = Median ..... : { $degrees }°
+offset-p10 = P10 ........ : { $degrees }°
+offset-locked = Eingerastet : { $percent }%
+
+reaction-times = Reaktionszeiten
+reaction-hook = Haken in Reichweite
+reaction-unfreeze = Auftauen
+reactions = { $label } : { $count } Reaktionen, Median { $median }ms, P10 { $p10 }ms, { $fast }% unter 100ms
+
+hammer = Hammer
+hammer-swings = Schläge ....... : { $swings }, { $hits } Treffer
+hammer-whiff = Fehlschläge ... : { $percent }%
+hammer-in-range = In Reichweite . : { $ticks }
+hammer-frame-perfect = Tickgenau ..... : { $percent }%, längste Serie { $streak }
+
+rehook = Nachhaken
+rehook-cycles = Zyklen ............. : { $cycles } in { $chains } Ketten
+rehook-frequency = Frequenz ........... : { $rate } pro Sekunde
+rehook-variation = Schwankung ......... : { $percent }%
+rehook-max-sustained = Max. gehalten ...... : { $rate } pro Sekunde
+rehook-longest = Längste regelmäßige  : { $seconds }s
+rehook-macro = Wahrscheinlich Makro : { $probable }
+
+spectrum = Spektrum
+spectrum-direction = Richtung
+spectrum-hook = Haken
+spectrum-line = { $label } : Flachheit { $flatness }, Spitzen { $peaks }
+
+runs = Läufe
+best-run = (bester)
+
+finishes = Zieleinläufe
+score = Punkte
+ends-run = beendet Lauf #{ $run }
+best-time = Bestzeit : { $time }
+
+did-not-finish = Nicht im Ziel
+dnf-kill-bind = Kill-Taste : { $count }
+dnf-hazard = Gefahr ... : { $count }
+dnf-disconnect = Verlassen  : { $count }
+abandoned-run = { $time } Lauf #{ $run } { $reason }
+
+pickups = Aufgesammelt
+pickup-weapons = Waffen . : { $count }
+pickup-ammo = Munition : { $count }
+pickup-hearts = Herzen . : { $count }
+pickup-shields = Schilde  : { $count }
+ammo-used = { $weapon } Munition verbraucht : { $count }
+
+reference = Referenz
+placement = { $metric }: { $value }, mindestens so hoch wie { $percentile }% der Referenzmenge
+
+gaps = Lücken
+gaps-count = Lücken ................ : { $gaps } ({ $missing } Ticks fehlen)
+gaps-excluded = Ausgeschlossene Wechsel : { $changes }
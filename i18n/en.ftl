@@ -0,0 +1,99 @@
+# The plain analyze report, the numbers come formatted
+insufficient-sample = Insufficient sample, only { $seconds }s of data
+end = END
+
+duration = Duration ................. : { $seconds }s
+time-played = Time Played .............. : { $seconds }s in { $stretches } stretches
+afk = AFK ...................... : { $percent }% ({ $seconds }s)
+bot-probability = Bot Probability (model) .. : { $percent }%
+input-changes = Overal Input State Changes : { $changes }
+direction-changes = Direction Changes ........ : { $changes } ({ $raw } raw)
+hook-changes = Hook Changes ............. : { $changes } ({ $raw } raw)
+
+direction-change-rate = Direction Change Rate
+hook-state-change-rate = Hook State Change Rate
+rate-average = Average : { $rate } per second
+rate-median = Median  : { $rate } per second
+rate-max = Max ... : { $rate } per second
+rate-interval = 95% CI  : { $low } - { $high } over { $windows } windows
+
+aim = Aim
+aim-angular-speed = Angular Speed : { $speed } rad/s
+aim-angular-jerk = Angular Jerk  : { $jerk } rad/s³
+aim-linear = Linear Aim .. : { $percent }%
+
+attacks = Attacks
+attack-count = Attacks ........ : { $attacks }
+attack-fast-fire = Fast Fire ...... : { $count }
+attack-double-clicks = Double Clicks .. : { $count } ({ $alternating } alternating)
+attack-at = at { $times }
+
+target-distance = Aim Target Distance
+distance-average = Average : { $tiles } tiles
+distance-median = Median  : { $tiles } tiles
+distance-p90 = P90 ... : { $tiles } tiles
+distance-max = Max ... : { $tiles } tiles
+distance-beyond-default = Beyond Default Range : { $percent }%
+distance-beyond-dyncam = Beyond Dyncam Range  : { $percent }%
+distance-constant-long = Constant Long Range  : { $percent }%
+probable-dyncam = Probable Dyncam .... : { $probable }
+probable-zoom = Probable Zoom ...... : { $probable }
+
+aim-offset = Aim Offset To Nearest Tee
+offset-samples = Samples .. : { $samples }
+offset-average = Average . : { $degrees }°
+offset-median = Median .. : { $degrees }°
+offset-p10 = P10 ..... : { $degrees }°
+offset-locked = Locked On : { $percent }%
+
+reaction-times = Reaction Times
+reaction-hook = Hook In Range
+reaction-unfreeze = Unfreeze
+reactions = { $label } : { $count } reactions, median { $median }ms, p10 { $p10 }ms, { $fast }% under 100ms
+
+hammer = Hammer
+hammer-swings = Swings ........ : { $swings }, { $hits } hits
+hammer-whiff = Whiff Rate .... : { $percent }%
+hammer-in-range = Ticks In Range  : { $ticks }
+hammer-frame-perfect = Frame Perfect . : { $percent }%, longest streak { $streak }
+
+rehook = Rehook
+rehook-cycles = Cycles ........ : { $cycles } in { $chains } chains
+rehook-frequency = Frequency ..... : { $rate } per second
+rehook-variation = Variation ..... : { $percent }%
+rehook-max-sustained = Max Sustained . : { $rate } per second
+rehook-longest = Longest Regular : { $seconds }s
+rehook-macro = Probable Macro  : { $probable }
+
+spectrum = Spectrum
+spectrum-direction = Direction
+spectrum-hook = Hook
+spectrum-line = { $label } : flatness { $flatness }, peaks { $peaks }
+
+runs = Runs
+best-run = (best)
+
+finishes = Finishes
+score = score
+ends-run = ends run #{ $run }
+best-time = Best Time : { $time }
+
+did-not-finish = Did Not Finish
+dnf-kill-bind = Kill Bind  : { $count }
+dnf-hazard = Hazard ... : { $count }
+dnf-disconnect = Disconnect : { $count }
+abandoned-run = { $time } run #{ $run } { $reason }
+
+pickups = Pickups
+pickup-weapons = Weapons : { $count }
+pickup-ammo = Ammo .. : { $count }
+pickup-hearts = Hearts  : { $count }
+pickup-shields = Shields : { $count }
+ammo-used = { $weapon } ammo used : { $count }
+
+reference = Reference
+placement = { $metric }: { $value }, at or above { $percentile }% of the reference set
+
+gaps = Gaps
+gaps-count = Gaps ............ : { $gaps } ({ $missing } ticks missing)
+gaps-excluded = Excluded Changes  : { $changes }
@@ -0,0 +1,296 @@
+use std::collections::{BTreeMap, HashMap};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use stringlit::s;
+
+use crate::{
+    attack,
+    data::{ActiveWeapon, Inputs, PIXELS_PER_TILE},
+    entities::{Entities, LaserKind},
+    tunes::{self, Tuning},
+};
+
+/// Lasers hit tees whose center is this close to the beam, in tiles.
+const TEE_RADIUS: f32 = 28.0 / 32.0;
+/// Grenades push tees within this distance of the explosion, in tiles.
+const EXPLOSION_RADIUS: f32 = 135.0 / 32.0;
+/// Shots that missed by less than this many tiles count as near hits, like a laser that ended
+/// in the wall right next to a player.
+const NEAR_MARGIN: f32 = 2.0;
+/// Targets slower than this many units per tick count as standing still.
+const MOVING_SPEED: f32 = 1.0;
+/// Default grenade tunings for demos without them, the snapshots only hold where a grenade
+/// was fired.
+const GRENADE_SPEED: f32 = 1000.0;
+const GRENADE_CURVATURE: f32 = 7.0;
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct WeaponAccuracy {
+    /// Attacks a laser or projectile of the player was found for
+    pub shots: usize,
+    pub hits: usize,
+    /// Missed, but by less than two tiles
+    pub near_hits: usize,
+    /// Hits per shot
+    pub accuracy: f32,
+    /// Shots where the nearest other player was moving
+    pub moving_target_shots: usize,
+    /// Average distance in tiles the shots at moving targets passed them by, none without
+    /// such shots
+    pub lead_error: Option<f32>,
+}
+
+/// Where a shot went, as the segments of a laser or the point and tick a grenade exploded at.
+enum Path {
+    Laser(Vec<([f32; 2], [f32; 2])>),
+    Explosion([f32; 2], i32),
+}
+
+struct Shot {
+    tick: i32,
+    weapon: ActiveWeapon,
+    path: Path,
+}
+
+fn distance_to_segment(point: [f32; 2], from: [f32; 2], to: [f32; 2]) -> f32 {
+    let (dx, dy) = (to[0] - from[0], to[1] - from[1]);
+    let length = dx * dx + dy * dy;
+    let along = if length == 0.0 {
+        0.0
+    } else {
+        (((point[0] - from[0]) * dx + (point[1] - from[1]) * dy) / length).clamp(0.0, 1.0)
+    };
+    (point[0] - from[0] - along * dx).hypot(point[1] - from[1] - along * dy)
+}
+
+/// The sample of the player at the tick, none if they weren't around.
+fn sample_at(inputs: &[Inputs], tick: i32, tick_rate: i32) -> Option<&Inputs> {
+    let i = inputs.partition_point(|i| i.tick <= tick).checked_sub(1)?;
+    (tick - inputs[i].tick <= tick_rate).then(|| &inputs[i])
+}
+
+/// Where the grenade is the given ticks after it was fired, in tiles, with the grenade
+/// tunings in effect when it was fired.
+fn grenade_position(
+    start: [f32; 2],
+    direction: [f32; 2],
+    fired: i32,
+    ticks: i32,
+    tunes: &[Tuning],
+    tick_rate: i32,
+) -> [f32; 2] {
+    let speed = tunes::value_at(tunes, "grenade_speed", fired).unwrap_or(GRENADE_SPEED);
+    let curvature = tunes::value_at(tunes, "grenade_curvature", fired).unwrap_or(GRENADE_CURVATURE);
+    let travelled = speed * ticks as f32 / tick_rate as f32;
+    [
+        start[0] + direction[0] * travelled / PIXELS_PER_TILE,
+        start[1]
+            + (direction[1] * travelled + curvature / 10000.0 * travelled * travelled)
+                / PIXELS_PER_TILE,
+    ]
+}
+
+/// The rifle, laser shotgun and grenade shots of the player, matched to their attacks.
+fn shots(
+    name: &str,
+    inputs: &[Inputs],
+    entities: &[Entities],
+    tunes: &[Tuning],
+    tick_rate: i32,
+) -> Vec<Shot> {
+    let owned = |owner: &Option<String>| owner.as_deref() == Some(name);
+    let mut lasers: Vec<(i32, LaserKind, [f32; 2], [f32; 2])> = Vec::new();
+    // Fired tick, start, direction and the last tick it was seen
+    let mut grenades: BTreeMap<i32, ([f32; 2], [f32; 2], i32)> = BTreeMap::new();
+    for snapshot in entities {
+        for laser in snapshot.lasers.iter().filter(|l| owned(&l.owner)) {
+            let segment = (
+                laser.start_tick,
+                laser.kind,
+                [laser.from.x.to_num(), laser.from.y.to_num()],
+                [laser.to.x.to_num(), laser.to.y.to_num()],
+            );
+            // Lasers stay in the snapshots for a while
+            if !lasers.contains(&segment) {
+                lasers.push(segment);
+            }
+        }
+        for projectile in snapshot
+            .projectiles
+            .iter()
+            .filter(|p| p.weapon == ActiveWeapon::Grenade && owned(&p.owner))
+        {
+            grenades
+                .entry(projectile.start_tick)
+                .or_insert((
+                    [projectile.pos.x.to_num(), projectile.pos.y.to_num()],
+                    projectile.direction,
+                    snapshot.tick,
+                ))
+                .2 = snapshot.tick;
+        }
+    }
+
+    let attacks = attack::attacks(inputs);
+    let mut shots = Vec::new();
+    for (i, attack) in attacks.iter().enumerate() {
+        let until = attacks.get(i + 1).map_or(i32::MAX, |a| a.tick);
+        let path = match attack.weapon {
+            ActiveWeapon::Rifle | ActiveWeapon::Shotgun => {
+                let kind = if attack.weapon == ActiveWeapon::Rifle {
+                    LaserKind::Rifle
+                } else {
+                    LaserKind::Shotgun
+                };
+                // Every bounce is a laser of its own, started after the one before
+                let segments: Vec<_> = lasers
+                    .iter()
+                    .filter(|l| l.1 == kind && (attack.tick..until).contains(&l.0))
+                    .map(|l| (l.2, l.3))
+                    .collect();
+                (!segments.is_empty()).then_some(Path::Laser(segments))
+            }
+            ActiveWeapon::Grenade => grenades.range(attack.tick..until).next().map(
+                |(fired, (start, direction, last))| {
+                    // It explodes between the last snapshot it was in and the next
+                    let ticks = last + 1 - fired;
+                    let at = grenade_position(*start, *direction, *fired, ticks, tunes, tick_rate);
+                    Path::Explosion(at, fired + ticks)
+                },
+            ),
+            _ => None,
+        };
+        if let Some(path) = path {
+            shots.push(Shot {
+                tick: attack.tick,
+                weapon: attack.weapon,
+                path,
+            });
+        }
+    }
+    shots
+}
+
+/// Accuracy of the rifle, laser shotgun and grenade of every filtered player. The shots come
+/// from the lasers and grenades of the player in the entities, the targets are all players in
+/// `inputs`. Grenade paths follow the `tunes` of the demo.
+pub fn calculate_accuracy(
+    players: &[&String],
+    inputs: &HashMap<String, Vec<Inputs>>,
+    entities: &[Entities],
+    tunes: &[Tuning],
+    tick_rate: i32,
+) -> HashMap<String, BTreeMap<ActiveWeapon, WeaponAccuracy>> {
+    players
+        .iter()
+        .map(|&name| {
+            let mut stats = BTreeMap::<ActiveWeapon, WeaponAccuracy>::new();
+            let mut lead_errors = BTreeMap::<ActiveWeapon, Vec<f32>>::new();
+            for shot in shots(name, &inputs[name], entities, tunes, tick_rate) {
+                let (radius, tick) = match &shot.path {
+                    Path::Laser(_) => (TEE_RADIUS, shot.tick),
+                    Path::Explosion(_, tick) => (EXPLOSION_RADIUS, *tick),
+                };
+                // The closest any other player got to the shot
+                let nearest = inputs
+                    .iter()
+                    .filter(|(other, _)| *other != name)
+                    .filter_map(|(_, i)| sample_at(i, tick, tick_rate))
+                    .map(|target| {
+                        let pos = [target.pos.x.to_num(), target.pos.y.to_num()];
+                        let distance = match &shot.path {
+                            Path::Laser(segments) => segments
+                                .iter()
+                                .map(|(from, to)| distance_to_segment(pos, *from, *to))
+                                .fold(f32::INFINITY, f32::min),
+                            Path::Explosion(at, _) => (pos[0] - at[0]).hypot(pos[1] - at[1]),
+                        };
+                        let speed = target.vel.x.to_num::<f32>().hypot(target.vel.y.to_num());
+                        (distance, speed)
+                    })
+                    .min_by(|a, b| a.0.total_cmp(&b.0));
+                let weapon = stats.entry(shot.weapon).or_default();
+                weapon.shots += 1;
+                let Some((distance, speed)) = nearest else {
+                    continue;
+                };
+                if distance <= radius {
+                    weapon.hits += 1;
+                } else if distance <= radius + NEAR_MARGIN {
+                    weapon.near_hits += 1;
+                }
+                if speed >= MOVING_SPEED {
+                    weapon.moving_target_shots += 1;
+                    lead_errors
+                        .entry(shot.weapon)
+                        .or_default()
+                        .push((distance - radius).max(0.0));
+                }
+            }
+            for (weapon, stats) in &mut stats {
+                stats.accuracy = stats.hits as f32 / stats.shots as f32;
+                stats.lead_error = lead_errors
+                    .get(weapon)
+                    .map(|e| e.iter().sum::<f32>() / e.len() as f32);
+            }
+            (name.clone(), stats)
+        })
+        .collect()
+}
+
+pub fn plain_report(accuracy: &HashMap<String, BTreeMap<ActiveWeapon, WeaponAccuracy>>) -> String {
+    let mut players: Vec<_> = accuracy.iter().collect();
+    players.sort_by_key(|(name, _)| *name);
+    let mut vec = Vec::new();
+    for (name, weapons) in players {
+        vec.push(format!("{:=^44}", format!(" {name} ")));
+        vec.push(s!(""));
+        if weapons.is_empty() {
+            vec.push(s!("No rifle, laser shotgun or grenade shots found"));
+        }
+        for (weapon, stats) in weapons {
+            vec.push(format!("{weapon:?}:"));
+            vec.push(format!("  Shots:          {}", stats.shots));
+            vec.push(format!("  Hits:           {}", stats.hits));
+            vec.push(format!("  Near hits:      {}", stats.near_hits));
+            vec.push(format!("  Accuracy:       {:.2}%", stats.accuracy * 100.0));
+            match stats.lead_error {
+                Some(error) => vec.push(format!(
+                    "  Lead error:     {error:.2} tiles over {} shots at moving targets",
+                    stats.moving_target_shots
+                )),
+                None => vec.push(s!("  Lead error:     -")),
+            }
+        }
+        vec.push(s!(""));
+    }
+    vec.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::timeline::Timeline;
+
+    #[test]
+    fn grenades_follow_the_tunings() {
+        let fire = |tunes: &[Tuning]| grenade_position([0.0, 0.0], [1.0, 0.0], 100, 50, tunes, 50);
+        let default = fire(&[]);
+        assert!((default[0] - GRENADE_SPEED / PIXELS_PER_TILE).abs() < 1e-3);
+
+        let tunes = [Tuning {
+            at: Timeline::default().timestamp(0),
+            params: [
+                (s!("grenade_speed"), 2000.0),
+                (s!("grenade_curvature"), 0.0),
+            ]
+            .into_iter()
+            .collect(),
+        }];
+        let tuned = fire(&tunes);
+        assert!((tuned[0] - 2.0 * default[0]).abs() < 1e-3);
+        assert_eq!(tuned[1], 0.0);
+        assert!(default[1] > 0.0);
+    }
+}
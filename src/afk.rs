@@ -0,0 +1,106 @@
+use std::ops::Range;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    data::Inputs,
+    timeline::{Timeline, Timestamp},
+};
+
+/// Slower than this, in units per tick, the tee counts as standing still.
+const STILL_SPEED: f32 = 0.5;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct AfkSegment {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub seconds: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct AfkStats {
+    pub segments: Vec<AfkSegment>,
+    pub seconds: f32,
+    /// Share of the duration the player was AFK
+    pub fraction: f32,
+    /// Whether the AFK stretches were left out of the aim, target distance, aim offset and
+    /// spectrum stats
+    pub excluded: bool,
+}
+
+/// Nothing was pressed, aimed or switched and the tee stood still.
+fn idle(before: &Inputs, after: &Inputs) -> bool {
+    before.direction == after.direction
+        && before.hook_state.pressed() == after.hook_state.pressed()
+        && before.target == after.target
+        && before.weapon == after.weapon
+        && before.attack_tick == after.attack_tick
+        && before.jumped_total == after.jumped_total
+        && after.vel.x.to_num::<f32>().hypot(after.vel.y.to_num()) < STILL_SPEED
+}
+
+/// The sample ranges where the player was idle for at least `min_seconds`, none if it is 0.
+pub fn find_afk(inputs: &[Inputs], min_seconds: f32, tick_rate: i32) -> Vec<Range<usize>> {
+    if min_seconds <= 0.0 {
+        return Vec::new();
+    }
+    let min_ticks = (min_seconds * tick_rate as f32) as i32;
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for i in 1..=inputs.len() {
+        if i < inputs.len() && idle(&inputs[i - 1], &inputs[i]) {
+            continue;
+        }
+        if inputs[i - 1].tick - inputs[start].tick >= min_ticks {
+            segments.push(start..i);
+        }
+        start = i;
+    }
+    segments
+}
+
+pub fn calculate_afk_stats(
+    inputs: &[Inputs],
+    segments: &[Range<usize>],
+    timeline: &Timeline,
+    excluded: bool,
+) -> AfkStats {
+    let segments: Vec<AfkSegment> = segments
+        .iter()
+        .map(|r| {
+            let (start, end) = (inputs[r.start].tick, inputs[r.end - 1].tick);
+            AfkSegment {
+                start: timeline.timestamp(start),
+                end: timeline.timestamp(end),
+                seconds: (end - start) as f32 / timeline.tick_rate as f32,
+            }
+        })
+        .collect();
+    let seconds = segments.iter().fold(0.0, |sum, s| sum + s.seconds);
+    let duration = match (inputs.first(), inputs.last()) {
+        (Some(first), Some(last)) => timeline.seconds(last.tick) - timeline.seconds(first.tick),
+        _ => 0.0,
+    };
+    AfkStats {
+        segments,
+        seconds,
+        fraction: if duration > 0.0 {
+            seconds / duration
+        } else {
+            0.0
+        },
+        excluded,
+    }
+}
+
+/// The samples outside of the AFK segments. The first and last sample of each segment stay, so
+/// the changes going in and out of it aren't lost.
+pub fn without_afk(inputs: &[Inputs], segments: &[Range<usize>]) -> Vec<Inputs> {
+    inputs
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !segments.iter().any(|r| r.start < *i && *i + 1 < r.end))
+        .map(|(_, input)| input.clone())
+        .collect()
+}
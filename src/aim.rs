@@ -0,0 +1,77 @@
+use std::f32::consts::{PI, TAU};
+
+use crate::data::Inputs;
+
+#[derive(Debug, Clone, Default)]
+pub struct AimStats {
+    /// Mean absolute angular speed in radians per second
+    pub angular_speed_average: f32,
+    /// Mean absolute angular jerk in radians per second cubed
+    pub angular_jerk_average: f32,
+    /// Fraction of moving aim samples where the angular speed did not change at all
+    pub linear_segment_fraction: f32,
+}
+
+/// Wraps an angle difference into the range `-PI..=PI`, so that crossing the
+/// `-PI`/`PI` boundary doesn't register as a full turn.
+pub fn wrap_angle(mut angle: f32) -> f32 {
+    while angle > PI {
+        angle -= TAU;
+    }
+    while angle < -PI {
+        angle += TAU;
+    }
+    angle
+}
+
+/// Differentiates the samples once, dividing by the elapsed time in seconds.
+/// Samples without elapsed time (duplicate ticks) are dropped.
+fn derive(samples: &[(i32, f32)], wrap: bool, tick_rate: i32) -> Vec<(i32, f32)> {
+    samples
+        .windows(2)
+        .filter(|w| w[1].0 > w[0].0)
+        .map(|w| {
+            let dt = (w[1].0 - w[0].0) as f32 / tick_rate as f32;
+            let delta = w[1].1 - w[0].1;
+            let delta = if wrap { wrap_angle(delta) } else { delta };
+            (w[1].0, delta / dt)
+        })
+        .collect()
+}
+
+fn mean_abs(samples: &[(i32, f32)]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    samples.iter().map(|(_, v)| v.abs()).sum::<f32>() / samples.len() as f32
+}
+
+pub fn calculate_aim_stats(inputs: &[Inputs], tick_rate: i32) -> AimStats {
+    let angles: Vec<(i32, f32)> = inputs
+        .iter()
+        .map(|i| (i.tick, i.angle.to_num::<f32>()))
+        .collect();
+
+    let speed = derive(&angles, true, tick_rate);
+    let acceleration = derive(&speed, false, tick_rate);
+    let jerk = derive(&acceleration, false, tick_rate);
+
+    // Silent-aim and aim-lock tools tend to produce piecewise-constant or
+    // perfectly linear angle traces, which humans practically never do.
+    let moving = speed.windows(2).filter(|w| w[1].1 != 0.0).count();
+    let linear = speed
+        .windows(2)
+        .filter(|w| w[1].1 != 0.0 && w[0].1 == w[1].1)
+        .count();
+    let linear_segment_fraction = if moving == 0 {
+        0.0
+    } else {
+        linear as f32 / moving as f32
+    };
+
+    AimStats {
+        angular_speed_average: mean_abs(&speed),
+        angular_jerk_average: mean_abs(&jerk),
+        linear_segment_fraction,
+    }
+}
@@ -0,0 +1,34 @@
+use std::collections::HashMap;
+
+use sha2::{Digest, Sha256};
+
+/// Replaces player names with pseudonyms derived from a salted hash, so the same player gets
+/// the same pseudonym in every demo and output that uses the same salt.
+#[derive(Clone, Copy)]
+pub struct Anonymizer {
+    salt: [u8; 32],
+}
+
+impl Anonymizer {
+    pub fn new(salt: &str) -> Self {
+        Self {
+            salt: Sha256::digest(salt).into(),
+        }
+    }
+
+    pub fn pseudonym(&self, name: &str) -> String {
+        let hash = Sha256::new()
+            .chain_update(self.salt)
+            .chain_update(name)
+            .finalize();
+        let id: String = hash[..3].iter().map(|b| format!("{b:02X}")).collect();
+        format!("Player-{id}")
+    }
+
+    pub fn rename<V>(&self, values: HashMap<String, V>) -> HashMap<String, V> {
+        values
+            .into_iter()
+            .map(|(name, value)| (self.pseudonym(&name), value))
+            .collect()
+    }
+}
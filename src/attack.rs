@@ -0,0 +1,100 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    data::{ActiveWeapon, Inputs},
+    timeline::{Timeline, Timestamp},
+};
+
+/// Two attacks this close together can only come from a double-click macro,
+/// no weapon fires that fast.
+const DOUBLE_CLICK_TICKS: i32 = 2;
+
+/// Minimum time between two shots in milliseconds, as defined by the default tunings.
+fn fire_delay_ms(weapon: ActiveWeapon) -> i32 {
+    match weapon {
+        ActiveWeapon::Hammer => 125,
+        ActiveWeapon::Pistol => 125,
+        ActiveWeapon::Shotgun => 500,
+        ActiveWeapon::Grenade => 500,
+        ActiveWeapon::Rifle => 800,
+        ActiveWeapon::Ninja => 800,
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct WeaponAttackStats {
+    pub attacks: usize,
+    /// Attacks that came faster than the weapon's fire delay allows
+    pub fast_fire: Vec<Timestamp>,
+    /// Attacks that came within two ticks of the previous one
+    pub double_clicks: Vec<Timestamp>,
+    /// How often double clicks alternated with normal shots, the typical macro rhythm
+    pub double_click_patterns: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Attack {
+    pub tick: i32,
+    pub weapon: ActiveWeapon,
+}
+
+pub fn attacks(inputs: &[Inputs]) -> Vec<Attack> {
+    let mut attacks = Vec::new();
+    let mut last_attack_tick = 0;
+    for input in inputs {
+        if input.attack_tick > 0 && input.attack_tick != last_attack_tick {
+            attacks.push(Attack {
+                tick: input.attack_tick,
+                weapon: input.weapon,
+            });
+        }
+        last_attack_tick = input.attack_tick;
+    }
+    attacks
+}
+
+pub fn calculate_attack_stats(
+    inputs: &[Inputs],
+    timeline: &Timeline,
+) -> BTreeMap<ActiveWeapon, WeaponAttackStats> {
+    let attacks = attacks(inputs);
+    let mut stats = BTreeMap::<ActiveWeapon, WeaponAttackStats>::new();
+
+    for attack in &attacks {
+        stats.entry(attack.weapon).or_default().attacks += 1;
+    }
+
+    // A switch resets the fire delay, so only shots in a row with the same weapon are compared
+    for streak in attacks.chunk_by(|a, b| a.weapon == b.weapon) {
+        let intervals: Vec<(Attack, i32)> = streak
+            .windows(2)
+            .map(|w| (w[1], w[1].tick - w[0].tick))
+            .collect();
+
+        let weapon_stats = stats.entry(streak[0].weapon).or_default();
+        for (attack, interval) in &intervals {
+            if *interval <= DOUBLE_CLICK_TICKS {
+                weapon_stats
+                    .double_clicks
+                    .push(timeline.timestamp(attack.tick));
+            } else if interval * 1000 < fire_delay_ms(attack.weapon) * timeline.tick_rate {
+                weapon_stats.fast_fire.push(timeline.timestamp(attack.tick));
+            }
+        }
+
+        for w in intervals.windows(3) {
+            let [(_, first), (_, normal), (_, second)] = [w[0], w[1], w[2]];
+            if first <= DOUBLE_CLICK_TICKS
+                && normal > DOUBLE_CLICK_TICKS
+                && second <= DOUBLE_CLICK_TICKS
+            {
+                weapon_stats.double_click_patterns += 1;
+            }
+        }
+    }
+
+    stats
+}
@@ -0,0 +1,114 @@
+use serde_json::{Map, Value};
+
+use crate::numbers::NumberFormat;
+
+fn cell(value: &Value, numbers: NumberFormat) -> String {
+    match value {
+        Value::Null => String::from("-"),
+        Value::String(s) => s.clone(),
+        // Three decimals are plenty for a forum post
+        Value::Number(n) if n.is_f64() => {
+            let n = format!("{:.3}", n.as_f64().unwrap_or_default());
+            numbers.number(n.trim_end_matches('0').trim_end_matches('.'))
+        }
+        Value::Number(n) => numbers.number(&n.to_string()),
+        Value::Array(items) => items
+            .iter()
+            .map(|i| cell(i, numbers))
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Object(map) => map
+            .iter()
+            .map(|(k, v)| format!("{k}: {}", cell(v, numbers)))
+            .collect::<Vec<_>>()
+            .join(", "),
+        value => value.to_string(),
+    }
+}
+
+fn row(cells: impl IntoIterator<Item = String>) -> String {
+    let cells: String = cells.into_iter().map(|c| format!("[td]{c}[/td]")).collect();
+    format!("[tr]{cells}[/tr]")
+}
+
+/// A list of objects as table with a column per field.
+fn list(items: &[Value], numbers: NumberFormat, lines: &mut Vec<String>) {
+    let mut columns: Vec<&String> = Vec::new();
+    for item in items.iter().filter_map(Value::as_object) {
+        for key in item.keys() {
+            if !columns.contains(&key) {
+                columns.push(key);
+            }
+        }
+    }
+    lines.push(String::from("[table]"));
+    lines.push(row(columns.iter().map(|c| format!("[b]{c}[/b]"))));
+    for item in items {
+        lines.push(row(columns
+            .iter()
+            .map(|c| cell(&item[c.as_str()], numbers))));
+    }
+    lines.push(String::from("[/table]"));
+}
+
+/// The plain fields of an object as table of names and values, nested objects and lists of
+/// objects follow as tables of their own titled with their path.
+fn object(path: &str, map: &Map<String, Value>, numbers: NumberFormat, lines: &mut Vec<String>) {
+    let is_list = |v: &Value| v.as_array().is_some_and(|a| a.iter().any(Value::is_object));
+    let fields: Vec<_> = map
+        .iter()
+        .filter(|(_, v)| !v.is_object() && !is_list(v))
+        .collect();
+    if !path.is_empty() && !map.is_empty() {
+        lines.push(format!("[b]{path}[/b]"));
+    }
+    if !fields.is_empty() {
+        lines.push(String::from("[table]"));
+        for (key, value) in fields {
+            lines.push(row([key.clone(), cell(value, numbers)]));
+        }
+        lines.push(String::from("[/table]"));
+    }
+    for (key, value) in map {
+        let path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        match value {
+            Value::Object(map) => object(&path, map, numbers, lines),
+            Value::Array(items) if is_list(value) => {
+                lines.push(format!("[b]{path}[/b]"));
+                list(items, numbers, lines);
+            }
+            _ => {}
+        }
+    }
+}
+
+fn section(value: &Value, numbers: NumberFormat, lines: &mut Vec<String>) {
+    match value {
+        Value::Object(map) => object("", map, numbers, lines),
+        Value::Array(items) => list(items, numbers, lines),
+        value => lines.push(cell(value, numbers)),
+    }
+}
+
+/// Formats structured output for the DDNet forum. Every entry of the top level map, usually
+/// a player, goes into a spoiler of its own so long reports stay readable in a thread.
+pub fn to_bbcode(value: &Value, numbers: NumberFormat) -> String {
+    let mut lines = Vec::new();
+    match value {
+        Value::Object(map) => {
+            let mut entries: Vec<_> = map.iter().collect();
+            entries.sort_by_key(|(name, _)| *name);
+            for (name, value) in entries {
+                lines.push(format!("[spoiler={name}]"));
+                section(value, numbers, &mut lines);
+                lines.push(String::from("[/spoiler]"));
+            }
+        }
+        value => section(value, numbers, &mut lines),
+    }
+    lines.join("\n")
+}
@@ -0,0 +1,52 @@
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+const BOOKMARKS_EXTENSION: &str = "bookmarks.json";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Bookmark {
+    pub player: String,
+    pub tick: i32,
+    /// Time since the start of the demo as mm:ss.ms
+    pub time: String,
+    pub label: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BookmarkFile {
+    demo: String,
+    bookmarks: Vec<Bookmark>,
+}
+
+/// The file next to the demo, `name.demo.bookmarks.json`.
+pub fn bookmarks_path(demo: &Path) -> PathBuf {
+    let mut path = demo.as_os_str().to_owned();
+    path.push(".");
+    path.push(BOOKMARKS_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// The bookmarks saved for the demo, empty if there are none.
+pub fn load(demo: &Path) -> anyhow::Result<Vec<Bookmark>> {
+    let path = bookmarks_path(demo);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let file: BookmarkFile = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    Ok(file.bookmarks)
+}
+
+pub fn save(demo: &Path, bookmarks: &[Bookmark]) -> anyhow::Result<PathBuf> {
+    let path = bookmarks_path(demo);
+    let file = BookmarkFile {
+        demo: demo
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .into_owned(),
+        bookmarks: bookmarks.to_vec(),
+    };
+    std::fs::write(&path, serde_json::to_string_pretty(&file)?)?;
+    Ok(path)
+}
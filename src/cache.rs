@@ -0,0 +1,87 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{changes::InputChanges, timeline::Timeline};
+
+/// Has to be bumped whenever `Inputs` or the way they are extracted changes.
+const CACHE_VERSION: u32 = 4;
+const CACHE_EXTENSION: &str = "tda";
+
+#[derive(Serialize, Deserialize)]
+struct CacheFile {
+    version: u32,
+    /// SHA-256 of the demo the inputs were extracted from
+    demo_hash: [u8; 32],
+    timeline: Timeline,
+    inputs: HashMap<String, InputChanges>,
+}
+
+/// The sidecar file next to the demo, `name.demo.tda`.
+fn cache_path(demo: &Path) -> PathBuf {
+    let mut path = demo.as_os_str().to_owned();
+    path.push(".");
+    path.push(CACHE_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// The start of a `CacheFile`, to check it without reading all inputs.
+#[derive(Deserialize)]
+struct CacheHeader {
+    version: u32,
+}
+
+/// Whether the demo has a cache of the current version that is newer than the demo. Doesn't
+/// hash the demo, so a cache written for different contents with a newer timestamp counts.
+pub fn is_cached(demo: &Path) -> bool {
+    let path = cache_path(demo);
+    let modified = |path: &Path| std::fs::metadata(path).and_then(|m| m.modified()).ok();
+    let (Some(demo_modified), Some(cache_modified)) = (modified(demo), modified(&path)) else {
+        return false;
+    };
+    let header = File::open(&path)
+        .ok()
+        .and_then(|f| bincode::deserialize_from::<_, CacheHeader>(BufReader::new(f)).ok());
+    cache_modified >= demo_modified && header.is_some_and(|h| h.version == CACHE_VERSION)
+}
+
+fn read(path: &Path, demo_hash: &[u8; 32]) -> Option<CacheFile> {
+    let file = BufReader::new(File::open(path).ok()?);
+    let cache: CacheFile = bincode::deserialize_from(file).ok()?;
+    (cache.version == CACHE_VERSION && &cache.demo_hash == demo_hash).then_some(cache)
+}
+
+/// Loads the inputs of all players from the sidecar cache, or extracts them and writes the
+/// cache. The cache is only used if it was written for the exact same demo.
+pub fn load_or_extract(
+    demo: &Path,
+    extract: impl FnOnce() -> anyhow::Result<(HashMap<String, InputChanges>, Timeline)>,
+) -> anyhow::Result<(HashMap<String, InputChanges>, Timeline)> {
+    let demo_hash: [u8; 32] = Sha256::digest(std::fs::read(demo)?).into();
+    let path = cache_path(demo);
+    if let Some(cache) = read(&path, &demo_hash) {
+        return Ok((cache.inputs, cache.timeline));
+    }
+
+    let (inputs, timeline) = extract()?;
+    let cache = CacheFile {
+        version: CACHE_VERSION,
+        demo_hash,
+        timeline,
+        inputs,
+    };
+    // The cache is only an optimization, a read-only directory shouldn't fail the command
+    let written = File::create(&path)
+        .map_err(bincode::Error::from)
+        .and_then(|f| bincode::serialize_into(BufWriter::new(f), &cache));
+    if let Err(e) = written {
+        eprintln!("Couldn't write cache {path:?}: {e}");
+    }
+    Ok((cache.inputs, cache.timeline))
+}
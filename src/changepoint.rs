@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use stringlit::s;
+
+use crate::{
+    aim,
+    data::Inputs,
+    timeline::{TimeFormat, Timeline, Timestamp},
+    zoom::percentile,
+};
+
+/// Segments are at least this many windows long, shorter shifts are treated as noise.
+const MIN_WINDOWS: usize = 6;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct BehaviorSegment {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    /// Seconds
+    pub duration: f32,
+    /// Per second
+    pub direction_change_rate: f32,
+    /// Per second
+    pub hook_change_rate: f32,
+    /// Mean absolute angular speed of the aim in radians per second
+    pub aim_angular_speed: f32,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Changepoints {
+    /// Seconds per window of the series the changepoints are searched in
+    pub window: f32,
+    /// Where the behavior shifted, the starts of all segments but the first
+    pub changes: Vec<Timestamp>,
+    pub segments: Vec<BehaviorSegment>,
+}
+
+fn changes<T: PartialEq>(inputs: &[Inputs], state: impl Fn(&Inputs) -> T) -> usize {
+    inputs
+        .windows(2)
+        .filter(|w| state(&w[0]) != state(&w[1]))
+        .count()
+}
+
+/// Direction change rate, hook change rate and aim speed of the samples.
+fn rates(inputs: &[Inputs], seconds: f32, tick_rate: i32) -> [f32; 3] {
+    [
+        changes(inputs, |i| i.direction) as f32 / seconds,
+        changes(inputs, |i| i.hook_state.pressed()) as f32 / seconds,
+        aim::calculate_aim_stats(inputs, tick_rate).angular_speed_average,
+    ]
+}
+
+/// The noise of a series from the spread of the steps between neighbors, which unlike the
+/// standard deviation isn't inflated by the shifts that are searched for.
+fn noise(series: &[f32]) -> f32 {
+    let mut steps: Vec<f32> = series.windows(2).map(|w| (w[1] - w[0]).abs()).collect();
+    steps.sort_by(f32::total_cmp);
+    let mad = if steps.is_empty() {
+        0.0
+    } else {
+        percentile(&steps, 0.5) / (0.6745 * std::f32::consts::SQRT_2)
+    };
+    if mad > 0.0 {
+        mad
+    } else {
+        1.0
+    }
+}
+
+/// PELT over the windows, with the squared distance to the segment mean as cost. Returns
+/// the indices of the windows new segments start at.
+fn pelt(series: &[Vec<f32>], penalty: f32) -> Vec<usize> {
+    let n = series.len();
+    if n < 2 * MIN_WINDOWS {
+        return Vec::new();
+    }
+    let dims = series[0].len();
+    // Prefix sums of the values and their squares make every segment cost O(dims)
+    let mut sums = vec![vec![0.0f64; dims]; n + 1];
+    let mut squares = vec![vec![0.0f64; dims]; n + 1];
+    for (t, values) in series.iter().enumerate() {
+        for d in 0..dims {
+            let v = f64::from(values[d]);
+            sums[t + 1][d] = sums[t][d] + v;
+            squares[t + 1][d] = squares[t][d] + v * v;
+        }
+    }
+    let cost = |s: usize, t: usize| -> f64 {
+        let len = (t - s) as f64;
+        (0..dims)
+            .map(|d| {
+                let sum = sums[t][d] - sums[s][d];
+                squares[t][d] - squares[s][d] - sum * sum / len
+            })
+            .sum()
+    };
+
+    let beta = f64::from(penalty) * dims as f64 * (n as f64).ln();
+    let mut best = vec![f64::INFINITY; n + 1];
+    let mut previous = vec![0; n + 1];
+    best[0] = -beta;
+    let mut candidates = vec![0];
+    for t in MIN_WINDOWS..=n {
+        if t >= 2 * MIN_WINDOWS {
+            candidates.push(t - MIN_WINDOWS);
+        }
+        let (s, value) = candidates
+            .iter()
+            .map(|&s| (s, best[s] + cost(s, t) + beta))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap_or((0, f64::INFINITY));
+        best[t] = value;
+        previous[t] = s;
+        candidates.retain(|&s| best[s] + cost(s, t) <= value);
+    }
+
+    let mut starts = Vec::new();
+    let mut t = n;
+    while previous[t] > 0 {
+        t = previous[t];
+        starts.push(t);
+    }
+    starts.reverse();
+    starts
+}
+
+/// Splits the samples of a player into segments of consistent behavior. The direction and
+/// hook change rates and the aim speed are taken in windows of `window` seconds, scaled by
+/// their noise, and PELT finds where their means shift. A higher `penalty` finds fewer
+/// changes.
+pub fn detect_changepoints(
+    inputs: &[Inputs],
+    timeline: &Timeline,
+    window: f32,
+    penalty: f32,
+) -> Changepoints {
+    let tick_rate = timeline.tick_rate;
+    let window_ticks = ((window * tick_rate as f32) as i32).max(1);
+    // Every window as the range of samples it contains, a partial last window is left out
+    let mut bounds = Vec::new();
+    if let (Some(first), Some(last)) = (inputs.first(), inputs.last()) {
+        let mut start = first.tick;
+        while start + window_ticks <= last.tick {
+            bounds.push(
+                inputs.partition_point(|i| i.tick < start)
+                    ..inputs.partition_point(|i| i.tick < start + window_ticks),
+            );
+            start += window_ticks;
+        }
+    }
+    let mut series: Vec<Vec<f32>> = bounds
+        .iter()
+        .map(|b| rates(&inputs[b.clone()], window, tick_rate).to_vec())
+        .collect();
+    for d in 0..3 {
+        let scale = noise(&series.iter().map(|v| v[d]).collect::<Vec<_>>());
+        series.iter_mut().for_each(|v| v[d] /= scale);
+    }
+
+    let starts = pelt(&series, penalty);
+    let mut edges: Vec<usize> = std::iter::once(0)
+        .chain(starts.iter().map(|&w| bounds[w].start))
+        .collect();
+    edges.push(inputs.len());
+    let segments = edges
+        .windows(2)
+        .filter(|e| e[1] > e[0])
+        .map(|e| {
+            let samples = &inputs[e[0]..e[1]];
+            let (start, end) = (samples[0].tick, samples[samples.len() - 1].tick);
+            let duration = ((end - start) as f32 / tick_rate as f32).max(f32::EPSILON);
+            let [direction_change_rate, hook_change_rate, aim_angular_speed] =
+                rates(samples, duration, tick_rate);
+            BehaviorSegment {
+                start: timeline.timestamp(start),
+                end: timeline.timestamp(end),
+                duration,
+                direction_change_rate,
+                hook_change_rate,
+                aim_angular_speed,
+            }
+        })
+        .collect::<Vec<_>>();
+    Changepoints {
+        window,
+        changes: segments.iter().skip(1).map(|s| s.start.clone()).collect(),
+        segments,
+    }
+}
+
+/// The segments of every player, with the stats of each.
+pub fn plain_report(
+    changepoints: &HashMap<String, Changepoints>,
+    timeline: &Timeline,
+    format: TimeFormat,
+) -> String {
+    let mut players: Vec<_> = changepoints.iter().collect();
+    players.sort_by_key(|(name, _)| *name);
+    let mut vec = Vec::new();
+    for (name, changepoints) in players {
+        vec.push(format!("{:=^44}", format!(" {name} ")));
+        vec.push(s!(""));
+        vec.push(format!(
+            "{} changes in windows of {}s",
+            changepoints.changes.len(),
+            changepoints.window
+        ));
+        vec.push(s!(""));
+        for (i, segment) in changepoints.segments.iter().enumerate() {
+            vec.push(format!(
+                "#{:<3} {:>9} - {:>9} {:0>5.2} dir/s {:0>5.2} hook/s {:0>5.2} rad/s",
+                i + 1,
+                format.format(timeline.seconds(segment.start.tick)),
+                format.format(timeline.seconds(segment.end.tick)),
+                segment.direction_change_rate,
+                segment.hook_change_rate,
+                segment.aim_angular_speed,
+            ));
+        }
+        vec.push(s!(""));
+    }
+    vec.join("\n")
+}
@@ -0,0 +1,124 @@
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::data::{Inputs, Position};
+
+/// A state that was held over several evenly spaced samples.
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Change {
+    /// The first sample, the following ones only differ in the tick
+    pub inputs: Inputs,
+    /// Number of samples the state was held for, including the first
+    pub samples: u32,
+    /// Ticks between the samples
+    pub step: i32,
+}
+
+impl Change {
+    fn last_tick(&self) -> i32 {
+        self.inputs.tick + self.step * (self.samples as i32 - 1)
+    }
+}
+
+/// The inputs of a player, run-length encoded so only the samples where something changed
+/// are stored. Players standing around take up next to no memory.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, JsonSchema)]
+#[serde(transparent)]
+pub struct InputChanges(Vec<Change>);
+
+impl InputChanges {
+    pub fn push(&mut self, inputs: Inputs) {
+        if let Some(last) = self.0.last_mut() {
+            let step = inputs.tick - last.last_tick();
+            let same_state = Inputs {
+                tick: last.inputs.tick,
+                ..inputs.clone()
+            } == last.inputs;
+            if same_state && (last.samples == 1 || step == last.step) {
+                last.step = step;
+                last.samples += 1;
+                return;
+            }
+        }
+        self.0.push(Change {
+            inputs,
+            samples: 1,
+            step: 0,
+        });
+    }
+
+    /// Every sample, as they were read from the demo.
+    pub fn iter(&self) -> impl Iterator<Item = Inputs> + '_ {
+        self.0.iter().flat_map(|change| {
+            (0..change.samples as i32).map(move |i| Inputs {
+                tick: change.inputs.tick + i * change.step,
+                ..change.inputs.clone()
+            })
+        })
+    }
+
+    pub fn to_vec(&self) -> Vec<Inputs> {
+        self.iter().collect()
+    }
+
+    /// Where the player was at the tick, if there is a sample for it. Looked up in the
+    /// changes, so the other samples don't have to be expanded for it.
+    pub fn position_at(&self, tick: i32) -> Option<&Position> {
+        let change = &self.0[self
+            .0
+            .partition_point(|c| c.inputs.tick <= tick)
+            .checked_sub(1)?];
+        let offset = tick - change.inputs.tick;
+        let sampled = if change.step == 0 {
+            offset == 0
+        } else {
+            offset % change.step == 0 && offset / change.step < change.samples as i32
+        };
+        sampled.then_some(&change.inputs.pos)
+    }
+}
+
+impl FromIterator<Inputs> for InputChanges {
+    fn from_iter<T: IntoIterator<Item = Inputs>>(iter: T) -> Self {
+        let mut changes = Self::default();
+        for inputs in iter {
+            changes.push(inputs);
+        }
+        changes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fixed::types::I27F5;
+    use twsnap::{
+        items::{Player, Tee},
+        time::{Duration, Instant},
+    };
+
+    fn sample(tick: i32, x: i32) -> Inputs {
+        let tee = Tee {
+            tick: Instant::zero() + Duration::from_ticks(tick),
+            pos: twsnap::Position::new(I27F5::from_num(x), I27F5::ZERO),
+            ..Default::default()
+        };
+        (&Player::default(), &tee).into()
+    }
+
+    #[test]
+    fn looks_up_positions_without_expanding() {
+        let samples = [(0, 1), (1, 1), (2, 1), (10, 2), (12, 2), (14, 2), (20, 3)];
+        let changes: InputChanges = samples.iter().map(|(t, x)| sample(*t, *x)).collect();
+        assert_eq!(changes.0.len(), 3);
+        assert_eq!(changes.to_vec().len(), samples.len());
+
+        let x = |tick| changes.position_at(tick).map(|p| p.x.to_num::<i32>());
+        for (tick, expected) in samples {
+            assert_eq!(x(tick), Some(expected));
+        }
+        for tick in [-1, 3, 9, 11, 13, 16, 21] {
+            assert_eq!(x(tick), None);
+        }
+    }
+}
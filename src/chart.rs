@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+
+use anyhow::Context;
+use tiny_skia::{Color, Paint, PathBuilder, Pixmap, Stroke, Transform};
+
+use crate::data::Inputs;
+
+const WIDTH: u32 = 1200;
+const HEIGHT: u32 = 400;
+const MARGIN: f32 = 20.0;
+
+/// Colors of the player lines, in the order of the sorted player names.
+pub const COLORS: [(&str, [u8; 3]); 6] = [
+    ("red", [230, 60, 60]),
+    ("blue", [60, 120, 230]),
+    ("green", [60, 180, 80]),
+    ("orange", [240, 150, 30]),
+    ("purple", [160, 80, 200]),
+    ("teal", [30, 180, 180]),
+];
+
+pub fn direction_changed(a: &Inputs, b: &Inputs) -> usize {
+    (a.direction != b.direction) as usize
+}
+
+pub fn hook_changed(a: &Inputs, b: &Inputs) -> usize {
+    (a.hook_state.pressed() != b.hook_state.pressed()) as usize
+}
+
+/// Counts the changes between consecutive samples in every second of the demo.
+pub fn changes_per_second(
+    inputs: &[Inputs],
+    start_tick: i32,
+    seconds: usize,
+    tick_rate: i32,
+    changes_between: impl Fn(&Inputs, &Inputs) -> usize,
+) -> Vec<usize> {
+    let mut changes = vec![0; seconds];
+    for w in inputs.windows(2) {
+        let changed = changes_between(&w[0], &w[1]);
+        let second = ((w[1].tick - start_tick) / tick_rate) as usize;
+        if let Some(c) = changes.get_mut(second) {
+            *c += changed;
+        }
+    }
+    changes
+}
+
+/// Plots the input changes per second of every player as PNG, returning the names in
+/// the order of [`COLORS`].
+pub fn render_activity(
+    inputs: &HashMap<String, Vec<Inputs>>,
+    tick_rate: i32,
+) -> anyhow::Result<(Vec<u8>, Vec<String>)> {
+    let mut names: Vec<String> = inputs.keys().cloned().collect();
+    names.sort();
+    names.truncate(COLORS.len());
+
+    let ticks = || names.iter().flat_map(|n| inputs[n].iter().map(|i| i.tick));
+    let start_tick = ticks().min().unwrap_or_default();
+    let end_tick = ticks().max().unwrap_or_default();
+    let seconds = ((end_tick - start_tick) / tick_rate) as usize + 1;
+    let series: Vec<Vec<usize>> = names
+        .iter()
+        .map(|n| {
+            changes_per_second(&inputs[n], start_tick, seconds, tick_rate, |a, b| {
+                direction_changed(a, b) + hook_changed(a, b)
+            })
+        })
+        .collect();
+    let max = series
+        .iter()
+        .flatten()
+        .copied()
+        .max()
+        .unwrap_or_default()
+        .max(1);
+
+    let mut pixmap = Pixmap::new(WIDTH, HEIGHT).context("Invalid chart size")?;
+    pixmap.fill(Color::from_rgba8(255, 255, 255, 255));
+    let x = |second: usize| {
+        MARGIN + second as f32 / (seconds - 1).max(1) as f32 * (WIDTH as f32 - 2.0 * MARGIN)
+    };
+    let y = |value: usize| {
+        HEIGHT as f32 - MARGIN - value as f32 / max as f32 * (HEIGHT as f32 - 2.0 * MARGIN)
+    };
+
+    let mut axis = PathBuilder::new();
+    axis.move_to(MARGIN, MARGIN);
+    axis.line_to(MARGIN, HEIGHT as f32 - MARGIN);
+    axis.line_to(WIDTH as f32 - MARGIN, HEIGHT as f32 - MARGIN);
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(0, 0, 0, 255);
+    if let Some(path) = axis.finish() {
+        pixmap.stroke_path(
+            &path,
+            &paint,
+            &Stroke::default(),
+            Transform::identity(),
+            None,
+        );
+    }
+
+    for (values, (_, [r, g, b])) in series.iter().zip(COLORS) {
+        let mut line = PathBuilder::new();
+        line.move_to(x(0), y(values[0]));
+        for (second, value) in values.iter().enumerate().skip(1) {
+            line.line_to(x(second), y(*value));
+        }
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(r, g, b, 255);
+        paint.anti_alias = true;
+        let stroke = Stroke {
+            width: 1.5,
+            ..Default::default()
+        };
+        if let Some(path) = line.finish() {
+            pixmap.stroke_path(&path, &paint, &stroke, Transform::identity(), None);
+        }
+    }
+
+    Ok((pixmap.encode_png()?, names))
+}
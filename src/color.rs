@@ -0,0 +1,78 @@
+use std::io::IsTerminal;
+
+use clap::ValueEnum;
+
+/// Change rates above this within one second are highlighted, the same as the default alert
+/// of the live command.
+pub const MAX_CHANGE_RATE: usize = 15;
+/// Model outputs from this on are highlighted.
+pub const BOT_PROBABILITY: f32 = 0.5;
+/// Placements in the reference set from this percentile on are highlighted.
+pub const REFERENCE_PERCENTILE: f32 = 99.0;
+
+const RED: &str = "\x1b[31m";
+const YELLOW: &str = "\x1b[33m";
+const BOLD: &str = "\x1b[1m";
+const CYAN: &str = "\x1b[36m";
+const RESET: &str = "\x1b[0m";
+
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Only when writing to a terminal and NO_COLOR isn't set
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+/// Colors for the plain report, everything stays as it is when they are off.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Palette {
+    enabled: bool,
+}
+
+impl Palette {
+    /// `to_file` is whether the output goes to `--out` instead of stdout.
+    pub fn new(choice: ColorChoice, to_file: bool) -> Self {
+        let enabled = match choice {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                !to_file
+                    && std::env::var_os("NO_COLOR").is_none_or(|v| v.is_empty())
+                    && std::io::stdout().is_terminal()
+            }
+        };
+        Self { enabled }
+    }
+
+    fn paint(self, color: &str, text: String) -> String {
+        if self.enabled {
+            format!("{color}{text}{RESET}")
+        } else {
+            text
+        }
+    }
+
+    /// Red if the value is out of the range humans usually stay in.
+    pub fn alert(self, text: String, out_of_range: bool) -> String {
+        if out_of_range {
+            self.paint(RED, text)
+        } else {
+            text
+        }
+    }
+
+    pub fn warning(self, text: String) -> String {
+        self.paint(YELLOW, text)
+    }
+
+    /// The banners and section headers made of `=` and `-`.
+    pub fn header(self, line: String) -> String {
+        match line.chars().next() {
+            Some('=') => self.paint(BOLD, line),
+            Some('-') => self.paint(CYAN, line),
+            _ => line,
+        }
+    }
+}
@@ -0,0 +1,191 @@
+use std::path::Path;
+
+use image::{Rgb, RgbImage};
+use serde::Serialize;
+
+use crate::{
+    data::{Inputs, DEFAULT_TICK_RATE},
+    pace,
+    runs::{self, RunEnd},
+};
+
+/// Leaving the ghost's route by more than this counts as taking a different route, in tiles.
+pub const DIVERGENCE_DISTANCE: f32 = 4.0;
+const OVERLAY_SIZE: u32 = 1024;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PositionOffset {
+    /// Seconds since the start of the run
+    pub time: f32,
+    /// Distance between the run and the ghost at that time, in tiles
+    pub distance: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Divergence {
+    pub time: f32,
+    pub x: f32,
+    pub y: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct GhostComparison {
+    pub ghost_owner: String,
+    pub ghost_time: f32,
+    /// Index of the compared run as reported by the run segmentation
+    pub run: usize,
+    pub run_time: f32,
+    pub time_delta: f32,
+    pub section_length: f32,
+    /// Time difference to the ghost when entering each section, in seconds
+    pub split_deltas: Vec<Option<f32>>,
+    pub offsets: Vec<PositionOffset>,
+    /// Where the run first left the route of the ghost
+    pub divergence: Option<Divergence>,
+}
+
+fn elapsed(samples: &[Inputs], tick: i32, tick_rate: i32) -> f32 {
+    (tick - samples[0].tick) as f32 / tick_rate as f32
+}
+
+/// The first sample at least `ticks` after the start.
+fn at(samples: &[Inputs], ticks: i32) -> Option<&Inputs> {
+    samples.iter().find(|i| i.tick - samples[0].tick >= ticks)
+}
+
+pub fn compare(
+    ghost: &crate::ghost::Ghost,
+    inputs: &[Inputs],
+    section_length: f32,
+    tick_rate: i32,
+) -> Option<GhostComparison> {
+    let reference = &ghost.inputs[..];
+    let start = reference.first()?;
+
+    let (run, (range, _)) = runs::segment_runs(inputs, tick_rate)
+        .into_iter()
+        .enumerate()
+        .filter(|(_, (_, end))| *end == RunEnd::Finish)
+        .min_by_key(|(_, (range, _))| inputs[range.end - 1].tick - inputs[range.start].tick)?;
+
+    // Runs start at the spawn, ghosts at the start line, so we start comparing from the
+    // point where the run is closest to the start of the ghost.
+    let samples = &inputs[range];
+    let offset = samples
+        .iter()
+        .take(samples.len() / 2 + 1)
+        .enumerate()
+        .min_by(|a, b| {
+            a.1.pos
+                .distance(&start.pos)
+                .total_cmp(&b.1.pos.distance(&start.pos))
+        })
+        .map(|(i, _)| i)?;
+    let samples = &samples[offset..];
+
+    let travelled = pace::travelled(reference);
+    let sections = (travelled.last().copied().unwrap_or_default() / section_length).ceil() as usize;
+    let matches = pace::matches(reference, samples);
+    let progress: Vec<f32> = matches.iter().map(|i| travelled[*i]).collect();
+
+    let ghost_entries = pace::section_entries(reference, &travelled, sections, section_length);
+    let run_entries = pace::section_entries(samples, &progress, sections, section_length);
+    let split_deltas = run_entries
+        .iter()
+        .zip(&ghost_entries)
+        .map(|(run, ghost)| {
+            Some(
+                elapsed(samples, (*run)?, tick_rate)
+                    - elapsed(reference, (*ghost)?, DEFAULT_TICK_RATE),
+            )
+        })
+        .collect();
+
+    let run_time = elapsed(samples, samples.last()?.tick, tick_rate);
+    let offsets = (0..=run_time as usize)
+        .filter_map(|second| {
+            let (run, ghost) = (
+                at(samples, second as i32 * tick_rate)?,
+                at(reference, second as i32 * DEFAULT_TICK_RATE)?,
+            );
+            Some(PositionOffset {
+                time: second as f32,
+                distance: run.pos.distance(&ghost.pos),
+            })
+        })
+        .collect();
+
+    let divergence = samples
+        .iter()
+        .zip(&matches)
+        .find(|(sample, i)| sample.pos.distance(&reference[**i].pos) > DIVERGENCE_DISTANCE)
+        .map(|(sample, _)| Divergence {
+            time: elapsed(samples, sample.tick, tick_rate),
+            x: sample.pos.x.to_num(),
+            y: sample.pos.y.to_num(),
+        });
+
+    let ghost_time = ghost.time as f32 / 1000.0;
+    Some(GhostComparison {
+        ghost_owner: ghost.owner.clone(),
+        ghost_time,
+        run,
+        run_time,
+        time_delta: run_time - ghost_time,
+        section_length,
+        split_deltas,
+        offsets,
+        divergence,
+    })
+}
+
+/// Renders the route of the ghost in blue and the routes of the runs in red.
+pub fn render_overlay(ghost: &[Inputs], runs: &[Vec<Inputs>], path: &Path) -> anyhow::Result<()> {
+    let points = || {
+        ghost
+            .iter()
+            .chain(runs.iter().flat_map(|r| r.iter()))
+            .map(|i| (i.pos.x.to_num::<f32>(), i.pos.y.to_num::<f32>()))
+    };
+    let (min_x, min_y, max_x, max_y) = points().fold(
+        (f32::MAX, f32::MAX, f32::MIN, f32::MIN),
+        |(min_x, min_y, max_x, max_y), (x, y)| {
+            (min_x.min(x), min_y.min(y), max_x.max(x), max_y.max(y))
+        },
+    );
+    let scale = (OVERLAY_SIZE - 1) as f32 / (max_x - min_x).max(max_y - min_y).max(1.0);
+    let width = ((max_x - min_x) * scale) as u32 + 1;
+    let height = ((max_y - min_y) * scale) as u32 + 1;
+
+    let mut image = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+    let mut draw = |samples: &[Inputs], color: Rgb<u8>| {
+        for w in samples.windows(2) {
+            let from = (
+                (w[0].pos.x.to_num::<f32>() - min_x) * scale,
+                (w[0].pos.y.to_num::<f32>() - min_y) * scale,
+            );
+            let to = (
+                (w[1].pos.x.to_num::<f32>() - min_x) * scale,
+                (w[1].pos.y.to_num::<f32>() - min_y) * scale,
+            );
+            let steps = (to.0 - from.0)
+                .abs()
+                .max((to.1 - from.1).abs())
+                .ceil()
+                .max(1.0) as usize;
+            for step in 0..=steps {
+                let t = step as f32 / steps as f32;
+                let x = (from.0 + (to.0 - from.0) * t) as u32;
+                let y = (from.1 + (to.1 - from.1) * t) as u32;
+                image.put_pixel(x.min(width - 1), y.min(height - 1), color);
+            }
+        }
+    };
+    draw(ghost, Rgb([0, 0, 255]));
+    for run in runs {
+        draw(run, Rgb([255, 0, 0]));
+    }
+
+    image.save(path)?;
+    Ok(())
+}
@@ -0,0 +1,105 @@
+use std::{collections::HashMap, ops::RangeInclusive};
+
+use anyhow::{bail, Context};
+use tiny_skia::{Color, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+use crate::data::Inputs;
+
+const SIZE: u32 = 600;
+const MARGIN: f32 = 20.0;
+const DOT: f32 = 2.0;
+
+/// Offsets from the tee in tiles, y pointing down like in the game.
+#[derive(Debug, Clone, Default)]
+pub struct Trace {
+    /// Where the player aimed
+    pub targets: Vec<[f32; 2]>,
+    /// Where the other players were at the same ticks. An aimbot locks the targets onto these.
+    pub others: Vec<[f32; 2]>,
+}
+
+fn sample_at(samples: &[Inputs], tick: i32) -> Option<&Inputs> {
+    samples
+        .get(samples.partition_point(|i| i.tick < tick))
+        .filter(|i| i.tick == tick)
+}
+
+/// The aim of the player relative to their tee in the given ticks, the target already is.
+pub fn trace(
+    inputs: &HashMap<String, Vec<Inputs>>,
+    player: &str,
+    ticks: RangeInclusive<i32>,
+) -> Trace {
+    let Some(samples) = inputs.get(player) else {
+        return Trace::default();
+    };
+    let mut trace = Trace::default();
+    for sample in samples.iter().filter(|i| ticks.contains(&i.tick)) {
+        let (x, y) = sample.pos.tiles();
+        let (target_x, target_y) = sample.target.tiles();
+        trace.targets.push([target_x, target_y]);
+        for (name, other) in inputs {
+            if name == player {
+                continue;
+            }
+            if let Some(other) = sample_at(other, sample.tick) {
+                let (other_x, other_y) = other.pos.tiles();
+                trace.others.push([other_x - x, other_y - y]);
+            }
+        }
+    }
+    trace
+}
+
+/// Draws the trace as PNG around the tee in the center, the other players in grey and the
+/// targets in red. The scale fits the farthest target, players farther away are left out.
+pub fn render(trace: &Trace, player: &str) -> anyhow::Result<Vec<u8>> {
+    if trace.targets.is_empty() {
+        bail!("No aim samples for {player}");
+    }
+    let extent = trace
+        .targets
+        .iter()
+        .map(|[x, y]| x.abs().max(y.abs()))
+        .fold(1.0, f32::max);
+    let center = SIZE as f32 / 2.0;
+    let scale = (center - MARGIN) / extent;
+
+    let mut pixmap = Pixmap::new(SIZE, SIZE).context("Invalid image size")?;
+    pixmap.fill(Color::from_rgba8(255, 255, 255, 255));
+
+    let mut axes = PathBuilder::new();
+    axes.move_to(center, MARGIN);
+    axes.line_to(center, SIZE as f32 - MARGIN);
+    axes.move_to(MARGIN, center);
+    axes.line_to(SIZE as f32 - MARGIN, center);
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(200, 200, 200, 255);
+    if let Some(path) = axes.finish() {
+        pixmap.stroke_path(
+            &path,
+            &paint,
+            &Stroke::default(),
+            Transform::identity(),
+            None,
+        );
+    }
+
+    let mut dots = |points: &[[f32; 2]], color: [u8; 4]| {
+        let mut paint = Paint::default();
+        paint.set_color_rgba8(color[0], color[1], color[2], color[3]);
+        for [x, y] in points {
+            if x.abs() > extent || y.abs() > extent {
+                continue;
+            }
+            let (x, y) = (center + x * scale, center + y * scale);
+            if let Some(rect) = Rect::from_xywh(x - DOT / 2.0, y - DOT / 2.0, DOT, DOT) {
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+        }
+    };
+    dots(&trace.others, [120, 120, 120, 60]);
+    dots(&trace.targets, [230, 60, 60, 90]);
+
+    Ok(pixmap.encode_png()?)
+}
@@ -0,0 +1,93 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde_json::Value;
+
+use crate::numbers::NumberFormat;
+
+fn flatten(prefix: &str, value: &Value, numbers: NumberFormat, fields: &mut Vec<(String, String)>) {
+    let path = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{prefix}.{key}")
+        }
+    };
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                flatten(&path(key), value, numbers, fields);
+            }
+        }
+        Value::Array(items) => {
+            for (i, value) in items.iter().enumerate() {
+                flatten(&path(&i.to_string()), value, numbers, fields);
+            }
+        }
+        Value::String(s) => fields.push((prefix.to_string(), s.clone())),
+        Value::Null => fields.push((prefix.to_string(), String::new())),
+        Value::Number(n) => fields.push((prefix.to_string(), numbers.number(&n.to_string()))),
+        value => fields.push((prefix.to_string(), value.to_string())),
+    }
+}
+
+fn escape(field: &str, separator: char) -> String {
+    if field.contains([separator, '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Flattens structured output into CSV. Every entry of the top level map becomes a row, or one
+/// row per element if it is a list, with the key in the `name` column. Nested fields become
+/// columns named by their path, like `pos.x.bits`. The tags are added as columns to every row.
+pub fn to_csv(value: &Value, numbers: NumberFormat, tags: &BTreeMap<String, String>) -> String {
+    let separator = numbers.csv_separator();
+    let mut rows = Vec::new();
+    let mut row = |name: Option<&str>, value: &Value| {
+        let mut fields: Vec<(String, String)> =
+            tags.iter().map(|(k, v)| (k.clone(), v.clone())).collect();
+        if let Some(name) = name {
+            fields.push((String::from("name"), name.to_string()));
+        }
+        flatten("", value, numbers, &mut fields);
+        rows.push(fields);
+    };
+    match value {
+        Value::Object(map) => {
+            for (name, value) in map {
+                match value {
+                    Value::Array(items) => items.iter().for_each(|i| row(Some(name), i)),
+                    value => row(Some(name), value),
+                }
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|i| row(None, i)),
+        value => row(None, value),
+    }
+
+    // Columns in the order they first show up, rows without a field leave it empty
+    let mut columns: Vec<&str> = Vec::new();
+    let mut index = HashMap::new();
+    for fields in &rows {
+        for (column, _) in fields {
+            index.entry(column.as_str()).or_insert_with(|| {
+                columns.push(column);
+                columns.len() - 1
+            });
+        }
+    }
+    let mut lines = vec![columns
+        .iter()
+        .map(|c| escape(c, separator))
+        .collect::<Vec<_>>()
+        .join(&separator.to_string())];
+    for fields in &rows {
+        let mut line = vec![String::new(); columns.len()];
+        for (column, value) in fields {
+            line[index[column.as_str()]] = escape(value, separator);
+        }
+        lines.push(line.join(&separator.to_string()));
+    }
+    lines.join("\n")
+}
@@ -1,17 +1,156 @@
-use serde::Serialize;
-use twsnap::{enums, items::Tee};
+use std::cell::Cell;
+
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize, Serializer};
+use twsnap::{
+    enums,
+    items::{Player, Tee},
+};
 
 use fixed::types::{I24F8, I27F5};
 pub type PositionPrecision = I27F5;
 pub type VelocityPrecision = I24F8;
 pub type AnglePrecision = I24F8;
 
-#[derive(Debug, Clone, Serialize)]
+/// The tick rate of vanilla and DDNet servers.
+pub const DEFAULT_TICK_RATE: i32 = 50;
+
+/// Demos don't store the tick rate, but the length in the header is calculated by the
+/// recording client with its own tick rate. Comparing it against the ticks actually recorded
+/// recovers the rate of clients that don't run at the default.
+pub fn detect_tick_rate(length_seconds: i32, first_tick: i32, last_tick: i32) -> i32 {
+    // Short demos don't have enough precision
+    if length_seconds < 10 || last_tick <= first_tick {
+        return DEFAULT_TICK_RATE;
+    }
+    // The length is rounded down to full seconds, so the real rate lies in this range
+    let ticks = (last_tick - first_tick) as f32;
+    let (min, max) = (
+        ticks / (length_seconds + 1) as f32,
+        ticks / length_seconds as f32,
+    );
+    if (min..=max).contains(&(DEFAULT_TICK_RATE as f32)) {
+        DEFAULT_TICK_RATE
+    } else {
+        (ticks / (length_seconds as f32 + 0.5)).round() as i32
+    }
+}
+
+/// How the fixed point numbers are serialized, used for the JSON schema.
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct FixedBits {
+    bits: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, JsonSchema)]
 pub struct Position {
+    #[schemars(with = "FixedBits")]
     pub x: PositionPrecision,
+    #[schemars(with = "FixedBits")]
     pub y: PositionPrecision,
 }
 
+/// World units, which are pixels at the default zoom, in a tile.
+pub const PIXELS_PER_TILE: f32 = 32.0;
+
+/// How positions are written in structured outputs.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Units {
+    /// The bits of the fixed point numbers, as stored in the demo
+    #[default]
+    Raw,
+    Tiles,
+    /// World units, 32 to a tile
+    Pixels,
+}
+
+thread_local! {
+    static UNITS: Cell<Units> = const { Cell::new(Units::Raw) };
+}
+
+impl Units {
+    /// Serializes positions in these units while `f` runs, they are raw outside of it.
+    pub fn scope<R>(self, f: impl FnOnce() -> R) -> R {
+        let previous = UNITS.replace(self);
+        let result = f();
+        UNITS.set(previous);
+        result
+    }
+
+    /// A coordinate given as the bits of its fixed point number in these units.
+    pub fn convert(self, bits: i32) -> f32 {
+        let tiles = PositionPrecision::from_bits(bits).to_num::<f32>();
+        match self {
+            Units::Raw => bits as f32,
+            Units::Tiles => tiles,
+            Units::Pixels => tiles * PIXELS_PER_TILE,
+        }
+    }
+
+    /// The bits of the fixed point number of a coordinate written in these units.
+    pub fn bits(self, value: f64) -> i32 {
+        let tiles = match self {
+            Units::Raw => return value.round() as i32,
+            Units::Tiles => value,
+            Units::Pixels => value / PIXELS_PER_TILE as f64,
+        };
+        PositionPrecision::from_num(tiles).to_bits()
+    }
+}
+
+impl Position {
+    pub fn from_tiles(x: f32, y: f32) -> Self {
+        Self {
+            x: PositionPrecision::from_num(x),
+            y: PositionPrecision::from_num(y),
+        }
+    }
+
+    pub fn tiles(&self) -> (f32, f32) {
+        (self.x.to_num(), self.y.to_num())
+    }
+
+    /// Distance in tiles
+    pub fn distance(&self, other: &Position) -> f32 {
+        let dx = (other.x - self.x).to_num::<f32>();
+        let dy = (other.y - self.y).to_num::<f32>();
+        dx.hypot(dy)
+    }
+}
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        #[derive(Serialize)]
+        #[serde(rename = "Position")]
+        struct Raw<'a> {
+            x: &'a PositionPrecision,
+            y: &'a PositionPrecision,
+        }
+
+        #[derive(Serialize)]
+        #[serde(rename = "Position")]
+        struct Converted {
+            x: f32,
+            y: f32,
+        }
+
+        match UNITS.get() {
+            Units::Raw => Raw {
+                x: &self.x,
+                y: &self.y,
+            }
+            .serialize(serializer),
+            units => Converted {
+                x: units.convert(self.x.to_bits()),
+                y: units.convert(self.y.to_bits()),
+            }
+            .serialize(serializer),
+        }
+    }
+}
+
 impl From<twsnap::Position> for Position {
     fn from(value: twsnap::Position) -> Self {
         Self {
@@ -21,9 +160,11 @@ impl From<twsnap::Position> for Position {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Velocity {
+    #[schemars(with = "FixedBits")]
     pub x: VelocityPrecision,
+    #[schemars(with = "FixedBits")]
     pub y: VelocityPrecision,
 }
 
@@ -36,7 +177,7 @@ impl From<twsnap::Velocity> for Velocity {
     }
 }
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum Direction {
     Left,
     None,
@@ -53,7 +194,7 @@ impl From<enums::Direction> for Direction {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum HookState {
     Retracted,
     Idle,
@@ -64,6 +205,20 @@ pub enum HookState {
     Grabbed,
 }
 
+impl HookState {
+    pub fn pressed(&self) -> bool {
+        match self {
+            HookState::Retracted => false,
+            HookState::Idle => false,
+            HookState::RetractStart => false,
+            HookState::Retracting => false,
+            HookState::RetractEnd => false,
+            HookState::Flying => true,
+            HookState::Grabbed => true,
+        }
+    }
+}
+
 impl From<enums::HookState> for HookState {
     fn from(value: enums::HookState) -> Self {
         match value {
@@ -78,7 +233,9 @@ impl From<enums::HookState> for HookState {
     }
 }
 
-#[derive(Serialize)]
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize, JsonSchema,
+)]
 pub enum ActiveWeapon {
     Hammer,
     Pistol,
@@ -101,7 +258,7 @@ impl From<enums::ActiveWeapon> for ActiveWeapon {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
 pub enum Emote {
     Normal,
     Pain,
@@ -124,12 +281,13 @@ impl From<enums::Emote> for Emote {
     }
 }
 
-#[derive(Serialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
 pub struct Inputs {
     pub tick: i32,
     pub pos: Position,
     pub vel: Velocity,
 
+    #[schemars(with = "FixedBits")]
     pub angle: AnglePrecision,
     pub direction: Direction,
 
@@ -154,12 +312,32 @@ pub struct Inputs {
     pub jumped_total: i32,
     pub ninja_activation_tick: i32,
     pub target: Position,
+
+    // PlayerInfo
+    pub score: i32,
+}
+
+impl Inputs {
+    /// Moves every tick of the sample by the offset, durations stay as they are.
+    pub fn shift_ticks(&mut self, offset: i32) {
+        self.tick += offset;
+        // Zero and below aren't ticks but flags, like no attack yet or -1 for deep freeze
+        for tick in [
+            &mut self.attack_tick,
+            &mut self.freeze_end,
+            &mut self.ninja_activation_tick,
+        ] {
+            if *tick > 0 {
+                *tick += offset;
+            }
+        }
+    }
 }
 
-impl From<&Tee> for Inputs {
-    fn from(value: &Tee) -> Self {
+impl From<(&Player, &Tee)> for Inputs {
+    fn from((player, value): (&Player, &Tee)) -> Self {
         Self {
-            tick: (value.tick.seconds() * 50.0) as i32,
+            tick: value.tick.snap_tick(),
             pos: value.pos.into(),
             vel: value.vel.into(),
             angle: value.angle,
@@ -173,14 +351,15 @@ impl From<&Tee> for Inputs {
             ammo_count: value.ammo_count,
             weapon: value.weapon.into(),
             emote: value.emote.into(),
-            attack_tick: (value.attack_tick.seconds() * 50.0) as i32,
-            freeze_end: (value.freeze_end.seconds() * 50.0) as i32,
+            attack_tick: value.attack_tick.snap_tick(),
+            freeze_end: value.freeze_end.snap_tick(),
             jumps: value.jumps,
             tele_checkpoint: value.tele_checkpoint,
             strong_weak_id: value.strong_weak_id,
             jumped_total: value.jumped_total,
-            ninja_activation_tick: (value.ninja_activation_tick.seconds() * 50.0) as i32,
+            ninja_activation_tick: value.ninja_activation_tick.snap_tick(),
             target: value.target.into(),
+            score: player.score,
         }
     }
 }
@@ -0,0 +1,197 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    data::{Inputs, Position, PIXELS_PER_TILE},
+    gaps::{usual_step, GAP_STEPS},
+    map::Map,
+    timeline::{Timeline, Timestamp},
+    tunes::{self, Tuning},
+};
+
+/// How far the tee can end up from where its velocity would have taken it before it counts
+/// as a desync, in tiles. Walls and hooks stop a tee well within this.
+const DESYNC_TILES: f32 = 2.0;
+/// How close to a teleporter or spawn point either end of a jump has to be, in tiles.
+const RADIUS_TILES: f32 = 2.0;
+/// A tee that ends up back on its old path within this long was put back by the server.
+const SNAP_BACK_SECONDS: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, JsonSchema)]
+pub enum DesyncKind {
+    /// A jump between two snapshots in a row that nothing on the map explains
+    Warp,
+    /// Snapshots went missing in between, or the tee was put back on its old path shortly
+    /// after, like the server correcting a lagging client
+    Lag,
+    /// Started or ended at a teleporter of the map, only known with a DDNet map
+    Teleporter,
+    /// Ended at a spawn point, or where the tee first appeared without the map
+    Respawn,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Desync {
+    pub kind: DesyncKind,
+    /// The last sample before the jump
+    pub start: Timestamp,
+    /// The first sample after the jump
+    pub end: Timestamp,
+    /// How far the tee moved, in tiles
+    pub distance: f32,
+    /// How far from where its velocity would have taken it the tee ended up, in tiles
+    pub error: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct DesyncStats {
+    pub counts: BTreeMap<DesyncKind, usize>,
+    pub desyncs: Vec<Desync>,
+    /// Whether the map had a teleporter layer to tell teleporters apart from warps
+    pub teleporters_known: bool,
+}
+
+/// The velocity ramp of the server, which slows down how far fast tees move per tick.
+#[derive(Debug, Clone, Copy)]
+struct Velramp {
+    start: f32,
+    range: f32,
+    curvature: f32,
+}
+
+impl Velramp {
+    /// The ramp in effect at the tick, the default tunings where the demo has none.
+    fn at(tunes: &[Tuning], tick: i32) -> Self {
+        let value = |name, default| tunes::value_at(tunes, name, tick).unwrap_or(default);
+        Self {
+            start: value("velramp_start", 550.0),
+            range: value("velramp_range", 2000.0),
+            curvature: value("velramp_curvature", 1.4),
+        }
+    }
+
+    /// The share of the velocity the tee moves by, like `VelocityRamp` of the game with the
+    /// speed in units per second.
+    fn factor(self, speed: f32) -> f32 {
+        if speed < self.start {
+            1.0
+        } else {
+            1.0 / self.curvature.powf((speed - self.start) / self.range)
+        }
+    }
+}
+
+/// Where the tee would be after the ticks with the average velocity of both samples.
+fn predict(from: &Inputs, to: &Inputs, tunes: &[Tuning], tick_rate: i32) -> Position {
+    let ticks = (to.tick - from.tick) as f32;
+    let average = |a: f32, b: f32| (a + b) / 2.0;
+    let vx = average(from.vel.x.to_num(), to.vel.x.to_num());
+    let vy = average(from.vel.y.to_num(), to.vel.y.to_num());
+    let ramp = Velramp::at(tunes, from.tick).factor(vx.hypot(vy) * tick_rate as f32);
+    let (x, y) = from.pos.tiles();
+    Position::from_tiles(
+        x + vx * ramp * ticks / PIXELS_PER_TILE,
+        y + vy * ramp * ticks / PIXELS_PER_TILE,
+    )
+}
+
+fn classify(
+    inputs: &[Inputs],
+    i: usize,
+    map: Option<&Map>,
+    tunes: &[Tuning],
+    step: i32,
+    tick_rate: i32,
+) -> DesyncKind {
+    let (prev, cur) = (&inputs[i - 1], &inputs[i]);
+    let ((from_x, from_y), (to_x, to_y)) = (prev.pos.tiles(), cur.pos.tiles());
+    if let Some(map) = map {
+        if map.near_teleporter(from_x, from_y, RADIUS_TILES)
+            || map.near_teleporter(to_x, to_y, RADIUS_TILES)
+        {
+            return DesyncKind::Teleporter;
+        }
+    }
+    let respawned = match map {
+        Some(map) => map.near_spawn(to_x, to_y, RADIUS_TILES),
+        None => inputs[0].pos.distance(&cur.pos) < RADIUS_TILES,
+    };
+    if respawned {
+        return DesyncKind::Respawn;
+    }
+    let snap_back = (SNAP_BACK_SECONDS * tick_rate as f32) as i32;
+    let put_back = inputs[i + 1..]
+        .iter()
+        .take_while(|later| later.tick - cur.tick <= snap_back)
+        .any(|later| predict(prev, later, tunes, tick_rate).distance(&later.pos) < DESYNC_TILES);
+    if cur.tick - prev.tick > step * GAP_STEPS || put_back {
+        DesyncKind::Lag
+    } else {
+        DesyncKind::Warp
+    }
+}
+
+/// Compares every sample to where the one before it would have moved on its own, and sorts
+/// the jumps that don't fit by what most likely caused them. The velocity ramp comes from the
+/// `tunes` of the demo.
+pub fn detect_desyncs(
+    inputs: &[Inputs],
+    map: Option<&Map>,
+    tunes: &[Tuning],
+    timeline: &Timeline,
+) -> DesyncStats {
+    let step = usual_step(inputs.iter().map(|i| i.tick)).unwrap_or(1);
+    let desyncs: Vec<Desync> = (1..inputs.len())
+        .filter_map(|i| {
+            let (prev, cur) = (&inputs[i - 1], &inputs[i]);
+            // The corner of every map is solid, a tee there hasn't been placed yet
+            if [prev, cur].iter().any(|s| s.pos.x == 0 && s.pos.y == 0) {
+                return None;
+            }
+            let error = predict(prev, cur, tunes, timeline.tick_rate).distance(&cur.pos);
+            (error > DESYNC_TILES).then(|| Desync {
+                kind: classify(inputs, i, map, tunes, step, timeline.tick_rate),
+                start: timeline.timestamp(prev.tick),
+                end: timeline.timestamp(cur.tick),
+                distance: prev.pos.distance(&cur.pos),
+                error,
+            })
+        })
+        .collect();
+
+    let mut counts = BTreeMap::new();
+    for desync in &desyncs {
+        *counts.entry(desync.kind).or_insert(0) += 1;
+    }
+    DesyncStats {
+        counts,
+        desyncs,
+        teleporters_known: map.is_some_and(|m| m.tele.is_some()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stringlit::s;
+
+    use super::*;
+
+    #[test]
+    fn ramps_velocity_with_the_tunings() {
+        let default = Velramp::at(&[], 0);
+        assert_eq!(default.factor(500.0), 1.0);
+        assert!((default.factor(2550.0) - 1.0 / 1.4).abs() < 1e-6);
+
+        let tunes = [Tuning {
+            at: Timeline::default().timestamp(0),
+            params: [(s!("velramp_start"), 100.0), (s!("velramp_curvature"), 2.0)]
+                .into_iter()
+                .collect(),
+        }];
+        let tuned = Velramp::at(&tunes, 10);
+        assert_eq!(tuned.factor(50.0), 1.0);
+        assert!((tuned.factor(2100.0) - 0.5).abs() < 1e-6);
+    }
+}
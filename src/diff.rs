@@ -0,0 +1,370 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use stringlit::s;
+
+use crate::{
+    changes::InputChanges,
+    compare::{Divergence, DIVERGENCE_DISTANCE},
+    data::{Inputs, Units},
+    pace, runs,
+};
+
+/// The players of an extract output, with or without --changes-only.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Extracted {
+    Samples(Vec<Inputs>),
+    Changes(InputChanges),
+}
+
+/// The field name as extract writes it without `--key-style`.
+fn snake_case(key: &str) -> String {
+    let mut result = String::with_capacity(key.len() + 4);
+    for c in key.chars() {
+        match c {
+            '-' => result.push('_'),
+            c if c.is_uppercase() => {
+                result.push('_');
+                result.extend(c.to_lowercase());
+            }
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Renames the fields back to snake case and turns positions written with `--units` back into
+/// the bits of their fixed point numbers, setting `converted` if there were any.
+fn normalize(value: Value, units: Units, converted: &mut bool) -> Value {
+    match value {
+        Value::Array(values) => Value::Array(
+            values
+                .into_iter()
+                .map(|v| normalize(v, units, converted))
+                .collect(),
+        ),
+        Value::Object(fields) => {
+            // Velocities and raw positions are `{"bits": ..}`, only converted positions are numbers
+            if let (2, Some(Value::Number(x)), Some(Value::Number(y))) =
+                (fields.len(), fields.get("x"), fields.get("y"))
+            {
+                *converted = true;
+                let bits = |n: &serde_json::Number| {
+                    let mut bits = Map::new();
+                    bits.insert(
+                        s!("bits"),
+                        units.bits(n.as_f64().unwrap_or_default()).into(),
+                    );
+                    Value::Object(bits)
+                };
+                let mut position = Map::new();
+                position.insert(s!("x"), bits(x));
+                position.insert(s!("y"), bits(y));
+                return Value::Object(position);
+            }
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(k, v)| (snake_case(&k), normalize(v, units, converted)))
+                    .collect(),
+            )
+        }
+        value => value,
+    }
+}
+
+/// Reads the json output of extract, also with `--tag`, `--key-style` or `--units`. The units
+/// can't be told from the output, so they have to be given like they were to extract.
+pub fn read_extraction(path: &Path, units: Units) -> anyhow::Result<HashMap<String, Vec<Inputs>>> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Couldn't read {path:?}"))?;
+    let mut value: Value = serde_json::from_str(&text)
+        .with_context(|| format!("{path:?} isn't a json output of extract"))?;
+    // With --tag the players are under `data`, next to the tags
+    if let Some(object) = value.as_object_mut() {
+        if object.get("tags").is_some_and(Value::is_object) && object.contains_key("data") {
+            value = object.remove("data").unwrap_or_default();
+        }
+    }
+    let Value::Object(players) = value else {
+        bail!("{path:?} isn't a json output of extract");
+    };
+    let mut converted = false;
+    let players: Map<String, Value> = players
+        .into_iter()
+        .map(|(name, inputs)| (name, normalize(inputs, units, &mut converted)))
+        .collect();
+    if converted && units == Units::Raw {
+        bail!("{path:?} was extracted with --units tiles or pixels, pass the same --units to diff");
+    }
+    let players: HashMap<String, Extracted> = serde_json::from_value(Value::Object(players))
+        .with_context(|| format!("{path:?} isn't a json output of extract"))?;
+    Ok(players
+        .into_iter()
+        .map(|(name, extracted)| match extracted {
+            Extracted::Samples(samples) => (name, samples),
+            Extracted::Changes(changes) => (name, changes.to_vec()),
+        })
+        .collect())
+}
+
+/// The samples of the player, or of the only player if none is given.
+pub fn select(
+    mut players: HashMap<String, Vec<Inputs>>,
+    player: Option<&str>,
+) -> Option<(String, Vec<Inputs>)> {
+    match player {
+        Some(player) => players.remove_entry(player),
+        None if players.len() == 1 => players.into_iter().next(),
+        None => None,
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SectionDiff {
+    /// Where the section starts, in tiles along the old route
+    pub start: f32,
+    /// Seconds since the start of the run when entering the section, none if never reached
+    pub old: Option<f32>,
+    pub new: Option<f32>,
+    pub delta: Option<f32>,
+    /// Farthest the new route got from the old one in the section, in tiles
+    pub max_offset: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RouteDiff {
+    pub player: String,
+    /// Whether the fastest finished run was compared, otherwise all samples are
+    pub old_finished: bool,
+    pub new_finished: bool,
+    pub old_time: f32,
+    pub new_time: f32,
+    pub time_delta: f32,
+    pub section_length: f32,
+    pub sections: Vec<SectionDiff>,
+    /// Where the new run first left the old route
+    pub divergence: Option<Divergence>,
+}
+
+fn elapsed(samples: &[Inputs], tick: i32, tick_rate: i32) -> f32 {
+    (tick - samples[0].tick) as f32 / tick_rate as f32
+}
+
+/// Compares the fastest runs of the player in two extractions by how far along the old route
+/// they are, not by tick, so a changed part of the map only shows up where it is.
+pub fn diff(
+    player: &str,
+    old: Vec<Inputs>,
+    new: Vec<Inputs>,
+    section_length: f32,
+    tick_rate: i32,
+) -> Option<RouteDiff> {
+    let best = |inputs: Vec<Inputs>| match runs::best_run(inputs.clone(), tick_rate) {
+        Some(run) => (run, true),
+        None => (inputs, false),
+    };
+    let (old, old_finished) = best(old);
+    let (new, new_finished) = best(new);
+    let (old_last, new_last) = (old.last()?, new.last()?);
+
+    let travelled = pace::travelled(&old);
+    let sections = (travelled.last().copied().unwrap_or_default() / section_length).ceil() as usize;
+    let matches = pace::matches(&old, &new);
+    let progress: Vec<f32> = matches.iter().map(|i| travelled[*i]).collect();
+    let old_entries = pace::section_entries(&old, &travelled, sections, section_length);
+    let new_entries = pace::section_entries(&new, &progress, sections, section_length);
+
+    let mut max_offsets = vec![0.0f32; sections];
+    for (sample, i) in new.iter().zip(&matches) {
+        let section = ((travelled[*i] / section_length) as usize).min(sections.saturating_sub(1));
+        if let Some(max) = max_offsets.get_mut(section) {
+            *max = max.max(sample.pos.distance(&old[*i].pos));
+        }
+    }
+    let sections = old_entries
+        .iter()
+        .zip(&new_entries)
+        .zip(max_offsets)
+        .enumerate()
+        .map(|(section, ((old_entry, new_entry), max_offset))| {
+            let old_time = old_entry.map(|t| elapsed(&old, t, tick_rate));
+            let new_time = new_entry.map(|t| elapsed(&new, t, tick_rate));
+            SectionDiff {
+                start: section as f32 * section_length,
+                old: old_time,
+                new: new_time,
+                delta: new_time.zip(old_time).map(|(new, old)| new - old),
+                max_offset,
+            }
+        })
+        .collect();
+
+    let divergence = new
+        .iter()
+        .zip(&matches)
+        .find(|(sample, i)| sample.pos.distance(&old[**i].pos) > DIVERGENCE_DISTANCE)
+        .map(|(sample, _)| Divergence {
+            time: elapsed(&new, sample.tick, tick_rate),
+            x: sample.pos.x.to_num(),
+            y: sample.pos.y.to_num(),
+        });
+
+    let old_time = elapsed(&old, old_last.tick, tick_rate);
+    let new_time = elapsed(&new, new_last.tick, tick_rate);
+    Some(RouteDiff {
+        player: player.to_string(),
+        old_finished,
+        new_finished,
+        old_time,
+        new_time,
+        time_delta: new_time - old_time,
+        section_length,
+        sections,
+        divergence,
+    })
+}
+
+pub fn plain_report(diff: &RouteDiff) -> String {
+    let run = |time: f32, finished: bool| {
+        if finished {
+            format!("{time:.2}s")
+        } else {
+            format!("{time:.2}s (no finished run)")
+        }
+    };
+    let mut vec = Vec::new();
+    vec.push(format!("{:=^44}", format!(" {} ", diff.player)));
+    vec.push(s!(""));
+    vec.push(format!(
+        "Old ....... : {}",
+        run(diff.old_time, diff.old_finished)
+    ));
+    vec.push(format!(
+        "New ....... : {}",
+        run(diff.new_time, diff.new_finished)
+    ));
+    vec.push(format!("Difference  : {:+.2}s", diff.time_delta));
+    vec.push(s!(""));
+    vec.push(format!("{:-^44}", " Sections "));
+    vec.push(s!(""));
+    for section in &diff.sections {
+        let time = |t: Option<f32>| t.map_or(s!("-"), |t| format!("{t:.2}s"));
+        let delta = section.delta.map_or(s!("-"), |d| format!("{d:+.2}s"));
+        vec.push(format!(
+            "{:>6.0} tiles : {:>8} {:>8} {delta:>8}  off by {:.1} tiles",
+            section.start,
+            time(section.old),
+            time(section.new),
+            section.max_offset
+        ));
+    }
+    vec.push(s!(""));
+    match &diff.divergence {
+        Some(d) => vec.push(format!(
+            "Diverges ... : at {:.2}s ({:.1}, {:.1})",
+            d.time, d.x, d.y
+        )),
+        None => vec.push(s!("Diverges ... : never")),
+    }
+    vec.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{KeyStyle, Keys, Style, Tagged};
+    use fixed::types::{I24F8, I27F5};
+    use std::collections::BTreeMap;
+    use twsnap::{
+        enums,
+        items::{Player, Tee},
+        time::{Duration, Instant},
+    };
+
+    fn sample(tick: i32, x: f32) -> Inputs {
+        let tee = Tee {
+            tick: Instant::zero() + Duration::from_ticks(tick),
+            pos: twsnap::Position::new(I27F5::from_num(x), I27F5::from_num(-2.5)),
+            vel: twsnap::Velocity::new(I24F8::from_num(1.25), I24F8::ZERO),
+            hook_state: enums::HookState::from(1),
+            ..Default::default()
+        };
+        (&Player::default(), &tee).into()
+    }
+
+    fn samples() -> Vec<Inputs> {
+        (0..5)
+            .map(|tick| sample(tick, 10.0 + tick as f32 / 4.0))
+            .collect()
+    }
+
+    /// Writes the inputs like extract does with the options and reads them back.
+    fn round_trip<T: Serialize>(
+        name: &str,
+        inputs: &T,
+        keys: &Keys,
+        units: Units,
+    ) -> anyhow::Result<HashMap<String, Vec<Inputs>>> {
+        let players = HashMap::from([(s!("nameless"), inputs)]);
+        let path = std::env::temp_dir().join(format!("{}-{name}.json", std::process::id()));
+        std::fs::write(
+            &path,
+            serde_json::to_string(&Tagged(&players, keys)).unwrap(),
+        )
+        .unwrap();
+        let players = read_extraction(&path, units);
+        std::fs::remove_file(&path).unwrap();
+        players
+    }
+
+    fn keys(keys: KeyStyle, units: Units, tags: &[(&str, &str)]) -> Keys {
+        Keys {
+            style: Style { keys, units },
+            tags: tags
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect::<BTreeMap<_, _>>(),
+            select: None,
+        }
+    }
+
+    #[test]
+    fn reads_every_option_of_extract() {
+        let samples = samples();
+        let changes: InputChanges = samples.iter().cloned().collect();
+        for (name, keys, units) in [
+            ("plain", keys(KeyStyle::Snake, Units::Raw, &[]), Units::Raw),
+            (
+                "tagged",
+                keys(KeyStyle::Snake, Units::Raw, &[("map", "Multeasymap")]),
+                Units::Raw,
+            ),
+            ("camel", keys(KeyStyle::Camel, Units::Raw, &[]), Units::Raw),
+            (
+                "tiles",
+                keys(KeyStyle::Snake, Units::Tiles, &[]),
+                Units::Tiles,
+            ),
+            (
+                "everything",
+                keys(KeyStyle::Kebab, Units::Pixels, &[("a", "b")]),
+                Units::Pixels,
+            ),
+        ] {
+            let players = round_trip(name, &samples, &keys, units).unwrap();
+            assert_eq!(players["nameless"], samples, "{name}");
+            let players = round_trip(name, &changes, &keys, units).unwrap();
+            assert_eq!(players["nameless"], samples, "{name} with --changes-only");
+        }
+    }
+
+    #[test]
+    fn needs_the_units_positions_were_written_in() {
+        let tiles = keys(KeyStyle::Snake, Units::Tiles, &[]);
+        let error = round_trip("units", &samples(), &tiles, Units::Raw).unwrap_err();
+        assert!(error.to_string().contains("--units"), "{error}");
+    }
+}
@@ -0,0 +1,128 @@
+use std::io::Cursor;
+
+use serenity::{
+    all::{
+        ChannelId, Context, CreateAttachment, CreateMessage, EventHandler, GatewayIntents, Message,
+    },
+    async_trait, Client,
+};
+use twsnap::compat::ddnet::DemoReader;
+
+use crate::{chart, serve::WORKER_STACK_SIZE, template, timeline::TimeFormat};
+
+/// Demos bigger than this are ignored, in bytes.
+const MAX_DEMO_SIZE: u32 = 64 * 1024 * 1024;
+/// Discord doesn't allow longer messages, longer reports are attached as file instead.
+const MAX_MESSAGE_LENGTH: usize = 2000;
+
+struct Handler {
+    /// Channels to listen in, all channels the bot can see if empty
+    channels: Vec<ChannelId>,
+}
+
+struct Report {
+    text: String,
+    chart: Vec<u8>,
+    players: Vec<String>,
+}
+
+fn report(demo: Vec<u8>) -> anyhow::Result<Report> {
+    let (changes, timeline) = crate::read_changes(
+        DemoReader::new(Cursor::new(demo))?,
+        &Default::default(),
+        None,
+    );
+    let stats = crate::analyze_inputs(&changes, &timeline, Default::default());
+    let inputs = changes.into_iter().map(|(n, c)| (n, c.to_vec())).collect();
+    let (chart, players) = chart::render_activity(&inputs, timeline.tick_rate)?;
+    let options = template::ReportOptions {
+        timeline: &timeline,
+        time_format: TimeFormat::default(),
+        lang: Default::default(),
+        numbers: Default::default(),
+        palette: Default::default(),
+    };
+    Ok(Report {
+        text: crate::plain_report(template::DEFAULT, &(), &stats, None, options)?,
+        chart,
+        players,
+    })
+}
+
+impl Handler {
+    async fn analyze_attachments(&self, ctx: &Context, msg: &Message) -> anyhow::Result<()> {
+        let demos = msg
+            .attachments
+            .iter()
+            .filter(|a| a.filename.ends_with(".demo") && a.size <= MAX_DEMO_SIZE);
+        for attachment in demos {
+            let demo = attachment.download().await?;
+            let report = tokio::task::spawn_blocking(move || report(demo)).await?;
+            let message = match report {
+                Ok(report) => {
+                    let legend = report
+                        .players
+                        .iter()
+                        .zip(chart::COLORS)
+                        .map(|(name, (color, _))| format!("{color}: {name}"))
+                        .collect::<Vec<_>>()
+                        .join(", ");
+                    let header = format!(
+                        "**{}**\nInput changes per second ({legend})",
+                        attachment.filename
+                    );
+                    let message = CreateMessage::new()
+                        .add_file(CreateAttachment::bytes(report.chart, "activity.png"));
+                    let inline = format!("{header}\n```\n{}\n```", report.text);
+                    if inline.len() <= MAX_MESSAGE_LENGTH {
+                        message.content(inline)
+                    } else {
+                        message
+                            .content(header)
+                            .add_file(CreateAttachment::bytes(report.text, "report.txt"))
+                    }
+                }
+                Err(e) => CreateMessage::new()
+                    .content(format!("Couldn't analyze {}: {e}", attachment.filename)),
+            };
+            msg.channel_id
+                .send_message(&ctx.http, message.reference_message(msg))
+                .await?;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventHandler for Handler {
+    async fn message(&self, ctx: Context, msg: Message) {
+        if msg.author.bot || !(self.channels.is_empty() || self.channels.contains(&msg.channel_id))
+        {
+            return;
+        }
+        if let Err(e) = self.analyze_attachments(&ctx, &msg).await {
+            eprintln!("Couldn't answer message {}: {e}", msg.id);
+        }
+    }
+}
+
+/// Runs a Discord bot that analyzes every demo posted in the given channels.
+pub fn run(token: &str, channels: &[u64]) -> anyhow::Result<()> {
+    let handler = Handler {
+        channels: channels.iter().map(|c| ChannelId::new(*c)).collect(),
+    };
+    let intents = GatewayIntents::GUILD_MESSAGES
+        | GatewayIntents::DIRECT_MESSAGES
+        | GatewayIntents::MESSAGE_CONTENT;
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .thread_stack_size(WORKER_STACK_SIZE)
+        .build()?
+        .block_on(async {
+            let mut client = Client::builder(token, intents)
+                .event_handler(handler)
+                .await?;
+            client.start().await?;
+            Ok(())
+        })
+}
@@ -0,0 +1,83 @@
+use serde::Serialize;
+
+const PERCENTILES: [f32; 7] = [0.1, 0.25, 0.5, 0.75, 0.9, 0.95, 0.99];
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Percentile {
+    pub percentile: f32,
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Bucket {
+    pub from: f32,
+    pub to: f32,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Distribution {
+    pub metric: String,
+    /// Number of players, every player of every demo counts separately
+    pub count: usize,
+    pub min: f32,
+    pub max: f32,
+    pub mean: f32,
+    pub percentiles: Vec<Percentile>,
+    pub histogram: Vec<Bucket>,
+}
+
+/// Looks up a metric in the serialized stats of a player, nested fields are separated by dots.
+pub fn lookup(stats: &serde_json::Value, metric: &str) -> Option<f32> {
+    let value = metric
+        .split('.')
+        .try_fold(stats, |value, key| value.get(key))?;
+    match value {
+        serde_json::Value::Bool(b) => Some(*b as u8 as f32),
+        value => value.as_f64().map(|v| v as f32),
+    }
+}
+
+pub fn distribution(metric: &str, mut values: Vec<f32>, buckets: usize) -> Option<Distribution> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    let (min, max) = (values[0], values[values.len() - 1]);
+    let buckets = buckets.max(1);
+    let width = (max - min) / buckets as f32;
+
+    let mut histogram: Vec<Bucket> = (0..buckets)
+        .map(|i| Bucket {
+            from: min + width * i as f32,
+            to: min + width * (i + 1) as f32,
+            count: 0,
+        })
+        .collect();
+    for value in &values {
+        let index = if width > 0.0 {
+            ((value - min) / width) as usize
+        } else {
+            0
+        };
+        histogram[index.min(buckets - 1)].count += 1;
+    }
+
+    let percentiles = PERCENTILES
+        .into_iter()
+        .map(|p| Percentile {
+            percentile: p * 100.0,
+            value: values[((values.len() - 1) as f32 * p).round() as usize],
+        })
+        .collect();
+
+    Some(Distribution {
+        metric: metric.to_string(),
+        count: values.len(),
+        min,
+        max,
+        mean: values.iter().sum::<f32>() / values.len() as f32,
+        percentiles,
+        histogram,
+    })
+}
@@ -0,0 +1,81 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    data::Inputs,
+    players::LEAVE_SECONDS,
+    runs::{RunEnd, Runs},
+    timeline::{Timeline, Timestamp},
+};
+
+/// Why a run didn't finish. Kill messages aren't in the demo, so this is told apart from the
+/// state of the tee right before it vanished.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum DnfReason {
+    /// Died while frozen, where killing is the only way out without a teammate
+    KillBind,
+    /// Died while able to move, like on a death tile or by falling out of the map. Kill
+    /// binds used before getting frozen end up here as well
+    Hazard,
+    /// Left the snapshots for longer than three seconds without finishing
+    Disconnect,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct AbandonedRun {
+    /// Index of the run as reported by the run segmentation
+    pub run: usize,
+    pub reason: DnfReason,
+    /// The last sample of the run
+    pub at: Timestamp,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct DnfStats {
+    pub kill_bind: usize,
+    pub hazard: usize,
+    pub disconnect: usize,
+    pub abandoned: Vec<AbandonedRun>,
+}
+
+/// Sorts the runs that didn't finish by why they ended. `demo_end` is the last tick any
+/// player was seen at, a run that ends there ended with the demo.
+pub fn calculate_dnf_stats(
+    inputs: &[Inputs],
+    runs: &Runs,
+    demo_end: i32,
+    timeline: &Timeline,
+) -> DnfStats {
+    let leave = LEAVE_SECONDS * timeline.tick_rate;
+    let mut stats = DnfStats::default();
+    for (index, run) in runs.runs.iter().enumerate() {
+        if run.end == RunEnd::Finish {
+            continue;
+        }
+        let next = inputs.partition_point(|i| i.tick <= run.end_tick);
+        let Some(last) = next.checked_sub(1).map(|i| &inputs[i]) else {
+            continue;
+        };
+        let back = inputs.get(next).map_or(demo_end, |i| i.tick);
+        let reason = if back - run.end_tick > leave {
+            DnfReason::Disconnect
+        } else if run.end == RunEnd::DemoEnd {
+            continue;
+        } else if last.freeze_end != 0 {
+            DnfReason::KillBind
+        } else {
+            DnfReason::Hazard
+        };
+        *match reason {
+            DnfReason::KillBind => &mut stats.kill_bind,
+            DnfReason::Hazard => &mut stats.hazard,
+            DnfReason::Disconnect => &mut stats.disconnect,
+        } += 1;
+        stats.abandoned.push(AbandonedRun {
+            run: index,
+            reason,
+            at: timeline.timestamp(last.tick),
+        });
+    }
+    stats
+}
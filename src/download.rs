@@ -0,0 +1,46 @@
+use std::io::Read;
+
+use anyhow::{bail, Context};
+use twsnap::compat::ddnet::DemoMapHash;
+
+use crate::map;
+
+const MAP_SERVER: &str = "https://maps.ddnet.org";
+
+/// Percent-encodes everything but the unreserved characters, like `EscapeUrl` of DDNet, so
+/// map names with spaces or other special characters stay one path segment.
+fn escape_url(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            b => format!("%{b:02X}"),
+        })
+        .collect()
+}
+
+/// Fetches a map by name and hash from the public DDNet map download server.
+pub fn download_map(name: &str, hash: &DemoMapHash) -> anyhow::Result<Vec<u8>> {
+    let file = match hash {
+        DemoMapHash::Sha256(sha) => {
+            let sha: String = sha.iter().map(|b| format!("{b:02x}")).collect();
+            format!("{name}_{sha}.map")
+        }
+        DemoMapHash::Crc(crc) => format!("{name}_{crc:08x}.map"),
+    };
+    let url = format!("{MAP_SERVER}/{}", escape_url(&file));
+    crate::status::note!("Downloading {url}");
+
+    let response = ureq::get(&url)
+        .call()
+        .with_context(|| format!("Couldn't download {url}"))?;
+    let mut data = Vec::new();
+    response.into_reader().read_to_end(&mut data)?;
+
+    if !map::matches_hash(&data, hash) {
+        bail!("Downloaded map doesn't match the map recorded in the demo");
+    }
+    Ok(data)
+}
@@ -0,0 +1,218 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use twsnap::{
+    compat::ddnet::{DemoChunk, DemoReader},
+    enums, items,
+    uid::PlayerUid,
+    Snap,
+};
+
+use crate::{
+    anonymize::Anonymizer,
+    data::{ActiveWeapon, Position},
+    timeline::{self, PauseMode},
+};
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Projectile {
+    pub pos: Position,
+    /// Unit vector of the direction it was fired in
+    pub direction: [f32; 2],
+    pub weapon: ActiveWeapon,
+    pub start_tick: i32,
+    /// None for projectiles the map fires
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum LaserKind {
+    Rifle,
+    Shotgun,
+    Door,
+    Freeze,
+    Dragger,
+    Gun,
+    Plasma,
+}
+
+impl From<enums::LaserType> for LaserKind {
+    fn from(value: enums::LaserType) -> Self {
+        match value {
+            enums::LaserType::Rifle => LaserKind::Rifle,
+            enums::LaserType::Shotgun => LaserKind::Shotgun,
+            enums::LaserType::Door => LaserKind::Door,
+            enums::LaserType::Freeze => LaserKind::Freeze,
+            enums::LaserType::Dragger(_) => LaserKind::Dragger,
+            enums::LaserType::Gun(_) => LaserKind::Gun,
+            enums::LaserType::Plasma => LaserKind::Plasma,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Laser {
+    pub from: Position,
+    pub to: Position,
+    pub kind: LaserKind,
+    pub start_tick: i32,
+    /// None for lasers of the map
+    pub owner: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum PickupKind {
+    Health,
+    Armor,
+    Weapon,
+    Shield,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Pickup {
+    pub pos: Position,
+    pub kind: PickupKind,
+    /// The weapon of weapon pickups and of the DDNet shields that take it away
+    pub weapon: Option<ActiveWeapon>,
+}
+
+impl From<&items::Pickup> for Pickup {
+    fn from(value: &items::Pickup) -> Self {
+        let weapon = |w: enums::CollectableWeapon| {
+            Some(match w {
+                enums::CollectableWeapon::Shotgun => ActiveWeapon::Shotgun,
+                enums::CollectableWeapon::Grenade => ActiveWeapon::Grenade,
+                enums::CollectableWeapon::Rifle => ActiveWeapon::Rifle,
+                enums::CollectableWeapon::Ninja => ActiveWeapon::Ninja,
+            })
+        };
+        let (kind, weapon) = match value.kind {
+            enums::Powerup::Health => (PickupKind::Health, None),
+            enums::Powerup::Armor => (PickupKind::Armor, None),
+            enums::Powerup::Weapon(w) => (PickupKind::Weapon, weapon(w)),
+            enums::Powerup::Shield(w) => (PickupKind::Shield, weapon(w)),
+        };
+        Self {
+            pos: value.pos.into(),
+            kind,
+            weapon,
+        }
+    }
+}
+
+/// The world state of a snapshot besides the tees.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Entities {
+    pub tick: i32,
+    pub projectiles: Vec<Projectile>,
+    pub lasers: Vec<Laser>,
+    pub pickups: Vec<Pickup>,
+}
+
+impl Entities {
+    pub fn is_empty(&self) -> bool {
+        self.projectiles.is_empty() && self.lasers.is_empty() && self.pickups.is_empty()
+    }
+
+    fn shift_ticks(&mut self, offset: i32) {
+        self.tick += offset;
+        for tick in self
+            .projectiles
+            .iter_mut()
+            .map(|p| &mut p.start_tick)
+            .chain(self.lasers.iter_mut().map(|l| &mut l.start_tick))
+        {
+            *tick += offset;
+        }
+    }
+}
+
+/// Projectiles store their direction scaled by 100 and rounded.
+fn direction(x: i32, y: i32) -> [f32; 2] {
+    let (x, y) = (x as f32, y as f32);
+    let length = x.hypot(y);
+    if length > 0.0 {
+        [x / length, y / length]
+    } else {
+        [0.0, 0.0]
+    }
+}
+
+/// Reads the projectiles, lasers and pickups of every snapshot, leaving out the snapshots
+/// without any. Pauses are cut out the same way as from the timeline of the inputs.
+pub fn read_entities(
+    mut reader: DemoReader,
+    anonymize: Option<Anonymizer>,
+    pauses: PauseMode,
+) -> Vec<Entities> {
+    let mut entities = Vec::new();
+    let mut snap = Snap::default();
+    let mut last = None;
+    let mut cut = 0;
+    while let Ok(Some(chunk)) = reader.next_chunk(&mut snap) {
+        let DemoChunk::Snapshot(tick) = chunk else {
+            continue;
+        };
+        if let Some(last) = last.replace(tick) {
+            if tick - last >= timeline::MIN_PAUSE_TICKS {
+                cut += tick - last - 1;
+            }
+        }
+        let owner = |owner: PlayerUid| {
+            let name = snap.players.get(owner.sort_id())?.name.to_string();
+            Some(match anonymize {
+                Some(anonymizer) => anonymizer.pseudonym(&name),
+                None => name,
+            })
+        };
+        let projectiles = snap
+            .projectiles
+            .values()
+            .map(|p| Projectile {
+                pos: p.pos.into(),
+                direction: direction(p.direction.x, p.direction.y),
+                weapon: p.kind.into(),
+                start_tick: p.start_tick.snap_tick(),
+                owner: owner(p.owner),
+            })
+            .chain(snap.map_projectiles.values().map(|p| Projectile {
+                pos: p.pos.into(),
+                direction: direction(p.direction.x, p.direction.y),
+                weapon: p.kind.into(),
+                start_tick: p.start_tick.snap_tick(),
+                owner: None,
+            }))
+            .collect();
+        let lasers = snap
+            .lasers
+            .values()
+            .map(|l| Laser {
+                from: l.from.into(),
+                to: l.to.into(),
+                kind: l.kind.into(),
+                start_tick: l.start_tick.snap_tick(),
+                owner: l.owner.and_then(owner),
+            })
+            .collect();
+        let pickups = snap.pickups.values().map(Pickup::from).collect();
+        let mut snapshot = Entities {
+            tick,
+            projectiles,
+            lasers,
+            pickups,
+        };
+        if pauses == PauseMode::Compress {
+            snapshot.shift_ticks(-cut);
+        }
+        if !snapshot.is_empty() {
+            entities.push(snapshot);
+        }
+    }
+    entities
+}
+
+/// The output of extract with `--entities`, the players next to the world state.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct WithEntities<T> {
+    pub players: T,
+    pub entities: Vec<Entities>,
+}
@@ -0,0 +1,141 @@
+use serde::{Deserialize, Serialize};
+use stringlit::s;
+
+use crate::distribution;
+
+/// The serialized stats of a labeled player.
+#[derive(Serialize, Deserialize)]
+pub struct Sample {
+    pub stats: serde_json::Value,
+    pub cheating: bool,
+}
+
+/// How well flagging every player at or above the threshold matches the labels.
+#[derive(Debug, Clone, Serialize)]
+pub struct Score {
+    pub threshold: f32,
+    pub true_positives: usize,
+    pub false_positives: usize,
+    pub false_negatives: usize,
+    pub precision: f32,
+    pub recall: f32,
+    pub f1: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricEvaluation {
+    pub metric: String,
+    pub cheating: usize,
+    pub legit: usize,
+    /// The threshold with the highest F1 on all samples
+    pub best: Option<Score>,
+    /// Average F1 on held out folds of the threshold picked on the others, what to expect
+    /// from the best threshold on new demos
+    pub cross_validated_f1: Option<f32>,
+    pub sweep: Vec<Score>,
+}
+
+fn score(points: &[(f32, bool)], threshold: f32) -> Score {
+    let flagged = |cheating: bool| {
+        points
+            .iter()
+            .filter(|(value, c)| *c == cheating && *value >= threshold)
+            .count()
+    };
+    let true_positives = flagged(true);
+    let false_positives = flagged(false);
+    let false_negatives = points.iter().filter(|(_, c)| *c).count() - true_positives;
+    let ratio = |a: usize, b: usize| if b == 0 { 0.0 } else { a as f32 / b as f32 };
+    let precision = ratio(true_positives, true_positives + false_positives);
+    let recall = ratio(true_positives, true_positives + false_negatives);
+    Score {
+        threshold,
+        true_positives,
+        false_positives,
+        false_negatives,
+        precision,
+        recall,
+        f1: if precision + recall == 0.0 {
+            0.0
+        } else {
+            2.0 * precision * recall / (precision + recall)
+        },
+    }
+}
+
+/// Every value that occurs is tried as a threshold.
+fn sweep(points: &[(f32, bool)]) -> Vec<Score> {
+    let mut thresholds: Vec<f32> = points.iter().map(|(value, _)| *value).collect();
+    thresholds.sort_by(f32::total_cmp);
+    thresholds.dedup();
+    thresholds.into_iter().map(|t| score(points, t)).collect()
+}
+
+fn best(sweep: &[Score]) -> Option<Score> {
+    sweep.iter().max_by(|a, b| a.f1.total_cmp(&b.f1)).cloned()
+}
+
+fn cross_validate(points: &[(f32, bool)], folds: usize) -> Option<f32> {
+    if folds < 2 || points.len() < folds {
+        return None;
+    }
+    let f1: f32 = (0..folds)
+        .filter_map(|fold| {
+            let (test, train): (Vec<_>, Vec<_>) = points
+                .iter()
+                .enumerate()
+                .partition(|(i, _)| i % folds == fold);
+            let train: Vec<(f32, bool)> = train.into_iter().map(|(_, p)| *p).collect();
+            let test: Vec<(f32, bool)> = test.into_iter().map(|(_, p)| *p).collect();
+            let threshold = best(&sweep(&train))?.threshold;
+            Some(score(&test, threshold).f1)
+        })
+        .sum();
+    Some(f1 / folds as f32)
+}
+
+/// Sweeps the thresholds of every metric over the labeled samples.
+pub fn evaluate(samples: &[Sample], metrics: &[String], folds: usize) -> Vec<MetricEvaluation> {
+    metrics
+        .iter()
+        .map(|metric| {
+            let points: Vec<(f32, bool)> = samples
+                .iter()
+                .filter_map(|s| Some((distribution::lookup(&s.stats, metric)?, s.cheating)))
+                .collect();
+            let sweep = sweep(&points);
+            let cheating = points.iter().filter(|(_, c)| *c).count();
+            MetricEvaluation {
+                metric: metric.clone(),
+                cheating,
+                legit: points.len() - cheating,
+                best: best(&sweep),
+                cross_validated_f1: cross_validate(&points, folds),
+                sweep,
+            }
+        })
+        .collect()
+}
+
+pub fn plain_report(evaluations: &[MetricEvaluation]) -> String {
+    let mut vec = Vec::new();
+    for e in evaluations {
+        vec.push(format!("{:=^44}", format!(" {} ", e.metric)));
+        vec.push(s!(""));
+        vec.push(format!(
+            "Samples ....... : {} cheating, {} legit",
+            e.cheating, e.legit
+        ));
+        if let Some(best) = &e.best {
+            vec.push(format!("Best Threshold  : {:.2}", best.threshold));
+            vec.push(format!("Precision ..... : {:.2}", best.precision));
+            vec.push(format!("Recall ........ : {:.2}", best.recall));
+            vec.push(format!("F1 ............ : {:.2}", best.f1));
+        }
+        if let Some(f1) = e.cross_validated_f1 {
+            vec.push(format!("Cross-Validated : {f1:.2} F1"));
+        }
+        vec.push(s!(""));
+    }
+    vec.join("\n")
+}
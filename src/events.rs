@@ -0,0 +1,86 @@
+use stringlit::s;
+
+use crate::{
+    data::{ActiveWeapon, Inputs},
+    runs::{self, RunEnd},
+    tee::{TeeChange, TeeTracker},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EventKind {
+    Death,
+    Finish,
+    FreezeStart,
+    FreezeEnd,
+    /// First time a weapon the tee doesn't spawn with was held in a run
+    WeaponPickup(ActiveWeapon),
+}
+
+impl EventKind {
+    pub fn label(&self) -> String {
+        match self {
+            EventKind::Death => s!("Death"),
+            EventKind::Finish => s!("Finish"),
+            EventKind::FreezeStart => s!("Freeze"),
+            EventKind::FreezeEnd => s!("Unfreeze"),
+            EventKind::WeaponPickup(weapon) => format!("Picked up {weapon:?}"),
+        }
+    }
+}
+
+impl From<TeeChange> for EventKind {
+    fn from(change: TeeChange) -> Self {
+        match change {
+            TeeChange::FreezeStart => EventKind::FreezeStart,
+            TeeChange::FreezeEnd => EventKind::FreezeEnd,
+            TeeChange::WeaponPickup(weapon) => EventKind::WeaponPickup(weapon),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct GameEvent {
+    pub tick: i32,
+    pub kind: EventKind,
+}
+
+/// What happened to the player, as far as it can be told from the snapshots. Kills aren't
+/// part of them, only that a tee died.
+pub fn detect_events(inputs: &[Inputs], tick_rate: i32) -> Vec<GameEvent> {
+    let mut events = Vec::new();
+    let runs = runs::segment_runs(inputs, tick_rate);
+    for (range, end) in &runs {
+        let run = &inputs[range.clone()];
+        let Some(last) = run.last() else {
+            continue;
+        };
+        match *end {
+            RunEnd::Death => events.push(GameEvent {
+                tick: last.tick,
+                kind: EventKind::Death,
+            }),
+            RunEnd::Finish => events.push(GameEvent {
+                tick: last.tick,
+                kind: EventKind::Finish,
+            }),
+            RunEnd::DemoEnd => {}
+        }
+    }
+
+    let mut spawns = runs.iter().map(|(range, _)| range.start).peekable();
+    let mut tee = TeeTracker::default();
+    for (i, input) in inputs.iter().enumerate() {
+        if spawns.next_if_eq(&i).is_some() {
+            tee = TeeTracker::default();
+        }
+        tee.update(input, |change| {
+            events.push(GameEvent {
+                tick: input.tick,
+                kind: change.into(),
+            })
+        });
+    }
+
+    events.sort_by_key(|e| (e.tick, e.kind));
+    events
+}
@@ -0,0 +1,331 @@
+#[derive(Debug, Clone, Copy)]
+pub struct MetricDoc {
+    /// The keys in the analyze output this entry describes
+    pub names: &'static [&'static str],
+    pub summary: &'static str,
+    pub definition: &'static str,
+    pub window: &'static str,
+    pub interpretation: &'static str,
+}
+
+pub const METRICS: &[MetricDoc] = &[
+    MetricDoc {
+        names: &[
+            "direction_change_rate_average",
+            "direction_change_rate_median",
+            "direction_change_rate_max",
+        ],
+        summary: "How many times per second the player switches between left, right and \
+            standing still.",
+        definition: "For every direction change, the changes within the following second \
+            (including itself) are counted. Average, median and max are taken over these \
+            counts, so they describe the rate in the busiest stretches the player had, not \
+            over the whole demo.",
+        window: "One second (50 ticks, or the tick rate of the demo) starting at each change. Only changes are counted, \
+            time without any input doesn't lower the rate. Changes within a second of a gap \
+            in the snapshots are left out, see gaps.",
+        interpretation: "Normal gameplay rarely goes above 8 to 10 changes per second. \
+            Maxima well above that, especially close to the tick rate, point to scripted \
+            inputs like balance or wall-jump macros. A high median means the player does \
+            this constantly rather than in short bursts.",
+    },
+    MetricDoc {
+        names: &[
+            "hook_state_change_rate_average",
+            "hook_state_change_rate_median",
+            "hook_state_change_rate_max",
+        ],
+        summary: "How many times per second the hook goes from held to released or back.",
+        definition: "The hook counts as held while it is flying or attached, and as released \
+            in every other state. Each change between the two is counted like the direction \
+            changes: for every change, the changes within the following second are counted \
+            and average, median and max are taken over these counts.",
+        window: "One second (50 ticks, or the tick rate of the demo) starting at each change.",
+        interpretation: "The hook state is what the server simulated, not the raw mouse \
+            button, so very short taps can be missing. Humans can't hook and release much \
+            faster than 6 to 8 times per second for long, sustained higher rates point to a \
+            hook macro.",
+    },
+    MetricDoc {
+        names: &[
+            "direction_changes",
+            "hook_changes",
+            "overall_changes",
+            "raw_direction_changes",
+            "raw_hook_changes",
+        ],
+        summary: "The total number of direction and hook changes in the demo.",
+        definition: "Counts every snapshot where the direction or the hook state differs from \
+            the previous one. overall_changes is the sum of both. With --debounce N, a state \
+            that switches back to the one before it within N ticks is ignored here and in the \
+            rates, the raw counts are taken without it.",
+        window: "The whole demo.",
+        interpretation: "Mostly useful as context for the rates: a high max with only a \
+            handful of changes is less meaningful than the same max over thousands of changes.",
+    },
+    MetricDoc {
+        names: &["aim_angular_speed_average"],
+        summary: "How fast the aim direction turns, on average, in radians per second.",
+        definition: "The aim angle is differentiated between consecutive snapshots, wrapping \
+            around at a full turn, and the absolute values are averaged.",
+        window: "The whole demo, snapshots without elapsed time are skipped.",
+        interpretation: "Depends a lot on playstyle and mode. Compare it against other \
+            players on the same map rather than against a fixed value.",
+    },
+    MetricDoc {
+        names: &["aim_angular_jerk_average"],
+        summary: "How abruptly the aim speed changes, in radians per second cubed.",
+        definition: "The third derivative of the aim angle over time, averaged as absolute \
+            value.",
+        window: "The whole demo.",
+        interpretation: "Human mouse movement is smooth, so the jerk stays moderate. Aim that \
+            snaps instantly from target to target produces very high values.",
+    },
+    MetricDoc {
+        names: &["aim_linear_segment_fraction"],
+        summary: "The fraction of aim movement that happened at exactly constant speed.",
+        definition: "Of all snapshots where the aim moved, the fraction where the angular \
+            speed was exactly the same as in the previous snapshot.",
+        window: "The whole demo.",
+        interpretation: "Humans practically never turn at a perfectly constant speed. Values \
+            above a few percent suggest interpolated aim from a silent aim or aim lock tool.",
+    },
+    MetricDoc {
+        names: &[
+            "attacks",
+            "fast_fire",
+            "double_clicks",
+            "double_click_patterns",
+        ],
+        summary: "Shots per weapon and shots that came faster than possible.",
+        definition: "attacks counts the shots per weapon. fast_fire lists shots that came \
+            sooner after the previous one than the weapon's fire delay allows. double_clicks \
+            lists shots within two ticks of the previous one, double_click_patterns counts how \
+            often a double click, a normal shot and another double click followed each other.",
+        window: "Consecutive shots, regardless of how much time is between them.",
+        interpretation: "Fast fire shouldn't happen with the default tunings, so any entry is \
+            worth a look, but check the server's tunings first. Alternating double click \
+            patterns are the typical rhythm of a double click macro.",
+    },
+    MetricDoc {
+        names: &[
+            "target_distance",
+            "beyond_default_range_fraction",
+            "beyond_dyncam_range_fraction",
+            "constant_long_range_fraction",
+            "probable_dyncam",
+            "probable_zoom",
+        ],
+        summary: "How far from the tee the player aims, in tiles.",
+        definition: "The distance of the cursor target from the tee, with average, median, \
+            90th percentile, max and a histogram in buckets of two tiles. The fractions count \
+            the snapshots beyond the default mouse range (12.5 tiles) and beyond the default \
+            dynamic camera range (31.25 tiles), and the share of the single most common \
+            distance out of the default range.",
+        window: "The whole demo.",
+        interpretation: "probable_dyncam is set when more than 5% of the snapshots are beyond \
+            the default range, probable_zoom when more than 5% are beyond the dynamic camera \
+            range. Both are legitimate client settings on many servers, they mainly explain \
+            otherwise unusual aim. A large constant long range fraction points to a fixed \
+            aim offset.",
+    },
+    MetricDoc {
+        names: &["aim_offset", "within_lock_fraction"],
+        summary: "How far the aim is off from the nearest other tee, in degrees.",
+        definition: "For every snapshot with another tee within 25 tiles, the default laser \
+            reach, the angle between the aim and the direction to the nearest of them, \
+            teammate or enemy. Reported as average, median, 10th percentile and a histogram in \
+            buckets of five degrees. within_lock_fraction is the share of these snapshots \
+            aimed within --lock-degrees of the tee.",
+        window: "The snapshots where another tee is in reach.",
+        interpretation: "Humans aim near opponents in fights but keep a few degrees of error. \
+            A large fraction within a degree or two, or a low 10th percentile over a long \
+            demo, is the typical result of an aimbot locking onto tees. Compare against other \
+            players of the same demo, hammer fights at close range naturally score higher.",
+    },
+    MetricDoc {
+        names: &["reactions"],
+        summary: "How fast the player reacts to another tee coming into hook range and to \
+            unfreezing, in milliseconds.",
+        definition: "hook measures from the snapshot another tee comes within the default \
+            hook length (11.875 tiles) to the next hook press, if the player wasn't hooking \
+            already. unfreeze measures from the snapshot the freeze ends to the first change \
+            of direction, hook or jump, leaving out keys that were held through the freeze. \
+            Reactions later than a second are dropped. Each has count, average, median, 10th \
+            percentile and fast_fraction, the share faster than 100ms.",
+        window: "Every stimulus in the demo. Times are only as precise as the snapshots, one \
+            tick at best.",
+        interpretation: "Human reactions to visual stimuli rarely go below 150ms and vary a \
+            lot. A median under 100ms, or a large fast_fraction over many reactions, points to \
+            automated input. Projectiles aren't part of the extracted data, so dodges can't be \
+            measured.",
+    },
+    MetricDoc {
+        names: &["hammer", "whiff_rate", "frame_perfect"],
+        summary: "How often hammer swings hit another tee, and how early in the chance to hit.",
+        definition: "A swing hits when another tee is within 42 units of the point 21 units in \
+            front of the player along the aim, like the game checks it. whiff_rate is the \
+            share of swings without a tee in range. For every hit the snapshots are walked \
+            back for as long as the tee stayed in range, ticks_in_range_average is how long \
+            that was. A frame perfect hit lands on the first snapshot the tee was in range, \
+            longest_frame_perfect_streak counts them in a row.",
+        window: "Every hammer swing in the demo. Timing is only as precise as the snapshots.",
+        interpretation: "Good players time their hammers well, so single frame perfect hits \
+            mean nothing. A high frame_perfect_fraction over many hits, or long streaks of \
+            them, is hard to do by hand and typical for triggerbots. Block players swing a lot \
+            and whiff often, which is normal.",
+    },
+    MetricDoc {
+        names: &["rehook"],
+        summary: "The rhythm of hook-release-hook cycles, as used to gain speed.",
+        definition: "A cycle is the time from one hook press to the next. Three or more \
+            cycles in a row, each shorter than a second, form a chain. frequency is the average \
+            rate within chains, period_variation the standard deviation of the cycle length \
+            divided by its mean, max_sustained_rate the highest rate held for at least two \
+            seconds and longest_periodic_seconds the longest stretch where all cycles had the \
+            same length, give or take two ticks.",
+        window: "Chains of consecutive cycles, cycles outside of chains are ignored.",
+        interpretation: "Fast, regular rehooking is a skill, so a high frequency alone means \
+            nothing. Human rhythm always drifts a little, probable_macro is set when the exact \
+            same cycle length was held for a minute or longer.",
+    },
+    MetricDoc {
+        names: &["spectrum", "flatness", "peaks"],
+        summary: "Which frequencies the direction and hook inputs repeat at.",
+        definition: "The direction (-1, 0, 1) and the hook (0, 1) as one value per tick, cut \
+            into segments of 256 ticks that overlap by half. The power spectrum of every \
+            segment is averaged. peaks are the three strongest frequencies with their share of \
+            the total power, flatness the geometric over the arithmetic mean of the power.",
+        window: "Segments of 256 ticks, about five seconds. Demos shorter than that have no \
+            spectrum.",
+        interpretation: "Human inputs spread their power over many frequencies. A macro \
+            repeats at one fixed rate, which puts a large share of the power into a single \
+            peak and pulls the flatness towards zero. The spectrogram command draws the \
+            spectrum over time, a macro shows up as a bright horizontal line.",
+    },
+    MetricDoc {
+        names: &["runs", "best_run"],
+        summary: "The demo split into individual race attempts.",
+        definition: "A run starts at a spawn and ends with a finish (the score changed), a \
+            death (the tee vanished or was teleported back to spawn) or the end of the demo. \
+            Every run has its duration, distance and its own direction and hook change rates.",
+        window: "One run each.",
+        interpretation: "Comparing the rates of the best run against the other runs shows \
+            whether the player behaved differently when it mattered.",
+    },
+    MetricDoc {
+        names: &["finishes", "best_time"],
+        summary: "The score changes of the player and the finish times they stand for.",
+        definition: "Every change of the score is listed with the run it ended. Race servers \
+            send the finish time as the negated score in whole seconds, best_time is the \
+            fastest of those.",
+        window: "The whole demo.",
+        interpretation: "Ties the inputs of a run to the time that is being claimed. Chat \
+            messages aren't in the demo, so the exact record time to the millisecond isn't \
+            available.",
+    },
+    MetricDoc {
+        names: &["dnf"],
+        summary: "Runs that ended without a finish, by what ended them.",
+        definition: "Kill messages aren't in the demo, so the reason comes from the tee right \
+            before the run ended. A player gone for more than three seconds disconnected, a tee \
+            that vanished while frozen was killed with the kill bind, any other death is a \
+            hazard like a death tile. Runs cut off by the end of the demo aren't counted.",
+        window: "One run each.",
+        interpretation: "Many kill binds mean a lot of restarting after mistakes, many \
+            disconnects close to the end of runs look like rage quits. A kill bind used before \
+            getting frozen counts as a hazard.",
+    },
+    MetricDoc {
+        names: &[
+            "duration",
+            "insufficient_sample",
+            "direction_change_rate_interval",
+            "direction_change_rate_samples",
+            "hook_state_change_rate_interval",
+            "hook_state_change_rate_samples",
+        ],
+        summary: "How much data the stats of a player are based on.",
+        definition: "duration is the time between the first and the last sample of the player. \
+            The samples are the one second windows the change rates are taken over, the \
+            intervals are 95% bootstrap confidence intervals of the average rate. \
+            insufficient_sample is set when the duration is below --min-duration.",
+        window: "The whole demo.",
+        interpretation: "A few seconds of data can produce a max rate that is just chance. A \
+            wide interval or a handful of windows means the rates say little about the player.",
+    },
+    MetricDoc {
+        names: &["pickups"],
+        summary: "Weapons, ammo, hearts and shields the player picked up, and the ammo they used.",
+        definition: "Health, armor and the ammo of the active weapon going up between two \
+            snapshots are a pickup. Only the active weapon is in the snapshots, so a weapon \
+            counts as picked up the first time the player switches to it. ammo_used adds up \
+            the ammo going down per weapon, weapons with unlimited ammo are left out.",
+        window: "The whole demo.",
+        interpretation: "Mostly relevant outside of race modes, where pickups are part of the \
+            game. Other players' ammo is often not in the demo at all, then only hearts and \
+            shields are counted.",
+    },
+    MetricDoc {
+        names: &["bot_probability"],
+        summary: "The output of a model trained on the columns of export-features.",
+        definition: "Only set with --model. The model is a logistic regression, the weighted \
+            sum of the features plus the bias put through the logistic function.",
+        window: "The whole demo, the same as export-features.",
+        interpretation: "Only as good as the data the model was trained on. Players unlike any \
+            in the training data can get any probability.",
+    },
+    MetricDoc {
+        names: &["gaps"],
+        summary: "Stretches where the player has no snapshots, from server lag or a paused demo.",
+        definition: "The step most snapshots of the player are apart is taken as normal, two \
+            snapshots more than twice that apart are a gap. missing_ticks is the time lost in \
+            all gaps, excluded_changes the direction and hook changes left out of the change \
+            rates because they were within a second of a gap.",
+        window: "The whole demo.",
+        interpretation: "Inputs buffered during lag arrive in a burst once it ends, which \
+            would look like superhuman change rates. Many gaps mean the rates are based on \
+            less of the demo than its length suggests.",
+    },
+    MetricDoc {
+        names: &["presence", "time_played"],
+        summary: "When the player was in the demo and for how long.",
+        definition: "The samples of the player split into stretches wherever there is no \
+            sample for more than three seconds, like after leaving, spectating or a long \
+            pause. time_played is the length of all stretches together. The players command \
+            lists the same for every player, from the snapshots they were in.",
+        window: "The whole demo.",
+        interpretation: "Demos often have players coming and going. Someone who was only there \
+            for two minutes of a long demo has all their stats from those two minutes, compare \
+            with time_played rather than the length of the demo.",
+    },
+    MetricDoc {
+        names: &["afk"],
+        summary: "Stretches where the player stood still without touching anything.",
+        definition: "A stretch of at least --afk-seconds (30 by default) where direction, \
+            hook, aim, weapon, attacks and jumps didn't change and the tee moved slower than \
+            half a unit per tick. fraction is the share of the duration spent AFK. Unless \
+            --include-afk is given, the AFK samples are left out of the aim, target distance, \
+            aim offset and spectrum stats.",
+        window: "The whole demo.",
+        interpretation: "Time away from the keyboard would pull per-sample averages like the \
+            aim speed towards zero. A large fraction means the other stats rest on less of the \
+            demo than its duration suggests.",
+    },
+];
+
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace([' ', '-', '.'], "_")
+}
+
+pub fn find(name: &str) -> Option<&'static MetricDoc> {
+    let name = normalize(name);
+    METRICS
+        .iter()
+        .find(|m| m.names.contains(&name.as_str()))
+        .or_else(|| {
+            METRICS
+                .iter()
+                .find(|m| m.names.iter().any(|n| n.starts_with(&name)))
+        })
+}
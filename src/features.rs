@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    aim,
+    data::{Inputs, DEFAULT_TICK_RATE},
+    rehook,
+};
+
+/// Histogram buckets of the intervals, a tenth of a second each, the last contains
+/// everything longer.
+const BUCKETS: usize = 10;
+const BUCKET_TICKS: i32 = DEFAULT_TICK_RATE / 10;
+
+/// One row of the export, the same columns for every player.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct FeatureVector {
+    pub demo: String,
+    pub player: String,
+    #[serde(flatten)]
+    pub features: BTreeMap<String, f32>,
+}
+
+/// Ticks between consecutive direction changes and ticks the hook was held.
+fn intervals(inputs: &[Inputs]) -> (Vec<i32>, Vec<i32>) {
+    let mut direction = Vec::new();
+    let mut hook = Vec::new();
+    let mut last_change = None;
+    let mut hook_start = None;
+    for w in inputs.windows(2) {
+        let tick = w[1].tick;
+        if w[0].direction != w[1].direction {
+            if let Some(last) = last_change {
+                direction.push(tick - last);
+            }
+            last_change = Some(tick);
+        }
+        match (w[0].hook_state.pressed(), w[1].hook_state.pressed()) {
+            (false, true) => hook_start = Some(tick),
+            (true, false) => {
+                if let Some(start) = hook_start.take() {
+                    hook.push(tick - start);
+                }
+            }
+            _ => {}
+        }
+    }
+    (direction, hook)
+}
+
+/// Share of the intervals in each bucket.
+fn histogram(intervals: &[i32], tick_rate: i32) -> [f32; BUCKETS] {
+    let mut histogram = [0.0; BUCKETS];
+    for ticks in intervals {
+        let bucket = (ticks * DEFAULT_TICK_RATE / tick_rate / BUCKET_TICKS) as usize;
+        histogram[bucket.min(BUCKETS - 1)] += 1.0;
+    }
+    let total = intervals.len().max(1) as f32;
+    histogram.map(|count| count / total)
+}
+
+/// Shannon entropy of the histogram in bits, low when the intervals are always the same.
+fn entropy(histogram: &[f32]) -> f32 {
+    histogram
+        .iter()
+        .filter(|p| **p > 0.0)
+        .map(|p| p * (1.0 / p).log2())
+        .sum()
+}
+
+/// Mean and variance of the changes per whole second of the player's time.
+fn rate(
+    inputs: &[Inputs],
+    changed: impl Fn(&Inputs, &Inputs) -> bool,
+    tick_rate: i32,
+) -> (f32, f32) {
+    let Some(first) = inputs.first() else {
+        return (0.0, 0.0);
+    };
+    let seconds = inputs.last().map_or(0, |l| l.tick - first.tick) / tick_rate + 1;
+    let mut counts = vec![0.0f32; seconds as usize];
+    for w in inputs.windows(2) {
+        if changed(&w[0], &w[1]) {
+            counts[((w[1].tick - first.tick) / tick_rate) as usize] += 1.0;
+        }
+    }
+    let mean = counts.iter().sum::<f32>() / counts.len() as f32;
+    let variance = counts.iter().map(|c| (c - mean).powi(2)).sum::<f32>() / counts.len() as f32;
+    (mean, variance)
+}
+
+/// Converts the inputs of a player into a fixed set of numeric features, for training
+/// classifiers outside of this tool.
+pub fn features(demo: &str, player: &str, inputs: &[Inputs], tick_rate: i32) -> FeatureVector {
+    let mut features = BTreeMap::new();
+    let mut push = |name: &str, value: f32| {
+        features.insert(name.to_string(), value);
+    };
+
+    push("samples", inputs.len() as f32);
+    push(
+        "duration",
+        match (inputs.first(), inputs.last()) {
+            (Some(first), Some(last)) => (last.tick - first.tick) as f32 / tick_rate as f32,
+            _ => 0.0,
+        },
+    );
+
+    let (mean, variance) = rate(inputs, |a, b| a.direction != b.direction, tick_rate);
+    push("direction_change_rate_mean", mean);
+    push("direction_change_rate_variance", variance);
+    let (mean, variance) = rate(
+        inputs,
+        |a, b| a.hook_state.pressed() != b.hook_state.pressed(),
+        tick_rate,
+    );
+    push("hook_change_rate_mean", mean);
+    push("hook_change_rate_variance", variance);
+
+    let (direction, hook) = intervals(inputs);
+    for (name, intervals) in [("direction_interval", direction), ("hook_duration", hook)] {
+        let histogram = histogram(&intervals, tick_rate);
+        push(&format!("{name}_entropy"), entropy(&histogram));
+        for (i, share) in histogram.iter().enumerate() {
+            push(&format!("{name}_hist_{i:02}"), *share);
+        }
+    }
+
+    let aim = aim::calculate_aim_stats(inputs, tick_rate);
+    push("aim_angular_speed_average", aim.angular_speed_average);
+    push("aim_angular_jerk_average", aim.angular_jerk_average);
+    push("aim_linear_segment_fraction", aim.linear_segment_fraction);
+
+    let rehook = rehook::calculate_rehook_stats(inputs, tick_rate);
+    push("rehook_frequency", rehook.frequency);
+    push("rehook_period_variation", rehook.period_variation);
+
+    FeatureVector {
+        demo: demo.to_string(),
+        player: player.to_string(),
+        features,
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    aim,
+    data::{Inputs, DEFAULT_TICK_RATE},
+};
+
+/// Histogram buckets, one per tick at the default tick rate up to a second, the last
+/// contains everything longer.
+const BUCKETS: usize = 51;
+/// Players with fewer input changes than this don't get compared at all.
+const MIN_CHANGES: usize = 100;
+
+/// How much each part of the fingerprint contributes to the similarity.
+const WEIGHT_DIRECTION_TIMING: f32 = 0.3;
+const WEIGHT_HOOK_RHYTHM: f32 = 0.3;
+const WEIGHT_AIM: f32 = 0.4;
+
+/// Behavioral fingerprint of a player, accumulated over all demos they appear in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// Ticks between consecutive direction changes
+    direction_intervals: Vec<usize>,
+    /// Ticks the hook was held
+    hook_durations: Vec<usize>,
+    /// Aim stats summed up weighted by the number of samples
+    aim_angular_speed: f32,
+    aim_angular_jerk: f32,
+    aim_linear_segment_fraction: f32,
+    samples: usize,
+    demos: BTreeSet<String>,
+}
+
+impl Default for Fingerprint {
+    fn default() -> Self {
+        Self {
+            direction_intervals: vec![0; BUCKETS],
+            hook_durations: vec![0; BUCKETS],
+            aim_angular_speed: 0.0,
+            aim_angular_jerk: 0.0,
+            aim_linear_segment_fraction: 0.0,
+            samples: 0,
+            demos: BTreeSet::new(),
+        }
+    }
+}
+
+impl Fingerprint {
+    pub fn add(&mut self, demo: &str, inputs: &[Inputs], tick_rate: i32) {
+        self.demos.insert(demo.to_string());
+        let bucket =
+            |ticks: i32| ((ticks * DEFAULT_TICK_RATE / tick_rate) as usize).min(BUCKETS - 1);
+
+        let mut last_change = None;
+        let mut hook_start = None;
+        for w in inputs.windows(2) {
+            let tick = w[1].tick;
+            if w[0].direction != w[1].direction {
+                if let Some(last) = last_change {
+                    self.direction_intervals[bucket(tick - last)] += 1;
+                }
+                last_change = Some(tick);
+            }
+            match (w[0].hook_state.pressed(), w[1].hook_state.pressed()) {
+                (false, true) => hook_start = Some(tick),
+                (true, false) => {
+                    if let Some(start) = hook_start.take() {
+                        self.hook_durations[bucket(tick - start)] += 1;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let aim = aim::calculate_aim_stats(inputs, tick_rate);
+        let samples = inputs.len() as f32;
+        self.aim_angular_speed += aim.angular_speed_average * samples;
+        self.aim_angular_jerk += aim.angular_jerk_average * samples;
+        self.aim_linear_segment_fraction += aim.linear_segment_fraction * samples;
+        self.samples += inputs.len();
+    }
+
+    /// Adds the fingerprint of the same player from other demos.
+    pub fn merge(&mut self, other: Fingerprint) {
+        for (a, b) in self
+            .direction_intervals
+            .iter_mut()
+            .zip(other.direction_intervals)
+        {
+            *a += b;
+        }
+        for (a, b) in self.hook_durations.iter_mut().zip(other.hook_durations) {
+            *a += b;
+        }
+        self.aim_angular_speed += other.aim_angular_speed;
+        self.aim_angular_jerk += other.aim_angular_jerk;
+        self.aim_linear_segment_fraction += other.aim_linear_segment_fraction;
+        self.samples += other.samples;
+        self.demos.extend(other.demos);
+    }
+
+    fn changes(&self) -> usize {
+        self.direction_intervals.iter().sum::<usize>() + self.hook_durations.iter().sum::<usize>()
+    }
+
+    fn aim(&self) -> [f32; 3] {
+        let samples = self.samples.max(1) as f32;
+        [
+            self.aim_angular_speed / samples,
+            self.aim_angular_jerk / samples,
+            self.aim_linear_segment_fraction / samples,
+        ]
+    }
+
+    /// Similarity between zero (nothing in common) and one (indistinguishable).
+    pub fn similarity(&self, other: &Fingerprint) -> f32 {
+        let aim = self
+            .aim()
+            .iter()
+            .zip(other.aim())
+            .map(|(a, b)| scalar_similarity(*a, b))
+            .sum::<f32>()
+            / 3.0;
+        WEIGHT_DIRECTION_TIMING
+            * histogram_similarity(&self.direction_intervals, &other.direction_intervals)
+            + WEIGHT_HOOK_RHYTHM * histogram_similarity(&self.hook_durations, &other.hook_durations)
+            + WEIGHT_AIM * aim
+    }
+}
+
+/// Overlap of the two normalized histograms.
+fn histogram_similarity(a: &[usize], b: &[usize]) -> f32 {
+    let (total_a, total_b) = (a.iter().sum::<usize>(), b.iter().sum::<usize>());
+    if total_a == 0 || total_b == 0 {
+        return 0.0;
+    }
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (*a as f32 / total_a as f32).min(*b as f32 / total_b as f32))
+        .sum()
+}
+
+fn scalar_similarity(a: f32, b: f32) -> f32 {
+    let max = a.abs().max(b.abs());
+    if max == 0.0 {
+        return 1.0;
+    }
+    1.0 - (a - b).abs() / max
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimilarPair {
+    pub a: String,
+    pub b: String,
+    pub similarity: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Clustering {
+    /// Pairs at or above the minimum similarity, most similar first
+    pub pairs: Vec<SimilarPair>,
+    /// Groups of names connected by similar pairs
+    pub clusters: Vec<Vec<String>>,
+    /// Names that had too little data to be compared
+    pub skipped: Vec<String>,
+}
+
+/// Compares all players with each other. Names that were in the same demo are never
+/// paired, since one person can't play two tees at once.
+pub fn cluster(fingerprints: &HashMap<String, Fingerprint>, min_similarity: f32) -> Clustering {
+    let mut names: Vec<&String> = fingerprints.keys().collect();
+    names.sort();
+    let (names, skipped): (Vec<&String>, Vec<&String>) = names
+        .into_iter()
+        .partition(|n| fingerprints[*n].changes() >= MIN_CHANGES);
+
+    let mut pairs = Vec::new();
+    for (i, a) in names.iter().enumerate() {
+        for b in &names[i + 1..] {
+            let (fa, fb) = (&fingerprints[*a], &fingerprints[*b]);
+            if !fa.demos.is_disjoint(&fb.demos) {
+                continue;
+            }
+            let similarity = fa.similarity(fb);
+            if similarity >= min_similarity {
+                pairs.push(SimilarPair {
+                    a: a.to_string(),
+                    b: b.to_string(),
+                    similarity,
+                });
+            }
+        }
+    }
+    pairs.sort_by(|a, b| b.similarity.total_cmp(&a.similarity));
+
+    // Union find over the similar pairs
+    let mut parent: BTreeMap<&str, &str> = BTreeMap::new();
+    fn root<'a>(parent: &BTreeMap<&'a str, &'a str>, mut name: &'a str) -> &'a str {
+        while let Some(p) = parent.get(name).filter(|p| **p != name) {
+            name = p;
+        }
+        name
+    }
+    for pair in &pairs {
+        let (a, b) = (root(&parent, &pair.a), root(&parent, &pair.b));
+        parent.insert(a, a);
+        parent.insert(b, a);
+    }
+    let mut clusters: BTreeMap<&str, Vec<String>> = BTreeMap::new();
+    for name in parent.keys() {
+        clusters
+            .entry(root(&parent, name))
+            .or_default()
+            .push(name.to_string());
+    }
+    let clusters = clusters.into_values().collect();
+
+    Clustering {
+        pairs,
+        clusters,
+        skipped: skipped.into_iter().cloned().collect(),
+    }
+}
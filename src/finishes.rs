@@ -0,0 +1,58 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    data::Inputs,
+    runs::{RunEnd, Runs},
+    timeline::{Timeline, Timestamp},
+};
+
+/// The score DDNet gives players that haven't finished yet.
+const NO_TIME: i32 = -9999;
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Finish {
+    pub at: Timestamp,
+    pub score: i32,
+    /// The finish time in whole seconds, race servers send it as the negated score
+    pub time: Option<i32>,
+    /// Index of the run that ended with this finish
+    pub run: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Finishes {
+    pub finishes: Vec<Finish>,
+    /// The fastest time the score claimed, in whole seconds
+    pub best_time: Option<i32>,
+}
+
+/// The finish time a score stands for, if it is one.
+pub fn finish_time(score: i32) -> Option<i32> {
+    (score < 0 && score != NO_TIME).then_some(-score)
+}
+
+/// Every score change of the player, tied to the run it ended.
+pub fn calculate_finishes(inputs: &[Inputs], timeline: &Timeline, runs: &Runs) -> Finishes {
+    let finishes: Vec<Finish> = inputs
+        .windows(2)
+        .filter(|w| w[0].score != w[1].score)
+        .map(|w| {
+            let tick = w[1].tick;
+            Finish {
+                at: timeline.timestamp(tick),
+                score: w[1].score,
+                time: finish_time(w[1].score),
+                run: runs
+                    .runs
+                    .iter()
+                    .position(|r| r.end == RunEnd::Finish && r.end_tick == tick),
+            }
+        })
+        .collect();
+    let best_time = inputs.iter().filter_map(|i| finish_time(i.score)).min();
+    Finishes {
+        finishes,
+        best_time,
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::timeline::{Timeline, Timestamp};
+
+/// Samples further apart than this many times the usual step mean snapshots went missing.
+pub(crate) const GAP_STEPS: i32 = 2;
+
+/// Ticks in which the player has no samples, because the server lagged or the demo was paused.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Gap {
+    /// The last sample before the gap
+    pub start: Timestamp,
+    /// The first sample after the gap
+    pub end: Timestamp,
+    pub missing_ticks: i32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct GapStats {
+    pub gaps: Vec<Gap>,
+    pub missing_ticks: i32,
+    /// Direction and hook changes within a second of a gap, left out of the change rates
+    pub excluded_changes: usize,
+}
+
+/// The step most samples are apart, `None` with less than two samples.
+pub(crate) fn usual_step(ticks: impl Iterator<Item = i32>) -> Option<i32> {
+    let mut steps = HashMap::new();
+    let mut ticks = ticks.peekable();
+    while let (Some(tick), Some(next)) = (ticks.next(), ticks.peek()) {
+        *steps.entry(next - tick).or_insert(0) += 1;
+    }
+    steps
+        .into_iter()
+        .max_by_key(|(step, count)| (*count, -step))
+        .map(|(step, _)| step)
+}
+
+/// The gaps between the sample ticks of a player, compared to the step most samples are apart.
+pub fn find_gaps(ticks: &[i32], timeline: &Timeline) -> Vec<Gap> {
+    let Some(step) = usual_step(ticks.iter().copied()) else {
+        return Vec::new();
+    };
+    ticks
+        .windows(2)
+        .filter(|w| w[1] - w[0] > step * GAP_STEPS)
+        .map(|w| Gap {
+            start: timeline.timestamp(w[0]),
+            end: timeline.timestamp(w[1]),
+            missing_ticks: w[1] - w[0] - step,
+        })
+        .collect()
+}
+
+/// Drops the changes whose one second window for the rates would reach into a gap, or that
+/// happened within a second after it, where buffered inputs arrive in a burst.
+pub fn without_gaps(changes: &[i32], gaps: &[Gap], tick_rate: i32) -> Vec<i32> {
+    changes
+        .iter()
+        .copied()
+        .filter(|tick| {
+            !gaps
+                .iter()
+                .any(|g| (g.start.tick - tick_rate..=g.end.tick + tick_rate).contains(tick))
+        })
+        .collect()
+}
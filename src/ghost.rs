@@ -0,0 +1,263 @@
+use std::path::Path;
+
+use anyhow::{bail, Context};
+use fixed::types::{I24F8, I27F5};
+use twsnap::{
+    enums,
+    items::{Player, Tee},
+    time::{Duration, Instant},
+};
+
+use crate::data::Inputs;
+
+const MARKER: &[u8; 8] = b"TWGHOST\0";
+const NAME_LENGTH: usize = 16;
+const MAP_NAME_LENGTH: usize = 64;
+
+const TYPE_SKIN: u8 = 0;
+const TYPE_CHARACTER_NO_TICK: u8 = 1;
+const TYPE_CHARACTER: u8 = 2;
+const TYPE_START_TICK: u8 = 3;
+
+/// A DDNet ghost file (`.gho`), as recorded by the client for the personal best.
+pub struct Ghost {
+    pub owner: String,
+    /// Finish time in milliseconds
+    pub time: i32,
+    pub inputs: Vec<Inputs>,
+}
+
+fn c_str(bytes: &[u8]) -> String {
+    let end = bytes.iter().position(|b| *b == 0).unwrap_or(bytes.len());
+    String::from_utf8_lossy(&bytes[..end]).into_owned()
+}
+
+fn be_i32(bytes: &[u8]) -> i32 {
+    i32::from_be_bytes(bytes[..4].try_into().unwrap())
+}
+
+/// Decodes the variable length integers teeworlds uses for network and file data.
+fn decompress_ints(mut data: &[u8]) -> Vec<i32> {
+    let mut ints = Vec::new();
+    while let Some((&first, rest)) = data.split_first() {
+        data = rest;
+        let sign = (first >> 6) & 1;
+        let mut value = (first & 0x3f) as i32;
+        let mut more = first & 0x80 != 0;
+        let mut shift = 6;
+        while more {
+            let Some((&next, rest)) = data.split_first() else {
+                break;
+            };
+            data = rest;
+            value |= ((next & 0x7f) as i32) << shift;
+            more = next & 0x80 != 0;
+            shift += 7;
+        }
+        ints.push(value ^ -(sign as i32));
+    }
+    ints
+}
+
+fn item_size(kind: u8) -> anyhow::Result<usize> {
+    Ok(match kind {
+        TYPE_SKIN => 9,
+        TYPE_CHARACTER_NO_TICK => 11,
+        TYPE_CHARACTER => 12,
+        TYPE_START_TICK => 1,
+        _ => bail!("Unknown ghost item type {kind}"),
+    })
+}
+
+fn tee(item: &[i32], tick: i32) -> Tee {
+    let position = |x, y| twsnap::Position::new(I27F5::from_bits(x), I27F5::from_bits(y));
+    Tee {
+        tick: Instant::zero() + Duration::from_ticks(tick),
+        pos: position(item[0], item[1]),
+        vel: twsnap::Velocity::new(I24F8::from_bits(item[2]), I24F8::from_bits(item[3])),
+        angle: I24F8::from_bits(item[4]),
+        direction: enums::Direction::from(item[5]),
+        weapon: enums::ActiveWeapon::from(item[6]),
+        hook_state: enums::HookState::from(item[7]),
+        hook_pos: position(item[8], item[9]),
+        attack_tick: Instant::zero() + Duration::from_ticks(item[10]),
+        ..Default::default()
+    }
+}
+
+pub fn read_ghost(path: &Path) -> anyhow::Result<Ghost> {
+    let data = std::fs::read(path).with_context(|| format!("Couldn't read ghost {path:?}"))?;
+    if data.len() < MARKER.len() + 1 || &data[..MARKER.len()] != MARKER {
+        bail!("{path:?} is not a ghost file");
+    }
+    let version = data[MARKER.len()];
+    if !(4..=6).contains(&version) {
+        bail!("Unsupported ghost version {version}");
+    }
+
+    // marker, version, owner, map name, crc/zeroes, tick count, time and since version 6 the map sha256
+    let header_size = MARKER.len() + 1 + NAME_LENGTH + MAP_NAME_LENGTH + 12;
+    let header_size = if version >= 6 {
+        header_size + 32
+    } else {
+        header_size
+    };
+    if data.len() < header_size {
+        bail!("Ghost header is truncated");
+    }
+    let owner_start = MARKER.len() + 1;
+    let map_start = owner_start + NAME_LENGTH;
+    let time_start = map_start + MAP_NAME_LENGTH + 8;
+    let owner = c_str(&data[owner_start..map_start]);
+    let time = be_i32(&data[time_start..]);
+
+    let player = Player {
+        name: owner.as_str().try_into().unwrap_or_default(),
+        ..Default::default()
+    };
+
+    let mut inputs = Vec::new();
+    let mut last: Option<(u8, Vec<i32>)> = None;
+    let mut tick = 0;
+    let mut rest = &data[header_size..];
+    while rest.len() >= 4 {
+        let kind = rest[0];
+        let count = rest[1] as usize;
+        let size = (rest[2] as usize) << 8 | rest[3] as usize;
+        if rest.len() < 4 + size {
+            bail!("Ghost chunk is truncated");
+        }
+        let chunk = libtw2_huffman::decompress(&rest[4..4 + size])
+            .map_err(|_| anyhow::anyhow!("Ghost chunk couldn't be decompressed"))?;
+        rest = &rest[4 + size..];
+
+        let ints = decompress_ints(&chunk);
+        let item_size = item_size(kind)?;
+        for raw in ints.chunks_exact(item_size).take(count) {
+            // Consecutive items of the same type are stored as difference to the previous one
+            let item: Vec<i32> = match &last {
+                Some((last_kind, last_item)) if *last_kind == kind => raw
+                    .iter()
+                    .zip(last_item)
+                    .map(|(diff, past)| past.wrapping_add(*diff))
+                    .collect(),
+                _ => raw.to_vec(),
+            };
+            match kind {
+                TYPE_START_TICK => tick = item[0],
+                TYPE_CHARACTER => inputs.push((&player, &tee(&item, item[11])).into()),
+                TYPE_CHARACTER_NO_TICK => {
+                    inputs.push((&player, &tee(&item, tick)).into());
+                    tick += 1;
+                }
+                _ => {}
+            }
+            last = Some((kind, item));
+        }
+    }
+
+    Ok(Ghost {
+        owner,
+        time,
+        inputs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::Position;
+
+    fn compress_ints(ints: &[i32]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for &int in ints {
+            let sign = (int >> 31) as u8 & 1;
+            let mut value = (int ^ (int >> 31)) as u32;
+            let mut byte = sign << 6 | (value & 0x3f) as u8;
+            value >>= 6;
+            while value != 0 {
+                data.push(byte | 0x80);
+                byte = (value & 0x7f) as u8;
+                value >>= 7;
+            }
+            data.push(byte);
+        }
+        data
+    }
+
+    fn chunk(kind: u8, items: &[&[i32]]) -> Vec<u8> {
+        let ints: Vec<i32> = items.iter().flat_map(|item| item.iter().copied()).collect();
+        let compressed = libtw2_huffman::compress(&compress_ints(&ints));
+        let mut data = vec![kind, items.len() as u8];
+        data.extend_from_slice(&(compressed.len() as u16).to_be_bytes());
+        data.extend(compressed);
+        data
+    }
+
+    fn ghost(chunks: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = MARKER.to_vec();
+        data.push(6);
+        let mut owner = [0; NAME_LENGTH];
+        owner[..8].copy_from_slice(b"nameless");
+        data.extend(owner);
+        data.extend([0; MAP_NAME_LENGTH + 8]);
+        data.extend(12_345i32.to_be_bytes());
+        data.extend([0; 32]);
+        for chunk in chunks {
+            data.extend(chunk);
+        }
+        data
+    }
+
+    fn read(name: &str, data: &[u8]) -> anyhow::Result<Ghost> {
+        let path = std::env::temp_dir().join(format!("{}-{name}.gho", std::process::id()));
+        std::fs::write(&path, data).unwrap();
+        let ghost = read_ghost(&path);
+        std::fs::remove_file(&path).unwrap();
+        ghost
+    }
+
+    #[test]
+    fn decompresses_ints() {
+        let ints = [0, 1, -1, 63, 64, -64, 1 << 20, i32::MIN, i32::MAX];
+        assert_eq!(decompress_ints(&compress_ints(&ints)), ints);
+    }
+
+    #[test]
+    fn reads_characters() {
+        let first: &[i32] = &[32 * 10, 32 * 20, 256, 0, 0, 1, 1, 0, 0, 0, 0];
+        // Stored as difference to the first character
+        let second: &[i32] = &[32, -32, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let data = ghost(&[
+            chunk(TYPE_START_TICK, &[&[100]]),
+            chunk(TYPE_CHARACTER_NO_TICK, &[first, second]),
+        ]);
+
+        let ghost = read("characters", &data).unwrap();
+        assert_eq!(ghost.owner, "nameless");
+        assert_eq!(ghost.time, 12_345);
+        let ticks: Vec<_> = ghost.inputs.iter().map(|input| input.tick).collect();
+        assert_eq!(ticks, [100, 101]);
+        assert_eq!(ghost.inputs[0].pos, Position::from_tiles(10.0, 20.0));
+        assert_eq!(ghost.inputs[1].pos, Position::from_tiles(11.0, 19.0));
+        assert_eq!(ghost.inputs[1].vel, ghost.inputs[0].vel);
+    }
+
+    #[test]
+    fn rejects_broken_files() {
+        assert!(read("marker", b"TWDEMO\0\0\x06").is_err());
+
+        let mut version = ghost(&[]);
+        version[MARKER.len()] = 3;
+        assert!(read("version", &version).is_err());
+
+        let header = ghost(&[]);
+        assert!(read("header", &header[..header.len() - 1]).is_err());
+
+        let mut truncated = ghost(&[chunk(TYPE_START_TICK, &[&[100]])]);
+        truncated.pop();
+        assert!(read("chunk", &truncated).is_err());
+
+        assert!(read("kind", &ghost(&[chunk(9, &[&[1]])])).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    attack,
+    changes::InputChanges,
+    data::{ActiveWeapon, Inputs, Position},
+};
+
+/// The hammer hits around a point this far in front of the tee, in tiles.
+const REACH: f32 = 28.0 * 0.75 / 32.0;
+/// Tees closer than this to that point are hit, in tiles.
+const HIT_RADIUS: f32 = (14.0 + 28.0) / 32.0;
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct HammerStats {
+    pub swings: usize,
+    /// Swings with another tee in range
+    pub hits: usize,
+    /// Fraction of the swings without a tee in range
+    pub whiff_rate: f32,
+    /// Ticks the tee had already been in range when the hammer hit, averaged over the hits
+    pub ticks_in_range_average: f32,
+    /// Hits on the first snapshot the tee was in range, the earliest a swing can connect
+    pub frame_perfect_hits: usize,
+    pub frame_perfect_fraction: f32,
+    /// Most frame perfect hits in a row
+    pub longest_frame_perfect_streak: usize,
+}
+
+fn in_range(player: &Inputs, target: &Position) -> bool {
+    let (x, y) = (
+        player.target.x.to_num::<f32>(),
+        player.target.y.to_num::<f32>(),
+    );
+    let length = x.hypot(y);
+    if length == 0.0 {
+        return false;
+    }
+    let center_x = player.pos.x.to_num::<f32>() + x / length * REACH;
+    let center_y = player.pos.y.to_num::<f32>() + y / length * REACH;
+    (target.x.to_num::<f32>() - center_x).hypot(target.y.to_num::<f32>() - center_y) < HIT_RADIUS
+}
+
+/// Whiff rate and timing of the hammer swings. For every hit it is counted how long the tee
+/// that came into range last had been in range, with the aim of the player at each snapshot.
+pub fn calculate_hammer_stats(inputs: &[Inputs], others: &[&InputChanges]) -> HammerStats {
+    let mut stats = HammerStats::default();
+    let mut ticks_in_range = Vec::new();
+    let mut streak = 0;
+    for swing in attack::attacks(inputs)
+        .iter()
+        .filter(|a| a.weapon == ActiveWeapon::Hammer)
+    {
+        stats.swings += 1;
+        let Some(index) = inputs
+            .partition_point(|i| i.tick <= swing.tick)
+            .checked_sub(1)
+        else {
+            continue;
+        };
+        let player = &inputs[index];
+        let ticks = others
+            .iter()
+            .filter(|other| {
+                other
+                    .position_at(player.tick)
+                    .is_some_and(|t| in_range(player, t))
+            })
+            .map(|other| {
+                // Walk back through the snapshots for as long as the tee stayed in range
+                let mut first = player.tick;
+                let mut frame_perfect = true;
+                for earlier in inputs[..index].iter().rev() {
+                    match other.position_at(earlier.tick) {
+                        Some(target) if in_range(earlier, target) => {
+                            first = earlier.tick;
+                            frame_perfect = false;
+                        }
+                        _ => break,
+                    }
+                }
+                (swing.tick - first, frame_perfect)
+            })
+            .min();
+        let Some((ticks, frame_perfect)) = ticks else {
+            continue;
+        };
+        stats.hits += 1;
+        ticks_in_range.push(ticks as f32);
+        if frame_perfect {
+            stats.frame_perfect_hits += 1;
+            streak += 1;
+            stats.longest_frame_perfect_streak = stats.longest_frame_perfect_streak.max(streak);
+        } else {
+            streak = 0;
+        }
+    }
+    if stats.swings > 0 {
+        stats.whiff_rate = (stats.swings - stats.hits) as f32 / stats.swings as f32;
+    }
+    if stats.hits > 0 {
+        stats.ticks_in_range_average =
+            ticks_in_range.iter().sum::<f32>() / ticks_in_range.len() as f32;
+        stats.frame_perfect_fraction = stats.frame_perfect_hits as f32 / stats.hits as f32;
+    }
+    stats
+}
@@ -0,0 +1,294 @@
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use stringlit::s;
+
+use crate::{
+    data::Inputs,
+    events::{self, EventKind},
+    map::{self, Map},
+    rehook,
+    timeline::{TimeFormat, Timeline, Timestamp},
+};
+
+/// An unfreeze only counts as saved if the tee stays alive and unfrozen this long after it.
+const SAVE_SECONDS: f32 = 2.0;
+/// Half the size of a tee, in tiles.
+const TEE_RADIUS: f32 = 14.0 / 32.0;
+/// Passing a freeze or death tile with less than this many tiles between it and the tee is a
+/// near miss.
+const NEAR_MISS_MARGIN: f32 = 0.25;
+/// Speed gained within a second, in units per tick.
+const SPEED_GAIN: f32 = 15.0;
+const MIN_HOOK_CYCLES: usize = 6;
+const MIN_KILL_STREAK: usize = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum HighlightKind {
+    FreezeSave,
+    NearMiss,
+    SpeedGain,
+    HookChain,
+    KillStreak,
+}
+
+impl HighlightKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            HighlightKind::FreezeSave => "Freeze save",
+            HighlightKind::NearMiss => "Near miss",
+            HighlightKind::SpeedGain => "Speed gain",
+            HighlightKind::HookChain => "Hook chain",
+            HighlightKind::KillStreak => "Kill streak",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Highlight {
+    pub kind: HighlightKind,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub description: String,
+}
+
+struct Span {
+    kind: HighlightKind,
+    start: i32,
+    end: i32,
+    description: String,
+}
+
+fn speed(input: &Inputs) -> f32 {
+    input.vel.x.to_num::<f32>().hypot(input.vel.y.to_num())
+}
+
+fn freeze_saves(events: &[events::GameEvent], tick_rate: i32) -> Vec<Span> {
+    let save = (SAVE_SECONDS * tick_rate as f32) as i32;
+    let mut spans = Vec::new();
+    let mut frozen = None;
+    for (i, event) in events.iter().enumerate() {
+        match event.kind {
+            EventKind::FreezeStart => frozen = Some(event.tick),
+            EventKind::FreezeEnd => {
+                let Some(start) = frozen.take() else {
+                    continue;
+                };
+                let caught = events[i + 1..]
+                    .iter()
+                    .take_while(|e| e.tick - event.tick <= save)
+                    .any(|e| matches!(e.kind, EventKind::FreezeStart | EventKind::Death));
+                if !caught {
+                    spans.push(Span {
+                        kind: HighlightKind::FreezeSave,
+                        start,
+                        end: event.tick,
+                        description: format!(
+                            "got out after {:.2}s frozen",
+                            (event.tick - start) as f32 / tick_rate as f32
+                        ),
+                    });
+                }
+            }
+            EventKind::Death => frozen = None,
+            _ => {}
+        }
+    }
+    spans
+}
+
+/// Distance between the edge of the tee and the closest freeze or death tile around it.
+fn clearance(map: &Map, input: &Inputs) -> Option<f32> {
+    let (x, y) = (input.pos.x.to_num::<f32>(), input.pos.y.to_num::<f32>());
+    let (tx, ty) = (x.floor() as i32, y.floor() as i32);
+    (ty - 1..=ty + 1)
+        .flat_map(|ty| (tx - 1..=tx + 1).map(move |tx| (tx, ty)))
+        .filter(|&(tx, ty)| matches!(map.game.get(tx, ty), map::TILE_FREEZE | map::TILE_DEATH))
+        .map(|(tx, ty)| {
+            let dx = (tx as f32 - x).max(x - (tx + 1) as f32).max(0.0);
+            let dy = (ty as f32 - y).max(y - (ty + 1) as f32).max(0.0);
+            dx.hypot(dy) - TEE_RADIUS
+        })
+        .min_by(f32::total_cmp)
+}
+
+/// Stretches where the tee brushed past a freeze or death tile, without getting frozen or
+/// dying within a second.
+fn near_misses(
+    inputs: &[Inputs],
+    map: &Map,
+    events: &[events::GameEvent],
+    tick_rate: i32,
+) -> Vec<Span> {
+    let mut spans: Vec<(i32, i32, f32)> = Vec::new();
+    let mut last_near = None;
+    for input in inputs {
+        let near = clearance(map, input).filter(|c| *c > 0.0 && *c < NEAR_MISS_MARGIN);
+        match (near, spans.last_mut()) {
+            (Some(c), Some(span)) if last_near.is_some() => {
+                span.1 = input.tick;
+                span.2 = span.2.min(c);
+            }
+            (Some(c), _) => spans.push((input.tick, input.tick, c)),
+            _ => {}
+        }
+        last_near = near;
+    }
+    spans
+        .into_iter()
+        .filter(|(_, end, _)| {
+            !events.iter().any(|e| {
+                matches!(e.kind, EventKind::FreezeStart | EventKind::Death)
+                    && (*end..=end + tick_rate).contains(&e.tick)
+            })
+        })
+        .map(|(start, end, c)| Span {
+            kind: HighlightKind::NearMiss,
+            start,
+            end,
+            description: format!("passed {c:.2} tiles from a freeze or death tile"),
+        })
+        .collect()
+}
+
+/// Stretches where the tee got faster by a lot within a second, from the slowest sample to the
+/// fastest.
+fn speed_gains(inputs: &[Inputs], tick_rate: i32) -> Vec<Span> {
+    let mut spans: Vec<Span> = Vec::new();
+    let mut best_gain = 0.0;
+    let mut window_start = 0;
+    for (i, input) in inputs.iter().enumerate() {
+        while input.tick - inputs[window_start].tick > tick_rate {
+            window_start += 1;
+        }
+        let Some(slowest) = inputs[window_start..=i]
+            .iter()
+            .min_by(|a, b| speed(a).total_cmp(&speed(b)))
+        else {
+            continue;
+        };
+        let gain = speed(input) - speed(slowest);
+        if gain < SPEED_GAIN {
+            continue;
+        }
+        match spans.last_mut() {
+            // Still the same burst of speed, it only grows
+            Some(span) if span.end >= slowest.tick => {
+                span.end = input.tick;
+                if gain > best_gain {
+                    best_gain = gain;
+                    span.description = format!("gained {gain:.1} units per tick");
+                }
+            }
+            _ => {
+                best_gain = gain;
+                spans.push(Span {
+                    kind: HighlightKind::SpeedGain,
+                    start: slowest.tick,
+                    end: input.tick,
+                    description: format!("gained {gain:.1} units per tick"),
+                });
+            }
+        }
+    }
+    spans
+}
+
+fn hook_chains(inputs: &[Inputs], tick_rate: i32) -> Vec<Span> {
+    rehook::chains(inputs, tick_rate)
+        .into_iter()
+        .filter(|(_, periods)| periods.len() >= MIN_HOOK_CYCLES)
+        .map(|(start, periods)| Span {
+            kind: HighlightKind::HookChain,
+            start,
+            end: start + periods.iter().sum::<i32>(),
+            description: format!("{} rehooks in a row", periods.len()),
+        })
+        .collect()
+}
+
+/// Kills without dying in between. Only the score tells about kills, so this only works
+/// where a kill is worth one point, like in vanilla modes.
+fn kill_streaks(inputs: &[Inputs], events: &[events::GameEvent]) -> Vec<Span> {
+    let deaths: Vec<i32> = events
+        .iter()
+        .filter(|e| e.kind == EventKind::Death)
+        .map(|e| e.tick)
+        .collect();
+    let mut streaks: Vec<Vec<i32>> = vec![Vec::new()];
+    for w in inputs.windows(2) {
+        let died = deaths.iter().any(|d| (w[0].tick..w[1].tick).contains(d));
+        if died && streaks.last().is_some_and(|s| !s.is_empty()) {
+            streaks.push(Vec::new());
+        }
+        if w[1].score == w[0].score + 1 {
+            streaks.last_mut().unwrap().push(w[1].tick);
+        }
+    }
+    streaks
+        .into_iter()
+        .filter(|kills| kills.len() >= MIN_KILL_STREAK)
+        .map(|kills| Span {
+            kind: HighlightKind::KillStreak,
+            start: kills[0],
+            end: kills[kills.len() - 1],
+            description: format!("{} kills without dying", kills.len()),
+        })
+        .collect()
+}
+
+/// The interesting moments of a player, in the order they happened. Near misses need the map.
+pub fn detect_highlights(
+    inputs: &[Inputs],
+    map: Option<&Map>,
+    timeline: &Timeline,
+) -> Vec<Highlight> {
+    let tick_rate = timeline.tick_rate;
+    let events = events::detect_events(inputs, tick_rate);
+    let mut spans = freeze_saves(&events, tick_rate);
+    if let Some(map) = map {
+        spans.extend(near_misses(inputs, map, &events, tick_rate));
+    }
+    spans.extend(speed_gains(inputs, tick_rate));
+    spans.extend(hook_chains(inputs, tick_rate));
+    spans.extend(kill_streaks(inputs, &events));
+    spans.sort_by_key(|s| (s.start, s.end));
+    spans
+        .into_iter()
+        .map(|s| Highlight {
+            kind: s.kind,
+            start: timeline.timestamp(s.start),
+            end: timeline.timestamp(s.end),
+            description: s.description,
+        })
+        .collect()
+}
+
+pub fn plain_report(
+    highlights: &HashMap<String, Vec<Highlight>>,
+    timeline: &Timeline,
+    format: TimeFormat,
+) -> String {
+    let mut players: Vec<_> = highlights.iter().collect();
+    players.sort_by_key(|(name, _)| *name);
+    let mut vec = Vec::new();
+    for (name, highlights) in players {
+        vec.push(format!("{:=^44}", format!(" {name} ")));
+        vec.push(s!(""));
+        if highlights.is_empty() {
+            vec.push(s!("No highlights found"));
+        }
+        for highlight in highlights {
+            vec.push(format!(
+                "{:>9} - {:>9} {:<12} {}",
+                format.format(timeline.seconds(highlight.start.tick)),
+                format.format(timeline.seconds(highlight.end.tick)),
+                highlight.kind.label(),
+                highlight.description,
+            ));
+        }
+        vec.push(s!(""));
+    }
+    vec.join("\n")
+}
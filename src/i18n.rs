@@ -0,0 +1,127 @@
+use clap::ValueEnum;
+use fluent_bundle::{concurrent::FluentBundle, FluentArgs, FluentResource};
+use unic_langid::LanguageIdentifier;
+
+/// Language of the plain reports, structured output is always in English.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Lang {
+    #[default]
+    En,
+    De,
+}
+
+const EN: &str = include_str!("../i18n/en.ftl");
+const DE: &str = include_str!("../i18n/de.ftl");
+
+impl Lang {
+    fn id(self) -> &'static str {
+        match self {
+            Lang::En => "en",
+            Lang::De => "de",
+        }
+    }
+
+    fn resource(self) -> &'static str {
+        match self {
+            Lang::En => EN,
+            Lang::De => DE,
+        }
+    }
+}
+
+/// The strings of the report in one language, with English for the ones it doesn't have.
+pub struct Translations {
+    bundles: Vec<FluentBundle<FluentResource>>,
+}
+
+fn bundle(lang: Lang) -> FluentBundle<FluentResource> {
+    let id: LanguageIdentifier = lang.id().parse().expect("the languages have valid ids");
+    let mut bundle = FluentBundle::new_concurrent(vec![id]);
+    // The report is plain text, the marks would end up in it
+    bundle.set_use_isolating(false);
+    let resource = FluentResource::try_new(lang.resource().to_string())
+        .expect("the translations are valid fluent");
+    bundle
+        .add_resource(resource)
+        .expect("the translations have no duplicate ids");
+    bundle
+}
+
+impl Translations {
+    pub fn new(lang: Lang) -> Self {
+        let mut bundles = vec![bundle(lang)];
+        if lang != Lang::En {
+            bundles.push(bundle(Lang::En));
+        }
+        Self { bundles }
+    }
+
+    /// Whether the first language, not the English fallback, has the message.
+    #[cfg(test)]
+    fn has(&self, id: &str) -> bool {
+        self.bundles[0].has_message(id)
+    }
+
+    /// The message with the arguments filled in, the id itself if no language has it.
+    pub fn tr(&self, id: &str, args: &FluentArgs) -> String {
+        self.bundles
+            .iter()
+            .find_map(|bundle| {
+                let pattern = bundle.get_message(id)?.value()?;
+                let mut errors = Vec::new();
+                Some(
+                    bundle
+                        .format_pattern(pattern, Some(args), &mut errors)
+                        .into_owned(),
+                )
+            })
+            .unwrap_or_else(|| id.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The ids the default template translates, the first argument of every `tr(`.
+    fn template_ids() -> Vec<&'static str> {
+        let ids: Vec<_> = crate::template::DEFAULT
+            .split("tr(\"")
+            .skip(1)
+            .filter_map(|rest| rest.split('"').next())
+            .chain(
+                // The labels of the reactions and spectrum loops, translated as `tr(label)`
+                crate::template::DEFAULT
+                    .split("(\"")
+                    .skip(1)
+                    .filter_map(|rest| rest.split_once("\", stats."))
+                    .map(|(id, _)| id),
+            )
+            .collect();
+        assert!(ids.len() > 50, "found only {ids:?}");
+        ids
+    }
+
+    #[test]
+    fn every_language_has_every_string_of_the_report() {
+        for lang in Lang::value_variants() {
+            let translations = Translations::new(*lang);
+            for id in template_ids() {
+                assert!(translations.has(id), "{lang:?} has no {id}");
+            }
+        }
+    }
+
+    #[test]
+    fn fills_in_the_arguments() {
+        let mut args = FluentArgs::new();
+        args.set("seconds", "1.50");
+        let de = Translations::new(Lang::De);
+        assert_eq!(
+            de.tr("insufficient-sample", &args),
+            "Zu wenig Daten, nur 1.50s"
+        );
+        assert_eq!(de.tr("rehook", &FluentArgs::new()), "Nachhaken");
+        assert_eq!(de.tr("missing", &FluentArgs::new()), "missing");
+    }
+}
@@ -0,0 +1,197 @@
+use schemars::JsonSchema;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use crate::data::{Inputs, Position, Velocity, VelocityPrecision};
+
+/// Samples further apart than this aren't interpolated between, the tee died or the snapshots
+/// went missing and the client wouldn't have anything to interpolate either.
+const MAX_STEP_SECONDS: f32 = 0.2;
+
+/// A sample at a point between two ticks.
+#[derive(Debug, Clone, JsonSchema)]
+pub struct Frame {
+    /// How far the frame is between `tick` and the next tick, from 0 to 1
+    pub intra_tick: f32,
+    #[serde(flatten)]
+    pub inputs: Inputs,
+}
+
+/// Written as a struct rather than with `#[serde(flatten)]`, which turns it into a map whose
+/// keys `--key-style` can't tell apart from player names.
+impl Serialize for Frame {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Inputs {
+            tick,
+            pos,
+            vel,
+            angle,
+            direction,
+            hook_state,
+            hook_tick,
+            hook_pos,
+            hook_direction,
+            health,
+            armor,
+            ammo_count,
+            weapon,
+            emote,
+            attack_tick,
+            freeze_end,
+            jumps,
+            tele_checkpoint,
+            strong_weak_id,
+            jumped_total,
+            ninja_activation_tick,
+            target,
+            score,
+        } = &self.inputs;
+        let mut frame = serializer.serialize_struct("Frame", 24)?;
+        frame.serialize_field("intra_tick", &self.intra_tick)?;
+        frame.serialize_field("tick", tick)?;
+        frame.serialize_field("pos", pos)?;
+        frame.serialize_field("vel", vel)?;
+        frame.serialize_field("angle", angle)?;
+        frame.serialize_field("direction", direction)?;
+        frame.serialize_field("hook_state", hook_state)?;
+        frame.serialize_field("hook_tick", hook_tick)?;
+        frame.serialize_field("hook_pos", hook_pos)?;
+        frame.serialize_field("hook_direction", hook_direction)?;
+        frame.serialize_field("health", health)?;
+        frame.serialize_field("armor", armor)?;
+        frame.serialize_field("ammo_count", ammo_count)?;
+        frame.serialize_field("weapon", weapon)?;
+        frame.serialize_field("emote", emote)?;
+        frame.serialize_field("attack_tick", attack_tick)?;
+        frame.serialize_field("freeze_end", freeze_end)?;
+        frame.serialize_field("jumps", jumps)?;
+        frame.serialize_field("tele_checkpoint", tele_checkpoint)?;
+        frame.serialize_field("strong_weak_id", strong_weak_id)?;
+        frame.serialize_field("jumped_total", jumped_total)?;
+        frame.serialize_field("ninja_activation_tick", ninja_activation_tick)?;
+        frame.serialize_field("target", target)?;
+        frame.serialize_field("score", score)?;
+        frame.end()
+    }
+}
+
+fn mix(from: f32, to: f32, amount: f32) -> f32 {
+    from + (to - from) * amount
+}
+
+fn mix_position(from: &Position, to: &Position, amount: f32) -> Position {
+    let ((from_x, from_y), (to_x, to_y)) = (from.tiles(), to.tiles());
+    Position::from_tiles(mix(from_x, to_x, amount), mix(from_y, to_y, amount))
+}
+
+fn mix_velocity(from: &Velocity, to: &Velocity, amount: f32) -> Velocity {
+    Velocity {
+        x: VelocityPrecision::from_num(mix(from.x.to_num(), to.x.to_num(), amount)),
+        y: VelocityPrecision::from_num(mix(from.y.to_num(), to.y.to_num(), amount)),
+    }
+}
+
+/// The sample at the time, in ticks, with positions and velocities mixed between the samples
+/// before and after it like the client does. Everything else, like the pressed keys, is taken
+/// from the sample before. `index` is the sample at or before the time.
+pub fn at(samples: &[Inputs], index: usize, time: f64, tick_rate: i32) -> Frame {
+    let from = &samples[index];
+    let mut inputs = from.clone();
+    if let Some(to) = samples.get(index + 1) {
+        let step = (to.tick - from.tick) as f32;
+        if step > 0.0 && step <= MAX_STEP_SECONDS * tick_rate as f32 {
+            let amount = ((time - from.tick as f64) as f32 / step).clamp(0.0, 1.0);
+            inputs.pos = mix_position(&from.pos, &to.pos, amount);
+            inputs.vel = mix_velocity(&from.vel, &to.vel, amount);
+            inputs.hook_pos = mix_position(&from.hook_pos, &to.hook_pos, amount);
+            inputs.target = mix_position(&from.target, &to.target, amount);
+        }
+    }
+    inputs.tick = time.floor() as i32;
+    Frame {
+        intra_tick: time.fract() as f32,
+        inputs,
+    }
+}
+
+/// Resamples the inputs at `hz` frames per second, from the first sample to the last.
+pub fn interpolate(samples: &[Inputs], hz: u32, tick_rate: i32) -> Vec<Frame> {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return Vec::new();
+    };
+    let ticks_per_frame = tick_rate as f64 / hz as f64;
+    let frames = ((last.tick - first.tick) as f64 / ticks_per_frame) as usize;
+    let mut index = 0;
+    (0..=frames)
+        .map(|frame| {
+            let time = first.tick as f64 + frame as f64 * ticks_per_frame;
+            while index + 1 < samples.len() && samples[index + 1].tick as f64 <= time {
+                index += 1;
+            }
+            at(samples, index, time, tick_rate)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{KeyStyle, Keys, Style, Tagged};
+    use fixed::types::I27F5;
+    use twsnap::{
+        items::{Player, Tee},
+        time::{Duration, Instant},
+    };
+
+    fn sample(tick: i32, x: i32) -> Inputs {
+        let tee = Tee {
+            tick: Instant::zero() + Duration::from_ticks(tick),
+            pos: twsnap::Position::new(I27F5::from_num(x), I27F5::ZERO),
+            ..Default::default()
+        };
+        (&Player::default(), &tee).into()
+    }
+
+    fn keys(value: &serde_json::Value) -> Vec<String> {
+        let mut keys: Vec<String> = value.as_object().unwrap().keys().cloned().collect();
+        keys.sort();
+        keys
+    }
+
+    #[test]
+    fn writes_every_field_of_the_inputs() {
+        let frame = at(&[sample(0, 1), sample(1, 2)], 0, 0.5, 50);
+        assert_eq!(frame.inputs.pos, Position::from_tiles(1.5, 0.0));
+
+        let mut expected = keys(&serde_json::to_value(&frame.inputs).unwrap());
+        expected.push("intra_tick".to_string());
+        expected.sort();
+        assert_eq!(keys(&serde_json::to_value(&frame).unwrap()), expected);
+    }
+
+    #[test]
+    fn renames_the_fields_with_the_key_style() {
+        let frames = interpolate(&[sample(0, 1), sample(1, 2)], 100, 50);
+        let keys_of = |keys: KeyStyle| {
+            let style = Style {
+                keys,
+                ..Default::default()
+            };
+            let keys = Keys {
+                style,
+                ..Default::default()
+            };
+            let value = serde_json::to_value(Tagged(&frames, &keys)).unwrap();
+            self::keys(&value[1])
+        };
+
+        let camel = keys_of(KeyStyle::Camel);
+        for key in ["intraTick", "hookState", "ammoCount", "ninjaActivationTick"] {
+            assert!(camel.iter().any(|k| k == key), "{key} missing in {camel:?}");
+        }
+        assert!(camel.iter().all(|k| !k.contains('_')), "{camel:?}");
+
+        let kebab = keys_of(KeyStyle::Kebab);
+        assert!(kebab.iter().any(|k| k == "intra-tick"), "{kebab:?}");
+        assert!(kebab.iter().all(|k| !k.contains('_')), "{kebab:?}");
+    }
+}
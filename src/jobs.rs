@@ -0,0 +1,162 @@
+use std::{
+    path::Path,
+    sync::{Condvar, Mutex},
+};
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+}
+
+impl JobStatus {
+    fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Done => "done",
+            JobStatus::Failed => "failed",
+        }
+    }
+
+    fn parse(status: &str) -> Self {
+        match status {
+            "running" => JobStatus::Running,
+            "done" => JobStatus::Done,
+            "failed" => JobStatus::Failed,
+            _ => JobStatus::Queued,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Job {
+    pub id: i64,
+    pub name: String,
+    pub status: JobStatus,
+    /// The summary of the demo, once the job is done
+    pub result: Option<serde_json::Value>,
+    pub error: Option<String>,
+}
+
+/// A demo waiting to be analyzed, taken from the queue by a worker.
+pub struct PendingJob {
+    pub id: i64,
+    pub name: String,
+    pub filter: String,
+    pub demo: Vec<u8>,
+}
+
+/// Job queue backed by SQLite, so queued jobs and results survive restarts.
+pub struct JobStore {
+    connection: Mutex<Connection>,
+    queued: Condvar,
+}
+
+impl JobStore {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS jobs (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                name TEXT NOT NULL,
+                filter TEXT NOT NULL,
+                demo BLOB,
+                status TEXT NOT NULL,
+                result TEXT,
+                error TEXT
+            );
+            CREATE INDEX IF NOT EXISTS jobs_status ON jobs (status);",
+        )?;
+        // Jobs that were running when the server stopped have to be redone
+        connection.execute(
+            "UPDATE jobs SET status = ?1 WHERE status = ?2",
+            params![JobStatus::Queued.as_str(), JobStatus::Running.as_str()],
+        )?;
+        Ok(Self {
+            connection: Mutex::new(connection),
+            queued: Condvar::new(),
+        })
+    }
+
+    pub fn submit(&self, name: &str, filter: &str, demo: &[u8]) -> anyhow::Result<i64> {
+        let connection = self.connection.lock().unwrap();
+        connection.execute(
+            "INSERT INTO jobs (name, filter, demo, status) VALUES (?1, ?2, ?3, ?4)",
+            params![name, filter, demo, JobStatus::Queued.as_str()],
+        )?;
+        let id = connection.last_insert_rowid();
+        self.queued.notify_one();
+        Ok(id)
+    }
+
+    pub fn get(&self, id: i64) -> anyhow::Result<Option<Job>> {
+        let connection = self.connection.lock().unwrap();
+        let job = connection
+            .query_row(
+                "SELECT id, name, status, result, error FROM jobs WHERE id = ?1",
+                [id],
+                |row| {
+                    let status: String = row.get(2)?;
+                    let result: Option<String> = row.get(3)?;
+                    Ok(Job {
+                        id: row.get(0)?,
+                        name: row.get(1)?,
+                        status: JobStatus::parse(&status),
+                        result: result.and_then(|r| serde_json::from_str(&r).ok()),
+                        error: row.get(4)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(job)
+    }
+
+    /// Blocks until a job is queued and marks it as running.
+    pub fn take(&self) -> anyhow::Result<PendingJob> {
+        let mut connection = self.connection.lock().unwrap();
+        loop {
+            let job = connection
+                .query_row(
+                    "SELECT id, name, filter, demo FROM jobs WHERE status = ?1 ORDER BY id LIMIT 1",
+                    [JobStatus::Queued.as_str()],
+                    |row| {
+                        Ok(PendingJob {
+                            id: row.get(0)?,
+                            name: row.get(1)?,
+                            filter: row.get(2)?,
+                            demo: row.get(3)?,
+                        })
+                    },
+                )
+                .optional()?;
+            if let Some(job) = job {
+                connection.execute(
+                    "UPDATE jobs SET status = ?1 WHERE id = ?2",
+                    params![JobStatus::Running.as_str(), job.id],
+                )?;
+                return Ok(job);
+            }
+            connection = self.queued.wait(connection).unwrap();
+        }
+    }
+
+    /// Stores the outcome of a job. The demo itself isn't needed anymore afterwards.
+    pub fn finish(&self, id: i64, result: Result<String, String>) -> anyhow::Result<()> {
+        let (status, result, error) = match result {
+            Ok(result) => (JobStatus::Done, Some(result), None),
+            Err(error) => (JobStatus::Failed, None, Some(error)),
+        };
+        self.connection.lock().unwrap().execute(
+            "UPDATE jobs SET status = ?1, result = ?2, error = ?3, demo = NULL WHERE id = ?4",
+            params![status.as_str(), result, error, id],
+        )?;
+        Ok(())
+    }
+}
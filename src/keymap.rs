@@ -0,0 +1,144 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use eframe::egui::{Key, KeyboardShortcut, Modifiers};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    PreviousFrame,
+    NextFrame,
+    Back1s,
+    Forward1s,
+    Back10s,
+    Forward10s,
+    PreviousDirectionChange,
+    NextDirectionChange,
+    PreviousHook,
+    NextHook,
+    PreviousPlayer,
+    NextPlayer,
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::PreviousFrame => "Previous frame",
+            Action::NextFrame => "Next frame",
+            Action::Back1s => "Back 1s",
+            Action::Forward1s => "Forward 1s",
+            Action::Back10s => "Back 10s",
+            Action::Forward10s => "Forward 10s",
+            Action::PreviousDirectionChange => "Previous direction change",
+            Action::NextDirectionChange => "Next direction change",
+            Action::PreviousHook => "Previous hook",
+            Action::NextHook => "Next hook",
+            Action::PreviousPlayer => "Previous player",
+            Action::NextPlayer => "Next player",
+        }
+    }
+}
+
+fn default_bindings() -> BTreeMap<Action, Vec<String>> {
+    [
+        (Action::PreviousFrame, "Left"),
+        (Action::NextFrame, "Right"),
+        (Action::Back1s, "Shift+Left"),
+        (Action::Forward1s, "Shift+Right"),
+        (Action::Back10s, "Ctrl+Left"),
+        (Action::Forward10s, "Ctrl+Right"),
+        (Action::PreviousDirectionChange, "Shift+D"),
+        (Action::NextDirectionChange, "D"),
+        (Action::PreviousHook, "Shift+H"),
+        (Action::NextHook, "H"),
+        (Action::PreviousPlayer, "Up"),
+        (Action::NextPlayer, "Down"),
+    ]
+    .into_iter()
+    .map(|(action, key)| (action, vec![key.to_owned()]))
+    .collect()
+}
+
+/// Parses bindings like `Ctrl+Shift+Right`.
+fn parse_shortcut(binding: &str) -> anyhow::Result<KeyboardShortcut> {
+    let mut parts: Vec<_> = binding.split('+').map(str::trim).collect();
+    let key = parts.pop().unwrap_or_default();
+    let Some(key) = Key::from_name(key) else {
+        bail!("Unknown key {key:?} in {binding:?}");
+    };
+    let mut modifiers = Modifiers::NONE;
+    for modifier in parts {
+        modifiers = modifiers
+            | match modifier.to_lowercase().as_str() {
+                "ctrl" | "control" => Modifiers::CTRL,
+                "shift" => Modifiers::SHIFT,
+                "alt" => Modifiers::ALT,
+                "cmd" | "command" => Modifiers::COMMAND,
+                _ => bail!("Unknown modifier {modifier:?} in {binding:?}"),
+            };
+    }
+    Ok(KeyboardShortcut::new(modifiers, key))
+}
+
+/// `keymap.toml` in the config directory of the user.
+pub fn default_path() -> Option<PathBuf> {
+    let config = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+    Some(config.join("demo_analyzer").join("keymap.toml"))
+}
+
+pub struct Keymap {
+    /// Most specific shortcuts first, egui ignores extra Shift and Alt when matching
+    bindings: Vec<(KeyboardShortcut, Action)>,
+    names: BTreeMap<Action, Vec<String>>,
+}
+
+impl Keymap {
+    /// The default bindings, with the actions in the file replacing theirs. A missing file
+    /// just keeps the defaults.
+    pub fn load(path: Option<&Path>) -> anyhow::Result<Self> {
+        let mut names = default_bindings();
+        if let Some(path) = path.filter(|p| p.exists()) {
+            let loaded: BTreeMap<Action, Vec<String>> =
+                toml::from_str(&std::fs::read_to_string(path)?)
+                    .with_context(|| format!("Couldn't read keymap {path:?}"))?;
+            names.extend(loaded);
+        }
+        let mut bindings = Vec::new();
+        for (action, keys) in &names {
+            for key in keys {
+                bindings.push((parse_shortcut(key)?, *action));
+            }
+        }
+        bindings.sort_by_key(|(shortcut, _)| {
+            let m = shortcut.modifiers;
+            let count = [m.ctrl, m.shift, m.alt, m.command]
+                .iter()
+                .filter(|m| **m)
+                .count();
+            std::cmp::Reverse(count)
+        });
+        Ok(Self { bindings, names })
+    }
+
+    /// The actions whose shortcuts were pressed this frame.
+    pub fn pressed(&self, ctx: &eframe::egui::Context) -> Vec<Action> {
+        ctx.input_mut(|i| {
+            self.bindings
+                .iter()
+                .filter(|(shortcut, _)| i.consume_shortcut(shortcut))
+                .map(|(_, action)| *action)
+                .collect()
+        })
+    }
+
+    pub fn names(&self) -> &BTreeMap<Action, Vec<String>> {
+        &self.names
+    }
+}
@@ -0,0 +1,464 @@
+use std::collections::BTreeMap;
+
+use clap::ValueEnum;
+use serde::{
+    ser::{self, SerializeMap},
+    Serialize, Serializer,
+};
+
+use crate::{data::Units, select::Select};
+
+/// How the field names of structured outputs are written. Map keys like player names and
+/// enum values stay as they are.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeyStyle {
+    /// `time_played`
+    #[default]
+    Snake,
+    /// `timePlayed`
+    Camel,
+    /// `time-played`
+    Kebab,
+}
+
+impl KeyStyle {
+    fn convert(self, key: &str) -> String {
+        match self {
+            KeyStyle::Snake => key.to_string(),
+            KeyStyle::Kebab => key.replace('_', "-"),
+            KeyStyle::Camel => {
+                let mut result = String::with_capacity(key.len());
+                let mut upper = false;
+                for c in key.chars() {
+                    match c {
+                        '_' if !result.is_empty() => upper = true,
+                        '_' => {}
+                        c if upper => {
+                            result.extend(c.to_uppercase());
+                            upper = false;
+                        }
+                        c => result.push(c),
+                    }
+                }
+                result
+            }
+        }
+    }
+}
+
+/// How the field names and positions of structured outputs are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Style {
+    pub keys: KeyStyle,
+    pub units: Units,
+}
+
+/// What structured outputs get on top of the data.
+#[derive(Debug, Clone, Default)]
+pub struct Keys {
+    pub style: Style,
+    /// Written next to the output, which goes under `data` then
+    pub tags: BTreeMap<String, String>,
+    /// Applied to the output before the tags are added
+    pub select: Option<Select>,
+}
+
+impl Keys {
+    /// The same without renaming the fields, for outputs whose field names are fixed.
+    pub fn snake_case(&self) -> Keys {
+        Keys {
+            style: Style {
+                keys: KeyStyle::Snake,
+                ..self.style
+            },
+            ..self.clone()
+        }
+    }
+}
+
+/// Splits a `--tag` into its key and value.
+pub fn parse_tag(tag: &str) -> Result<(String, String), String> {
+    match tag.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_string(), value.to_string())),
+        _ => Err(format!("expected key=value, got `{tag}`")),
+    }
+}
+
+/// Serializes the value with the field names written in the given style. With tags, the
+/// value is put under `data` next to them, so they can't clash with its own keys.
+pub struct Tagged<'a, T: ?Sized>(pub &'a T, pub &'a Keys);
+
+impl<T: Serialize + ?Sized> Serialize for Tagged<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Tagged(value, keys) = *self;
+        if keys.tags.is_empty() {
+            return Styled(value, keys.style).serialize(serializer);
+        }
+        let mut map = serializer.serialize_map(Some(2))?;
+        map.serialize_entry(&keys.style.keys.convert("tags"), &keys.tags)?;
+        map.serialize_entry(&keys.style.keys.convert("data"), &Styled(value, keys.style))?;
+        map.end()
+    }
+}
+
+/// Serializes the value with the field names and positions written in the given style.
+pub struct Styled<'a, T: ?Sized>(pub &'a T, pub Style);
+
+impl<T: Serialize + ?Sized> Serialize for Styled<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let Styled(value, style) = *self;
+        style
+            .units
+            .scope(|| Renamed(value, style.keys).serialize(serializer))
+    }
+}
+
+/// Serializes the value with the field names written in the given style.
+struct Renamed<'a, T: ?Sized>(&'a T, KeyStyle);
+
+impl<T: Serialize + ?Sized> Serialize for Renamed<'_, T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if self.1 == KeyStyle::Snake {
+            return self.0.serialize(serializer);
+        }
+        self.0.serialize(KeySerializer {
+            inner: serializer,
+            keys: self.1,
+        })
+    }
+}
+
+struct KeySerializer<S> {
+    inner: S,
+    keys: KeyStyle,
+}
+
+/// Passes everything on to the wrapped serializer, only renaming the fields of structs and
+/// struct variants on the way. Serde only takes field names that live forever, so structs
+/// are written as maps with the renamed keys instead.
+struct Compound<C> {
+    inner: C,
+    keys: KeyStyle,
+}
+
+impl<S: Serializer> Serializer for KeySerializer<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+    type SerializeSeq = Compound<S::SerializeSeq>;
+    type SerializeTuple = Compound<S::SerializeTuple>;
+    type SerializeTupleStruct = Compound<S::SerializeTupleStruct>;
+    type SerializeTupleVariant = Compound<S::SerializeTupleVariant>;
+    type SerializeMap = Compound<S::SerializeMap>;
+    type SerializeStruct = Compound<S::SerializeMap>;
+    type SerializeStructVariant = VariantFields<S>;
+
+    fn serialize_bool(self, v: bool) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_bool(v)
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i8(v)
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i16(v)
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i32(v)
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i64(v)
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_i128(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u8(v)
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u16(v)
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u32(v)
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u64(v)
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_u128(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_f32(v)
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_f64(v)
+    }
+
+    fn serialize_char(self, v: char) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_char(v)
+    }
+
+    fn serialize_str(self, v: &str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_str(v)
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_bytes(v)
+    }
+
+    fn serialize_none(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_none()
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_some(&Renamed(value, self.keys))
+    }
+
+    fn serialize_unit(self) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit()
+    }
+
+    fn serialize_unit_struct(self, name: &'static str) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_struct(name)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner.serialize_unit_variant(name, index, variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner
+            .serialize_newtype_struct(name, &Renamed(value, self.keys))
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<S::Ok, S::Error> {
+        self.inner
+            .serialize_newtype_variant(name, index, variant, &Renamed(value, self.keys))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<Self::SerializeSeq, S::Error> {
+        Ok(Compound {
+            inner: self.inner.serialize_seq(len)?,
+            keys: self.keys,
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple, S::Error> {
+        Ok(Compound {
+            inner: self.inner.serialize_tuple(len)?,
+            keys: self.keys,
+        })
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        name: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleStruct, S::Error> {
+        Ok(Compound {
+            inner: self.inner.serialize_tuple_struct(name, len)?,
+            keys: self.keys,
+        })
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeTupleVariant, S::Error> {
+        Ok(Compound {
+            inner: self
+                .inner
+                .serialize_tuple_variant(name, index, variant, len)?,
+            keys: self.keys,
+        })
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap, S::Error> {
+        Ok(Compound {
+            inner: self.inner.serialize_map(len)?,
+            keys: self.keys,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStruct, S::Error> {
+        Ok(Compound {
+            inner: self.inner.serialize_map(Some(len))?,
+            keys: self.keys,
+        })
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        index: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<Self::SerializeStructVariant, S::Error> {
+        Ok(VariantFields {
+            inner: self.inner,
+            keys: self.keys,
+            name,
+            index,
+            variant,
+            fields: serde_json::Map::with_capacity(len),
+        })
+    }
+
+    fn is_human_readable(&self) -> bool {
+        self.inner.is_human_readable()
+    }
+}
+
+impl<C: ser::SerializeSeq> ser::SerializeSeq for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_element(&Renamed(value, self.keys))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeTuple> ser::SerializeTuple for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_element(&Renamed(value, self.keys))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeTupleStruct> ser::SerializeTupleStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(&Renamed(value, self.keys))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeTupleVariant> ser::SerializeTupleVariant for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_field(&Renamed(value, self.keys))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeMap> ser::SerializeMap for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    // The keys are data like player names, only the values get styled
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), C::Error> {
+        self.inner.serialize_key(key)
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), C::Error> {
+        self.inner.serialize_value(&Renamed(value, self.keys))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+impl<C: ser::SerializeMap> ser::SerializeStruct for Compound<C> {
+    type Ok = C::Ok;
+    type Error = C::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), C::Error> {
+        self.inner
+            .serialize_entry(&self.keys.convert(key), &Renamed(value, self.keys))
+    }
+
+    fn end(self) -> Result<C::Ok, C::Error> {
+        self.inner.end()
+    }
+}
+
+/// Struct variants can't be turned into maps on the fly, their fields are collected and
+/// written as a newtype variant holding the map at the end.
+struct VariantFields<S> {
+    inner: S,
+    keys: KeyStyle,
+    name: &'static str,
+    index: u32,
+    variant: &'static str,
+    fields: serde_json::Map<String, serde_json::Value>,
+}
+
+impl<S: Serializer> ser::SerializeStructVariant for VariantFields<S> {
+    type Ok = S::Ok;
+    type Error = S::Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &T,
+    ) -> Result<(), S::Error> {
+        // Going through the text keeps f32s as short as they are written
+        let value = serde_json::to_string(&Renamed(value, self.keys))
+            .and_then(|text| serde_json::from_str(&text))
+            .map_err(ser::Error::custom)?;
+        self.fields.insert(self.keys.convert(key), value);
+        Ok(())
+    }
+
+    fn end(self) -> Result<S::Ok, S::Error> {
+        self.inner
+            .serialize_newtype_variant(self.name, self.index, self.variant, &self.fields)
+    }
+}
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use rusqlite::{params, Connection};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// What a human decided after looking at a player.
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Legit,
+    Cheating,
+    Unsure,
+}
+
+impl Verdict {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Verdict::Legit => "legit",
+            Verdict::Cheating => "cheating",
+            Verdict::Unsure => "unsure",
+        }
+    }
+
+    fn parse(verdict: &str) -> Self {
+        match verdict {
+            "legit" => Verdict::Legit,
+            "cheating" => Verdict::Cheating,
+            _ => Verdict::Unsure,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Label {
+    /// sha256 of the demo file, so renamed copies keep their labels
+    pub demo_hash: String,
+    /// File name of the demo when it was labeled
+    pub demo: String,
+    pub player: String,
+    pub verdict: Verdict,
+    pub notes: Option<String>,
+}
+
+pub fn demo_hash(path: &Path) -> anyhow::Result<String> {
+    let hash = Sha256::digest(std::fs::read(path)?);
+    Ok(hash.iter().map(|b| format!("{b:02x}")).collect())
+}
+
+/// Verdicts per demo and player backed by SQLite, the labeled dataset for training models on
+/// the output of export-features.
+pub struct LabelStore {
+    connection: Connection,
+}
+
+impl LabelStore {
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let connection = Connection::open(path)?;
+        connection.execute_batch(
+            "CREATE TABLE IF NOT EXISTS labels (
+                demo_hash TEXT NOT NULL,
+                demo TEXT NOT NULL,
+                player TEXT NOT NULL,
+                verdict TEXT NOT NULL,
+                notes TEXT,
+                PRIMARY KEY (demo_hash, player)
+            );",
+        )?;
+        Ok(Self { connection })
+    }
+
+    /// Records the verdict, replacing an earlier one for the same player in the same demo.
+    pub fn set(&self, label: &Label) -> anyhow::Result<()> {
+        self.connection.execute(
+            "INSERT OR REPLACE INTO labels (demo_hash, demo, player, verdict, notes)
+            VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                label.demo_hash,
+                label.demo,
+                label.player,
+                label.verdict.as_str(),
+                label.notes
+            ],
+        )?;
+        Ok(())
+    }
+
+    /// Every label, or only the ones of the demo with the given hash.
+    pub fn list(&self, demo_hash: Option<&str>) -> anyhow::Result<Vec<Label>> {
+        let mut statement = self.connection.prepare(
+            "SELECT demo_hash, demo, player, verdict, notes FROM labels
+            WHERE ?1 IS NULL OR demo_hash = ?1 ORDER BY demo, player",
+        )?;
+        let labels = statement
+            .query_map([demo_hash], |row| {
+                let verdict: String = row.get(3)?;
+                Ok(Label {
+                    demo_hash: row.get(0)?,
+                    demo: row.get(1)?,
+                    player: row.get(2)?,
+                    verdict: Verdict::parse(&verdict),
+                    notes: row.get(4)?,
+                })
+            })?
+            .collect::<Result<_, _>>()?;
+        Ok(labels)
+    }
+}
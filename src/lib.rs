@@ -0,0 +1,30 @@
+//! Streams the inputs and events of the players out of a Teeworlds or DDNet demo, for tools
+//! that compute their own statistics in one pass.
+//!
+//! ```no_run
+//! use std::{collections::HashMap, fs::File, io::BufReader};
+//!
+//! use demo_analyzer::{DemoAnalyzer, DemoReader, EventKind};
+//!
+//! let reader = DemoReader::new(BufReader::new(File::open("demo.demo")?))?;
+//! let mut jumps = HashMap::<String, i32>::new();
+//! let mut deaths = 0;
+//! DemoAnalyzer::new(reader)
+//!     .on_tick(|player, inputs| {
+//!         jumps.insert(player.to_string(), inputs.jumped_total);
+//!     })
+//!     .on_event(|event| {
+//!         if event.kind == EventKind::Death {
+//!             deaths += 1;
+//!         }
+//!     })
+//!     .run();
+//! # anyhow::Ok(())
+//! ```
+
+pub mod data;
+mod stream;
+mod tee;
+
+pub use stream::{DemoAnalyzer, Event, EventKind};
+pub use twsnap::compat::ddnet::DemoReader;
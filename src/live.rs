@@ -0,0 +1,270 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    time::Duration,
+};
+
+use anyhow::{bail, Context};
+
+use crate::{
+    names::NameFilter,
+    teehistorian::{Event, Input, Teehistorian},
+    timeline::{TimeFormat, Timeline},
+};
+
+pub struct LiveOptions {
+    pub filter: NameFilter,
+    pub tick_rate: i32,
+    /// Seconds the average rates are taken over
+    pub window: f32,
+    /// Seconds between two reports
+    pub interval: f32,
+    /// Changes within one second that trigger an alert
+    pub max_direction_changes: usize,
+    pub max_hook_changes: usize,
+    pub time_format: TimeFormat,
+}
+
+/// A connection to the external console of the server, alerts are echoed to its console.
+pub struct Econ {
+    stream: TcpStream,
+}
+
+impl Econ {
+    pub fn connect(address: &str, password: &str) -> anyhow::Result<Self> {
+        let mut stream = TcpStream::connect(address)
+            .with_context(|| format!("Couldn't connect to econ at {address}"))?;
+        stream.set_read_timeout(Some(Duration::from_secs(10)))?;
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        if !line.contains("Enter password") {
+            bail!("Unexpected econ greeting: {}", line.trim());
+        }
+        writeln!(stream, "{password}")?;
+        line.clear();
+        reader.read_line(&mut line)?;
+        if !line.contains("Authentication successful") {
+            bail!("Econ login failed: {}", line.trim());
+        }
+        // The server sends its whole log, read it so its send buffer never fills up
+        reader.get_ref().set_read_timeout(None)?;
+        std::thread::spawn(move || io::copy(&mut reader, &mut io::sink()));
+        Ok(Self { stream })
+    }
+
+    fn echo(&mut self, message: &str) -> io::Result<()> {
+        // Quoted, so player names can't end the command and start another one
+        let message: String = message
+            .chars()
+            .filter(|c| !c.is_control())
+            .flat_map(|c| match c {
+                '"' | '\\' => vec!['\\', c],
+                c => vec![c],
+            })
+            .collect();
+        writeln!(self.stream, "echo \"{message}\"")
+    }
+}
+
+#[derive(Default)]
+struct Player {
+    name: Option<String>,
+    last_input: Option<Input>,
+    direction_changes: VecDeque<i32>,
+    hook_changes: VecDeque<i32>,
+    direction_alert: bool,
+    hook_alert: bool,
+}
+
+impl Player {
+    fn name(&self, client: i32) -> String {
+        self.name
+            .clone()
+            .unwrap_or_else(|| format!("client {client}"))
+    }
+}
+
+/// Changes in the last second and average changes per second over the window.
+fn rates(changes: &VecDeque<i32>, tick: i32, tick_rate: i32, window: f32) -> (usize, f32) {
+    let last_second = changes
+        .iter()
+        .rev()
+        .take_while(|t| **t > tick - tick_rate)
+        .count();
+    (last_second, changes.len() as f32 / window)
+}
+
+struct Live<'a> {
+    options: &'a LiveOptions,
+    timeline: Timeline,
+    players: BTreeMap<i32, Player>,
+    econ: Option<Econ>,
+    out: Box<dyn Write>,
+    /// Whether any alert fired, for the exit code
+    alerted: bool,
+}
+
+impl Live<'_> {
+    fn matches(&self, player: &Player) -> bool {
+        self.options.filter.is_empty()
+            || player
+                .name
+                .as_ref()
+                .is_some_and(|n| self.options.filter.matches(n))
+    }
+
+    fn time(&self, tick: i32) -> String {
+        self.options.time_format.format(self.timeline.seconds(tick))
+    }
+
+    fn input(&mut self, client: i32, input: Input, tick: i32) -> anyhow::Result<()> {
+        let window = (self.options.window * self.options.tick_rate as f32) as i32;
+        let player = self.players.entry(client).or_default();
+        if let Some(last) = player.last_input {
+            if last.direction != input.direction {
+                player.direction_changes.push_back(tick);
+            }
+            if last.hook != input.hook {
+                player.hook_changes.push_back(tick);
+            }
+        }
+        player.last_input = Some(input);
+        for changes in [&mut player.direction_changes, &mut player.hook_changes] {
+            while changes.front().is_some_and(|t| *t <= tick - window) {
+                changes.pop_front();
+            }
+        }
+
+        let player = &self.players[&client];
+        if !self.matches(player) {
+            return Ok(());
+        }
+        let name = player.name(client);
+        let tick_rate = self.options.tick_rate;
+        let (direction, _) = rates(&player.direction_changes, tick, tick_rate, 1.0);
+        let (hook, _) = rates(&player.hook_changes, tick, tick_rate, 1.0);
+        let direction_alert = direction > self.options.max_direction_changes;
+        let hook_alert = hook > self.options.max_hook_changes;
+        let mut alerts = Vec::new();
+        if direction_alert && !player.direction_alert {
+            alerts.push(format!(
+                "{name} changed direction {direction} times in one second"
+            ));
+        }
+        if hook_alert && !player.hook_alert {
+            alerts.push(format!("{name} changed hook {hook} times in one second"));
+        }
+
+        let player = self.players.get_mut(&client).unwrap();
+        player.direction_alert = direction_alert;
+        player.hook_alert = hook_alert;
+        self.alerted |= !alerts.is_empty();
+        for alert in alerts {
+            writeln!(self.out, "{} ALERT {alert}", self.time(tick))?;
+            if let Some(econ) = &mut self.econ {
+                if let Err(e) = econ.echo(&format!("demo_analyzer: {alert}")) {
+                    eprintln!("Couldn't send alert to econ: {e}");
+                    self.econ = None;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn report(&mut self, tick: i32) -> anyhow::Result<()> {
+        let tick_rate = self.options.tick_rate;
+        // Until the window is full, the rates are taken over the time that already passed
+        let window = self
+            .timeline
+            .seconds(tick)
+            .clamp(1.0 / tick_rate as f32, self.options.window);
+        let mut lines = Vec::new();
+        for (client, player) in &self.players {
+            if !self.matches(player) {
+                continue;
+            }
+            let (direction_last, direction) =
+                rates(&player.direction_changes, tick, tick_rate, window);
+            let (hook_last, hook) = rates(&player.hook_changes, tick, tick_rate, window);
+            lines.push(format!(
+                "{} {:<15} direction {direction:>5.2}/s ({direction_last} last second), hook {hook:>5.2}/s ({hook_last} last second)",
+                self.time(tick),
+                player.name(*client),
+            ));
+        }
+        for line in lines {
+            writeln!(self.out, "{line}")?;
+        }
+        self.out.flush()?;
+        Ok(())
+    }
+}
+
+/// Follows the teehistorian file and reports the change rates of the players every interval,
+/// alerting as soon as a player crosses a threshold.
+pub fn run(
+    source: impl Read,
+    options: &LiveOptions,
+    econ: Option<Econ>,
+    out: Box<dyn Write>,
+) -> anyhow::Result<bool> {
+    let mut teehistorian = Teehistorian::new(source)?;
+    let interval = (options.interval * options.tick_rate as f32) as i32;
+    let mut live = Live {
+        options,
+        timeline: Timeline {
+            start_tick: 0,
+            tick_rate: options.tick_rate,
+            ..Timeline::default()
+        },
+        players: BTreeMap::new(),
+        econ,
+        out,
+        alerted: false,
+    };
+    if let Some(map) = teehistorian.header["map_name"].as_str() {
+        writeln!(live.out, "Following game on {map}")?;
+    }
+
+    let mut started = false;
+    let mut tick = 0;
+    let mut next_report = 0;
+    loop {
+        let event = match teehistorian.next_event() {
+            Ok(event) => event,
+            // Only happens when not following, the file ended without the server closing it
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => Event::Finish,
+            Err(e) => return Err(e.into()),
+        };
+        match event {
+            Event::Tick(t) => {
+                tick = t;
+                if !started {
+                    started = true;
+                    live.timeline.start_tick = tick;
+                    next_report = tick + interval;
+                }
+                if tick >= next_report {
+                    live.report(tick)?;
+                    next_report = tick + interval;
+                }
+            }
+            Event::Join(client) => {
+                live.players.insert(client, Player::default());
+            }
+            Event::Drop(client) => {
+                live.players.remove(&client);
+            }
+            Event::Name { client, name } => {
+                live.players.entry(client).or_default().name = Some(name);
+            }
+            Event::Input { client, input } => live.input(client, input, tick)?,
+            Event::Finish => {
+                live.report(tick)?;
+                return Ok(live.alerted);
+            }
+        }
+    }
+}
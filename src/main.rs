@@ -1,19 +1,150 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
-use std::{collections::HashMap, fs::File, io::BufReader, path::PathBuf, process::exit};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    ffi::OsString,
+    fs::File,
+    io::{self, BufReader, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::exit,
+};
 
-use clap::{Parser, Subcommand, ValueEnum};
+use anyhow::{bail, Context};
+use clap::{
+    builder::{PossibleValuesParser, TypedValueParser},
+    error::ErrorKind,
+    CommandFactory, FromArgMatches, Parser, Subcommand, ValueEnum,
+};
 use eframe::egui;
-use serde::Serialize;
+use schemars::{schema::RootSchema, schema_for, JsonSchema};
+use serde::{de::DeserializeOwned, Serialize};
+use status::{note, NoPlayersMatched, Usage};
 use stringlit::s;
-use twsnap::{compat::ddnet::DemoReader, enums::HookState, Snap};
+use twsnap::{
+    compat::ddnet::{DemoChunk, DemoReader},
+    Snap,
+};
 use winit::platform::x11::EventLoopBuilderExtX11;
 
+mod accuracy;
+mod afk;
+mod aim;
+mod anonymize;
+mod attack;
+mod bbcode;
+mod bookmarks;
+mod cache;
+mod changepoint;
+mod changes;
+mod chart;
+mod color;
+mod compare;
+mod crosshair;
+mod csv;
 mod data;
+mod desync;
+mod diff;
+mod discord;
+mod distribution;
+mod dnf;
+mod download;
+mod entities;
+mod evaluate;
+mod events;
+mod explain;
+mod features;
+mod fingerprint;
+mod finishes;
+mod gaps;
+mod ghost;
+mod hammer;
+mod highlights;
+mod i18n;
+mod interpolate;
+mod jobs;
+mod keymap;
+mod keys;
+mod labels;
+mod live;
+mod map;
+mod merge;
+mod metric;
+mod model;
+mod names;
+mod numbers;
+mod offset;
+mod overlay;
+mod pace;
+mod pickups;
+mod plan;
+mod players;
+mod profile;
+mod progress;
+mod query;
+mod reaction;
+mod reference;
+mod rehook;
+mod render;
+mod runs;
+mod select;
+mod serve;
+mod session;
+mod significance;
+mod spectrum;
+mod status;
+mod stitch;
+mod summary;
+mod sync;
+mod table;
+mod tee;
+mod teehistorian;
+mod teepath;
+mod template;
+mod timeline;
+mod tricks;
+mod tui;
+mod tunes;
 mod ui;
+mod vanilla;
+mod zoom;
 
-use data::Inputs;
-use ui::MyApp;
+use afk::AfkStats;
+use anonymize::Anonymizer;
+use attack::WeaponAttackStats;
+use changes::InputChanges;
+use color::{ColorChoice, Palette};
+use compare::GhostComparison;
+use data::{ActiveWeapon, Inputs, Units};
+use desync::DesyncStats;
+use dnf::DnfStats;
+use fingerprint::Fingerprint;
+use finishes::Finishes;
+use gaps::GapStats;
+use hammer::HammerStats;
+use i18n::Lang;
+use keymap::Keymap;
+use keys::{KeyStyle, Keys, Style, Styled, Tagged};
+use map::{LayerKind, Map, MapInfo};
+use metric::{ChangeRateStats, MetricKind, MetricSet};
+use model::Model;
+use names::NameFilter;
+use numbers::NumberFormat;
+use offset::AimOffsetStats;
+use overlay::OverlayFormat;
+use pace::Pace;
+use pickups::PickupStats;
+use players::Presence;
+use profile::{DemoMetrics, Profile};
+use reaction::ReactionStats;
+use reference::Reference;
+use rehook::RehookStats;
+use runs::Runs;
+use spectrum::SpectralStats;
+use sync::HammerflySync;
+use timeline::{Pause, PauseMode, TimeFormat, Timeline};
+use tricks::Tricks;
+use ui::{MyApp, PlayerInfo, SessionMode, Tab};
+use zoom::TargetDistanceStats;
 
 #[derive(ValueEnum, Clone)]
 enum AnalysisOutputFormat {
@@ -22,14 +153,99 @@ enum AnalysisOutputFormat {
     Yaml,
     Toml,
     Rsn,
+    /// Compact and stable per demo summary, only for analyze
+    SummaryJson,
+    /// One object per demo with the spread of the stats over the players and the outliers,
+    /// without the players themselves, only for analyze
+    RollupJson,
+    /// Tables in a spoiler per player, to post on the DDNet forum
+    Bbcode,
 }
 
-#[derive(ValueEnum, Clone)]
+impl AnalysisOutputFormat {
+    /// Formats only analyze can write.
+    fn analyze_only(&self) -> bool {
+        matches!(
+            self,
+            AnalysisOutputFormat::SummaryJson | AnalysisOutputFormat::RollupJson
+        )
+    }
+
+    /// The equivalent extraction format, `None` for formats only available for analysis.
+    fn structured(&self) -> Option<ExtractionOutputFormat> {
+        match self {
+            AnalysisOutputFormat::Plain => None,
+            AnalysisOutputFormat::SummaryJson => Some(ExtractionOutputFormat::Json),
+            AnalysisOutputFormat::RollupJson => Some(ExtractionOutputFormat::Json),
+            AnalysisOutputFormat::Json => Some(ExtractionOutputFormat::Json),
+            AnalysisOutputFormat::Yaml => Some(ExtractionOutputFormat::Yaml),
+            AnalysisOutputFormat::Toml => Some(ExtractionOutputFormat::Toml),
+            AnalysisOutputFormat::Rsn => Some(ExtractionOutputFormat::Rsn),
+            AnalysisOutputFormat::Bbcode => Some(ExtractionOutputFormat::Bbcode),
+        }
+    }
+}
+
+/// `--format` of the commands besides analyze, which don't know the analyze only formats.
+fn report_format() -> impl TypedValueParser<Value = AnalysisOutputFormat> {
+    let formats = AnalysisOutputFormat::value_variants()
+        .iter()
+        .filter(|f| !f.analyze_only())
+        .filter_map(|f| f.to_possible_value());
+    PossibleValuesParser::new(formats).map(|f| {
+        AnalysisOutputFormat::from_str(&f, false).expect("only known formats are possible")
+    })
+}
+
+#[derive(ValueEnum, Clone, Copy, PartialEq, Eq)]
 enum ExtractionOutputFormat {
     Json,
+    /// One row per sample, nested fields are flattened into columns
+    Csv,
     Yaml,
     Toml,
     Rsn,
+    /// MessagePack, binary
+    Msgpack,
+    /// bincode, binary and only readable with the same type definitions
+    Bincode,
+    /// Tables in a spoiler per player, to post on the DDNet forum
+    Bbcode,
+}
+
+impl ExtractionOutputFormat {
+    fn extension(&self) -> &'static str {
+        match self {
+            ExtractionOutputFormat::Json => "json",
+            ExtractionOutputFormat::Csv => "csv",
+            ExtractionOutputFormat::Yaml => "yaml",
+            ExtractionOutputFormat::Toml => "toml",
+            ExtractionOutputFormat::Rsn => "rsn",
+            ExtractionOutputFormat::Msgpack => "msgpack",
+            ExtractionOutputFormat::Bincode => "bin",
+            ExtractionOutputFormat::Bbcode => "txt",
+        }
+    }
+}
+
+/// Version of the structured output of analyze and extract. Has to be bumped whenever a
+/// field is renamed, removed or changes its type, adding fields is fine.
+const OUTPUT_VERSION: u32 = 2;
+
+#[derive(ValueEnum, Clone, Copy)]
+enum SchemaKind {
+    /// The output of analyze
+    Analysis,
+    /// The output of extract
+    Extraction,
+    /// The output of extract with `--changes-only`
+    Changes,
+    /// The output of analyze with `--format summary-json`
+    Summary,
+    /// The output of analyze with `--format rollup-json`
+    Rollup,
+    /// The output of extract with `--entities`
+    Entities,
 }
 
 #[derive(Parser, Clone)]
@@ -37,17 +253,165 @@ struct FilterOptions {
     #[arg(short, long, default_value = "")]
     filter: String,
 
+    #[arg(long)]
+    /// Only ignore case when matching names, instead of also normalizing unicode and folding
+    /// look-alike characters
+    strict: bool,
+
     #[arg(short, long)]
     /// Pretty print if the format supports it
     pretty: bool,
 }
 
+impl FilterOptions {
+    fn name_filter(&self) -> NameFilter {
+        NameFilter::new(&self.filter, self.strict)
+    }
+}
+
 #[derive(Parser)]
+#[command(after_help = status::EXIT_CODES)]
 struct Args {
     #[arg(global = true, short, long)]
     /// Where to output the file to. If not specified, stdout is used.
     out: Option<PathBuf>,
 
+    #[arg(global = true, short, long)]
+    /// Only print the output and errors, no progress or informational messages
+    quiet: bool,
+
+    #[arg(global = true, long, requires = "out")]
+    /// Add to the end of the --out file instead of replacing it. Compact json becomes one line
+    /// per run, and CSV rows are added below the existing header.
+    append: bool,
+
+    #[arg(global = true, long)]
+    /// Only list the demos a batch command would process, which of them are cached and where
+    /// the output would be written
+    dry_run: bool,
+
+    #[arg(global = true, long)]
+    /// Ticks per second of the demo. If not specified, it is detected from the demo header.
+    tickrate: Option<i32>,
+
+    #[arg(global = true, long)]
+    /// Don't read or write the .tda cache files next to the demos
+    no_cache: bool,
+
+    #[arg(global = true, long, default_value = "clock")]
+    /// How times are shown in plain reports and on the axes of the visualizer
+    time_format: TimeFormat,
+
+    #[arg(global = true, long, default_value_t = 0)]
+    /// Ignore direction and hook changes that revert within this many ticks when computing the
+    /// change stats, like the one tick flickers prediction leaves in the snapshots. The raw
+    /// counts are still reported.
+    debounce: i32,
+
+    #[arg(global = true, long, default_value_t = 0.0)]
+    /// Players with fewer seconds of samples are marked as an insufficient sample instead of
+    /// reporting stats that short demos make misleading
+    min_duration: f32,
+
+    #[arg(global = true, long, default_value_t = 5.0)]
+    /// Aim within this many degrees of the nearest other tee counts as locked on it
+    lock_degrees: f32,
+
+    #[arg(global = true, long, default_value_t = 30.0)]
+    /// Standing still without any input change for this many seconds counts as AFK. The AFK
+    /// stretches are left out of the per-sample stats like the aim speed, 0 turns this off.
+    afk_seconds: f32,
+
+    #[arg(global = true, long)]
+    /// Still report the AFK stretches, but keep them in the per-sample stats
+    include_afk: bool,
+
+    #[arg(global = true, long, value_enum, value_delimiter = ',')]
+    /// Metrics to compute in the analysis, comma separated, all of them if not given. The
+    /// fields of the metrics left out stay at zero.
+    metrics: Vec<MetricKind>,
+
+    #[arg(global = true, long, value_name = "HZ")]
+    /// Move positions and velocities smoothly between the snapshots, like the client does.
+    /// Extract then writes this many samples per second with an `intra_tick`, render
+    /// interpolates at the frame rate of the video.
+    interpolate: Option<u32>,
+
+    #[arg(global = true, long, value_enum, default_value_t)]
+    /// How positions are written in structured outputs, and for diff how they were written in
+    /// the extract outputs it reads
+    units: Units,
+
+    #[arg(global = true, long)]
+    /// Replace the player names with pseudonyms and leave out the clans, in the outputs and
+    /// in the visualizer
+    anonymize: bool,
+
+    #[arg(
+        global = true,
+        long,
+        env = "DEMO_ANALYZER_SALT",
+        hide_env_values = true,
+        default_value = ""
+    )]
+    /// Salt of the pseudonyms. Keep it secret, without one anyone can check a guessed name.
+    salt: String,
+
+    #[arg(global = true, long, env = "DEMO_ANALYZER_LANG", default_value = "en")]
+    /// Language of the plain analyze report
+    lang: Lang,
+
+    #[arg(global = true, long)]
+    /// Write numbers in the plain analyze report and in CSV with a decimal comma, CSV columns
+    /// are then separated by semicolons
+    decimal_comma: bool,
+
+    #[arg(global = true, long)]
+    /// Group the thousands of numbers in the plain analyze report and in CSV with this
+    /// character, e.g. `.` or `'`
+    thousands_separator: Option<char>,
+
+    #[arg(global = true, long, value_enum, default_value_t)]
+    /// How the field names of structured outputs are written, to match what reads them.
+    /// summary-json always keeps its names
+    key_style: KeyStyle,
+
+    #[arg(global = true, long = "tag", value_name = "KEY=VALUE", value_parser = keys::parse_tag)]
+    /// Metadata like a tournament name or ticket id to add to structured outputs, which then
+    /// go under `data` next to `tags`. Can be given multiple times
+    tags: Vec<(String, String)>,
+
+    #[arg(global = true, long, value_enum, default_value_t)]
+    /// Color the plain analyze report, highlighting values humans rarely reach
+    color: ColorChoice,
+
+    #[arg(global = true, long, value_name = "EXPR", value_parser = select::parse)]
+    /// Shape structured outputs with a JMESPath-like expression before writing them, like
+    /// `*.direction_change_rate_median` for only the medians per player. Unlike JMESPath `*`
+    /// keeps the keys, numbers in filters don't need backticks and there are no functions,
+    /// slices or `&expression`
+    select: Option<select::Select>,
+
+    #[arg(global = true, long, default_value = "keep")]
+    /// Whether pauses and tick jumps in the demos are kept or cut out of the ticks and times
+    pauses: PauseMode,
+
+    #[arg(global = true, long)]
+    /// Add the UTC time to the timestamps of structured outputs, from the recording time in
+    /// the demo header, to match findings against server logs. Pauses cut out with
+    /// `--pauses compress` are missing from these times.
+    wallclock: bool,
+
+    #[arg(
+        global = true,
+        long,
+        default_value_t = 0.0,
+        allow_negative_numbers = true
+    )]
+    /// Hours the clock of the recording machine was ahead of UTC. Demo headers store the local
+    /// time of whoever recorded them, server demos usually the time zone of the server.
+    utc_offset: f32,
+
     #[command(subcommand)]
     command: Command,
 }
@@ -60,6 +424,22 @@ enum Command {
         filter_options: FilterOptions,
         #[arg(long, default_value = "plain")]
         format: AnalysisOutputFormat,
+        #[arg(long, num_args = 1..)]
+        /// Demos recorded after this one in the same session, read as one continuous timeline
+        stitch: Vec<PathBuf>,
+        #[arg(long, env = "DEMO_ANALYZER_REFERENCE")]
+        /// Reference set built with the reference command, the plain report shows which
+        /// percentile of it the rates of each player fall in
+        reference: Option<PathBuf>,
+        #[arg(long, env = "DEMO_ANALYZER_MODEL")]
+        /// Logistic regression over the columns of export-features as json, with a `bias` and
+        /// the `weights` by column name, adds the bot probability of each player. Builds with
+        /// the `onnx` feature also take a `.onnx` model of the columns in order
+        model: Option<PathBuf>,
+        #[arg(long, env = "DEMO_ANALYZER_TEMPLATE")]
+        /// minijinja template the plain report is rendered with instead of the built-in layout,
+        /// the template command prints the default one to start from
+        template: Option<PathBuf>,
         path: PathBuf,
     },
     #[command(visible_alias = "e")]
@@ -68,15 +448,565 @@ enum Command {
         filter_options: FilterOptions,
         #[arg(short, long, default_value = "json")]
         format: ExtractionOutputFormat,
+        #[arg(long)]
+        /// Only output the fastest finished run of each player
+        best_run: bool,
+        #[arg(long)]
+        /// Only output the samples where something changed, with how often they repeat
+        changes_only: bool,
+        #[arg(long, conflicts_with = "stitch")]
+        /// Also output the projectiles, lasers and pickups of every tick, next to the players
+        entities: bool,
+        #[arg(long, num_args = 1..)]
+        /// Demos recorded after this one in the same session, read as one continuous timeline
+        stitch: Vec<PathBuf>,
+        path: PathBuf,
+    },
+
+    /// Write the inputs of every player to a file of their own, named after the demo and the
+    /// player, to hand the players of a team demo to different reviewers
+    Split {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "json")]
+        format: ExtractionOutputFormat,
+        #[arg(long)]
+        /// Only output the samples where something changed, with how often they repeat
+        changes_only: bool,
+        #[arg(long, default_value = ".")]
+        /// Directory the files are written to
+        dir: PathBuf,
+        path: PathBuf,
+    },
+
+    #[command(visible_alias = "list-players")]
+    /// List the players in the demo with their ids, clans and when they were present,
+    /// without extracting their inputs
+    Players {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        path: PathBuf,
+    },
+
+    /// List the tune parameters the server sent, and when they changed
+    Tunes {
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        pretty: bool,
+        path: PathBuf,
+    },
+
+    /// Print the samples of a time range tick by tick
+    Dump {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "json")]
+        format: ExtractionOutputFormat,
+        #[arg(long)]
+        /// Print an aligned table instead of the structured format
+        table: bool,
+        #[arg(long)]
+        /// Start of the range in seconds since the start of the demo
+        from: Option<f32>,
+        #[arg(long)]
+        /// End of the range in seconds since the start of the demo
+        to: Option<f32>,
+        path: PathBuf,
+    },
+
+    #[command(visible_alias = "p")]
+    /// Compare the time spent per section of the map against the best run
+    Pace {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long, default_value_t = 20.0, value_parser = pace::parse_section_length)]
+        /// Length of a section in tiles, measured along the path of the best run
+        section_length: f32,
+        path: PathBuf,
+    },
+
+    /// Find where the behavior of a player shifted within the demo, with the stats of every
+    /// segment in between
+    Changepoints {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long, default_value_t = 5.0)]
+        /// Seconds per window the change rates and the aim speed are taken over
+        window: f32,
+        #[arg(long, default_value_t = 2.0)]
+        /// Higher values only report larger or longer lasting shifts
+        penalty: f32,
+        path: PathBuf,
+    },
+
+    /// How often the rifle, laser shotgun and grenade shots of a player hit another player,
+    /// from the lasers and projectiles in the demo
+    Accuracy {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        path: PathBuf,
+    },
+
+    /// Detect known techniques like hammerfly, rocketfly, edge jumps and speedfly
+    Tricks {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        /// Use this map file instead of the one embedded in the demo, needed for edge jumps
+        map: Option<PathBuf>,
+        path: PathBuf,
+    },
+
+    /// Find where a tee jumped further than its velocity allows between two snapshots, and
+    /// whether lag, a teleporter or a respawn explains it
+    Desync {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        /// Use this map file instead of the one embedded in the demo, needed to leave out
+        /// teleporters
+        map: Option<PathBuf>,
+        path: PathBuf,
+    },
+
+    /// Print where the inputs of every player match a condition, like
+    /// `speed > 40 && hook_state == "Grabbed"`. Conditions compare fields with ==, !=, <, <=, >
+    /// and >=, and combine with &&, || and !. Positions are in tiles, velocities and speed in
+    /// units per tick
+    Query {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        path: PathBuf,
+        #[arg(value_parser = query::parse)]
+        /// The condition, one of the fields tick, x, y, vel_x, vel_y, speed, angle, direction,
+        /// hook_state, hook_x, hook_y, health, armor, ammo_count, weapon, emote, frozen, jumps,
+        /// jumped_total, score and target_distance compared to a value
+        query: query::Query,
+    },
+
+    /// List the interesting moments of every player, like freeze saves, near misses, big speed
+    /// gains, long rehook chains and kill streaks
+    Highlights {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        /// Use this map file instead of the one embedded in the demo, needed for near misses
+        map: Option<PathBuf>,
+        #[arg(long)]
+        /// Render every highlight as video clip into this directory, requires ffmpeg
+        clips: Option<PathBuf>,
+        #[arg(long, default_value_t = 2.0)]
+        /// Seconds of the clips before and after each highlight
+        padding: f32,
+        #[arg(long, default_value = "ffmpeg")]
+        /// The ffmpeg executable to encode the clips with
+        ffmpeg: String,
+        path: PathBuf,
+    },
+
+    /// Compare the hammer timing of duo partners during hammerflies
+    HammerflySync {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        path: PathBuf,
+    },
+
+    /// Build longitudinal player profiles from all demos in a directory
+    Profile {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        /// TOML file mapping identities to the names they used, e.g. `"Main" = ["Main", "Main2"]`
+        identities: Option<PathBuf>,
+        #[arg(long, default_value_t = 3.0)]
+        /// How many standard deviations a demo has to differ from the previous ones to be
+        /// reported as sudden change
+        threshold: f32,
+        #[arg(long)]
+        /// Go through every demo again, even the ones an earlier run with this --out already
+        /// went through
+        force: bool,
+        /// Directory containing the demos
+        path: PathBuf,
+    },
+
+    /// Find players that are likely the same person under different names, by comparing
+    /// their input timing, hook rhythm and aim across all demos in a directory
+    Cluster {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long, default_value_t = 0.85)]
+        /// Minimum similarity between 0 and 1 for two names to be reported
+        min_similarity: f32,
+        #[arg(long)]
+        /// Go through every demo again, even the ones an earlier run with this --out already
+        /// went through
+        force: bool,
+        /// Directory containing the demos
+        path: PathBuf,
+    },
+
+    /// Show how a metric is distributed over all players in all demos of a directory,
+    /// to pick thresholds from real data
+    StatsDistribution {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        /// Name of the metric as in the json output of analyze, nested fields are separated
+        /// by dots, e.g. target_distance.average
+        metric: String,
+        #[arg(long, default_value_t = 20)]
+        buckets: usize,
+        #[arg(long)]
+        /// Go through every demo again, even the ones an earlier run with this --out already
+        /// went through
+        force: bool,
+        /// Directory containing the demos
+        path: PathBuf,
+    },
+
+    /// Stats for vanilla CTF and DM: flag grabs and captures, kills and deaths, and how hits
+    /// line up with aim snaps
+    Vanilla {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        path: PathBuf,
+    },
+
+    /// Build a reference set of the change rates from demos of players known to be legitimate,
+    /// for analyze --reference
+    Reference {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long)]
+        /// Go through every demo again, even the ones an earlier run with this --out already
+        /// went through
+        force: bool,
+        /// Directory containing the demos
+        path: PathBuf,
+    },
+
+    /// Convert every player of the demos into a fixed-length numeric feature vector, for
+    /// training classifiers
+    ExportFeatures {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "csv")]
+        format: ExtractionOutputFormat,
+        #[arg(long)]
+        /// Process every demo again, even the ones an earlier run already wrote to --out
+        force: bool,
+        /// A demo or a directory containing demos
+        path: PathBuf,
+    },
+
+    /// Record a verdict for a player of a demo, or list the recorded verdicts when no player
+    /// is given
+    Label {
+        #[arg(long, env = "DEMO_ANALYZER_LABELS", default_value = "labels.db")]
+        /// SQLite database the verdicts are kept in
+        database: PathBuf,
+        #[arg(long)]
+        notes: Option<String>,
+        #[arg(long, default_value = "json")]
+        /// Format of the list
+        format: ExtractionOutputFormat,
+        #[arg(long)]
+        pretty: bool,
+        /// The demo, every label is listed without one
+        path: Option<PathBuf>,
+        player: Option<String>,
+        verdict: Option<labels::Verdict>,
+    },
+
+    /// Sweep the thresholds of the metrics over the labeled demos and report precision, recall
+    /// and F1, to pick thresholds that hold up
+    Evaluate {
+        #[arg(long, env = "DEMO_ANALYZER_LABELS", default_value = "labels.db")]
+        /// SQLite database the verdicts were recorded in with the label command
+        database: PathBuf,
+        #[arg(long, num_args = 1..)]
+        /// Metrics to evaluate as in the json output of analyze, the ones of the reference
+        /// set by default
+        metric: Vec<String>,
+        #[arg(long, default_value_t = 5)]
+        /// Folds of the cross-validation
+        folds: usize,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        pretty: bool,
+        #[arg(long)]
+        /// Go through every demo again, even the ones an earlier run with this --out already
+        /// went through
+        force: bool,
+        /// Directory containing the labeled demos
+        path: PathBuf,
+    },
+
+    /// Combine json analysis outputs, also ones written with --append, into the mean, min and
+    /// max of every metric per player
+    MergeReports {
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        pretty: bool,
+        #[arg(required = true)]
+        paths: Vec<PathBuf>,
+    },
+
+    #[command(visible_alias = "c")]
+    /// Compare the best run of each player against a DDNet ghost
+    Compare {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        /// The ghost file (.gho) to compare against
+        ghost: PathBuf,
+        #[arg(long, default_value_t = 20.0, value_parser = pace::parse_section_length)]
+        /// Length of a section in tiles, measured along the path of the ghost
+        section_length: f32,
+        #[arg(long)]
+        /// Render the routes of the ghost and the runs into this png
+        overlay: Option<PathBuf>,
+        path: PathBuf,
+    },
+
+    /// Compare the fastest run of a player in two json outputs of extract, like before and
+    /// after a map change, section by section along the old route instead of by tick
+    Diff {
+        #[arg(long, default_value = "plain", value_parser = report_format())]
+        format: AnalysisOutputFormat,
+        #[arg(long)]
+        pretty: bool,
+        #[arg(short, long)]
+        /// The player to compare, can be left out if both outputs contain only one
+        player: Option<String>,
+        #[arg(long, default_value_t = 20.0, value_parser = pace::parse_section_length)]
+        /// Length of a section in tiles, measured along the old route
+        section_length: f32,
+        old: PathBuf,
+        new: PathBuf,
+    },
+
+    #[command(visible_alias = "r")]
+    /// Render a video of the demo following one player, requires ffmpeg
+    Render {
+        #[arg(short, long)]
+        /// The player the camera follows
+        player: String,
+        #[arg(long, default_value_t = 1280)]
+        width: u32,
+        #[arg(long, default_value_t = 720)]
+        height: u32,
+        #[arg(long, default_value_t = 50)]
+        fps: u32,
+        #[arg(long, default_value_t = 20.0)]
+        /// How many tiles are visible vertically
+        tiles_visible: f32,
+        #[arg(long, default_value = "ffmpeg")]
+        /// The ffmpeg executable to encode the video with
+        ffmpeg: String,
+        #[arg(long)]
+        /// Use this map file instead of the one embedded in the demo
+        map: Option<PathBuf>,
+        path: PathBuf,
+    },
+
+    /// Draw where a player aimed relative to their tee as PNG, with the other players at the
+    /// same ticks, so aim locking onto them stands out
+    Crosshair {
+        #[arg(short, long)]
+        /// The player whose aim is drawn
+        player: String,
+        path: PathBuf,
+    },
+
+    /// Export the positions of a player as a list of points with the time in seconds and the
+    /// position in tiles, for route comparison and mapping tools
+    TeePath {
+        #[arg(short, long)]
+        /// The player whose path is exported
+        player: String,
+        #[arg(long, default_value = "json")]
+        format: ExtractionOutputFormat,
+        #[arg(long)]
+        pretty: bool,
+        #[arg(long)]
+        /// Simplify the path with Ramer-Douglas-Peucker, leaving out points as long as the
+        /// path stays within this many tiles of them
+        tolerance: Option<f32>,
+        path: PathBuf,
+    },
+
+    /// Draw the spectrogram of a player's direction or hook inputs as PNG, macros show up as
+    /// bright horizontal lines at their frequency
+    Spectrogram {
+        #[arg(short, long)]
+        /// The player whose inputs are drawn
+        player: String,
+        #[arg(long, default_value = "hook")]
+        signal: spectrum::Signal,
+        path: PathBuf,
+    },
+
+    #[command(visible_alias = "o")]
+    /// Export a player's inputs as subtitle track to composite over a recording of the demo
+    Overlay {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, default_value = "srt")]
+        format: OverlayFormat,
         path: PathBuf,
     },
 
     #[command(visible_aliases = ["m", "em"])]
-    ExtractMap { path: PathBuf },
+    ExtractMap {
+        #[arg(long)]
+        /// Print a summary of the groups, layers and images instead of writing the map
+        dump_info: bool,
+        #[arg(long)]
+        /// Download the map from the DDNet map servers if it isn't embedded in the demo
+        download: bool,
+        #[arg(long)]
+        /// Write a picture of the game layer as PNG to this path instead of the map
+        thumbnail: Option<PathBuf>,
+        #[arg(long, default_value_t = 512)]
+        /// Pixels of the longer side of the thumbnail
+        thumbnail_size: u32,
+        path: PathBuf,
+    },
+
+    /// Run an HTTP server that analyzes demos posted to `/analyze` or `/jobs` and exposes `/metrics`
+    Serve {
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        address: String,
+        #[arg(long, default_value_t = 4)]
+        /// How many requests can be answered at the same time
+        workers: usize,
+        #[arg(long, default_value_t = 2)]
+        /// How many queued jobs are analyzed at the same time
+        job_workers: usize,
+        #[arg(long, default_value = "jobs.sqlite")]
+        /// SQLite database the job queue and results are stored in
+        database: PathBuf,
+    },
+
+    /// Follow the teehistorian file of a running server and report the change rates of the
+    /// players while they play, alerting when they get too high
+    Live {
+        /// The teehistorian file the server is writing, or `-` to read it from stdin
+        path: PathBuf,
+        #[arg(short, long, default_value = "")]
+        filter: String,
+        #[arg(long)]
+        /// Only ignore case when matching names
+        strict: bool,
+        #[arg(long, default_value_t = 10.0)]
+        /// Seconds the average rates are taken over
+        window: f32,
+        #[arg(long, default_value_t = 10.0)]
+        /// Seconds of game time between two reports
+        interval: f32,
+        #[arg(long, default_value_t = 15)]
+        /// Alert when a player changes direction more often than this within one second
+        max_direction_changes: usize,
+        #[arg(long, default_value_t = 15)]
+        /// Alert when a player presses or releases hook more often than this within one second
+        max_hook_changes: usize,
+        #[arg(long, requires = "econ_password")]
+        /// Address of the server's external console, alerts are echoed to its console
+        econ: Option<String>,
+        #[arg(long, env = "ECON_PASSWORD", hide_env_values = true)]
+        econ_password: Option<String>,
+    },
+
+    /// Run a Discord bot that replies to posted demos with the report and an activity chart
+    DiscordBot {
+        #[arg(long, env = "DISCORD_TOKEN", hide_env_values = true)]
+        token: String,
+        #[arg(long = "channel")]
+        /// Id of a channel to listen in, can be repeated. Listens everywhere if omitted.
+        channels: Vec<u64>,
+    },
+
+    /// Describe what a metric of the analysis means, or list all metrics
+    Explain {
+        /// Name of the metric as in the json output, e.g. hook_state_change_rate_median
+        metric: Option<String>,
+    },
+
+    /// Print the JSON schema of the structured output
+    Schema {
+        #[arg(default_value = "analysis")]
+        kind: SchemaKind,
+    },
+
+    /// Print the default template of the plain report, to customize for analyze --template
+    Template,
+
+    /// Browse the analysis in the terminal
+    Tui {
+        #[command(flatten)]
+        filter_options: FilterOptions,
+        #[arg(long, num_args = 1..)]
+        /// Demos recorded after this one in the same session, read as one continuous timeline
+        stitch: Vec<PathBuf>,
+        path: PathBuf,
+    },
 
     #[command(visible_alias = "v")]
     Visualize {
-        path: PathBuf,
+        #[arg(required = true)]
+        /// Every demo is opened in its own tab
+        paths: Vec<PathBuf>,
+
+        #[arg(long, num_args = 1..)]
+        /// Demos recorded after the first one in the same session, read as one continuous
+        /// timeline
+        stitch: Vec<PathBuf>,
+
+        #[arg(long, env = "DEMO_ANALYZER_KEYMAP")]
+        /// TOML file mapping actions like `next_frame` to lists of keys like `"Shift+Right"`,
+        /// defaults to `demo_analyzer/keymap.toml` in the config directory
+        keymap: Option<PathBuf>,
+
+        #[arg(long, conflicts_with = "replay")]
+        /// Record the review, player switches, zooms, bookmarks and notes, into this file
+        record: Option<PathBuf>,
+
+        #[arg(long)]
+        /// Step through a review recorded with --record
+        replay: Option<PathBuf>,
 
         #[command(flatten)]
         filter_options: FilterOptions,
@@ -89,22 +1019,62 @@ struct Stats {
     median: f32,
     max: usize,
     overall_changes: usize,
+    /// 95% confidence interval of the average
+    interval: (f32, f32),
+    /// Number of one second windows the rates are taken over
+    windows: usize,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 struct CombinedStats {
     direction_change_rate_average: f32,
     direction_change_rate_median: f32,
     direction_change_rate_max: usize,
+    /// 95% bootstrap confidence interval of the average
+    direction_change_rate_interval: (f32, f32),
+    /// Windows the direction change rates are taken over
+    direction_change_rate_samples: usize,
     hook_state_change_rate_average: f32,
     hook_state_change_rate_median: f32,
     hook_state_change_rate_max: usize,
+    hook_state_change_rate_interval: (f32, f32),
+    hook_state_change_rate_samples: usize,
+    /// Seconds between the first and the last sample
+    duration: f32,
+    /// Shorter than `--min-duration`, the stats can't be relied on
+    insufficient_sample: bool,
+    /// When the tee was in the demo, a new stretch starts after three seconds without it
+    presence: Vec<Presence>,
+    /// Seconds of all stretches together
+    time_played: f32,
+    afk: AfkStats,
     direction_changes: usize,
     hook_changes: usize,
     overall_changes: usize,
+    /// The changes before debouncing, the same as the counts above without `--debounce`
+    raw_direction_changes: usize,
+    raw_hook_changes: usize,
+    aim_angular_speed_average: f32,
+    aim_angular_jerk_average: f32,
+    aim_linear_segment_fraction: f32,
+    attacks: BTreeMap<ActiveWeapon, WeaponAttackStats>,
+    target_distance: TargetDistanceStats,
+    aim_offset: AimOffsetStats,
+    reactions: ReactionStats,
+    hammer: HammerStats,
+    rehook: RehookStats,
+    spectrum: SpectralStats,
+    runs: Runs,
+    finishes: Finishes,
+    /// Runs that ended without a finish, by what ended them
+    dnf: DnfStats,
+    gaps: GapStats,
+    pickups: PickupStats,
+    /// Output of the --model, none without one
+    bot_probability: Option<f32>,
 }
 
-fn calculate_direction_change_stats(mut changes: Vec<i32>) -> Stats {
+fn calculate_direction_change_stats(mut changes: Vec<i32>, tick_rate: i32) -> Stats {
     if changes.is_empty() {
         return Stats::default();
     }
@@ -114,9 +1084,9 @@ fn calculate_direction_change_stats(mut changes: Vec<i32>) -> Stats {
     let mut times = Vec::new();
     let changes_count = changes.len();
     for i in 0..changes_count {
-        let last_tick = changes[i] + 50;
+        let last_tick = changes[i] + tick_rate;
         let mut actions = 1;
-        for n in 1..50 {
+        for n in 1..tick_rate as usize {
             if i + n >= changes_count || changes[i + n] > last_tick {
                 break;
             }
@@ -126,7 +1096,7 @@ fn calculate_direction_change_stats(mut changes: Vec<i32>) -> Stats {
     }
 
     assert!(
-        times.len() > 0,
+        !times.is_empty(),
         "If we are here, we must have at least one action per second"
     );
 
@@ -151,288 +1121,2392 @@ fn calculate_direction_change_stats(mut changes: Vec<i32>) -> Stats {
         median,
         max,
         overall_changes: changes.len(),
+        interval: significance::mean_interval(&times),
+        windows: times.len(),
     }
 }
 
-fn hook_pressed(hs: HookState) -> bool {
-    match hs {
-        HookState::Retracted => false,
-        HookState::Idle => false,
-        HookState::RetractStart => false,
-        HookState::Retracting => false,
-        HookState::RetractEnd => false,
-        HookState::Flying => true,
-        HookState::Grabbed => true,
-    }
+/// Analyzes the demo, returning the stats per player and the timeline of the demo.
+fn analyze(
+    reader: DemoReader,
+    filter: &NameFilter,
+    tick_rate: Option<i32>,
+) -> (HashMap<String, CombinedStats>, Timeline) {
+    let (inputs, timeline) = read_changes(reader, filter, tick_rate);
+    (
+        analyze_inputs(&inputs, &timeline, AnalysisOptions::default()),
+        timeline,
+    )
 }
 
-fn extract(path: PathBuf, filter: &str) -> anyhow::Result<HashMap<String, Vec<Inputs>>> {
-    let file = BufReader::new(File::open(path).unwrap());
-    let mut reader = DemoReader::new(file).expect("Couldn't open demo reader");
-    let mut inputs = HashMap::new();
-    let mut snap = Snap::default();
-    while let Ok(Some(_chunk)) = reader.next_chunk(&mut snap) {
-        for (_id, p) in snap.players.iter() {
-            let name = p.name.to_string();
-            if !name.to_lowercase().contains(&filter.to_lowercase()) {
-                continue;
-            }
-            if let Some(tee) = &p.tee {
-                inputs
-                    .entry(name.clone())
-                    .or_insert_with(|| Vec::<Inputs>::new())
-                    .push(tee.into());
-            }
+fn change_ticks<T: PartialEq>(inputs: &[Inputs], state: impl Fn(&Inputs) -> T) -> Vec<i32> {
+    inputs
+        .windows(2)
+        .filter(|w| state(&w[0]) != state(&w[1]))
+        .map(|w| w[1].tick)
+        .collect()
+}
+
+/// Players are expanded one at a time, so only one of them is fully in memory.
+fn analyze_inputs(
+    inputs: &HashMap<String, InputChanges>,
+    timeline: &Timeline,
+    options: AnalysisOptions,
+) -> HashMap<String, CombinedStats> {
+    let tick_rate = timeline.tick_rate;
+    let mut metrics = metric::registry(options.metrics, options.debounce, timeline);
+    // Runs that end here ended with the demo rather than the player leaving
+    let mut demo_end = 0;
+    for (name, changes) in inputs {
+        for sample in changes.iter() {
+            metrics.iter_mut().for_each(|m| m.feed(name, &sample));
+            demo_end = demo_end.max(sample.tick);
         }
     }
-    Ok(inputs)
+    let results: HashMap<&str, serde_json::Value> =
+        metrics.iter().map(|m| (m.name(), m.finish())).collect();
+    // Disabled metrics stay at zero
+    let rates = |kind: MetricKind, name: &str| {
+        results
+            .get(kind.name())
+            .and_then(|players| players.get(name))
+            .and_then(|stats| serde_json::from_value::<ChangeRateStats>(stats.clone()).ok())
+            .unwrap_or_default()
+    };
+    inputs
+        .iter()
+        .filter_map(|(n, changes)| {
+            let i = &changes.to_vec();
+            let raw_direction_changes = change_ticks(i, |i| i.direction).len();
+            if raw_direction_changes == 0 {
+                return None;
+            }
+            let raw_hook_changes = change_ticks(i, |i| i.hook_state.pressed()).len();
+            let ds = rates(MetricKind::DirectionChangeRate, n);
+            let hs = rates(MetricKind::HookStateChangeRate, n);
+            let ticks: Vec<i32> = i.iter().map(|i| i.tick).collect();
+            let gaps = gaps::find_gaps(&ticks, timeline);
+            let afk = afk::find_afk(i, options.afk_seconds, tick_rate);
+            let active = if options.include_afk {
+                i.to_vec()
+            } else {
+                afk::without_afk(i, &afk)
+            };
+            let aim = aim::calculate_aim_stats(&active, tick_rate);
+            let duration = match (i.first(), i.last()) {
+                (Some(first), Some(last)) => (last.tick - first.tick) as f32 / tick_rate as f32,
+                _ => 0.0,
+            };
+            let presence = players::presence(i.iter().map(|i| i.tick), timeline);
+            let runs = runs::calculate_runs(i, timeline);
+            // Only the positions of the other players are looked up, they stay compressed
+            let others: Vec<&InputChanges> = inputs
+                .iter()
+                .filter(|(o, _)| *o != n)
+                .map(|(_, o)| o)
+                .collect();
+            let c = CombinedStats {
+                direction_change_rate_average: ds.average,
+                direction_change_rate_median: ds.median,
+                direction_change_rate_max: ds.max,
+                hook_state_change_rate_average: hs.average,
+                hook_state_change_rate_median: hs.median,
+                hook_state_change_rate_max: hs.max,
+                direction_change_rate_interval: ds.interval,
+                direction_change_rate_samples: ds.windows,
+                hook_state_change_rate_interval: hs.interval,
+                hook_state_change_rate_samples: hs.windows,
+                duration,
+                insufficient_sample: duration < options.min_duration,
+                time_played: presence.iter().fold(0.0, |sum, p| sum + p.seconds),
+                presence,
+                afk: afk::calculate_afk_stats(i, &afk, timeline, !options.include_afk),
+                direction_changes: ds.changes,
+                hook_changes: hs.changes,
+                overall_changes: ds.changes + hs.changes,
+                raw_direction_changes,
+                raw_hook_changes,
+                aim_angular_speed_average: aim.angular_speed_average,
+                aim_angular_jerk_average: aim.angular_jerk_average,
+                aim_linear_segment_fraction: aim.linear_segment_fraction,
+                attacks: attack::calculate_attack_stats(i, timeline),
+                target_distance: zoom::calculate_target_distance_stats(&active),
+                aim_offset: offset::calculate_aim_offset_stats(
+                    &active,
+                    &others,
+                    options.lock_degrees,
+                ),
+                reactions: reaction::calculate_reaction_stats(i, &others, tick_rate),
+                hammer: hammer::calculate_hammer_stats(i, &others),
+                rehook: rehook::calculate_rehook_stats(i, tick_rate),
+                spectrum: spectrum::calculate_spectral_stats(&active, tick_rate),
+                finishes: finishes::calculate_finishes(i, timeline, &runs),
+                dnf: dnf::calculate_dnf_stats(i, &runs, demo_end, timeline),
+                runs,
+                pickups: pickups::calculate_pickup_stats(i),
+                bot_probability: None,
+                gaps: GapStats {
+                    missing_ticks: gaps.iter().map(|g| g.missing_ticks).sum(),
+                    gaps,
+                    excluded_changes: ds.excluded_changes + hs.excluded_changes,
+                },
+            };
+            Some((n.clone(), c))
+        })
+        .collect()
 }
 
-fn main() -> anyhow::Result<()> {
-    let args = Args::parse();
+type ExtractedInputs = (HashMap<String, Vec<Inputs>>, Timeline);
+type ExtractedChanges = (HashMap<String, InputChanges>, Timeline);
 
-    match args.command {
-        Command::Analyze {
-            path,
-            format,
-            filter_options,
-        } => {
-            let file = BufReader::new(File::open(path).unwrap());
-            let mut reader = DemoReader::new(file).expect("Couldn't open demo reader");
-            let mut direction_stats = HashMap::new();
-            let mut hook_stats = HashMap::new();
-            let mut inputs = HashMap::<String, Vec<Inputs>>::new();
-            let mut snap = Snap::default();
-            let mut last_input_direction = HashMap::new();
-            let mut last_input_hook = HashMap::new();
-            while let Ok(Some(_chunk)) = reader.next_chunk(&mut snap) {
-                for (_id, p) in snap.players.iter() {
-                    let name = p.name.to_string();
-                    if !name
-                        .to_lowercase()
-                        .contains(&filter_options.filter.to_lowercase())
-                    {
-                        continue;
-                    }
-                    if let Some(tee) = &p.tee {
-                        let tick = (tee.tick.seconds() * 50.0) as i32;
-                        inputs
-                            .entry(name.clone())
-                            .or_insert_with(|| Vec::new())
-                            .push(tee.into());
-                        let input_changed_direction = *last_input_direction
-                            .entry(name.clone())
-                            .or_insert(tee.direction)
-                            != tee.direction;
-                        if input_changed_direction {
-                            direction_stats
-                                .entry(name.clone())
-                                .or_insert(Vec::new())
-                                .push(tick);
-                        }
-                        last_input_direction.insert(name.clone(), tee.direction);
-
-                        let input_changed_hook = *last_input_hook
-                            .entry(name.clone())
-                            .or_insert(hook_pressed(tee.hook_state))
-                            != hook_pressed(tee.hook_state);
-                        if input_changed_hook {
-                            hook_stats
-                                .entry(name.clone())
-                                .or_insert(Vec::new())
-                                .push(tick);
-                        }
-                        last_input_hook.insert(name.clone(), hook_pressed(tee.hook_state));
-                    }
-                }
-            }
+/// How the stats of the players are computed.
+#[derive(Clone, Copy, Default)]
+struct AnalysisOptions {
+    debounce: i32,
+    min_duration: f32,
+    lock_degrees: f32,
+    afk_seconds: f32,
+    include_afk: bool,
+    metrics: MetricSet,
+}
 
-            let direction_stats = direction_stats
-                .into_iter()
-                .map(|(n, s)| (n, calculate_direction_change_stats(s)));
+impl From<&Args> for AnalysisOptions {
+    fn from(args: &Args) -> Self {
+        Self {
+            debounce: args.debounce,
+            min_duration: args.min_duration,
+            lock_degrees: args.lock_degrees,
+            afk_seconds: args.afk_seconds,
+            include_afk: args.include_afk,
+            metrics: if args.metrics.is_empty() {
+                MetricSet::default()
+            } else {
+                args.metrics.iter().copied().collect()
+            },
+        }
+    }
+}
 
-            let mut hook_stats = hook_stats
-                .into_iter()
-                .map(|(n, s)| (n, calculate_direction_change_stats(s)))
-                .collect::<HashMap<_, _>>();
-
-            let stats = direction_stats
-                .map(move |(n, ds)| {
-                    let hs = hook_stats.remove(&n).unwrap_or_default();
-                    let c = CombinedStats {
-                        direction_change_rate_average: ds.average,
-                        direction_change_rate_median: ds.median,
-                        direction_change_rate_max: ds.max,
-                        hook_state_change_rate_average: hs.average,
-                        hook_state_change_rate_median: hs.median,
-                        hook_state_change_rate_max: hs.max,
-                        direction_changes: ds.overall_changes,
-                        hook_changes: hs.overall_changes,
-                        overall_changes: ds.overall_changes + hs.overall_changes,
-                    };
-                    (n, c)
-                })
-                .collect::<HashMap<_, _>>();
+impl From<&Args> for Keys {
+    fn from(args: &Args) -> Self {
+        Self {
+            style: Style {
+                keys: args.key_style,
+                units: args.units,
+            },
+            tags: args.tags.iter().cloned().collect(),
+            select: args.select.clone(),
+        }
+    }
+}
 
-            let output = match format {
-                AnalysisOutputFormat::Json => {
-                    if filter_options.pretty {
-                        serde_json::to_string_pretty(&stats).unwrap()
-                    } else {
-                        serde_json::to_string(&stats).unwrap()
-                    }
-                }
-                AnalysisOutputFormat::Yaml => serde_yaml::to_string(&stats).unwrap(),
-                AnalysisOutputFormat::Toml => {
-                    if filter_options.pretty {
-                        toml::to_string_pretty(&stats).unwrap()
-                    } else {
-                        toml::to_string(&stats).unwrap()
+impl From<&Args> for NumberFormat {
+    fn from(args: &Args) -> Self {
+        Self {
+            decimal_comma: args.decimal_comma,
+            thousands_separator: args.thousands_separator,
+        }
+    }
+}
+
+/// How demos are read from disk.
+#[derive(Clone, Copy)]
+struct ReadOptions {
+    tick_rate: Option<i32>,
+    /// Hours ahead of UTC of the recording time in the demo header, none without --wallclock
+    utc_offset: Option<f32>,
+    cache: bool,
+    pauses: PauseMode,
+    anonymize: Option<Anonymizer>,
+}
+
+impl From<&Args> for ReadOptions {
+    fn from(args: &Args) -> Self {
+        Self {
+            tick_rate: args.tickrate,
+            utc_offset: args.wallclock.then_some(args.utc_offset),
+            cache: !args.no_cache,
+            pauses: args.pauses,
+            anonymize: args.anonymize.then(|| Anonymizer::new(&args.salt)),
+        }
+    }
+}
+
+fn extract(
+    path: PathBuf,
+    filter: &NameFilter,
+    options: ReadOptions,
+) -> anyhow::Result<ExtractedInputs> {
+    let (changes, timeline) = extract_changes(path, filter, options)?;
+    let inputs = changes.into_iter().map(|(n, c)| (n, c.to_vec())).collect();
+    Ok((inputs, timeline))
+}
+
+/// Like `extract`, but keeps the inputs run-length encoded.
+fn extract_changes(
+    path: PathBuf,
+    filter: &NameFilter,
+    options: ReadOptions,
+) -> anyhow::Result<ExtractedChanges> {
+    let read = || {
+        let file = BufReader::new(File::open(&path)?);
+        Ok(read_changes(
+            DemoReader::new(file)?,
+            &NameFilter::default(),
+            None,
+        ))
+    };
+    let (mut inputs, mut timeline) = if options.cache {
+        cache::load_or_extract(&path, read)?
+    } else {
+        read()?
+    };
+    inputs.retain(|name, _| filter.matches(name));
+    if inputs.is_empty() {
+        return Err(NoPlayersMatched.into());
+    }
+    if let Some(anonymizer) = options.anonymize {
+        inputs = anonymizer.rename(inputs);
+    }
+    if !filter.is_empty() {
+        let mut names: Vec<&String> = inputs.keys().collect();
+        names.sort();
+        note!("Matched players: {names:?}");
+    }
+    if let Some(tick_rate) = options.tick_rate {
+        timeline.tick_rate = tick_rate;
+    }
+    if let Some(offset) = options.utc_offset {
+        match timeline::parse_timestamp(&demo_timestamp(&path)?) {
+            Some(recorded) => {
+                let offset = (offset * 3_600_000.0).round() as i64;
+                timeline.recorded = Some(recorded * 1000 - offset);
+            }
+            None => eprintln!("Warning: the demo header has no recording time"),
+        }
+    }
+    if options.pauses == PauseMode::Compress {
+        for changes in inputs.values_mut() {
+            *changes = compress_pauses(changes.iter(), &timeline).collect();
+        }
+        timeline.compress();
+    }
+    Ok((inputs, timeline))
+}
+
+/// Moves the samples so the pauses of the timeline are cut out.
+fn compress_pauses<'a>(
+    inputs: impl Iterator<Item = Inputs> + 'a,
+    timeline: &'a Timeline,
+) -> impl Iterator<Item = Inputs> + 'a {
+    inputs.map(|mut i| {
+        i.shift_ticks(timeline.compressed_tick(i.tick) - i.tick);
+        i
+    })
+}
+
+/// Reads the demo and the demos to stitch to it as one session.
+fn extract_session(
+    path: PathBuf,
+    stitched: &[PathBuf],
+    filter: &NameFilter,
+    options: ReadOptions,
+) -> anyhow::Result<ExtractedInputs> {
+    if stitched.is_empty() {
+        return extract(path, filter, options);
+    }
+    let parts = std::iter::once(path)
+        .chain(stitched.iter().cloned())
+        .map(|path| {
+            let recorded = timeline::parse_timestamp(&demo_timestamp(&path)?);
+            let (inputs, timeline) = extract(path, filter, options)?;
+            Ok(stitch::Part {
+                recorded,
+                inputs,
+                timeline,
+            })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let (mut inputs, mut timeline) = stitch::stitch(parts);
+    if options.pauses == PauseMode::Compress {
+        for samples in inputs.values_mut() {
+            *samples = compress_pauses(samples.drain(..), &timeline).collect();
+        }
+        timeline.compress();
+    }
+    Ok((inputs, timeline))
+}
+
+fn extract_session_changes(
+    path: PathBuf,
+    stitched: &[PathBuf],
+    filter: &NameFilter,
+    options: ReadOptions,
+) -> anyhow::Result<ExtractedChanges> {
+    if stitched.is_empty() {
+        return extract_changes(path, filter, options);
+    }
+    let (inputs, timeline) = extract_session(path, stitched, filter, options)?;
+    Ok((compress(&inputs), timeline))
+}
+
+fn compress(inputs: &HashMap<String, Vec<Inputs>>) -> HashMap<String, InputChanges> {
+    inputs
+        .iter()
+        .map(|(n, i)| (n.clone(), i.iter().cloned().collect()))
+        .collect()
+}
+
+/// Reads the inputs of every player, returning them and the timeline of the demo.
+fn read_changes(
+    mut reader: DemoReader,
+    filter: &NameFilter,
+    tick_rate: Option<i32>,
+) -> ExtractedChanges {
+    let length = reader.length();
+    let mut ticks = None;
+    // Names are interned the first time a player shows up, `None` if the filter excludes them,
+    // so the loop over the snapshots neither allocates nor hashes strings.
+    let mut ids = HashMap::new();
+    let mut names = Vec::new();
+    // The inputs of every client that used the name
+    let mut clients: Vec<Vec<(u16, InputChanges)>> = Vec::new();
+    // The snapshot and client a name was last seen in, to notice two clients using it at once
+    let mut last_seen: Vec<(usize, u16)> = Vec::new();
+    let mut shared = Vec::new();
+    let mut pauses = Vec::new();
+    let mut snap = Snap::default();
+    let mut snapshot = 0;
+    while let Ok(Some(chunk)) = reader.next_chunk(&mut snap) {
+        let last = ticks.map(|(_, last)| last);
+        track_ticks(&mut ticks, chunk);
+        if let (Some(from), Some((_, to))) = (last, ticks) {
+            if to - from >= timeline::MIN_PAUSE_TICKS {
+                pauses.push(Pause { from, to });
+            }
+        }
+        snapshot += 1;
+        for (client, p) in snap.players.iter() {
+            let Some(tee) = &p.tee else {
+                continue;
+            };
+            let id = *ids.entry(p.name).or_insert_with(|| {
+                let name = p.name.to_string();
+                filter.matches(&name).then(|| {
+                    names.push(name);
+                    clients.push(Vec::new());
+                    last_seen.push((0, 0));
+                    shared.push(false);
+                    names.len() - 1
+                })
+            });
+            let Some(id) = id else {
+                continue;
+            };
+            let client = client.legacy_id();
+            if last_seen[id].0 == snapshot && last_seen[id].1 != client {
+                shared[id] = true;
+            }
+            last_seen[id] = (snapshot, client);
+            let inputs = match clients[id].iter().position(|(c, _)| *c == client) {
+                Some(i) => &mut clients[id][i].1,
+                None => {
+                    clients[id].push((client, InputChanges::default()));
+                    &mut clients[id].last_mut().unwrap().1
+                }
+            };
+            inputs.push((p, tee).into());
+        }
+    }
+
+    let mut inputs = HashMap::new();
+    for ((name, mut clients), shared) in names.into_iter().zip(clients).zip(shared) {
+        if shared {
+            // Merging would interleave the samples of different tees, so every client keeps its own
+            clients.sort_by_key(|(client, _)| *client);
+            let split: Vec<String> = clients.iter().map(|(c, _)| format!("{name}#{c}")).collect();
+            eprintln!("Warning: several players used the name {name:?} at once, they are split into {split:?}");
+            inputs.extend(split.into_iter().zip(clients.into_iter().map(|(_, i)| i)));
+        } else if clients.len() == 1 {
+            inputs.insert(name, clients.pop().unwrap().1);
+        } else {
+            // The player reconnected and got a different client id
+            let mut samples: Vec<Inputs> = clients.iter().flat_map(|(_, i)| i.iter()).collect();
+            samples.sort_by_key(|i| i.tick);
+            inputs.insert(name, samples.into_iter().collect());
+        }
+    }
+    let timeline = Timeline {
+        pauses,
+        ..timeline(length, ticks, tick_rate)
+    };
+    (inputs, timeline)
+}
+
+/// The projectiles, lasers and pickups of the demo, read apart from the cached inputs.
+fn read_entities(path: &Path, options: ReadOptions) -> anyhow::Result<Vec<entities::Entities>> {
+    let reader = DemoReader::new(BufReader::new(File::open(path)?))?;
+    Ok(entities::read_entities(
+        reader,
+        options.anonymize,
+        options.pauses,
+    ))
+}
+
+/// The clan, DDNet team and skin color of every player, as last seen in the demos.
+fn read_player_info(paths: &[&Path]) -> anyhow::Result<HashMap<String, PlayerInfo>> {
+    let mut info = HashMap::new();
+    for path in paths {
+        let mut reader = DemoReader::new(BufReader::new(File::open(path)?))?;
+        let mut snap = Snap::default();
+        while let Ok(Some(_)) = reader.next_chunk(&mut snap) {
+            for (id, p) in snap.players.iter() {
+                let player = PlayerInfo {
+                    clan: p.clan.to_string(),
+                    team: p.team.to_u32(),
+                    color: p.use_custom_color.then(|| {
+                        let c = p.color_body.to_rgba();
+                        [c.r, c.g, c.b].map(|v| (v * 255.0).round() as u8)
+                    }),
+                };
+                // Players sharing a name are split into `name#client` by the extraction
+                info.insert(format!("{}#{}", p.name, id.legacy_id()), player.clone());
+                info.insert(p.name.to_string(), player);
+            }
+        }
+    }
+    Ok(info)
+}
+
+/// Keeps track of the first and last snapshot tick.
+fn track_ticks(ticks: &mut Option<(i32, i32)>, chunk: DemoChunk) {
+    if let DemoChunk::Snapshot(tick) = chunk {
+        let first = ticks.map_or(tick, |(first, _)| first);
+        *ticks = Some((first, tick));
+    }
+}
+
+fn timeline(length: i32, ticks: Option<(i32, i32)>, tick_rate: Option<i32>) -> Timeline {
+    let (first, last) = ticks.unwrap_or_default();
+    Timeline {
+        start_tick: first,
+        tick_rate: tick_rate.unwrap_or_else(|| data::detect_tick_rate(length, first, last)),
+        ..Timeline::default()
+    }
+}
+
+impl Command {
+    /// The directory of demos of the commands that process many of them.
+    fn batch_path(&self) -> Option<&Path> {
+        match self {
+            Command::Profile { path, .. }
+            | Command::Cluster { path, .. }
+            | Command::StatsDistribution { path, .. }
+            | Command::Reference { path, .. }
+            | Command::ExportFeatures { path, .. }
+            | Command::Evaluate { path, .. } => Some(path),
+            _ => None,
+        }
+    }
+}
+
+/// All demos in the directory, sorted by file name.
+fn demo_files(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut demos: Vec<PathBuf> = std::fs::read_dir(dir)?
+        .filter_map(|e| Some(e.ok()?.path()))
+        .filter(|p| p.extension().is_some_and(|e| e == "demo"))
+        .collect();
+    demos.sort();
+    Ok(demos)
+}
+
+/// What `process` takes from every demo in the directory, it returns none for demos it skips.
+/// With `out` the results are also kept next to the output, so a run that got interrupted
+/// continues where it stopped instead of going through every demo again, unless `force` is
+/// set.
+fn each_demo<T: Serialize + DeserializeOwned>(
+    dir: &Path,
+    out: Option<&Path>,
+    force: bool,
+    mut process: impl FnMut(&Path) -> anyhow::Result<Option<T>>,
+) -> anyhow::Result<Vec<T>> {
+    let mut progress = out
+        .map(|out| progress::Progress::load(out, force))
+        .transpose()?;
+    let mut earlier = match &progress {
+        Some(progress) => progress.results()?,
+        None => HashMap::new(),
+    };
+    let mut results = Vec::new();
+    for demo in demo_files(dir)? {
+        let Some(progress) = &mut progress else {
+            results.extend(process(&demo)?);
+            continue;
+        };
+        let hash = labels::demo_hash(&demo)?;
+        if let Some(result) = earlier.remove(&hash) {
+            note!("Skipping {demo:?}, an earlier run already went through it");
+            results.push(result);
+            continue;
+        }
+        if let Some(result) = process(&demo)? {
+            progress.finish_with(hash, &result)?;
+            results.push(result);
+        }
+    }
+    Ok(results)
+}
+
+fn demo_timestamp(path: &Path) -> anyhow::Result<String> {
+    let file = BufReader::new(File::open(path)?);
+    let reader = DemoReader::new(file)?;
+    Ok(reader.timestamp().to_string())
+}
+
+/// Loads the map from the given file, or from the demo if there is none.
+fn read_map(path: &Path, map: Option<&Path>) -> anyhow::Result<Option<Map>> {
+    if let Some(map) = map {
+        return Ok(Some(Map::parse(&std::fs::read(map)?)?));
+    }
+    let file = BufReader::new(File::open(path)?);
+    let reader = DemoReader::new(file)?;
+    reader.map_data().map(Map::parse).transpose()
+}
+
+/// The output of a command, binary formats can't be represented as string.
+enum Output {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl From<String> for Output {
+    fn from(value: String) -> Self {
+        Output::Text(value)
+    }
+}
+
+fn serialize<T: Serialize>(
+    value: &T,
+    format: ExtractionOutputFormat,
+    pretty: bool,
+    numbers: NumberFormat,
+    keys: &Keys,
+) -> Output {
+    // Works on the output as written, with the keys already styled. Going through the text
+    // keeps f32s as short as they are written instead of widening them.
+    if let Some(select) = &keys.select {
+        let text = serde_json::to_string(&Styled(value, keys.style)).unwrap();
+        let selected = select.apply(serde_json::from_str(&text).unwrap());
+        let keys = Keys {
+            select: None,
+            ..keys.clone()
+        };
+        return serialize(&selected, format, pretty, numbers, &keys);
+    }
+    let tagged = &Tagged(value, keys);
+    match format {
+        ExtractionOutputFormat::Json => {
+            if pretty {
+                serde_json::to_string_pretty(tagged).unwrap().into()
+            } else {
+                serde_json::to_string(tagged).unwrap().into()
+            }
+        }
+        ExtractionOutputFormat::Csv => {
+            // Every row gets the tags as columns instead
+            let value = serde_json::to_value(Styled(value, keys.style)).unwrap();
+            csv::to_csv(&value, numbers, &keys.tags).into()
+        }
+        ExtractionOutputFormat::Yaml => serde_yaml::to_string(tagged).unwrap().into(),
+        ExtractionOutputFormat::Toml => {
+            if pretty {
+                toml::to_string_pretty(tagged).unwrap().into()
+            } else {
+                toml::to_string(tagged).unwrap().into()
+            }
+        }
+        ExtractionOutputFormat::Rsn => {
+            if pretty {
+                rsn::to_string_pretty(tagged).into()
+            } else {
+                rsn::to_string(tagged).into()
+            }
+        }
+        // Named fields keep the output readable without knowing the exact struct layout
+        ExtractionOutputFormat::Msgpack => Output::Binary(rmp_serde::to_vec_named(tagged).unwrap()),
+        // Field names aren't written, renaming them would only break reading it back
+        ExtractionOutputFormat::Bincode => {
+            Output::Binary(bincode::serialize(&Tagged(value, &keys.snake_case())).unwrap())
+        }
+        ExtractionOutputFormat::Bbcode => {
+            bbcode::to_bbcode(&serde_json::to_value(tagged).unwrap(), numbers).into()
+        }
+    }
+}
+
+fn schema(kind: SchemaKind) -> RootSchema {
+    let (mut schema, name) = match kind {
+        SchemaKind::Analysis => (schema_for!(HashMap<String, CombinedStats>), "analysis"),
+        SchemaKind::Extraction => (schema_for!(HashMap<String, Vec<Inputs>>), "extraction"),
+        SchemaKind::Changes => (schema_for!(HashMap<String, InputChanges>), "changes"),
+        SchemaKind::Summary => (schema_for!(summary::Summary), "summary"),
+        SchemaKind::Rollup => (schema_for!(summary::RollUp), "rollup"),
+        SchemaKind::Entities => (
+            schema_for!(entities::WithEntities<HashMap<String, Vec<Inputs>>>),
+            "entities",
+        ),
+    };
+    schema.schema.metadata().id = Some(format!(
+        "https://github.com/hardliner66/tw_demo_analyzer/schema/v{OUTPUT_VERSION}/{name}.json"
+    ));
+    schema
+        .schema
+        .extensions
+        .insert(s!("version"), OUTPUT_VERSION.into());
+    schema
+}
+
+/// The human readable report of analyze, with the built-in layout or a `--template`.
+fn plain_report(
+    template: &str,
+    demo: &impl Serialize,
+    stats: &HashMap<String, CombinedStats>,
+    reference: Option<&Reference>,
+    options: template::ReportOptions,
+) -> anyhow::Result<String> {
+    // Where the rates of every player fall in the reference set
+    let placements: HashMap<String, Vec<reference::Placement>> = reference
+        .map(|reference| {
+            stats
+                .iter()
+                .map(|(name, stats)| {
+                    let stats = serde_json::to_value(stats).unwrap_or_default();
+                    (name.clone(), reference.placements(&stats))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    template::render(template, demo, stats, &placements, options)
+}
+
+fn write_output(
+    out: Option<PathBuf>,
+    append: bool,
+    output: impl Into<Output>,
+) -> anyhow::Result<()> {
+    if let (Some(out), true) = (&out, append) {
+        return append_output(out, output.into());
+    }
+    match (out, output.into()) {
+        (Some(out), Output::Text(output)) => std::fs::write(out, output)?,
+        (Some(out), Output::Binary(output)) => std::fs::write(out, output)?,
+        (None, Output::Text(output)) => println!("{output}"),
+        (None, Output::Binary(output)) => std::io::stdout().write_all(&output)?,
+    }
+    Ok(())
+}
+
+/// Adds the output to the end of the file, one line per compact json document makes NDJSON.
+/// The header of CSV output is left out if the file already has one.
+fn append_output(out: &Path, output: Output) -> anyhow::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .read(true)
+        .append(true)
+        .open(out)?;
+    let existing = file.metadata()?.len();
+    // The file may have been written without --append, which leaves out the last newline
+    if existing > 0 {
+        let mut last = [0];
+        file.seek(SeekFrom::End(-1))?;
+        file.read_exact(&mut last)?;
+        if last[0] != b'\n' {
+            file.write_all(b"\n")?;
+        }
+    }
+    match output {
+        Output::Text(mut output) => {
+            if existing > 0 && out.extension().is_some_and(|e| e == "csv") {
+                output = output
+                    .split_once('\n')
+                    .map_or("", |(_, rows)| rows)
+                    .to_string();
+            }
+            if !output.ends_with('\n') {
+                output.push('\n');
+            }
+            file.write_all(output.as_bytes())?;
+        }
+        Output::Binary(output) => file.write_all(&output)?,
+    }
+    Ok(())
+}
+
+impl Args {
+    /// Parses the arguments like `Args::parse`, and also rejects `--select` for commands that
+    /// write the plain report.
+    fn try_parse_checked<I, T>(args: I) -> Result<Self, clap::Error>
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<OsString> + Clone,
+    {
+        let matches = Args::command().try_get_matches_from(args)?;
+        let args = Args::from_arg_matches(&matches)?;
+        let plain = matches.subcommand().is_some_and(|(_, command)| {
+            matches!(
+                command.try_get_one::<AnalysisOutputFormat>("format"),
+                Ok(Some(AnalysisOutputFormat::Plain))
+            )
+        });
+        if args.select.is_some() && plain {
+            return Err(Args::command().error(
+                ErrorKind::ArgumentConflict,
+                "--select only works with structured formats, not with --format plain",
+            ));
+        }
+        Ok(args)
+    }
+}
+
+fn main() {
+    let args = Args::try_parse_checked(std::env::args_os()).unwrap_or_else(|e| {
+        // Clap exits with 2 for usage errors, which is a corrupt demo here
+        let _ = e.print();
+        exit(if e.use_stderr() { status::USAGE } else { 0 })
+    });
+    status::set_quiet(args.quiet);
+    if let Err(e) = run(args) {
+        eprintln!("Error: {e:?}");
+        exit(status::code(&e));
+    }
+}
+
+fn run(args: Args) -> anyhow::Result<()> {
+    let read_options = ReadOptions::from(&args);
+    let analysis_options = AnalysisOptions::from(&args);
+    let numbers = NumberFormat::from(&args);
+    let keys = Keys::from(&args);
+
+    if args.dry_run {
+        let Some(path) = args.command.batch_path() else {
+            return Err(anyhow::anyhow!(
+                "--dry-run is only supported by commands that process a directory of demos"
+            ));
+        };
+        let demos = if path.is_dir() {
+            demo_files(path)?
+        } else {
+            vec![path.to_path_buf()]
+        };
+        let plan = plan::plan(demos, !args.no_cache, args.out.as_deref(), args.append);
+        println!("{}", plan::plain_report(&plan));
+        return Ok(());
+    }
+
+    match args.command {
+        Command::Analyze {
+            path,
+            format,
+            stitch,
+            reference,
+            model,
+            template,
+            filter_options,
+        } => {
+            let reference = reference.as_deref().map(Reference::load).transpose()?;
+            let model = model.as_deref().map(Model::load).transpose()?;
+            let file = BufReader::new(File::open(&path)?);
+            let reader = DemoReader::new(file)?;
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            let demo_info = summary::DemoInfo::new(file_name.into_owned(), &reader);
+            let (inputs, timeline) = extract_session_changes(
+                path,
+                &stitch,
+                &filter_options.name_filter(),
+                read_options,
+            )?;
+            let mut stats = analyze_inputs(&inputs, &timeline, analysis_options);
+            if let Some(model) = &model {
+                for (name, player) in stats.iter_mut() {
+                    let i = inputs[name].to_vec();
+                    let features = features::features("", name, &i, timeline.tick_rate).features;
+                    player.bot_probability = Some(model.probability(&features)?);
+                }
+            }
+
+            if let AnalysisOutputFormat::SummaryJson = format {
+                let summary = summary::summarize(demo_info, &stats);
+                // The summary is a stable contract, its fields keep their names
+                let output = serialize(
+                    &summary,
+                    ExtractionOutputFormat::Json,
+                    filter_options.pretty,
+                    numbers,
+                    &keys.snake_case(),
+                );
+                write_output(args.out, args.append, output)?;
+                return Ok(());
+            }
+            if let AnalysisOutputFormat::RollupJson = format {
+                let roll_up = summary::roll_up(demo_info, &stats);
+                let output = serialize(
+                    &roll_up,
+                    ExtractionOutputFormat::Json,
+                    filter_options.pretty,
+                    numbers,
+                    &keys,
+                );
+                write_output(args.out, args.append, output)?;
+                return Ok(());
+            }
+
+            let output = match format.structured() {
+                Some(format) => serialize(&stats, format, filter_options.pretty, numbers, &keys),
+                None => {
+                    let template = match template {
+                        Some(template) => std::fs::read_to_string(&template)
+                            .with_context(|| format!("Couldn't read the template {template:?}"))?,
+                        None => s!(template::DEFAULT),
+                    };
+                    let options = template::ReportOptions {
+                        timeline: &timeline,
+                        time_format: args.time_format,
+                        lang: args.lang,
+                        numbers,
+                        palette: Palette::new(args.color, args.out.is_some()),
+                    };
+                    plain_report(&template, &demo_info, &stats, reference.as_ref(), options)?.into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Extract {
+            path,
+            format,
+            best_run,
+            changes_only,
+            entities,
+            stitch,
+            filter_options,
+        } => {
+            let entities = if entities {
+                Some(read_entities(&path, read_options)?)
+            } else {
+                None
+            };
+            let (mut inputs, timeline) =
+                extract_session(path, &stitch, &filter_options.name_filter(), read_options)?;
+            if best_run {
+                inputs = inputs
+                    .into_iter()
+                    .filter_map(|(name, i)| match runs::best_run(i, timeline.tick_rate) {
+                        Some(run) => Some((name, run)),
+                        None => {
+                            note!("No finished run found for {name}");
+                            None
+                        }
+                    })
+                    .collect();
+            }
+            let pretty = filter_options.pretty;
+            if let Some(hz) = args.interpolate {
+                if changes_only {
+                    return Err(anyhow::anyhow!(
+                        "--interpolate can't be used with --changes-only, the positions change with every sample"
+                    ));
+                }
+                let frames: HashMap<String, Vec<interpolate::Frame>> = inputs
+                    .iter()
+                    .map(|(n, i)| {
+                        (
+                            n.clone(),
+                            interpolate::interpolate(i, hz, timeline.tick_rate),
+                        )
+                    })
+                    .collect();
+                let output = match entities {
+                    Some(entities) => {
+                        let value = entities::WithEntities {
+                            players: frames,
+                            entities,
+                        };
+                        serialize(&value, format, pretty, numbers, &keys)
                     }
+                    None => serialize(&frames, format, pretty, numbers, &keys),
+                };
+                write_output(args.out, args.append, output)?;
+                return Ok(());
+            }
+            let output = match (entities, changes_only) {
+                (Some(entities), true) => {
+                    let players = compress(&inputs);
+                    let value = entities::WithEntities { players, entities };
+                    serialize(&value, format, pretty, numbers, &keys)
+                }
+                (Some(entities), false) => {
+                    let value = entities::WithEntities {
+                        players: inputs,
+                        entities,
+                    };
+                    serialize(&value, format, pretty, numbers, &keys)
+                }
+                (None, true) => serialize(&compress(&inputs), format, pretty, numbers, &keys),
+                (None, false) => serialize(&inputs, format, pretty, numbers, &keys),
+            };
+
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Split {
+            path,
+            format,
+            changes_only,
+            dir,
+            filter_options,
+        } => {
+            let (inputs, _) = extract(path.clone(), &filter_options.name_filter(), read_options)?;
+            let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+            std::fs::create_dir_all(&dir)?;
+            let mut players: Vec<_> = inputs.into_iter().collect();
+            players.sort_by(|(a, _), (b, _)| a.cmp(b));
+            let mut used = HashSet::new();
+            for (name, inputs) in players {
+                let safe: String = name
+                    .chars()
+                    .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                    .collect();
+                // Names that only differ in special characters would end up in the same file
+                let mut file_name = format!("{stem}.{safe}.{}", format.extension());
+                let mut n = 1;
+                while !used.insert(file_name.clone()) {
+                    n += 1;
+                    file_name = format!("{stem}.{safe}-{n}.{}", format.extension());
+                }
+                let player = HashMap::from([(name, inputs)]);
+                let output = if changes_only {
+                    serialize(
+                        &compress(&player),
+                        format,
+                        filter_options.pretty,
+                        numbers,
+                        &keys,
+                    )
+                } else {
+                    serialize(&player, format, filter_options.pretty, numbers, &keys)
+                };
+                let out = dir.join(file_name);
+                write_output(Some(out.clone()), false, output)?;
+                note!("Wrote {out:?}");
+            }
+        }
+        Command::Players {
+            path,
+            format,
+            filter_options,
+        } => {
+            let reader = DemoReader::new(BufReader::new(File::open(path)?))?;
+            let (mut players, timeline) = players::list_players(
+                reader,
+                &filter_options.name_filter(),
+                read_options.tick_rate,
+            );
+            if let Some(anonymizer) = read_options.anonymize {
+                for player in &mut players {
+                    player.name = anonymizer.pseudonym(&player.name);
+                    player.clan.clear();
+                }
+                players.sort_by(|a, b| a.name.cmp(&b.name));
+            }
+            let output = match format.structured() {
+                Some(format) => serialize(&players, format, filter_options.pretty, numbers, &keys),
+                None => players::plain_report(&players, &timeline, args.time_format).into(),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Tunes {
+            path,
+            format,
+            pretty,
+        } => {
+            let (tunes, timeline) = tunes::read_tunes(&path, read_options.tick_rate)?;
+            let output = match format.structured() {
+                Some(format) => serialize(&tunes, format, pretty, numbers, &keys),
+                None => tunes::plain_report(&tunes, &timeline, args.time_format).into(),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Dump {
+            path,
+            format,
+            table,
+            from,
+            to,
+            filter_options,
+        } => {
+            let (inputs, timeline) = extract(path, &filter_options.name_filter(), read_options)?;
+            let in_range = |i: &Inputs| {
+                let time = timeline.seconds(i.tick);
+                from.is_none_or(|from| time >= from) && to.is_none_or(|to| time <= to)
+            };
+            let inputs: BTreeMap<String, Vec<Inputs>> = inputs
+                .into_iter()
+                .map(|(name, i)| (name, i.into_iter().filter(in_range).collect()))
+                .collect();
+            let output = if table {
+                let strings: Vec<String> = inputs
+                    .iter()
+                    .map(|(name, inputs)| {
+                        let rows: Vec<_> = inputs
+                            .iter()
+                            .map(|i| table::row(i, &timeline, args.time_format))
+                            .collect();
+                        format!(
+                            "{:=^44}\n\n{}\n",
+                            format!(" {name} "),
+                            table::format_table(&rows)
+                        )
+                    })
+                    .collect();
+                strings.join("\n").into()
+            } else {
+                serialize(&inputs, format, filter_options.pretty, numbers, &keys)
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Pace {
+            path,
+            format,
+            section_length,
+            filter_options,
+        } => {
+            let (inputs, timeline) = extract(path, &filter_options.name_filter(), read_options)?;
+            let pace: HashMap<String, Pace> = inputs
+                .into_iter()
+                .filter_map(|(name, i)| {
+                    Some((
+                        name,
+                        pace::calculate_pace(&i, section_length, timeline.tick_rate)?,
+                    ))
+                })
+                .collect();
+
+            let output = match format.structured() {
+                Some(format) => serialize(&pace, format, filter_options.pretty, numbers, &keys),
+                None => {
+                    let strings: Vec<String> = pace
+                        .into_iter()
+                        .map(|(name, pace)| {
+                            let mut vec = Vec::new();
+                            vec.push(format!("{:=^44}", format!(" {name} ")));
+                            vec.push(s!(""));
+                            vec.push(format!(
+                                "Best Run : #{} ({} sections of {} tiles)",
+                                pace.best_run + 1,
+                                pace.sections,
+                                pace.section_length
+                            ));
+                            for run in pace.runs {
+                                vec.push(s!(""));
+                                vec.push(format!("{:-^44}", format!(" Run #{} ", run.run + 1)));
+                                vec.push(s!(""));
+                                for (section, (time, delta)) in
+                                    run.section_times.iter().zip(&run.deltas).enumerate()
+                                {
+                                    let time = time.map_or(s!("-"), |t| format!("{t:.2}s"));
+                                    let delta = delta.map_or(s!("-"), |d| format!("{d:+.2}s"));
+                                    let slowest = if run.slowest_section == Some(section) {
+                                        " (slowest)"
+                                    } else {
+                                        ""
+                                    };
+                                    vec.push(format!(
+                                        "Section {:>3} : {time:>8} {delta:>8}{slowest}",
+                                        section + 1
+                                    ));
+                                }
+                            }
+                            vec.push(s!(""));
+                            vec.join("\n")
+                        })
+                        .collect();
+                    strings.join("\n").into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Changepoints {
+            path,
+            format,
+            window,
+            penalty,
+            filter_options,
+        } => {
+            let (inputs, timeline) = extract(path, &filter_options.name_filter(), read_options)?;
+            let changepoints: HashMap<String, changepoint::Changepoints> = inputs
+                .into_iter()
+                .map(|(name, i)| {
+                    let changepoints =
+                        changepoint::detect_changepoints(&i, &timeline, window, penalty);
+                    (name, changepoints)
+                })
+                .collect();
+            let output = match format.structured() {
+                Some(format) => {
+                    serialize(&changepoints, format, filter_options.pretty, numbers, &keys)
                 }
-                AnalysisOutputFormat::Rsn => {
-                    if filter_options.pretty {
-                        rsn::to_string_pretty(&stats)
-                    } else {
-                        rsn::to_string(&stats)
+                None => {
+                    changepoint::plain_report(&changepoints, &timeline, args.time_format).into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Accuracy {
+            path,
+            format,
+            filter_options,
+        } => {
+            let entities = read_entities(&path, read_options)?;
+            let (tunes, _) = tunes::read_tunes(&path, read_options.tick_rate)?;
+            // Every player is a target, only the filtered ones are reported
+            let (inputs, timeline) = extract(path, &NameFilter::default(), read_options)?;
+            let filter = filter_options.name_filter();
+            let players: Vec<&String> = inputs.keys().filter(|n| filter.matches(n)).collect();
+            if players.is_empty() {
+                return Err(NoPlayersMatched.into());
+            }
+            let accuracy = accuracy::calculate_accuracy(
+                &players,
+                &inputs,
+                &entities,
+                &tunes,
+                timeline.tick_rate,
+            );
+            let output = match format.structured() {
+                Some(format) => serialize(&accuracy, format, filter_options.pretty, numbers, &keys),
+                None => accuracy::plain_report(&accuracy).into(),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Highlights {
+            path,
+            format,
+            map,
+            clips,
+            padding,
+            ffmpeg,
+            filter_options,
+        } => {
+            let map = read_map(&path, map.as_deref()).unwrap_or_else(|e| {
+                eprintln!("Couldn't load map, near misses are not detected: {e}");
+                None
+            });
+            let stem = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            // The clips show the other players too
+            let (inputs, timeline) = extract(path, &NameFilter::default(), read_options)?;
+            let filter = filter_options.name_filter();
+            let highlights: HashMap<String, Vec<highlights::Highlight>> = inputs
+                .iter()
+                .filter(|(name, _)| filter.matches(name))
+                .map(|(name, i)| {
+                    let highlights = highlights::detect_highlights(i, map.as_ref(), &timeline);
+                    (name.clone(), highlights)
+                })
+                .collect();
+            if highlights.is_empty() {
+                return Err(NoPlayersMatched.into());
+            }
+            if let Some(dir) = clips {
+                std::fs::create_dir_all(&dir)?;
+                let padding = (padding * timeline.tick_rate as f32) as i32;
+                for (name, highlights) in &highlights {
+                    let safe: String = name
+                        .chars()
+                        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                        .collect();
+                    for (n, highlight) in highlights.iter().enumerate() {
+                        let kind = format!("{:?}", highlight.kind).to_lowercase();
+                        let output = dir.join(format!("{stem}.{safe}.{:02}.{kind}.mp4", n + 1));
+                        note!("Rendering {}", output.display());
+                        let options = render::RenderOptions {
+                            width: 1280,
+                            height: 720,
+                            fps: 50,
+                            tick_rate: timeline.tick_rate,
+                            tiles_visible: 20.0,
+                            ffmpeg: ffmpeg.clone(),
+                            output,
+                            ticks: Some((
+                                highlight.start.tick - padding,
+                                highlight.end.tick + padding,
+                            )),
+                            interpolate: args.interpolate.is_some(),
+                        };
+                        render::render(map.as_ref(), &inputs, name, &options)?;
                     }
                 }
-                AnalysisOutputFormat::Plain => {
-                    let strings: Vec<String> = stats
+            }
+            let output = match format.structured() {
+                Some(format) => {
+                    serialize(&highlights, format, filter_options.pretty, numbers, &keys)
+                }
+                None => highlights::plain_report(&highlights, &timeline, args.time_format).into(),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Tricks {
+            path,
+            format,
+            map,
+            filter_options,
+        } => {
+            let map = read_map(&path, map.as_deref()).unwrap_or_else(|e| {
+                eprintln!("Couldn't load map, edge jumps are not detected: {e}");
+                None
+            });
+            let (inputs, timeline) = extract(path, &filter_options.name_filter(), read_options)?;
+            let tricks: HashMap<String, Tricks> = inputs
+                .into_iter()
+                .map(|(name, i)| (name, tricks::detect_tricks(&i, map.as_ref(), &timeline)))
+                .collect();
+
+            let output = match format.structured() {
+                Some(format) => serialize(&tricks, format, filter_options.pretty, numbers, &keys),
+                None => {
+                    let strings: Vec<String> = tricks
                         .into_iter()
-                        .map(
-                            |(
-                                name,
-                                CombinedStats {
-                                    direction_change_rate_average,
-                                    direction_change_rate_median,
-                                    direction_change_rate_max,
-                                    hook_state_change_rate_average,
-                                    hook_state_change_rate_median,
-                                    hook_state_change_rate_max,
-                                    direction_changes,
-                                    hook_changes,
-                                    overall_changes,
-                                    ..
-                                },
-                            )| {
-                                let mut vec = Vec::with_capacity(11);
-                                vec.push(format!("{:=^44}", format!(" {name} ")));
+                        .map(|(name, tricks)| {
+                            let mut vec = Vec::new();
+                            vec.push(format!("{:=^44}", format!(" {name} ")));
+                            vec.push(s!(""));
+                            for (trick, count) in &tricks.counts {
+                                vec.push(format!("{:<10} : {count}", format!("{trick:?}")));
+                            }
+                            if !tricks.occurrences.is_empty() {
+                                vec.push(s!(""));
+                                vec.push(format!("{:-^44}", " Occurrences "));
                                 vec.push(s!(""));
-                                vec.push(format!("Overal Input State Changes : {overall_changes}"));
+                            }
+                            for o in &tricks.occurrences {
+                                let run = o.run.map_or(s!(""), |r| format!(" (run #{})", r + 1));
                                 vec.push(format!(
-                                    "Direction Changes ........ : {direction_changes}"
+                                    "{:<10} {} - {}{run}",
+                                    format!("{:?}", o.trick),
+                                    args.time_format.format(timeline.seconds(o.start.tick)),
+                                    args.time_format.format(timeline.seconds(o.end.tick)),
                                 ));
-                                vec.push(format!("Hook Changes ............. : {hook_changes}"));
+                            }
+                            vec.push(s!(""));
+                            vec.join("\n")
+                        })
+                        .collect();
+                    strings.join("\n").into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Desync {
+            path,
+            format,
+            map,
+            filter_options,
+        } => {
+            let map = read_map(&path, map.as_deref()).unwrap_or_else(|e| {
+                eprintln!("Couldn't load map, teleporters are not recognized: {e}");
+                None
+            });
+            let (tunes, _) = tunes::read_tunes(&path, read_options.tick_rate)?;
+            let (inputs, timeline) = extract(path, &filter_options.name_filter(), read_options)?;
+            let desyncs: HashMap<String, DesyncStats> = inputs
+                .into_iter()
+                .map(|(name, i)| {
+                    let stats = desync::detect_desyncs(&i, map.as_ref(), &tunes, &timeline);
+                    (name, stats)
+                })
+                .collect();
+
+            let output = match format.structured() {
+                Some(format) => serialize(&desyncs, format, filter_options.pretty, numbers, &keys),
+                None => {
+                    let strings: Vec<String> = desyncs
+                        .into_iter()
+                        .map(|(name, stats)| {
+                            let mut vec = Vec::new();
+                            vec.push(format!("{:=^44}", format!(" {name} ")));
+                            vec.push(s!(""));
+                            if !stats.teleporters_known {
+                                vec.push(s!("No teleporter layer, warps may be map teleporters"));
                                 vec.push(s!(""));
-                                vec.push(format!("{:-^44}", format!(" Direction Change Rate ")));
+                            }
+                            for (kind, count) in &stats.counts {
+                                vec.push(format!("{:<10} : {count}", format!("{kind:?}")));
+                            }
+                            if !stats.desyncs.is_empty() {
                                 vec.push(s!(""));
+                                vec.push(format!("{:-^44}", " Occurrences "));
+                                vec.push(s!(""));
+                            }
+                            for d in &stats.desyncs {
                                 vec.push(format!(
-                                    "Average : {direction_change_rate_average:0>5.2} per second"
-                                ));
-                                vec.push(format!(
-                                    "Median  : {direction_change_rate_median:0>5.2} per second"
-                                ));
-                                vec.push(format!(
-                                    "Max ... : {:0>5.2} per second",
-                                    direction_change_rate_max as f32
+                                    "{:<10} {} - {} {:.1} tiles ({:.1} off)",
+                                    format!("{:?}", d.kind),
+                                    args.time_format.format(timeline.seconds(d.start.tick)),
+                                    args.time_format.format(timeline.seconds(d.end.tick)),
+                                    d.distance,
+                                    d.error,
                                 ));
-                                vec.push(s!(""));
-                                vec.push(format!("{:-^44}", format!(" Hook State Change Rate ")));
-                                vec.push(s!(""));
+                            }
+                            vec.push(s!(""));
+                            vec.join("\n")
+                        })
+                        .collect();
+                    strings.join("\n").into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Query {
+            path,
+            format,
+            query,
+            filter_options,
+        } => {
+            let (inputs, timeline) = extract(path, &filter_options.name_filter(), read_options)?;
+            let matches: HashMap<String, Vec<query::Match>> = inputs
+                .into_iter()
+                .map(|(name, i)| (name, query::run(&query, &i, &timeline)))
+                .collect();
+
+            let output = match format.structured() {
+                Some(format) => serialize(&matches, format, filter_options.pretty, numbers, &keys),
+                None => {
+                    let strings: Vec<String> = matches
+                        .into_iter()
+                        .filter(|(_, matches)| !matches.is_empty())
+                        .map(|(name, matches)| {
+                            let mut vec = Vec::new();
+                            vec.push(format!("{:=^44}", format!(" {name} ")));
+                            vec.push(s!(""));
+                            for m in &matches {
                                 vec.push(format!(
-                                    "Average : {hook_state_change_rate_average:0>5.2} per second"
+                                    "{} - {} ({} samples)",
+                                    args.time_format.format(timeline.seconds(m.start.tick)),
+                                    args.time_format.format(timeline.seconds(m.end.tick)),
+                                    m.samples,
                                 ));
+                            }
+                            vec.push(s!(""));
+                            vec.join("\n")
+                        })
+                        .collect();
+                    strings.join("\n").into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::HammerflySync {
+            path,
+            format,
+            filter_options,
+        } => {
+            // The partner might not match the filter, so all players are read and the filter
+            // is applied to the pairs instead
+            let (inputs, timeline) = extract(path, &NameFilter::default(), read_options)?;
+            let filter = filter_options.name_filter();
+            let sync: Vec<HammerflySync> = sync::hammerfly_sync(&inputs, timeline.tick_rate)
+                .into_iter()
+                .filter(|s| s.players.iter().any(|p| filter.matches(p)))
+                .collect();
+
+            let output = match format.structured() {
+                Some(format) => serialize(&sync, format, filter_options.pretty, numbers, &keys),
+                None => {
+                    let strings: Vec<String> = sync
+                        .into_iter()
+                        .map(|s| {
+                            let mut vec = Vec::new();
+                            vec.push(format!(
+                                "{:=^44}",
+                                format!(" {} & {} ", s.players[0], s.players[1])
+                            ));
+                            vec.push(s!(""));
+                            vec.push(format!("Hammerflies ......... : {}", s.segments));
+                            vec.push(format!("Shot Pairs .......... : {}", s.pairs));
+                            vec.push(format!("Relative Phase ...... : {:+.2}", s.relative_phase));
+                            vec.push(format!("Phase Variance ...... : {:.4}", s.phase_variance));
+                            vec.push(format!(
+                                "Offset Deviation .... : {:.2} ticks",
+                                s.offset_deviation
+                            ));
+                            vec.push(format!(
+                                "Interval Deviation .. : {:.2} ticks",
+                                s.interval_deviation
+                            ));
+                            vec.push(format!("Superhuman .......... : {}", s.superhuman));
+                            vec.push(s!(""));
+                            vec.join("\n")
+                        })
+                        .collect();
+                    strings.join("\n").into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Profile {
+            path,
+            format,
+            identities,
+            threshold,
+            force,
+            filter_options,
+        } => {
+            let identities = identities
+                .map(|i| profile::read_identities(&i))
+                .transpose()?
+                .unwrap_or_default();
+            let demos = each_demo(&path, args.out.as_deref(), force, |demo| {
+                let inputs = demo_timestamp(demo).and_then(|t| {
+                    Ok((
+                        t,
+                        extract(demo.to_owned(), &filter_options.name_filter(), read_options)?,
+                    ))
+                });
+                let (timestamp, (inputs, timeline)) = match inputs {
+                    Ok(inputs) => inputs,
+                    Err(e) => {
+                        eprintln!("Skipping {demo:?}: {e}");
+                        return Ok(None);
+                    }
+                };
+                let demo_name = demo
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy()
+                    .into_owned();
+                let metrics: Vec<DemoMetrics> = inputs
+                    .into_iter()
+                    .map(|(name, inputs)| DemoMetrics {
+                        demo: demo_name.clone(),
+                        timestamp: timestamp.clone(),
+                        name,
+                        metrics: profile::calculate_metrics(&inputs, timeline.tick_rate),
+                    })
+                    .collect();
+                Ok(Some(metrics))
+            })?;
+            let mut histories: HashMap<String, Vec<DemoMetrics>> = HashMap::new();
+            for metrics in demos.into_iter().flatten() {
+                let identity = identities.get(&metrics.name).unwrap_or(&metrics.name);
+                histories.entry(identity.clone()).or_default().push(metrics);
+            }
+
+            let profiles: BTreeMap<String, Profile> = histories
+                .into_iter()
+                .map(|(identity, mut history)| {
+                    history.sort_by(|a, b| (&a.timestamp, &a.demo).cmp(&(&b.timestamp, &b.demo)));
+                    (identity, profile::build_profile(history, threshold))
+                })
+                .collect();
+
+            let output = match format.structured() {
+                Some(format) => serialize(&profiles, format, filter_options.pretty, numbers, &keys),
+                None => {
+                    let strings: Vec<String> = profiles
+                        .into_iter()
+                        .map(|(identity, p)| {
+                            let mut vec = Vec::new();
+                            vec.push(format!("{:=^44}", format!(" {identity} ")));
+                            vec.push(s!(""));
+                            vec.push(format!("Names : {}", p.names.join(", ")));
+                            vec.push(format!("Demos : {}", p.history.len()));
+                            vec.push(s!(""));
+                            vec.push(format!("{:-^44}", " History "));
+                            vec.push(s!(""));
+                            vec.push(format!(
+                                "{:<20} {:>6} {:>6} {:>6} {:>8} {:>6} {:>6}",
+                                "Recorded", "dir/s", "hook/s", "aim", "jerk", "linear", "target"
+                            ));
+                            for demo in &p.history {
+                                let m = |metric| demo.metrics[metric];
                                 vec.push(format!(
-                                    "Median  : {hook_state_change_rate_median:0>5.2} per second"
+                                    "{:<20} {:>6.2} {:>6.2} {:>6.2} {:>8.1} {:>5.1}% {:>6.2}",
+                                    demo.timestamp,
+                                    m("direction_change_rate_average"),
+                                    m("hook_state_change_rate_average"),
+                                    m("aim_angular_speed_average"),
+                                    m("aim_angular_jerk_average"),
+                                    m("aim_linear_segment_fraction") * 100.0,
+                                    m("target_distance_average"),
                                 ));
+                            }
+                            vec.push(s!(""));
+                            vec.push(format!("{:-^44}", " Trends "));
+                            vec.push(s!(""));
+                            for (metric, slope) in &p.trends {
+                                vec.push(format!("{metric:<32} : {slope:+.3} per demo"));
+                            }
+                            vec.push(s!(""));
+                            vec.push(format!("{:-^44}", " Sudden Changes "));
+                            vec.push(s!(""));
+                            if p.sudden_changes.is_empty() {
+                                vec.push(s!("None"));
+                            }
+                            for c in &p.sudden_changes {
                                 vec.push(format!(
-                                    "Max ... : {:0>5.2} per second",
-                                    hook_state_change_rate_max as f32
+                                    "{} {} : {:.2} (before {:.2}, {:+.1} sd)",
+                                    c.demo, c.metric, c.value, c.previous_average, c.deviation
                                 ));
-                                vec.push(s!(""));
-                                vec.push(s!("============================================"));
-                                vec.push(format!("{:=^44}", s!(" END ")));
-                                vec.push(s!("============================================"));
-                                vec.push(s!(""));
-                                vec.push(s!(""));
-
-                                vec.join("\n")
-                            },
-                        )
+                            }
+                            vec.push(s!(""));
+                            vec.join("\n")
+                        })
                         .collect();
-                    strings.join("\n")
+                    strings.join("\n").into()
                 }
             };
-            if let Some(out) = args.out {
-                std::fs::write(out, output)?;
-            } else {
-                println!("{output}");
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Cluster {
+            path,
+            format,
+            min_similarity,
+            force,
+            filter_options,
+        } => {
+            let demos = each_demo(&path, args.out.as_deref(), force, |demo| {
+                let (inputs, timeline) =
+                    match extract(demo.to_owned(), &filter_options.name_filter(), read_options) {
+                        Ok(inputs) => inputs,
+                        Err(e) => {
+                            eprintln!("Skipping {demo:?}: {e}");
+                            return Ok(None);
+                        }
+                    };
+                let demo_name = demo.to_string_lossy();
+                let fingerprints: Vec<(String, Fingerprint)> = inputs
+                    .into_iter()
+                    .map(|(name, inputs)| {
+                        let mut fingerprint = Fingerprint::default();
+                        fingerprint.add(&demo_name, &inputs, timeline.tick_rate);
+                        (name, fingerprint)
+                    })
+                    .collect();
+                Ok(Some(fingerprints))
+            })?;
+            let mut fingerprints: HashMap<String, Fingerprint> = HashMap::new();
+            for (name, fingerprint) in demos.into_iter().flatten() {
+                fingerprints.entry(name).or_default().merge(fingerprint);
             }
+            let clustering = fingerprint::cluster(&fingerprints, min_similarity);
+
+            let output = match format.structured() {
+                Some(format) => {
+                    serialize(&clustering, format, filter_options.pretty, numbers, &keys)
+                }
+                None => {
+                    let mut vec = Vec::new();
+                    vec.push(format!("{:=^44}", " Likely Same Player "));
+                    vec.push(s!(""));
+                    if clustering.pairs.is_empty() {
+                        vec.push(s!("None"));
+                    }
+                    for pair in &clustering.pairs {
+                        vec.push(format!(
+                            "{:>5.1}% {} <-> {}",
+                            pair.similarity * 100.0,
+                            pair.a,
+                            pair.b
+                        ));
+                    }
+                    vec.push(s!(""));
+                    vec.push(format!("{:-^44}", " Clusters "));
+                    vec.push(s!(""));
+                    for (i, cluster) in clustering.clusters.iter().enumerate() {
+                        vec.push(format!("#{:<3} {}", i + 1, cluster.join(", ")));
+                    }
+                    if !clustering.skipped.is_empty() {
+                        vec.push(s!(""));
+                        vec.push(format!(
+                            "Not enough data : {}",
+                            clustering.skipped.join(", ")
+                        ));
+                    }
+                    vec.push(s!(""));
+                    vec.join("\n").into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
         }
-        Command::Extract {
+        Command::Vanilla {
             path,
             format,
             filter_options,
         } => {
-            let inputs = extract(path, &filter_options.filter)?;
-            let output = match format {
-                ExtractionOutputFormat::Json => {
-                    if filter_options.pretty {
-                        serde_json::to_string_pretty(&inputs).unwrap()
-                    } else {
-                        serde_json::to_string(&inputs).unwrap()
+            let reader = DemoReader::new(BufReader::new(File::open(path)?))?;
+            let mut report = vanilla::read_vanilla(reader, &filter_options.name_filter());
+            if let Some(anonymizer) = read_options.anonymize {
+                report.players = report
+                    .players
+                    .into_iter()
+                    .map(|(name, stats)| (anonymizer.pseudonym(&name), stats))
+                    .collect();
+            }
+            let output = match format.structured() {
+                Some(format) => serialize(&report, format, filter_options.pretty, numbers, &keys),
+                None => vanilla::plain_report(&report).into(),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Reference {
+            path,
+            force,
+            filter_options,
+        } => {
+            let demos = each_demo(&path, args.out.as_deref(), force, |demo| {
+                let (inputs, timeline) = match extract_changes(
+                    demo.to_owned(),
+                    &filter_options.name_filter(),
+                    read_options,
+                ) {
+                    Ok(inputs) => inputs,
+                    Err(e) => {
+                        eprintln!("Skipping {demo:?}: {e}");
+                        return Ok(None);
                     }
+                };
+                let stats = analyze_inputs(&inputs, &timeline, analysis_options)
+                    .values()
+                    .filter(|player| !player.insufficient_sample)
+                    .map(serde_json::to_value)
+                    .collect::<Result<Vec<_>, _>>()?;
+                Ok(Some(stats))
+            })?;
+            let stats: Vec<serde_json::Value> = demos.into_iter().flatten().collect();
+            let reference = Reference::build(&stats);
+            let output = serialize(
+                &reference,
+                ExtractionOutputFormat::Json,
+                filter_options.pretty,
+                numbers,
+                &keys,
+            );
+            write_output(args.out, args.append, output)?;
+        }
+        Command::ExportFeatures {
+            path,
+            format,
+            force,
+            filter_options,
+        } => {
+            let demos = if path.is_dir() {
+                demo_files(&path)?
+            } else {
+                vec![path]
+            };
+            // With --out every demo is added to the file as soon as it is done, so a run that
+            // got interrupted can skip the demos it already wrote
+            let mut progress = args
+                .out
+                .as_deref()
+                .map(|out| progress::Progress::load(out, force))
+                .transpose()?;
+            if let (Some(out), Some(progress)) = (&args.out, &progress) {
+                if progress.is_fresh() && !args.append {
+                    File::create(out)?;
                 }
-                ExtractionOutputFormat::Yaml => serde_yaml::to_string(&inputs).unwrap(),
-                ExtractionOutputFormat::Toml => {
-                    if filter_options.pretty {
-                        toml::to_string_pretty(&inputs).unwrap()
-                    } else {
-                        toml::to_string(&inputs).unwrap()
+            }
+            let mut rows = Vec::new();
+            for demo in demos {
+                let hash = match &progress {
+                    Some(progress) => {
+                        let hash = labels::demo_hash(&demo)?;
+                        if progress.is_done(&hash) {
+                            note!("Skipping {demo:?}, it was already exported");
+                            continue;
+                        }
+                        Some(hash)
                     }
+                    None => None,
+                };
+                let (inputs, timeline) = match extract_changes(
+                    demo.clone(),
+                    &filter_options.name_filter(),
+                    read_options,
+                ) {
+                    Ok(inputs) => inputs,
+                    Err(e) => {
+                        eprintln!("Skipping {demo:?}: {e}");
+                        continue;
+                    }
+                };
+                let name = demo.file_name().unwrap_or_default().to_string_lossy();
+                let mut players: Vec<_> = inputs.iter().collect();
+                players.sort_by_key(|(player, _)| *player);
+                for (player, changes) in players {
+                    rows.push(features::features(
+                        &name,
+                        player,
+                        &changes.to_vec(),
+                        timeline.tick_rate,
+                    ));
                 }
-                ExtractionOutputFormat::Rsn => {
-                    if filter_options.pretty {
-                        rsn::to_string_pretty(&inputs)
-                    } else {
-                        rsn::to_string(&inputs)
+                if let (Some(progress), Some(hash)) = (&mut progress, hash) {
+                    if !rows.is_empty() {
+                        let output =
+                            serialize(&rows, format, filter_options.pretty, numbers, &keys);
+                        write_output(args.out.clone(), true, output)?;
+                        rows.clear();
                     }
+                    progress.finish(hash)?;
                 }
+            }
+            if progress.is_none() {
+                write_output(
+                    args.out,
+                    args.append,
+                    serialize(&rows, format, filter_options.pretty, numbers, &keys),
+                )?;
+            }
+        }
+        Command::Label {
+            database,
+            notes,
+            format,
+            pretty,
+            path,
+            player,
+            verdict,
+        } => {
+            let store = labels::LabelStore::open(&database)?;
+            let demo_hash = path.as_deref().map(labels::demo_hash).transpose()?;
+            match (path, player, verdict) {
+                (Some(path), Some(player), Some(verdict)) => {
+                    let reader = DemoReader::new(BufReader::new(File::open(&path)?))?;
+                    let (players, _) =
+                        players::list_players(reader, &Default::default(), read_options.tick_rate);
+                    if !players.iter().any(|p| p.name == player) {
+                        return Err(Usage(format!("{player} isn't in {path:?}")).into());
+                    }
+                    store.set(&labels::Label {
+                        demo_hash: demo_hash.unwrap_or_default(),
+                        demo: path
+                            .file_name()
+                            .unwrap_or_default()
+                            .to_string_lossy()
+                            .into_owned(),
+                        player,
+                        verdict,
+                        notes,
+                    })?;
+                }
+                (_, Some(_), None) => {
+                    return Err(Usage(s!("A verdict is needed to label a player")).into());
+                }
+                _ => {
+                    let labels = store.list(demo_hash.as_deref())?;
+                    write_output(
+                        args.out,
+                        args.append,
+                        serialize(&labels, format, pretty, numbers, &keys),
+                    )?;
+                }
+            }
+        }
+        Command::Evaluate {
+            database,
+            metric,
+            folds,
+            format,
+            pretty,
+            force,
+            path,
+        } => {
+            let store = labels::LabelStore::open(&database)?;
+            let demos = each_demo(&path, args.out.as_deref(), force, |demo| {
+                let labels: Vec<labels::Label> = store
+                    .list(Some(&labels::demo_hash(demo)?))?
+                    .into_iter()
+                    .filter(|l| l.verdict != labels::Verdict::Unsure)
+                    .collect();
+                if labels.is_empty() {
+                    return Ok(None);
+                }
+                let (inputs, timeline) =
+                    match extract_changes(demo.to_owned(), &Default::default(), read_options) {
+                        Ok(inputs) => inputs,
+                        Err(e) => {
+                            eprintln!("Skipping {demo:?}: {e}");
+                            return Ok(None);
+                        }
+                    };
+                let stats = analyze_inputs(&inputs, &timeline, analysis_options);
+                let mut samples = Vec::new();
+                for label in labels {
+                    let Some(player) = stats.get(&label.player) else {
+                        eprintln!("No stats for {} in {demo:?}", label.player);
+                        continue;
+                    };
+                    samples.push(evaluate::Sample {
+                        stats: serde_json::to_value(player)?,
+                        cheating: label.verdict == labels::Verdict::Cheating,
+                    });
+                }
+                Ok(Some(samples))
+            })?;
+            let samples: Vec<evaluate::Sample> = demos.into_iter().flatten().collect();
+            let metrics = if metric.is_empty() {
+                reference::METRICS.map(String::from).to_vec()
+            } else {
+                metric
+            };
+            let evaluations = evaluate::evaluate(&samples, &metrics, folds);
+            let output = match format.structured() {
+                Some(format) => serialize(&evaluations, format, pretty, numbers, &keys),
+                None => evaluate::plain_report(&evaluations).into(),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::MergeReports {
+            format,
+            pretty,
+            paths,
+        } => {
+            let report = merge::merge(&paths)?;
+            let output = match format.structured() {
+                Some(format) => serialize(&report, format, pretty, numbers, &keys),
+                None => merge::plain_report(&report).into(),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::StatsDistribution {
+            path,
+            format,
+            metric,
+            buckets,
+            force,
+            filter_options,
+        } => {
+            let demos = each_demo(&path, args.out.as_deref(), force, |demo| {
+                let (inputs, timeline) = match extract_changes(
+                    demo.to_owned(),
+                    &filter_options.name_filter(),
+                    read_options,
+                ) {
+                    Ok(inputs) => inputs,
+                    Err(e) => {
+                        eprintln!("Skipping {demo:?}: {e}");
+                        return Ok(None);
+                    }
+                };
+                let mut values = Vec::new();
+                for stats in analyze_inputs(&inputs, &timeline, analysis_options).values() {
+                    let stats = serde_json::to_value(stats)?;
+                    values.extend(distribution::lookup(&stats, &metric));
+                }
+                Ok(Some(values))
+            })?;
+            let values = demos.into_iter().flatten().collect();
+            let Some(distribution) = distribution::distribution(&metric, values, buckets) else {
+                return Err(Usage(format!("No values found for {metric}")).into());
             };
 
-            if let Some(out) = args.out {
-                std::fs::write(out, output)?;
-            } else {
-                println!("{output}");
+            let output = match format.structured() {
+                Some(format) => {
+                    serialize(&distribution, format, filter_options.pretty, numbers, &keys)
+                }
+                None => {
+                    let mut vec = Vec::new();
+                    vec.push(format!("{:=^44}", format!(" {metric} ")));
+                    vec.push(s!(""));
+                    vec.push(format!("Players : {}", distribution.count));
+                    vec.push(format!("Min ... : {:.2}", distribution.min));
+                    vec.push(format!("Mean .. : {:.2}", distribution.mean));
+                    vec.push(format!("Max ... : {:.2}", distribution.max));
+                    vec.push(s!(""));
+                    vec.push(format!("{:-^44}", " Percentiles "));
+                    vec.push(s!(""));
+                    for p in &distribution.percentiles {
+                        vec.push(format!("P{:<4} : {:.2}", p.percentile, p.value));
+                    }
+                    vec.push(s!(""));
+                    vec.push(format!("{:-^44}", " Histogram "));
+                    vec.push(s!(""));
+                    let most = distribution
+                        .histogram
+                        .iter()
+                        .map(|b| b.count)
+                        .max()
+                        .unwrap_or_default()
+                        .max(1);
+                    for bucket in &distribution.histogram {
+                        vec.push(format!(
+                            "{:>8.2} - {:<8.2} {:>5} {}",
+                            bucket.from,
+                            bucket.to,
+                            bucket.count,
+                            "#".repeat(bucket.count * 30 / most)
+                        ));
+                    }
+                    vec.push(s!(""));
+                    vec.join("\n").into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Compare {
+            path,
+            format,
+            ghost,
+            section_length,
+            overlay,
+            filter_options,
+        } => {
+            let ghost = ghost::read_ghost(&ghost)?;
+            let (inputs, timeline) = extract(path, &filter_options.name_filter(), read_options)?;
+            let comparisons: HashMap<String, GhostComparison> = inputs
+                .iter()
+                .filter_map(|(name, i)| {
+                    Some((
+                        name.clone(),
+                        compare::compare(&ghost, i, section_length, timeline.tick_rate)?,
+                    ))
+                })
+                .collect();
+
+            if let Some(overlay) = overlay {
+                let runs: Vec<Vec<Inputs>> = inputs
+                    .into_values()
+                    .filter_map(|i| runs::best_run(i, timeline.tick_rate))
+                    .collect();
+                compare::render_overlay(&ghost.inputs, &runs, &overlay)?;
+            }
+
+            let output = match format.structured() {
+                Some(format) => {
+                    serialize(&comparisons, format, filter_options.pretty, numbers, &keys)
+                }
+                None => {
+                    let strings: Vec<String> = comparisons
+                        .into_iter()
+                        .map(|(name, c)| {
+                            let mut vec = Vec::new();
+                            vec.push(format!("{:=^44}", format!(" {name} ")));
+                            vec.push(s!(""));
+                            vec.push(format!(
+                                "Ghost ..... : {} ({:.2}s)",
+                                c.ghost_owner, c.ghost_time
+                            ));
+                            vec.push(format!("Run ....... : #{} ({:.2}s)", c.run + 1, c.run_time));
+                            vec.push(format!("Difference  : {:+.2}s", c.time_delta));
+                            vec.push(s!(""));
+                            vec.push(format!("{:-^44}", " Splits "));
+                            vec.push(s!(""));
+                            for (section, delta) in c.split_deltas.iter().enumerate() {
+                                let delta = delta.map_or(s!("-"), |d| format!("{d:+.2}s"));
+                                vec.push(format!(
+                                    "{:>6.0} tiles : {delta:>8}",
+                                    section as f32 * c.section_length
+                                ));
+                            }
+                            vec.push(s!(""));
+                            vec.push(format!("{:-^44}", " Route "));
+                            vec.push(s!(""));
+                            let max_offset = c
+                                .offsets
+                                .iter()
+                                .max_by(|a, b| a.distance.total_cmp(&b.distance));
+                            if let Some(max) = max_offset {
+                                vec.push(format!(
+                                    "Max Distance : {:.2} tiles at {:.0}s",
+                                    max.distance, max.time
+                                ));
+                            }
+                            match c.divergence {
+                                Some(d) => vec.push(format!(
+                                    "Diverges ... : at {:.2}s ({:.1}, {:.1})",
+                                    d.time, d.x, d.y
+                                )),
+                                None => vec.push(s!("Diverges ... : never")),
+                            }
+                            vec.push(s!(""));
+                            vec.join("\n")
+                        })
+                        .collect();
+                    strings.join("\n").into()
+                }
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Diff {
+            format,
+            pretty,
+            player,
+            section_length,
+            old,
+            new,
+        } => {
+            let (old, new) = (
+                diff::read_extraction(&old, args.units)?,
+                diff::read_extraction(&new, args.units)?,
+            );
+            if player.is_none() && (old.len() > 1 || new.len() > 1) {
+                return Err(Usage(s!(
+                    "The outputs contain several players, select one with --player"
+                ))
+                .into());
+            }
+            let (Some((player, old)), Some((_, new))) = (
+                diff::select(old, player.as_deref()),
+                diff::select(new, player.as_deref()),
+            ) else {
+                return Err(NoPlayersMatched.into());
+            };
+            let tick_rate = args.tickrate.unwrap_or(data::DEFAULT_TICK_RATE);
+            let Some(diff) = diff::diff(&player, old, new, section_length, tick_rate) else {
+                bail!("No samples of {player} to compare");
+            };
+            let output = match format.structured() {
+                Some(format) => serialize(&diff, format, pretty, numbers, &keys),
+                None => diff::plain_report(&diff).into(),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Render {
+            path,
+            player,
+            width,
+            height,
+            fps,
+            tiles_visible,
+            ffmpeg,
+            map,
+        } => {
+            let Some(output) = args.out else {
+                return Err(Usage(s!("Rendering requires --out")).into());
+            };
+            let map = read_map(&path, map.as_deref()).unwrap_or_else(|e| {
+                eprintln!("Couldn't load map, rendering without it: {e}");
+                None
+            });
+            let (inputs, timeline) = extract(path, &NameFilter::default(), read_options)?;
+            let options = render::RenderOptions {
+                width,
+                height,
+                fps,
+                tick_rate: timeline.tick_rate,
+                tiles_visible,
+                ffmpeg,
+                output,
+                ticks: None,
+                interpolate: args.interpolate.is_some(),
+            };
+            render::render(map.as_ref(), &inputs, &player, &options)?;
+        }
+        Command::Crosshair { path, player } => {
+            let (inputs, _) = extract(path, &NameFilter::default(), read_options)?;
+            if !inputs.contains_key(&player) {
+                return Err(NoPlayersMatched.into());
+            }
+            let trace = crosshair::trace(&inputs, &player, i32::MIN..=i32::MAX);
+            let image = crosshair::render(&trace, &player)?;
+            write_output(args.out, args.append, Output::Binary(image))?;
+        }
+        Command::TeePath {
+            path,
+            player,
+            format,
+            pretty,
+            tolerance,
+        } => {
+            let (inputs, timeline) = extract(path, &NameFilter::default(), read_options)?;
+            let Some(inputs) = inputs.get(&player) else {
+                return Err(NoPlayersMatched.into());
+            };
+            let mut points = teepath::trace(inputs, &timeline);
+            if let Some(tolerance) = tolerance {
+                points = teepath::simplify(&points, tolerance);
             }
+            write_output(
+                args.out,
+                args.append,
+                serialize(&points, format, pretty, numbers, &keys),
+            )?;
+        }
+        Command::Spectrogram {
+            path,
+            player,
+            signal,
+        } => {
+            let (inputs, _) = extract(path, &NameFilter::default(), read_options)?;
+            let Some(inputs) = inputs.get(&player) else {
+                return Err(NoPlayersMatched.into());
+            };
+            let image = spectrum::render(&spectrum::spectrogram(inputs, signal), &player)?;
+            write_output(args.out, args.append, Output::Binary(image))?;
         }
-        Command::ExtractMap { path } => {
-            let file = BufReader::new(File::open(path).unwrap());
-            let reader = DemoReader::new(file).expect("Couldn't open demo reader");
+        Command::Overlay {
+            path,
+            format,
+            filter_options,
+        } => {
+            let (inputs, timeline) = extract(path, &filter_options.name_filter(), read_options)?;
+            if inputs.len() != 1 {
+                let mut names: Vec<_> = inputs.keys().collect();
+                names.sort();
+                return Err(Usage(format!(
+                    "The filter has to match exactly one player, matched: {names:?}"
+                ))
+                .into());
+            }
+            let (_, inputs) = inputs.into_iter().next().unwrap();
+            let keyframes = overlay::keyframes(&inputs, &timeline);
+            let output = match format {
+                OverlayFormat::Srt => overlay::to_srt(&keyframes).into(),
+                OverlayFormat::Ass => overlay::to_ass(&keyframes).into(),
+                OverlayFormat::Json => serialize(
+                    &keyframes,
+                    ExtractionOutputFormat::Json,
+                    filter_options.pretty,
+                    numbers,
+                    &keys,
+                ),
+            };
+            write_output(args.out, args.append, output)?;
+        }
+        Command::ExtractMap {
+            path,
+            dump_info,
+            download,
+            thumbnail,
+            thumbnail_size,
+        } => {
+            let file = BufReader::new(File::open(path)?);
+            let reader = DemoReader::new(file)?;
             let map_name = format!("{}.map", reader.map_name());
-            if let Some(map_data) = reader.map_data() {
-                let p: PathBuf = if let Some(out) = args.out {
-                    if out.is_dir() {
-                        out.join(map_name).into()
-                    } else {
-                        out.into()
+            let map_data = match reader.map_data() {
+                Some(map_data) => map_data.to_vec(),
+                None if download => download::download_map(reader.map_name(), &reader.map_hash())?,
+                None => {
+                    return Err(
+                        Usage(s!("Map not found in demo! Use --download to fetch it.")).into(),
+                    );
+                }
+            };
+            if let Err(e) = map::Datafile::parse(&map_data).and_then(|d| d.verify()) {
+                eprintln!("Warning: map is not a valid datafile: {e}");
+            }
+            if !map::matches_hash(&map_data, &reader.map_hash()) {
+                eprintln!("Warning: map doesn't match the map recorded in the demo header");
+            }
+
+            if dump_info {
+                let info = MapInfo::parse(&map_data)?;
+                let mut vec = Vec::new();
+                for (i, group) in info.groups.iter().enumerate() {
+                    vec.push(format!("Group #{i} {}", group.name));
+                    for layer in &group.layers {
+                        let kind = match layer.kind {
+                            LayerKind::Tiles {
+                                width,
+                                height,
+                                flags,
+                            } => format!("tiles {width}x{height} (flags {flags})"),
+                            LayerKind::Quads { quads } => format!("{quads} quads"),
+                            LayerKind::Sounds => s!("sounds"),
+                            LayerKind::Unknown(kind) => format!("unknown type {kind}"),
+                        };
+                        vec.push(format!("  {:<12} {kind}", layer.name));
                     }
+                }
+                vec.push(s!(""));
+                vec.push(format!("{} Images", info.images.len()));
+                for image in &info.images {
+                    vec.push(format!(
+                        "  {:<24} {}x{}{}",
+                        image.name,
+                        image.width,
+                        image.height,
+                        if image.external { " (external)" } else { "" }
+                    ));
+                }
+                write_output(args.out, args.append, vec.join("\n"))?;
+                return Ok(());
+            }
+            if let Some(thumbnail) = thumbnail {
+                let map = Map::parse(&map_data)?;
+                let pixmap = render::thumbnail(&map, thumbnail_size)?;
+                std::fs::write(thumbnail, pixmap.encode_png()?)?;
+                return Ok(());
+            }
+
+            let p: PathBuf = if let Some(out) = args.out {
+                if out.is_dir() {
+                    out.join(map_name)
                 } else {
-                    map_name.into()
-                };
-                std::fs::write(&p, map_data).unwrap();
-                println!("Exported map to {p:?}");
+                    out
+                }
             } else {
-                eprintln!("Map not found in demo!");
-                exit(1);
+                map_name.into()
+            };
+            std::fs::write(&p, &map_data).unwrap();
+            println!("Exported map to {p:?}");
+        }
+        Command::Serve {
+            address,
+            workers,
+            job_workers,
+            database,
+        } => serve::serve(&address, workers, job_workers, &database)?,
+        Command::Live {
+            path,
+            filter,
+            strict,
+            window,
+            interval,
+            max_direction_changes,
+            max_hook_changes,
+            econ,
+            econ_password,
+        } => {
+            let econ = match econ {
+                Some(address) => Some(live::Econ::connect(
+                    &address,
+                    &econ_password.unwrap_or_default(),
+                )?),
+                None => None,
+            };
+            let options = live::LiveOptions {
+                filter: NameFilter::new(&filter, strict),
+                tick_rate: args.tickrate.unwrap_or(data::DEFAULT_TICK_RATE),
+                window,
+                interval,
+                max_direction_changes,
+                max_hook_changes,
+                time_format: args.time_format,
+            };
+            let out: Box<dyn Write> = match args.out {
+                Some(path) => Box::new(File::create(path)?),
+                None => Box::new(io::stdout()),
+            };
+            // Files are followed as the server writes them, stdin ends when the sender stops
+            let alerted = if path == Path::new("-") {
+                live::run(BufReader::new(io::stdin()), &options, econ, out)?
+            } else {
+                let file = teehistorian::Follow::new(File::open(&path)?, true);
+                live::run(BufReader::new(file), &options, econ, out)?
+            };
+            if alerted {
+                exit(status::THRESHOLDS_EXCEEDED);
             }
         }
-        Command::Visualize {
+        Command::DiscordBot { token, channels } => discord::run(&token, &channels)?,
+        Command::Explain { metric } => {
+            let mut vec = Vec::new();
+            match metric {
+                None => {
+                    for doc in explain::METRICS {
+                        vec.push(doc.names.join(", "));
+                        vec.push(format!("  {}", doc.summary));
+                        vec.push(s!(""));
+                    }
+                }
+                Some(metric) => {
+                    let Some(doc) = explain::find(&metric) else {
+                        return Err(Usage(format!(
+                            "Unknown metric {metric}, run explain without a metric to list all of them"
+                        ))
+                        .into());
+                    };
+                    vec.push(format!("{:=^44}", format!(" {} ", doc.names.join(", "))));
+                    vec.push(s!(""));
+                    vec.push(s!(doc.summary));
+                    for (title, text) in [
+                        (" Definition ", doc.definition),
+                        (" Window ", doc.window),
+                        (" Interpretation ", doc.interpretation),
+                    ] {
+                        vec.push(s!(""));
+                        vec.push(format!("{title:-^44}"));
+                        vec.push(s!(""));
+                        vec.push(s!(text));
+                    }
+                    vec.push(s!(""));
+                }
+            }
+            write_output(args.out, args.append, vec.join("\n"))?;
+        }
+        Command::Schema { kind } => {
+            let output = serde_json::to_string_pretty(&schema(kind))?;
+            write_output(args.out, args.append, output)?;
+        }
+        Command::Template => {
+            write_output(args.out, args.append, s!(template::DEFAULT))?;
+        }
+        Command::Tui {
             path,
+            stitch,
+            filter_options,
+        } => {
+            let (inputs, timeline) =
+                extract_session(path, &stitch, &filter_options.name_filter(), read_options)?;
+            let stats = analyze_inputs(&compress(&inputs), &timeline, analysis_options);
+            tui::run(stats, &inputs, &timeline, args.time_format)?;
+        }
+        Command::Visualize {
+            paths,
+            stitch,
+            keymap,
+            record,
+            replay,
             filter_options,
         } => {
-            let inputs = extract(path, &filter_options.filter)?;
+            let keymap = Keymap::load(keymap.or_else(keymap::default_path).as_deref())?;
+            let mut tabs = Vec::new();
+            for (i, path) in paths.into_iter().enumerate() {
+                let title = path.file_name().unwrap_or_default().to_string_lossy();
+                let title = match stitch.len() {
+                    n if i == 0 && n > 0 => format!("{title} + {n}"),
+                    _ => title.into_owned(),
+                };
+                let stitch: &[PathBuf] = if i == 0 { &stitch } else { &[] };
+                let bookmarks = bookmarks::load(&path).unwrap_or_else(|e| {
+                    eprintln!("Couldn't read the bookmarks of {path:?}: {e}");
+                    Vec::new()
+                });
+                let demos: Vec<&Path> = std::iter::once(path.as_path())
+                    .chain(stitch.iter().map(PathBuf::as_path))
+                    .collect();
+                let mut info = read_player_info(&demos)?;
+                if let Some(anonymizer) = read_options.anonymize {
+                    info = anonymizer.rename(info);
+                    info.values_mut().for_each(|i| i.clan.clear());
+                }
+                let (inputs, timeline) = extract_session(
+                    path.clone(),
+                    stitch,
+                    &filter_options.name_filter(),
+                    read_options,
+                )?;
+                tabs.push(Tab::new(title, path, inputs, timeline, bookmarks, info));
+            }
+
+            let titles: Vec<String> = tabs.iter().map(|t| t.title.clone()).collect();
+            let session = match (record, replay) {
+                (Some(path), _) => SessionMode::Record(session::Recorder::new(path, titles)),
+                (_, Some(path)) => {
+                    let session = session::load(&path)?;
+                    if session.demos != titles {
+                        eprintln!(
+                            "The session was recorded with {:?}, the steps might not match",
+                            session.demos
+                        );
+                    }
+                    SessionMode::Replay(session)
+                }
+                (None, None) => SessionMode::Off,
+            };
 
             let options = eframe::NativeOptions {
                 viewport: egui::ViewportBuilder::default(),
@@ -441,24 +3515,16 @@ fn main() -> anyhow::Result<()> {
                 })),
                 ..Default::default()
             };
-            let max_name = inputs
-                .iter()
-                .max_by_key(|i| i.1.len())
-                .unwrap()
-                .0
-                .to_owned();
-            let mut names: Vec<_> = inputs.keys().cloned().collect();
-            names.sort();
             eframe::run_native(
                 "TW Demo Analyzer",
                 options,
-                Box::new(|_| {
-                    Ok(Box::<MyApp>::new(MyApp {
-                        names,
-                        inputs,
-                        filter: max_name,
-                        ..Default::default()
-                    }))
+                Box::new(move |_| {
+                    Ok(Box::new(MyApp::new(
+                        tabs,
+                        args.time_format,
+                        keymap,
+                        session,
+                    )))
                 }),
             )
             .unwrap();
@@ -0,0 +1,417 @@
+use std::io::Read;
+
+use anyhow::{bail, Context};
+use flate2::read::ZlibDecoder;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use twsnap::compat::ddnet::DemoMapHash;
+
+const ITEM_TYPE_IMAGE: u16 = 2;
+const ITEM_TYPE_GROUP: u16 = 4;
+const ITEM_TYPE_LAYER: u16 = 5;
+
+const LAYER_TYPE_TILES: i32 = 2;
+const LAYER_TYPE_QUADS: i32 = 3;
+const LAYER_TYPE_SOUNDS: i32 = 10;
+const LAYER_FLAG_GAME: i32 = 1;
+const LAYER_FLAG_TELE: i32 = 2;
+
+pub const TILE_AIR: u8 = 0;
+pub const TILE_SOLID: u8 = 1;
+pub const TILE_DEATH: u8 = 2;
+pub const TILE_NOHOOK: u8 = 3;
+pub const TILE_FREEZE: u8 = 9;
+pub const TILE_START: u8 = 33;
+pub const TILE_FINISH: u8 = 34;
+/// The spawn entities, for any team, in the game layer.
+const TILE_SPAWNS: std::ops::RangeInclusive<u8> = 192..=194;
+
+/// Checks the map against the hash recorded in the demo header.
+pub fn matches_hash(data: &[u8], hash: &DemoMapHash) -> bool {
+    match hash {
+        DemoMapHash::Crc(crc) => crc32fast::hash(data) == *crc,
+        DemoMapHash::Sha256(sha) => Sha256::digest(data)[..] == sha[..],
+    }
+}
+
+struct Item {
+    kind: u16,
+    data: Vec<i32>,
+}
+
+/// A teeworlds datafile, the container format used for maps.
+pub struct Datafile<'a> {
+    bytes: &'a [u8],
+    items: Vec<Item>,
+    data_offsets: Vec<usize>,
+    data_sizes: Vec<Option<usize>>,
+    data_start: usize,
+    data_end: usize,
+}
+
+/// Decodes the way teeworlds stores short names in items: four characters per integer,
+/// each shifted by 128.
+fn ints_to_str(ints: &[i32]) -> String {
+    let bytes: Vec<u8> = ints
+        .iter()
+        .flat_map(|i| i.to_be_bytes())
+        .map(|b| b.wrapping_sub(128))
+        .take_while(|b| *b != 0)
+        .collect();
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn read_i32s(bytes: &[u8]) -> Vec<i32> {
+    bytes
+        .chunks_exact(4)
+        .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+impl<'a> Datafile<'a> {
+    pub fn parse(bytes: &'a [u8]) -> anyhow::Result<Self> {
+        if bytes.len() < 36 || !(&bytes[..4] == b"DATA" || &bytes[..4] == b"ATAD") {
+            bail!("Not a datafile");
+        }
+        let header = read_i32s(&bytes[4..36]);
+        let [version, _size, _swaplen, num_item_types, num_items, num_data, item_size, data_size] =
+            header[..]
+        else {
+            unreachable!()
+        };
+        if !(3..=4).contains(&version) {
+            bail!("Unsupported datafile version {version}");
+        }
+        let count = |n: i32| usize::try_from(n).context("Corrupt datafile header");
+        let (num_item_types, num_items, num_data) =
+            (count(num_item_types)?, count(num_items)?, count(num_data)?);
+        let (item_size, data_size) = (count(item_size)?, count(data_size)?);
+
+        let item_offsets_start = 36 + num_item_types * 12;
+        let data_offsets_start = item_offsets_start + num_items * 4;
+        let data_sizes_start = data_offsets_start + num_data * 4;
+        let items_start = if version == 4 {
+            data_sizes_start + num_data * 4
+        } else {
+            data_sizes_start
+        };
+        let data_start = items_start + item_size;
+        let data_end = data_start + data_size;
+        if bytes.len() < data_end {
+            bail!("Datafile is truncated");
+        }
+
+        let item_offsets = read_i32s(&bytes[item_offsets_start..data_offsets_start]);
+        let data_offsets: Vec<usize> = read_i32s(&bytes[data_offsets_start..data_sizes_start])
+            .into_iter()
+            .map(count)
+            .collect::<anyhow::Result<_>>()?;
+        let data_sizes = if version == 4 {
+            read_i32s(&bytes[data_sizes_start..items_start])
+                .into_iter()
+                .map(|s| Some(s.max(0) as usize))
+                .collect()
+        } else {
+            vec![None; num_data]
+        };
+
+        let mut items = Vec::with_capacity(num_items);
+        for offset in item_offsets {
+            let start = items_start + count(offset)?;
+            if start + 8 > data_start {
+                bail!("Item out of bounds");
+            }
+            let type_and_id = read_i32s(&bytes[start..start + 4])[0];
+            let size = count(read_i32s(&bytes[start + 4..start + 8])[0])?;
+            if start + 8 + size > data_start {
+                bail!("Item out of bounds");
+            }
+            let raw = &bytes[start + 8..start + 8 + size];
+            items.push(Item {
+                kind: (type_and_id >> 16) as u16,
+                data: read_i32s(raw),
+            });
+        }
+
+        Ok(Self {
+            bytes,
+            items,
+            data_offsets,
+            data_sizes,
+            data_start,
+            data_end,
+        })
+    }
+
+    fn items(&self, kind: u16) -> impl Iterator<Item = &[i32]> {
+        self.items
+            .iter()
+            .filter(move |i| i.kind == kind)
+            .map(|i| &i.data[..])
+    }
+
+    /// Checks that every data entry can be decompressed.
+    pub fn verify(&self) -> anyhow::Result<()> {
+        for index in 0..self.data_offsets.len() {
+            self.data(index)
+                .with_context(|| format!("Data entry {index} is corrupt"))?;
+        }
+        Ok(())
+    }
+
+    /// Returns the decompressed data at the given index.
+    pub fn data(&self, index: usize) -> anyhow::Result<Vec<u8>> {
+        let start =
+            self.data_start + *self.data_offsets.get(index).context("Invalid data index")?;
+        let end = self
+            .data_offsets
+            .get(index + 1)
+            .map(|o| self.data_start + o)
+            .unwrap_or(self.data_end);
+        let raw = self.bytes.get(start..end).context("Data out of bounds")?;
+        match self.data_sizes[index] {
+            Some(size) => {
+                let mut data = Vec::with_capacity(size);
+                ZlibDecoder::new(raw).read_to_end(&mut data)?;
+                Ok(data)
+            }
+            None => Ok(raw.to_vec()),
+        }
+    }
+}
+
+/// A tile layer reduced to the tile indices, which is all the analysis needs.
+pub struct TileLayer {
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<u8>,
+}
+
+impl TileLayer {
+    pub fn get(&self, x: i32, y: i32) -> u8 {
+        if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+            return TILE_AIR;
+        }
+        self.tiles[y as usize * self.width + x as usize]
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub enum LayerKind {
+    Tiles { width: i32, height: i32, flags: i32 },
+    Quads { quads: i32 },
+    Sounds,
+    Unknown(i32),
+}
+
+#[derive(Debug, Serialize)]
+pub struct LayerInfo {
+    pub name: String,
+    pub kind: LayerKind,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GroupInfo {
+    pub name: String,
+    pub layers: Vec<LayerInfo>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImageInfo {
+    pub name: String,
+    pub width: i32,
+    pub height: i32,
+    pub external: bool,
+}
+
+/// Summary of the structure of a map, without any of the actual tile or image data.
+#[derive(Debug, Serialize)]
+pub struct MapInfo {
+    pub groups: Vec<GroupInfo>,
+    pub images: Vec<ImageInfo>,
+}
+
+impl MapInfo {
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let datafile = Datafile::parse(bytes)?;
+        let layers: Vec<&[i32]> = datafile.items(ITEM_TYPE_LAYER).collect();
+
+        let groups = datafile
+            .items(ITEM_TYPE_GROUP)
+            .filter(|g| g.len() >= 7)
+            .map(|g| {
+                let start = g[5].max(0) as usize;
+                let layers = layers
+                    .iter()
+                    .skip(start)
+                    .take(g[6].max(0) as usize)
+                    .map(|l| layer_info(l))
+                    .collect();
+                GroupInfo {
+                    name: g.get(12..15).map(ints_to_str).unwrap_or_default(),
+                    layers,
+                }
+            })
+            .collect();
+
+        let images = datafile
+            .items(ITEM_TYPE_IMAGE)
+            .filter(|i| i.len() >= 5)
+            .map(|i| ImageInfo {
+                name: usize::try_from(i[4])
+                    .ok()
+                    .and_then(|n| datafile.data(n).ok())
+                    .map(|n| {
+                        let end = n.iter().position(|b| *b == 0).unwrap_or(n.len());
+                        String::from_utf8_lossy(&n[..end]).into_owned()
+                    })
+                    .unwrap_or_default(),
+                width: i[1],
+                height: i[2],
+                external: i[3] != 0,
+            })
+            .collect();
+
+        Ok(Self { groups, images })
+    }
+}
+
+fn layer_info(layer: &[i32]) -> LayerInfo {
+    let kind = layer.get(1).copied().unwrap_or_default();
+    match kind {
+        LAYER_TYPE_TILES if layer.len() >= 18 => LayerInfo {
+            name: ints_to_str(&layer[15..18]),
+            kind: LayerKind::Tiles {
+                width: layer[4],
+                height: layer[5],
+                flags: layer[6],
+            },
+        },
+        LAYER_TYPE_QUADS if layer.len() >= 10 => LayerInfo {
+            name: ints_to_str(&layer[7..10]),
+            kind: LayerKind::Quads { quads: layer[4] },
+        },
+        LAYER_TYPE_SOUNDS => LayerInfo {
+            name: layer.get(7..10).map(ints_to_str).unwrap_or_default(),
+            kind: LayerKind::Sounds,
+        },
+        _ => LayerInfo {
+            name: String::new(),
+            kind: LayerKind::Unknown(kind),
+        },
+    }
+}
+
+pub struct Map {
+    pub game: TileLayer,
+    /// The DDNet teleporter layer, with the kind of teleporter tile as index
+    pub tele: Option<TileLayer>,
+}
+
+impl Map {
+    /// Whether the game layer collides at the position, in tiles.
+    pub fn is_solid(&self, x: f32, y: f32) -> bool {
+        matches!(
+            self.game.get(x.floor() as i32, y.floor() as i32),
+            TILE_SOLID | TILE_NOHOOK
+        )
+    }
+
+    /// Whether any tile within the radius around the position, all in tiles, matches.
+    fn any_near(
+        layer: &TileLayer,
+        x: f32,
+        y: f32,
+        radius: f32,
+        matches: impl Fn(u8) -> bool,
+    ) -> bool {
+        let reach = radius.ceil() as i32;
+        let (tile_x, tile_y) = (x.floor() as i32, y.floor() as i32);
+        (-reach..=reach).any(|dy| {
+            (-reach..=reach).any(|dx| {
+                let (cx, cy) = (tile_x + dx, tile_y + dy);
+                (cx as f32 + 0.5 - x).hypot(cy as f32 + 0.5 - y) <= radius
+                    && matches(layer.get(cx, cy))
+            })
+        })
+    }
+
+    /// Whether there is a teleporter tile of any kind near the position, in tiles.
+    pub fn near_teleporter(&self, x: f32, y: f32, radius: f32) -> bool {
+        self.tele
+            .as_ref()
+            .is_some_and(|tele| Self::any_near(tele, x, y, radius, |t| t != TILE_AIR))
+    }
+
+    /// Whether there is a spawn point near the position, in tiles.
+    pub fn near_spawn(&self, x: f32, y: f32, radius: f32) -> bool {
+        Self::any_near(&self.game, x, y, radius, |t| TILE_SPAWNS.contains(&t))
+    }
+
+    pub fn parse(bytes: &[u8]) -> anyhow::Result<Self> {
+        let datafile = Datafile::parse(bytes)?;
+        // Only layers that belong to a group are actually used by the game
+        let layers: Vec<&[i32]> = datafile.items(ITEM_TYPE_LAYER).collect();
+        let grouped = datafile
+            .items(ITEM_TYPE_GROUP)
+            .filter(|g| g.len() >= 7)
+            .flat_map(|g| (g[5].max(0) as usize)..(g[5].max(0) + g[6].max(0)) as usize);
+        let grouped: Vec<&[i32]> = grouped.filter_map(|i| layers.get(i).copied()).collect();
+        let game = grouped
+            .iter()
+            .find(|l| l.len() >= 15 && l[1] == LAYER_TYPE_TILES && l[6] & LAYER_FLAG_GAME != 0)
+            .context("Map has no game layer")?;
+        // Vanilla maps don't have one, and a broken one shouldn't stop the rest of the analysis
+        let tele = grouped
+            .iter()
+            .find(|l| l.len() >= 19 && l[1] == LAYER_TYPE_TILES && l[6] & LAYER_FLAG_TELE != 0)
+            .and_then(|l| tele_layer(&datafile, l).ok());
+
+        Ok(Self {
+            game: tile_layer(&datafile, game)?,
+            tele,
+        })
+    }
+}
+
+/// The teleporter layer keeps its tiles apart from the regular tile data, as a number and
+/// kind for every tile, and without run length encoding.
+fn tele_layer(datafile: &Datafile, layer: &[i32]) -> anyhow::Result<TileLayer> {
+    let width = usize::try_from(layer[4])?;
+    let height = usize::try_from(layer[5])?;
+    let data = datafile.data(usize::try_from(layer[18])?)?;
+    let mut tiles: Vec<u8> = data.chunks_exact(2).map(|tile| tile[1]).collect();
+    tiles.resize(width * height, TILE_AIR);
+
+    Ok(TileLayer {
+        width,
+        height,
+        tiles,
+    })
+}
+
+fn tile_layer(datafile: &Datafile, layer: &[i32]) -> anyhow::Result<TileLayer> {
+    let version = layer[3];
+    let width = usize::try_from(layer[4])?;
+    let height = usize::try_from(layer[5])?;
+    let data = datafile.data(usize::try_from(layer[14])?)?;
+
+    // Every tile is index, flags, skip and a reserved byte. Since tilemap version 4
+    // the skip byte is used to run length encode repeated tiles.
+    let mut tiles = Vec::with_capacity(width * height);
+    for tile in data.chunks_exact(4) {
+        let repeat = if version >= 4 {
+            tile[2] as usize + 1
+        } else {
+            1
+        };
+        tiles.extend(std::iter::repeat_n(tile[0], repeat));
+    }
+    tiles.resize(width * height, TILE_AIR);
+
+    Ok(TileLayer {
+        width,
+        height,
+        tiles,
+    })
+}
@@ -0,0 +1,109 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::Value;
+use stringlit::s;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MetricSummary {
+    pub mean: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergedPlayer {
+    /// Number of reports the player is in
+    pub reports: usize,
+    /// Every number of the analysis by its path, like `rehook.frequency`
+    pub metrics: BTreeMap<String, MetricSummary>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MergedReport {
+    pub reports: usize,
+    pub players: BTreeMap<String, MergedPlayer>,
+}
+
+/// The numbers in the stats by their dotted path, lists like the gaps are left out.
+fn numbers(prefix: &str, value: &Value, out: &mut Vec<(String, f64)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                numbers(&path, value, out);
+            }
+        }
+        Value::Number(n) => out.extend(n.as_f64().map(|n| (prefix.to_string(), n))),
+        Value::Bool(b) => out.push((prefix.to_string(), *b as u8 as f64)),
+        _ => {}
+    }
+}
+
+/// Combines json analysis outputs. A file can contain several of them, like one written with
+/// --append where every line is a report.
+pub fn merge(paths: &[impl AsRef<Path>]) -> anyhow::Result<MergedReport> {
+    let mut reports = 0;
+    let mut values: BTreeMap<String, (usize, BTreeMap<String, Vec<f64>>)> = BTreeMap::new();
+    for path in paths {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)?;
+        for report in
+            serde_json::Deserializer::from_str(&text).into_iter::<BTreeMap<String, Value>>()
+        {
+            let report = report.with_context(|| format!("{path:?} isn't an analysis output"))?;
+            reports += 1;
+            for (player, stats) in report {
+                let mut leaves = Vec::new();
+                numbers("", &stats, &mut leaves);
+                let (count, metrics) = values.entry(player).or_default();
+                *count += 1;
+                for (metric, value) in leaves {
+                    metrics.entry(metric).or_default().push(value);
+                }
+            }
+        }
+    }
+
+    let players = values
+        .into_iter()
+        .map(|(player, (reports, metrics))| {
+            let metrics = metrics
+                .into_iter()
+                .map(|(metric, values)| {
+                    let summary = MetricSummary {
+                        mean: values.iter().sum::<f64>() / values.len() as f64,
+                        min: values.iter().copied().fold(f64::INFINITY, f64::min),
+                        max: values.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+                    };
+                    (metric, summary)
+                })
+                .collect();
+            (player, MergedPlayer { reports, metrics })
+        })
+        .collect();
+    Ok(MergedReport { reports, players })
+}
+
+pub fn plain_report(report: &MergedReport) -> String {
+    let mut vec = Vec::new();
+    vec.push(format!("Reports : {}", report.reports));
+    for (name, player) in &report.players {
+        vec.push(s!(""));
+        vec.push(format!("{:=^44}", format!(" {name} ")));
+        vec.push(s!(""));
+        vec.push(format!("In {} reports", player.reports));
+        for (metric, m) in &player.metrics {
+            vec.push(format!(
+                "{metric}: {:.2} ({:.2} - {:.2})",
+                m.mean, m.min, m.max
+            ));
+        }
+    }
+    vec.join("\n")
+}
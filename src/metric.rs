@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::{calculate_direction_change_stats, data::Inputs, gaps, timeline::Timeline};
+
+/// A statistic computed from the samples of every player, one at a time.
+pub trait Metric {
+    fn name(&self) -> &'static str;
+    /// Called with every sample, in the order of the ticks for each player.
+    fn feed(&mut self, player: &str, inputs: &Inputs);
+    /// The result of every player that was fed, by name.
+    fn finish(&self) -> Value;
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MetricKind {
+    DirectionChangeRate,
+    HookStateChangeRate,
+}
+
+impl MetricKind {
+    pub const ALL: [MetricKind; 2] = [
+        MetricKind::DirectionChangeRate,
+        MetricKind::HookStateChangeRate,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MetricKind::DirectionChangeRate => "direction_change_rate",
+            MetricKind::HookStateChangeRate => "hook_state_change_rate",
+        }
+    }
+}
+
+/// The metrics to compute, all of them by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MetricSet(u32);
+
+impl Default for MetricSet {
+    fn default() -> Self {
+        MetricKind::ALL.into_iter().collect()
+    }
+}
+
+impl MetricSet {
+    pub fn contains(self, kind: MetricKind) -> bool {
+        self.0 & 1 << kind as u32 != 0
+    }
+}
+
+impl FromIterator<MetricKind> for MetricSet {
+    fn from_iter<T: IntoIterator<Item = MetricKind>>(iter: T) -> Self {
+        Self(iter.into_iter().fold(0, |set, kind| set | 1 << kind as u32))
+    }
+}
+
+/// Creates the enabled metrics.
+pub fn registry(metrics: MetricSet, debounce: i32, timeline: &Timeline) -> Vec<Box<dyn Metric>> {
+    MetricKind::ALL
+        .into_iter()
+        .filter(|kind| metrics.contains(*kind))
+        .map(|kind| -> Box<dyn Metric> {
+            match kind {
+                MetricKind::DirectionChangeRate => Box::new(ChangeRate::new(
+                    kind.name(),
+                    |i| i.direction,
+                    debounce,
+                    timeline,
+                )),
+                MetricKind::HookStateChangeRate => Box::new(ChangeRate::new(
+                    kind.name(),
+                    |i| i.hook_state.pressed(),
+                    debounce,
+                    timeline,
+                )),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ChangeRateStats {
+    /// Changes per second, over one second windows starting at every change
+    pub average: f32,
+    pub median: f32,
+    pub max: usize,
+    /// 95% confidence interval of the average
+    pub interval: (f32, f32),
+    pub windows: usize,
+    /// After debouncing
+    pub changes: usize,
+    /// Changes within a second of a gap, left out of the rates
+    pub excluded_changes: usize,
+}
+
+struct PlayerChanges<T> {
+    /// The states kept after debouncing and the ticks they started at
+    kept: Vec<(i32, T)>,
+    /// The ticks of all samples, to find the gaps
+    ticks: Vec<i32>,
+}
+
+/// How often a state of the inputs changes within a second. A state that reverts to the one
+/// before it within the debounce ticks is treated as if it never happened, as prediction
+/// artifacts in the snapshots do.
+pub struct ChangeRate<T> {
+    name: &'static str,
+    state: fn(&Inputs) -> T,
+    debounce: i32,
+    timeline: Timeline,
+    players: HashMap<String, PlayerChanges<T>>,
+}
+
+impl<T> ChangeRate<T> {
+    pub fn new(
+        name: &'static str,
+        state: fn(&Inputs) -> T,
+        debounce: i32,
+        timeline: &Timeline,
+    ) -> Self {
+        Self {
+            name,
+            state,
+            debounce,
+            timeline: timeline.clone(),
+            players: HashMap::new(),
+        }
+    }
+}
+
+impl<T: PartialEq> Metric for ChangeRate<T> {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn feed(&mut self, player: &str, inputs: &Inputs) {
+        if !self.players.contains_key(player) {
+            self.players.insert(
+                player.to_string(),
+                PlayerChanges {
+                    kept: Vec::new(),
+                    ticks: Vec::new(),
+                },
+            );
+        }
+        let changes = self.players.get_mut(player).unwrap();
+        let current = (self.state)(inputs);
+        changes.ticks.push(inputs.tick);
+        if let [.., (_, before), (flicker, _)] = &changes.kept[..] {
+            if inputs.tick - flicker <= self.debounce && *before == current {
+                changes.kept.pop();
+                return;
+            }
+        }
+        if changes.kept.last().is_none_or(|(_, last)| *last != current) {
+            changes.kept.push((inputs.tick, current));
+        }
+    }
+
+    fn finish(&self) -> Value {
+        let tick_rate = self.timeline.tick_rate;
+        self.players
+            .iter()
+            .map(|(name, changes)| {
+                let ticks: Vec<i32> = changes.kept.iter().skip(1).map(|(t, _)| *t).collect();
+                // Lag bursts would look like superhuman rates, the counts still include them
+                let gaps = gaps::find_gaps(&changes.ticks, &self.timeline);
+                let rated = gaps::without_gaps(&ticks, &gaps, tick_rate);
+                let excluded_changes = ticks.len() - rated.len();
+                let stats = calculate_direction_change_stats(rated, tick_rate);
+                let stats = ChangeRateStats {
+                    average: stats.average,
+                    median: stats.median,
+                    max: stats.max,
+                    interval: stats.interval,
+                    windows: stats.windows,
+                    changes: ticks.len(),
+                    excluded_changes,
+                };
+                (name.clone(), serde_json::to_value(stats).unwrap())
+            })
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+}
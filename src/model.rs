@@ -0,0 +1,189 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::{bail, Context};
+use serde::Deserialize;
+
+use crate::{data::DEFAULT_TICK_RATE, features};
+
+/// A bot classifier trained outside of this tool, picked by the extension of the model file.
+#[derive(Debug, Clone)]
+pub enum Model {
+    Logistic(Logistic),
+    /// Takes the columns of export-features in order as a `[1, n]` float tensor, the last
+    /// value of its first output is the bot probability
+    #[cfg(feature = "onnx")]
+    Onnx(std::sync::Arc<tract_onnx::prelude::TypedRunnableModel>),
+}
+
+/// A logistic regression over the columns of export-features, as json.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Logistic {
+    pub bias: f32,
+    /// Weight of every feature by its column name, missing features don't count
+    pub weights: BTreeMap<String, f32>,
+}
+
+/// The columns of export-features, which are the inputs of every model.
+fn columns() -> BTreeMap<String, f32> {
+    features::features("", "", &[], DEFAULT_TICK_RATE).features
+}
+
+impl Model {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        if path.extension().is_some_and(|e| e == "onnx") {
+            return Self::load_onnx(path);
+        }
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Couldn't read model {path:?}"))?;
+        let model: Logistic = serde_json::from_str(&text)
+            .with_context(|| format!("Couldn't parse model {path:?}"))?;
+        let known = columns();
+        if let Some(unknown) = model.weights.keys().find(|k| !known.contains_key(*k)) {
+            bail!("Model {path:?} has a weight for {unknown}, which isn't an exported feature");
+        }
+        Ok(Model::Logistic(model))
+    }
+
+    #[cfg(feature = "onnx")]
+    fn load_onnx(path: &Path) -> anyhow::Result<Self> {
+        use tract_onnx::prelude::*;
+
+        let width = columns().len();
+        let model = tract_onnx::onnx()
+            .model_for_path(path)
+            .and_then(|model| model.with_input_fact(0, f32::fact([1, width]).into()))
+            .and_then(|model| model.into_optimized())
+            .and_then(|model| model.into_runnable())
+            .with_context(|| format!("Couldn't load ONNX model {path:?}"))?;
+        Ok(Model::Onnx(model))
+    }
+
+    #[cfg(not(feature = "onnx"))]
+    fn load_onnx(path: &Path) -> anyhow::Result<Self> {
+        bail!("Model {path:?} is ONNX, which needs a build with `--features onnx`")
+    }
+
+    /// Probability between zero and one that the player is a bot.
+    pub fn probability(&self, features: &BTreeMap<String, f32>) -> anyhow::Result<f32> {
+        match self {
+            Model::Logistic(model) => Ok(model.probability(features)),
+            #[cfg(feature = "onnx")]
+            Model::Onnx(model) => {
+                use tract_onnx::prelude::*;
+
+                let values: Vec<f32> = columns()
+                    .keys()
+                    .map(|name| features.get(name).copied().unwrap_or_default())
+                    .collect();
+                let input = Tensor::from_shape(&[1, values.len()], &values)?;
+                let outputs = model.run(tvec!(input.into()))?;
+                let output = outputs[0].to_plain_array_view::<f32>()?;
+                output
+                    .iter()
+                    .last()
+                    .copied()
+                    .context("The ONNX model has an empty output")
+            }
+        }
+    }
+}
+
+impl Logistic {
+    fn probability(&self, features: &BTreeMap<String, f32>) -> f32 {
+        let z = self.bias
+            + self
+                .weights
+                .iter()
+                .map(|(name, weight)| weight * features.get(name).copied().unwrap_or_default())
+                .sum::<f32>();
+        1.0 / (1.0 + (-z).exp())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp(name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("{}-{name}", std::process::id()));
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    #[test]
+    fn names_the_model_it_cant_read() {
+        let path = std::env::temp_dir().join("missing-model.json");
+        let error = Model::load(&path).unwrap_err();
+        assert_eq!(error.to_string(), format!("Couldn't read model {path:?}"));
+    }
+
+    #[test]
+    fn computes_the_logistic_probability() {
+        let column = columns().into_keys().next().unwrap();
+        let json = format!(r#"{{"bias": 1.0, "weights": {{"{column}": 0.5}}}}"#);
+        let path = temp("model.json", json.as_bytes());
+        let model = Model::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let features = BTreeMap::from([(column, 2.0)]);
+        let probability = model.probability(&features).unwrap();
+        assert!((probability - 1.0 / (1.0 + (-2.0f32).exp())).abs() < 1e-6);
+    }
+
+    #[cfg(not(feature = "onnx"))]
+    #[test]
+    fn needs_the_feature_for_onnx() {
+        let path = std::path::PathBuf::from("model.onnx");
+        let error = Model::load(&path).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            r#"Model "model.onnx" is ONNX, which needs a build with `--features onnx`"#
+        );
+    }
+
+    /// A model that is the sigmoid of every column, its output is the one of the last column.
+    #[cfg(feature = "onnx")]
+    #[test]
+    fn runs_onnx_models() {
+        use prost::Message;
+        use tract_onnx::pb::{self, type_proto};
+
+        let tensor = |name: &str| pb::ValueInfoProto {
+            name: name.to_string(),
+            r#type: Some(pb::TypeProto {
+                value: Some(type_proto::Value::TensorType(type_proto::Tensor {
+                    elem_type: pb::tensor_proto::DataType::Float as i32,
+                    shape: None,
+                })),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let model = pb::ModelProto {
+            ir_version: 8,
+            opset_import: vec![pb::OperatorSetIdProto {
+                domain: String::new(),
+                version: 13,
+            }],
+            graph: Some(pb::GraphProto {
+                node: vec![pb::NodeProto {
+                    input: vec!["x".to_string()],
+                    output: vec!["y".to_string()],
+                    op_type: "Sigmoid".to_string(),
+                    ..Default::default()
+                }],
+                input: vec![tensor("x")],
+                output: vec![tensor("y")],
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let path = temp("model.onnx", &model.encode_to_vec());
+        let model = Model::load(&path).unwrap();
+        std::fs::remove_file(path).unwrap();
+        let last = columns().into_keys().last().unwrap();
+        let features = BTreeMap::from([(last, 2.0)]);
+        let probability = model.probability(&features).unwrap();
+        assert!((probability - 1.0 / (1.0 + (-2.0f32).exp())).abs() < 1e-6);
+        assert_eq!(model.probability(&BTreeMap::new()).unwrap(), 0.5);
+    }
+}
@@ -0,0 +1,69 @@
+use unicode_normalization::UnicodeNormalization;
+
+/// Characters that render as nothing or as a plain space, used to make names look alike.
+fn invisible(c: char) -> bool {
+    c.is_control()
+        || matches!(
+            c,
+            '\u{00ad}'
+                | '\u{034f}'
+                | '\u{061c}'
+                | '\u{115f}'
+                | '\u{1160}'
+                | '\u{17b4}'
+                | '\u{17b5}'
+                | '\u{180b}'..='\u{180f}'
+                | '\u{200b}'..='\u{200f}'
+                | '\u{202a}'..='\u{202e}'
+                | '\u{2060}'..='\u{206f}'
+                | '\u{3164}'
+                | '\u{fe00}'..='\u{fe0f}'
+                | '\u{feff}'
+                | '\u{ffa0}'
+                | '\u{e0000}'..='\u{e0fff}'
+        )
+}
+
+/// The form names are compared in when matching loosely: NFKC normalized, without invisible
+/// characters, with runs of whitespace collapsed, lowercase and with confusables folded, so a
+/// Cyrillic `а` or a zero width space in `nаme\u{200b}less tee` doesn't stop it from matching.
+pub fn normalize(name: &str) -> String {
+    let visible: String = name.nfkc().filter(|c| !invisible(*c)).collect();
+    let words: Vec<&str> = visible.split_whitespace().collect();
+    unicode_security::skeleton(&words.join(" ").to_lowercase())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Which players a command looks at. Matches names containing the filter, either loosely on
+/// the normalized names or, when strict, only ignoring case.
+#[derive(Debug, Clone, Default)]
+pub struct NameFilter {
+    filter: String,
+    strict: bool,
+}
+
+impl NameFilter {
+    pub fn new(filter: &str, strict: bool) -> Self {
+        let filter = if strict {
+            filter.to_lowercase()
+        } else {
+            normalize(filter)
+        };
+        Self { filter, strict }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.filter.is_empty()
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        if self.filter.is_empty() {
+            true
+        } else if self.strict {
+            name.to_lowercase().contains(&self.filter)
+        } else {
+            normalize(name).contains(&self.filter)
+        }
+    }
+}
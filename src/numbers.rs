@@ -0,0 +1,93 @@
+/// How numbers are written in the plain report and CSV, the structured formats always use the
+/// machine readable form.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NumberFormat {
+    pub decimal_comma: bool,
+    pub thousands_separator: Option<char>,
+}
+
+impl NumberFormat {
+    /// Spreadsheets that read a comma as decimal separator expect semicolons between columns.
+    pub fn csv_separator(&self) -> char {
+        if self.decimal_comma {
+            ';'
+        } else {
+            ','
+        }
+    }
+
+    /// Formats a number written with a decimal point, like `1234.5` or `-3`.
+    pub fn number(&self, number: &str) -> String {
+        let (sign, number) = match number.strip_prefix('-') {
+            Some(rest) => ("-", rest),
+            None => ("", number),
+        };
+        let (integer, fraction) = match number.split_once('.') {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (number, None),
+        };
+        let mut result = String::from(sign);
+        match self.thousands_separator {
+            Some(separator) if integer.len() > 3 => {
+                for (i, c) in integer.chars().enumerate() {
+                    if i > 0 && (integer.len() - i) % 3 == 0 {
+                        result.push(separator);
+                    }
+                    result.push(c);
+                }
+            }
+            _ => result.push_str(integer),
+        }
+        if let Some(fraction) = fraction {
+            result.push(if self.decimal_comma { ',' } else { '.' });
+            result.push_str(fraction);
+        }
+        result
+    }
+
+    /// Formats every number in a line of text. Digits that are part of a word, like in `P90`,
+    /// are left alone.
+    pub fn text(&self, text: &str) -> String {
+        if !self.decimal_comma && self.thousands_separator.is_none() {
+            return text.to_string();
+        }
+        let mut result = String::with_capacity(text.len());
+        let mut chars = text.char_indices().peekable();
+        let mut previous: Option<char> = None;
+        while let Some((start, c)) = chars.next() {
+            // Colors are copied as they are, the number after them isn't part of a word
+            if c == '\x1b' {
+                result.push(c);
+                for (_, next) in chars.by_ref() {
+                    result.push(next);
+                    if next == 'm' {
+                        break;
+                    }
+                }
+                continue;
+            }
+            let in_word = previous.is_some_and(|p| p.is_alphanumeric() || p == '_');
+            if !c.is_ascii_digit() || in_word {
+                result.push(c);
+                previous = Some(c);
+                continue;
+            }
+            let mut end = start + c.len_utf8();
+            let mut seen_point = false;
+            while let Some(&(i, next)) = chars.peek() {
+                let point = next == '.'
+                    && !seen_point
+                    && text[i + 1..].starts_with(|c: char| c.is_ascii_digit());
+                if !next.is_ascii_digit() && !point {
+                    break;
+                }
+                seen_point |= point;
+                end = i + next.len_utf8();
+                chars.next();
+            }
+            result.push_str(&self.number(&text[start..end]));
+            previous = text[..end].chars().next_back();
+        }
+        result
+    }
+}
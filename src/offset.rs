@@ -0,0 +1,73 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{aim::wrap_angle, changes::InputChanges, data::Inputs, zoom::percentile};
+
+/// Only tees within the default laser reach (800 units) count, farther ones can't be hit
+/// with the hammer or the rifle.
+const MAX_DISTANCE: f32 = 25.0;
+const BUCKET_SIZE: f32 = 5.0;
+const BUCKETS: usize = 18;
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct AimOffsetStats {
+    /// Snapshots with another tee in reach, the ones the other numbers are about
+    pub samples: usize,
+    /// All angles are in degrees between the aim and the direction to the nearest tee
+    pub average: f32,
+    pub median: f32,
+    pub p10: f32,
+    /// Sample counts in buckets of five degrees, the last bucket contains everything beyond
+    pub histogram: Vec<usize>,
+    /// Fraction of the samples aimed within `--lock-degrees` of the nearest tee
+    pub within_lock_fraction: f32,
+}
+
+/// How far the aim of the player is off from the nearest other tee in every snapshot, no
+/// matter if teammate or enemy.
+pub fn calculate_aim_offset_stats(
+    inputs: &[Inputs],
+    others: &[&InputChanges],
+    lock_degrees: f32,
+) -> AimOffsetStats {
+    let mut offsets: Vec<f32> = inputs
+        .iter()
+        .filter(|i| i.target.x != 0 || i.target.y != 0)
+        .filter_map(|sample| {
+            let nearest = others
+                .iter()
+                .filter_map(|other| other.position_at(sample.tick))
+                .map(|other| (sample.pos.distance(other), other))
+                .filter(|(distance, _)| *distance > 0.0 && *distance <= MAX_DISTANCE)
+                .min_by(|a, b| a.0.total_cmp(&b.0))?
+                .1;
+            let aim = sample
+                .target
+                .y
+                .to_num::<f32>()
+                .atan2(sample.target.x.to_num::<f32>());
+            let dx = (nearest.x - sample.pos.x).to_num::<f32>();
+            let dy = (nearest.y - sample.pos.y).to_num::<f32>();
+            let direction = dy.atan2(dx);
+            Some(wrap_angle(aim - direction).abs().to_degrees())
+        })
+        .collect();
+    if offsets.is_empty() {
+        return AimOffsetStats::default();
+    }
+    offsets.sort_by(f32::total_cmp);
+
+    let count = offsets.len() as f32;
+    let mut histogram = vec![0; BUCKETS];
+    for o in &offsets {
+        histogram[((o / BUCKET_SIZE) as usize).min(BUCKETS - 1)] += 1;
+    }
+    AimOffsetStats {
+        samples: offsets.len(),
+        average: offsets.iter().sum::<f32>() / count,
+        median: percentile(&offsets, 0.5),
+        p10: percentile(&offsets, 0.1),
+        histogram,
+        within_lock_fraction: offsets.iter().filter(|o| **o <= lock_degrees).count() as f32 / count,
+    }
+}
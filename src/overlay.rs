@@ -0,0 +1,134 @@
+use clap::ValueEnum;
+use serde::Serialize;
+
+use crate::{
+    data::{Direction, Inputs},
+    timeline::Timeline,
+};
+
+/// How long a shot is shown in the overlay, in ticks.
+const FIRE_TICKS: i32 = 5;
+
+#[derive(ValueEnum, Clone, Copy)]
+pub enum OverlayFormat {
+    Srt,
+    Ass,
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Keyframe {
+    /// Seconds since the start of the demo
+    pub time: f32,
+    pub tick: i32,
+    pub direction: Direction,
+    pub hook: bool,
+    pub fire: bool,
+}
+
+impl Keyframe {
+    fn label(&self) -> String {
+        let direction = match self.direction {
+            Direction::Left => "←",
+            Direction::None => "·",
+            Direction::Right => "→",
+        };
+        let mut label = String::from(direction);
+        if self.hook {
+            label.push_str(" HOOK");
+        }
+        if self.fire {
+            label.push_str(" FIRE");
+        }
+        label
+    }
+}
+
+/// Emits a keyframe for every change of the visible input state.
+pub fn keyframes(inputs: &[Inputs], timeline: &Timeline) -> Vec<Keyframe> {
+    let mut keyframes: Vec<Keyframe> = Vec::new();
+    let mut last_attack = inputs.first().map(|i| i.attack_tick).unwrap_or_default();
+    let mut fire_until = i32::MIN;
+    for input in inputs {
+        if input.attack_tick != last_attack {
+            last_attack = input.attack_tick;
+            fire_until = input.tick + FIRE_TICKS;
+        }
+        let keyframe = Keyframe {
+            time: timeline.seconds(input.tick),
+            tick: input.tick,
+            direction: input.direction,
+            hook: input.hook_state.pressed(),
+            fire: input.tick < fire_until,
+        };
+        let changed = keyframes.last().is_none_or(|k| {
+            (k.direction, k.hook, k.fire) != (keyframe.direction, keyframe.hook, keyframe.fire)
+        });
+        if changed {
+            keyframes.push(keyframe);
+        }
+    }
+    keyframes
+}
+
+fn timestamp(seconds: f32, separator: char, hour_digits: usize, fraction_digits: u32) -> String {
+    let total = (seconds.max(0.0) * 10f32.powi(fraction_digits as i32)).round() as u64;
+    let fraction = total % 10u64.pow(fraction_digits);
+    let seconds = total / 10u64.pow(fraction_digits);
+    format!(
+        "{:0>hour_digits$}:{:02}:{:02}{separator}{:0>width$}",
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60,
+        fraction,
+        width = fraction_digits as usize,
+    )
+}
+
+/// Pairs every keyframe with the time the next one starts.
+fn spans(keyframes: &[Keyframe]) -> impl Iterator<Item = (&Keyframe, f32)> {
+    keyframes.iter().enumerate().map(|(i, k)| {
+        let end = keyframes.get(i + 1).map_or(k.time + 1.0, |n| n.time);
+        (k, end)
+    })
+}
+
+pub fn to_srt(keyframes: &[Keyframe]) -> String {
+    spans(keyframes)
+        .enumerate()
+        .map(|(i, (k, end))| {
+            format!(
+                "{}\n{} --> {}\n{}\n",
+                i + 1,
+                timestamp(k.time, ',', 2, 3),
+                timestamp(end, ',', 2, 3),
+                k.label()
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn to_ass(keyframes: &[Keyframe]) -> String {
+    let mut lines = vec![
+        "[Script Info]".to_string(),
+        "ScriptType: v4.00+".to_string(),
+        String::new(),
+        "[V4+ Styles]".to_string(),
+        "Format: Name, Fontname, Fontsize, PrimaryColour, BackColour, Bold, Alignment, MarginV"
+            .to_string(),
+        "Style: Inputs, Arial, 36, &H00FFFFFF, &H80000000, -1, 1, 20".to_string(),
+        String::new(),
+        "[Events]".to_string(),
+        "Format: Layer, Start, End, Style, Text".to_string(),
+    ];
+    lines.extend(spans(keyframes).map(|(k, end)| {
+        format!(
+            "Dialogue: 0,{},{},Inputs,{}",
+            timestamp(k.time, '.', 1, 2),
+            timestamp(end, '.', 1, 2),
+            k.label()
+        )
+    }));
+    lines.join("\n")
+}
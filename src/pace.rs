@@ -0,0 +1,177 @@
+use serde::Serialize;
+
+use crate::{
+    data::Inputs,
+    runs::{self, RunEnd},
+};
+
+/// How many samples ahead of the last match the reference path is searched, so that
+/// routes crossing themselves don't make the progress jump around.
+const SEARCH_WINDOW: usize = 250;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RunPace {
+    /// Index of the run as reported by the run segmentation
+    pub run: usize,
+    pub end: RunEnd,
+    /// Time spent in each section in seconds, `None` if the run never got through it
+    pub section_times: Vec<Option<f32>>,
+    /// Difference to the best run for each section in seconds
+    pub deltas: Vec<Option<f32>>,
+    /// The section that cost the most time compared to the best run
+    pub slowest_section: Option<usize>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Pace {
+    pub section_length: f32,
+    pub sections: usize,
+    pub best_run: usize,
+    pub runs: Vec<RunPace>,
+}
+
+/// Parses `--section-length`, which has to be a positive number of tiles.
+pub fn parse_section_length(length: &str) -> Result<f32, String> {
+    match length.parse::<f32>() {
+        Ok(length) if length > 0.0 && length.is_finite() => Ok(length),
+        Ok(_) => Err(format!("has to be more than 0 tiles, got `{length}`")),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Finds the closest sample of the reference path for every sample of the run.
+pub fn matches(reference: &[Inputs], run: &[Inputs]) -> Vec<usize> {
+    let mut last = 0;
+    run.iter()
+        .map(|sample| {
+            let end = (last + SEARCH_WINDOW).min(reference.len());
+            last = (last..end)
+                .min_by(|a, b| {
+                    reference[*a]
+                        .pos
+                        .distance(&sample.pos)
+                        .total_cmp(&reference[*b].pos.distance(&sample.pos))
+                })
+                .unwrap_or(last);
+            last
+        })
+        .collect()
+}
+
+/// Cumulative distance travelled along the path at every sample.
+pub fn travelled(path: &[Inputs]) -> Vec<f32> {
+    std::iter::once(0.0)
+        .chain(path.windows(2).scan(0.0, |total, w| {
+            *total += w[0].pos.distance(&w[1].pos);
+            Some(*total)
+        }))
+        .collect()
+}
+
+/// Maps every sample of the run onto the distance travelled along the reference path.
+pub fn progress(reference: &[Inputs], travelled: &[f32], run: &[Inputs]) -> Vec<f32> {
+    matches(reference, run)
+        .into_iter()
+        .map(|i| travelled[i])
+        .collect()
+}
+
+/// The tick at which the run entered each section.
+pub fn section_entries(
+    run: &[Inputs],
+    progress: &[f32],
+    sections: usize,
+    section_length: f32,
+) -> Vec<Option<i32>> {
+    (0..sections)
+        .map(|section| {
+            let start = section as f32 * section_length;
+            progress
+                .iter()
+                .position(|p| *p >= start)
+                .map(|i| run[i].tick)
+        })
+        .collect()
+}
+
+fn section_times(
+    run: &[Inputs],
+    progress: &[f32],
+    end: RunEnd,
+    sections: usize,
+    section_length: f32,
+    tick_rate: i32,
+) -> Vec<Option<f32>> {
+    let mut entries = section_entries(run, progress, sections, section_length);
+    entries.push(if end == RunEnd::Finish {
+        run.last().map(|i| i.tick)
+    } else {
+        None
+    });
+
+    entries
+        .windows(2)
+        .map(|w| match (w[0], w[1]) {
+            (Some(start), Some(end)) => Some((end - start) as f32 / tick_rate as f32),
+            _ => None,
+        })
+        .collect()
+}
+
+pub fn calculate_pace(inputs: &[Inputs], section_length: f32, tick_rate: i32) -> Option<Pace> {
+    let segments = runs::segment_runs(inputs, tick_rate);
+    let (best_run, (best_range, _)) = segments
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, end))| *end == RunEnd::Finish)
+        .min_by_key(|(_, (range, _))| inputs[range.end - 1].tick - inputs[range.start].tick)?;
+
+    let reference = &inputs[best_range.clone()];
+    let travelled = travelled(reference);
+    let sections = (travelled.last().copied().unwrap_or_default() / section_length).ceil() as usize;
+
+    let best_times = section_times(
+        reference,
+        &travelled,
+        RunEnd::Finish,
+        sections,
+        section_length,
+        tick_rate,
+    );
+
+    let runs = segments
+        .iter()
+        .enumerate()
+        .map(|(i, (range, end))| {
+            let run = &inputs[range.clone()];
+            let progress = progress(reference, &travelled, run);
+            let section_times =
+                section_times(run, &progress, *end, sections, section_length, tick_rate);
+            let deltas: Vec<Option<f32>> = section_times
+                .iter()
+                .zip(&best_times)
+                .map(|(time, best)| Some((*time)? - (*best)?))
+                .collect();
+            let slowest_section = deltas
+                .iter()
+                .enumerate()
+                .filter_map(|(i, d)| Some((i, (*d)?)))
+                .max_by(|a, b| a.1.total_cmp(&b.1))
+                .map(|(i, _)| i);
+            RunPace {
+                run: i,
+                end: *end,
+                section_times,
+                deltas,
+                slowest_section,
+            }
+        })
+        .collect();
+
+    Some(Pace {
+        section_length,
+        sections,
+        best_run,
+        runs,
+    })
+}
@@ -0,0 +1,93 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::data::{ActiveWeapon, Inputs};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PickupKind {
+    Weapon,
+    Ammo,
+    Heart,
+    Shield,
+}
+
+impl PickupKind {
+    pub fn label(&self) -> &'static str {
+        match self {
+            PickupKind::Weapon => "Weapon",
+            PickupKind::Ammo => "Ammo",
+            PickupKind::Heart => "Heart",
+            PickupKind::Shield => "Shield",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Pickup {
+    pub tick: i32,
+    pub kind: PickupKind,
+    pub weapon: ActiveWeapon,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct PickupStats {
+    pub weapons: usize,
+    pub ammo: usize,
+    pub hearts: usize,
+    pub shields: usize,
+    /// Ammo spent with every weapon that has limited ammo
+    pub ammo_used: BTreeMap<ActiveWeapon, i32>,
+}
+
+/// Pickups inferred from the health, armor and ammo going up. Only the active weapon is in
+/// the snapshots, so a weapon counts as picked up the first time the player switches to it.
+pub fn pickups(inputs: &[Inputs]) -> Vec<Pickup> {
+    let mut pickups = Vec::new();
+    let mut held: BTreeSet<ActiveWeapon> = inputs.first().map(|i| i.weapon).into_iter().collect();
+    for w in inputs.windows(2) {
+        let (before, after) = (&w[0], &w[1]);
+        let mut push = |kind| {
+            pickups.push(Pickup {
+                tick: after.tick,
+                kind,
+                weapon: after.weapon,
+            })
+        };
+        if held.insert(after.weapon) {
+            push(PickupKind::Weapon);
+        } else if after.weapon == before.weapon
+            && before.ammo_count >= 0
+            && after.ammo_count > before.ammo_count
+        {
+            push(PickupKind::Ammo);
+        }
+        if after.health > before.health {
+            push(PickupKind::Heart);
+        }
+        if after.armor > before.armor {
+            push(PickupKind::Shield);
+        }
+    }
+    pickups
+}
+
+pub fn calculate_pickup_stats(inputs: &[Inputs]) -> PickupStats {
+    let mut stats = PickupStats::default();
+    for pickup in pickups(inputs) {
+        match pickup.kind {
+            PickupKind::Weapon => stats.weapons += 1,
+            PickupKind::Ammo => stats.ammo += 1,
+            PickupKind::Heart => stats.hearts += 1,
+            PickupKind::Shield => stats.shields += 1,
+        }
+    }
+    // Unlimited ammo is -1
+    for w in inputs.windows(2) {
+        if w[0].weapon == w[1].weapon && w[1].ammo_count >= 0 && w[1].ammo_count < w[0].ammo_count {
+            *stats.ammo_used.entry(w[1].weapon).or_default() += w[0].ammo_count - w[1].ammo_count;
+        }
+    }
+    stats
+}
@@ -0,0 +1,57 @@
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+use stringlit::s;
+
+use crate::cache;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedDemo {
+    pub path: PathBuf,
+    /// Whether the inputs would be read from the .tda cache instead of parsing the demo
+    pub cached: bool,
+}
+
+/// What a batch command would do, printed by --dry-run instead of running it.
+#[derive(Debug, Clone, Serialize)]
+pub struct Plan {
+    pub demos: Vec<PlannedDemo>,
+    /// The --out file, stdout if there is none
+    pub output: Option<PathBuf>,
+    pub append: bool,
+}
+
+pub fn plan(demos: Vec<PathBuf>, use_cache: bool, output: Option<&Path>, append: bool) -> Plan {
+    let demos = demos
+        .into_iter()
+        .map(|path| PlannedDemo {
+            cached: use_cache && cache::is_cached(&path),
+            path,
+        })
+        .collect();
+    Plan {
+        demos,
+        output: output.map(Path::to_path_buf),
+        append,
+    }
+}
+
+pub fn plain_report(plan: &Plan) -> String {
+    let cached = plan.demos.iter().filter(|d| d.cached).count();
+    let mut vec = Vec::new();
+    vec.push(format!(
+        "Would process {} demos, {cached} from the cache:",
+        plan.demos.len()
+    ));
+    for demo in &plan.demos {
+        let source = if demo.cached { "cached" } else { "parse" };
+        vec.push(format!("  [{source:>6}] {}", demo.path.display()));
+    }
+    vec.push(s!(""));
+    vec.push(match &plan.output {
+        Some(out) if plan.append => format!("Would append to {}", out.display()),
+        Some(out) => format!("Would write {}", out.display()),
+        None => s!("Would write to stdout"),
+    });
+    vec.join("\n")
+}
@@ -0,0 +1,175 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use twsnap::{compat::ddnet::DemoReader, Snap};
+
+use crate::{
+    finishes,
+    names::NameFilter,
+    timeline::{clock, TimeFormat, Timeline, Timestamp},
+};
+
+/// Missing from the snapshots for longer than this counts as having left.
+pub const LEAVE_SECONDS: i32 = 3;
+
+/// A stretch of time the player was in the demo.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Presence {
+    pub joined: Timestamp,
+    pub left: Timestamp,
+    pub seconds: f32,
+}
+
+/// The stretches of time the player was seen in, from the ticks they were seen at in order.
+pub fn presence(ticks: impl IntoIterator<Item = i32>, timeline: &Timeline) -> Vec<Presence> {
+    let mut intervals: Vec<(i32, i32)> = Vec::new();
+    for tick in ticks {
+        match intervals.last_mut() {
+            Some((_, last)) if tick - *last <= LEAVE_SECONDS * timeline.tick_rate => *last = tick,
+            _ => intervals.push((tick, tick)),
+        }
+    }
+    intervals
+        .into_iter()
+        .map(|(joined, left)| Presence {
+            joined: timeline.timestamp(joined),
+            left: timeline.timestamp(left),
+            seconds: (left - joined) as f32 / timeline.tick_rate as f32,
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PlayerListing {
+    pub name: String,
+    /// Client ids the player had, more than one if they reconnected
+    pub ids: BTreeSet<u16>,
+    pub clan: String,
+    pub first_seen: Timestamp,
+    pub last_seen: Timestamp,
+    /// When the player joined and left, a new stretch starts after three seconds away
+    pub presence: Vec<Presence>,
+    /// Seconds of all stretches together
+    pub seconds_present: f32,
+    /// Snapshots in which the player had a tee, what extract would output
+    pub samples: usize,
+    /// The score the player had last
+    pub score: i32,
+    /// The fastest finish time in whole seconds any of their scores stood for
+    pub best_time: Option<i32>,
+}
+
+struct Seen {
+    ids: BTreeSet<u16>,
+    clan: String,
+    first: i32,
+    last: i32,
+    ticks: Vec<i32>,
+    samples: usize,
+    score: i32,
+    best_time: Option<i32>,
+}
+
+/// Every player in the demo, read from the snapshots without extracting any inputs.
+pub fn list_players(
+    mut reader: DemoReader,
+    filter: &NameFilter,
+    tick_rate: Option<i32>,
+) -> (Vec<PlayerListing>, Timeline) {
+    let length = reader.length();
+    let mut ticks = None;
+    let mut players: BTreeMap<String, Seen> = BTreeMap::new();
+    let mut snap = Snap::default();
+    while let Ok(Some(chunk)) = reader.next_chunk(&mut snap) {
+        crate::track_ticks(&mut ticks, chunk);
+        let Some((_, tick)) = ticks else {
+            continue;
+        };
+        for (id, p) in snap.players.iter() {
+            let seen = players.entry(p.name.to_string()).or_insert_with(|| Seen {
+                ids: BTreeSet::new(),
+                clan: String::new(),
+                first: tick,
+                last: tick,
+                ticks: Vec::new(),
+                samples: 0,
+                score: p.score,
+                best_time: None,
+            });
+            seen.ids.insert(id.legacy_id());
+            seen.clan = p.clan.to_string();
+            seen.last = tick;
+            seen.ticks.push(tick);
+            seen.samples += usize::from(p.tee.is_some());
+            seen.score = p.score;
+            if let Some(time) = finishes::finish_time(p.score) {
+                seen.best_time = Some(seen.best_time.map_or(time, |best| best.min(time)));
+            }
+        }
+    }
+
+    let timeline = crate::timeline(length, ticks, tick_rate);
+    let players = players
+        .into_iter()
+        .filter(|(name, _)| filter.matches(name))
+        .map(|(name, seen)| {
+            let presence = presence(seen.ticks, &timeline);
+            PlayerListing {
+                name,
+                ids: seen.ids,
+                clan: seen.clan,
+                first_seen: timeline.timestamp(seen.first),
+                last_seen: timeline.timestamp(seen.last),
+                seconds_present: presence.iter().map(|p| p.seconds).fold(0.0, |a, b| a + b),
+                presence,
+                samples: seen.samples,
+                score: seen.score,
+                best_time: seen.best_time,
+            }
+        })
+        .collect();
+    (players, timeline)
+}
+
+pub fn plain_report(players: &[PlayerListing], timeline: &Timeline, format: TimeFormat) -> String {
+    let width = players
+        .iter()
+        .map(|p| p.name.chars().count())
+        .max()
+        .unwrap_or(0);
+    let mut vec = Vec::new();
+    vec.push(format!(
+        "{:<width$}  {:<8}  {:<11}  {:>10}  {:>10}  {:>10}  {:>7}  {:>6}  {:>6}",
+        "Name", "Ids", "Clan", "From", "To", "Present", "Samples", "Score", "Best"
+    ));
+    for p in players {
+        let ids: Vec<String> = p.ids.iter().map(u16::to_string).collect();
+        vec.push(format!(
+            "{:<width$}  {:<8}  {:<11}  {:>10}  {:>10}  {:>10}  {:>7}  {:>6}  {:>6}",
+            p.name,
+            ids.join(","),
+            p.clan,
+            format.format(timeline.seconds(p.first_seen.tick)),
+            format.format(timeline.seconds(p.last_seen.tick)),
+            format.format(p.seconds_present),
+            p.samples,
+            p.score,
+            p.best_time
+                .map(|t| clock(t as f32, false))
+                .unwrap_or_default(),
+        ));
+        // Players who were there the whole time in one go don't need the details
+        if p.presence.len() > 1 {
+            for presence in &p.presence {
+                vec.push(format!(
+                    "{:<width$}    present {} - {}",
+                    "",
+                    format.format(timeline.seconds(presence.joined.tick)),
+                    format.format(timeline.seconds(presence.left.tick)),
+                ));
+            }
+        }
+    }
+    vec.join("\n")
+}
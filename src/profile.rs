@@ -0,0 +1,166 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use serde::{de::Error, Deserialize, Deserializer, Serialize};
+
+use crate::{aim, data::Inputs, zoom};
+
+/// Demos needed before a new demo is checked for sudden changes.
+const MIN_HISTORY: usize = 3;
+
+/// The metrics tracked across demos, in the order they are reported.
+pub const METRICS: [&str; 6] = [
+    "direction_change_rate_average",
+    "hook_state_change_rate_average",
+    "aim_angular_speed_average",
+    "aim_angular_jerk_average",
+    "aim_linear_segment_fraction",
+    "target_distance_average",
+];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DemoMetrics {
+    pub demo: String,
+    /// Recording time from the demo header
+    pub timestamp: String,
+    /// The name the player used in this demo
+    pub name: String,
+    #[serde(deserialize_with = "metrics")]
+    pub metrics: BTreeMap<&'static str, f32>,
+}
+
+/// Reads the metrics back by their names in [`METRICS`].
+fn metrics<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<&'static str, f32>, D::Error> {
+    BTreeMap::<String, f32>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, value)| {
+            let metric = METRICS.into_iter().find(|metric| *metric == name);
+            metric
+                .map(|metric| (metric, value))
+                .ok_or_else(|| D::Error::custom(format!("unknown metric {name}")))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SuddenChange {
+    pub demo: String,
+    pub metric: &'static str,
+    pub value: f32,
+    pub previous_average: f32,
+    /// Distance to the previous average in standard deviations
+    pub deviation: f32,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Profile {
+    pub names: Vec<String>,
+    pub history: Vec<DemoMetrics>,
+    /// Least squares slope of every metric per demo
+    pub trends: BTreeMap<&'static str, f32>,
+    pub sudden_changes: Vec<SuddenChange>,
+}
+
+/// Maps every known alias to its identity. The file is a TOML table of identities
+/// to the names they used, e.g. `"Main" = ["Main", "Main2"]`.
+pub fn read_identities(path: &Path) -> anyhow::Result<HashMap<String, String>> {
+    let identities: HashMap<String, Vec<String>> = toml::from_str(&std::fs::read_to_string(path)?)?;
+    Ok(identities
+        .into_iter()
+        .flat_map(|(identity, names)| names.into_iter().map(move |n| (n, identity.clone())))
+        .collect())
+}
+
+pub fn calculate_metrics(inputs: &[Inputs], tick_rate: i32) -> BTreeMap<&'static str, f32> {
+    let directions = crate::calculate_direction_change_stats(
+        crate::change_ticks(inputs, |i| i.direction),
+        tick_rate,
+    );
+    let hooks = crate::calculate_direction_change_stats(
+        crate::change_ticks(inputs, |i| i.hook_state.pressed()),
+        tick_rate,
+    );
+    let aim = aim::calculate_aim_stats(inputs, tick_rate);
+    let target_distance = zoom::calculate_target_distance_stats(inputs);
+    METRICS
+        .into_iter()
+        .zip([
+            directions.average,
+            hooks.average,
+            aim.angular_speed_average,
+            aim.angular_jerk_average,
+            aim.linear_segment_fraction,
+            target_distance.average,
+        ])
+        .collect()
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len() as f32
+}
+
+fn slope(values: &[f32]) -> f32 {
+    if values.len() < 2 {
+        return 0.0;
+    }
+    let x_mean = (values.len() - 1) as f32 / 2.0;
+    let y_mean = mean(values);
+    let (covariance, variance) = values
+        .iter()
+        .enumerate()
+        .fold((0.0, 0.0), |(c, v), (x, y)| {
+            let dx = x as f32 - x_mean;
+            (c + dx * (y - y_mean), v + dx * dx)
+        });
+    covariance / variance
+}
+
+/// Builds the profile of one identity. The history has to be in chronological order.
+pub fn build_profile(history: Vec<DemoMetrics>, threshold: f32) -> Profile {
+    let mut names: Vec<String> = history.iter().map(|d| d.name.clone()).collect();
+    names.sort();
+    names.dedup();
+
+    let mut trends = BTreeMap::new();
+    let mut sudden_changes = Vec::new();
+    for metric in METRICS {
+        let values: Vec<f32> = history.iter().map(|d| d.metrics[metric]).collect();
+        trends.insert(metric, slope(&values));
+
+        for (i, value) in values.iter().enumerate().skip(MIN_HISTORY) {
+            let previous = &values[..i];
+            let previous_average = mean(previous);
+            let variance = previous
+                .iter()
+                .map(|v| (v - previous_average).powi(2))
+                .sum::<f32>()
+                / previous.len() as f32;
+            // Very consistent histories would flag every tiny fluctuation otherwise
+            let spread = variance
+                .sqrt()
+                .max(previous_average.abs() * 0.05)
+                .max(f32::EPSILON);
+            let deviation = (value - previous_average) / spread;
+            if deviation.abs() >= threshold {
+                sudden_changes.push(SuddenChange {
+                    demo: history[i].demo.clone(),
+                    metric,
+                    value: *value,
+                    previous_average,
+                    deviation,
+                });
+            }
+        }
+    }
+
+    Profile {
+        names,
+        history,
+        trends,
+        sudden_changes,
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::{HashMap, HashSet},
+    ffi::OsStr,
+    fs::OpenOptions,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+/// The demos a batch run already wrote to its output, kept in `out.done` next to the output
+/// as one demo hash per line, so an interrupted run continues where it stopped. Runs that
+/// only write their output at the end keep what they took from every demo in `out.partial`.
+pub struct Progress {
+    path: PathBuf,
+    partial: PathBuf,
+    done: HashSet<String>,
+}
+
+/// One line of `out.partial`.
+#[derive(Serialize, Deserialize)]
+struct Partial<T> {
+    hash: String,
+    result: T,
+}
+
+fn next_to(out: &Path, extension: &str) -> PathBuf {
+    let mut path = out.as_os_str().to_owned();
+    path.push(OsStr::new(extension));
+    PathBuf::from(path)
+}
+
+impl Progress {
+    /// Loads the state of the run writing to `out`, or starts over when `force` is set.
+    pub fn load(out: &Path, force: bool) -> anyhow::Result<Self> {
+        let path = next_to(out, ".done");
+        let partial = next_to(out, ".partial");
+        if force && partial.exists() {
+            std::fs::remove_file(&partial)?;
+        }
+        let done = match std::fs::read_to_string(&path) {
+            Ok(_) if force => {
+                std::fs::remove_file(&path)?;
+                HashSet::new()
+            }
+            Ok(text) => text.lines().map(String::from).collect(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashSet::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            partial,
+            done,
+        })
+    }
+
+    /// Whether nothing was written yet, so the output should be replaced instead of added to.
+    pub fn is_fresh(&self) -> bool {
+        self.done.is_empty()
+    }
+
+    pub fn is_done(&self, hash: &str) -> bool {
+        self.done.contains(hash)
+    }
+
+    /// Records the demo as done, after its output was written.
+    pub fn finish(&mut self, hash: String) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{hash}")?;
+        self.done.insert(hash);
+        Ok(())
+    }
+
+    /// Records the demo as done along with what the run took from it, for runs that only
+    /// write their output once every demo is done.
+    pub fn finish_with(&mut self, hash: String, result: &impl Serialize) -> anyhow::Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.partial)?;
+        let partial = Partial {
+            hash: hash.clone(),
+            result,
+        };
+        writeln!(file, "{}", serde_json::to_string(&partial)?)?;
+        self.finish(hash)
+    }
+
+    /// What the run took from the demos done before, by demo hash. A result written without
+    /// its demo being recorded as done is from a run that stopped in between and is left out.
+    pub fn results<T: DeserializeOwned>(&self) -> anyhow::Result<HashMap<String, T>> {
+        let text = match std::fs::read_to_string(&self.partial) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut results = HashMap::new();
+        for line in text.lines() {
+            let partial: Partial<T> = serde_json::from_str(line)?;
+            if self.is_done(&partial.hash) {
+                results.insert(partial.hash, partial.result);
+            }
+        }
+        Ok(results)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use stringlit::s;
+
+    use super::*;
+
+    #[test]
+    fn keeps_the_results_of_finished_demos() {
+        let out = std::env::temp_dir().join(format!("{}-progress.json", std::process::id()));
+        let mut progress = Progress::load(&out, true).unwrap();
+        assert!(progress.is_fresh());
+        progress.finish_with(s!("a"), &vec![1.0, 2.0]).unwrap();
+        // A run that stopped after writing the result but before recording the demo as done
+        writeln!(
+            OpenOptions::new()
+                .append(true)
+                .open(&progress.partial)
+                .unwrap(),
+            r#"{{"hash":"b","result":[3.0]}}"#
+        )
+        .unwrap();
+
+        let progress = Progress::load(&out, false).unwrap();
+        assert!(progress.is_done("a") && !progress.is_done("b"));
+        let results: HashMap<String, Vec<f32>> = progress.results().unwrap();
+        assert_eq!(results, HashMap::from([(s!("a"), vec![1.0, 2.0])]));
+
+        let progress = Progress::load(&out, true).unwrap();
+        assert!(progress.is_fresh());
+        assert!(progress.results::<Vec<f32>>().unwrap().is_empty());
+        assert!(!progress.path.exists() && !progress.partial.exists());
+    }
+}
@@ -0,0 +1,558 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+use stringlit::s;
+
+use crate::{
+    data::Inputs,
+    timeline::{Timeline, Timestamp},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Type {
+    Bool,
+    Number,
+    Text,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+struct Field {
+    name: &'static str,
+    ty: Type,
+    get: fn(&Inputs) -> Value,
+}
+
+fn number(value: impl Into<f64>) -> Value {
+    Value::Number(value.into())
+}
+
+fn text(value: impl std::fmt::Debug) -> Value {
+    Value::Text(format!("{value:?}"))
+}
+
+/// Everything a query can look at. Positions are in tiles, velocities in units per tick and
+/// the angle in radians.
+const FIELDS: &[Field] = &[
+    Field {
+        name: "tick",
+        ty: Type::Number,
+        get: |i| number(i.tick),
+    },
+    Field {
+        name: "x",
+        ty: Type::Number,
+        get: |i| number(i.pos.tiles().0),
+    },
+    Field {
+        name: "y",
+        ty: Type::Number,
+        get: |i| number(i.pos.tiles().1),
+    },
+    Field {
+        name: "vel_x",
+        ty: Type::Number,
+        get: |i| number(i.vel.x.to_num::<f32>()),
+    },
+    Field {
+        name: "vel_y",
+        ty: Type::Number,
+        get: |i| number(i.vel.y.to_num::<f32>()),
+    },
+    Field {
+        name: "speed",
+        ty: Type::Number,
+        get: |i| number(i.vel.x.to_num::<f32>().hypot(i.vel.y.to_num())),
+    },
+    Field {
+        name: "angle",
+        ty: Type::Number,
+        get: |i| number(i.angle.to_num::<f32>()),
+    },
+    Field {
+        name: "direction",
+        ty: Type::Text,
+        get: |i| text(i.direction),
+    },
+    Field {
+        name: "hook_state",
+        ty: Type::Text,
+        get: |i| text(i.hook_state),
+    },
+    Field {
+        name: "hook_x",
+        ty: Type::Number,
+        get: |i| number(i.hook_pos.tiles().0),
+    },
+    Field {
+        name: "hook_y",
+        ty: Type::Number,
+        get: |i| number(i.hook_pos.tiles().1),
+    },
+    Field {
+        name: "health",
+        ty: Type::Number,
+        get: |i| number(i.health),
+    },
+    Field {
+        name: "armor",
+        ty: Type::Number,
+        get: |i| number(i.armor),
+    },
+    Field {
+        name: "ammo_count",
+        ty: Type::Number,
+        get: |i| number(i.ammo_count),
+    },
+    Field {
+        name: "weapon",
+        ty: Type::Text,
+        get: |i| text(i.weapon),
+    },
+    Field {
+        name: "emote",
+        ty: Type::Text,
+        get: |i| text(i.emote),
+    },
+    Field {
+        name: "frozen",
+        ty: Type::Bool,
+        get: |i| Value::Bool(i.freeze_end != 0),
+    },
+    Field {
+        name: "jumps",
+        ty: Type::Number,
+        get: |i| number(i.jumps),
+    },
+    Field {
+        name: "jumped_total",
+        ty: Type::Number,
+        get: |i| number(i.jumped_total),
+    },
+    Field {
+        name: "score",
+        ty: Type::Number,
+        get: |i| number(i.score),
+    },
+    Field {
+        name: "target_distance",
+        ty: Type::Number,
+        get: |i| number(i.target.x.to_num::<f32>().hypot(i.target.y.to_num())),
+    },
+];
+
+/// The names of the fields, for the help text and error messages.
+pub fn field_names() -> String {
+    FIELDS.iter().map(|f| f.name).collect::<Vec<_>>().join(", ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Field(usize),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Compare(Box<Expr>, Op, Box<Expr>),
+}
+
+impl Expr {
+    fn ty(&self) -> Type {
+        match self {
+            Expr::Literal(Value::Bool(_)) => Type::Bool,
+            Expr::Literal(Value::Number(_)) => Type::Number,
+            Expr::Literal(Value::Text(_)) => Type::Text,
+            Expr::Field(field) => FIELDS[*field].ty,
+            _ => Type::Bool,
+        }
+    }
+
+    fn value(&self, inputs: &Inputs) -> Value {
+        match self {
+            Expr::Literal(value) => value.clone(),
+            Expr::Field(field) => (FIELDS[*field].get)(inputs),
+            _ => Value::Bool(self.matches(inputs)),
+        }
+    }
+
+    /// Whether the sample matches, only called on expressions the parser checked to be
+    /// conditions.
+    fn matches(&self, inputs: &Inputs) -> bool {
+        match self {
+            Expr::Not(e) => !e.matches(inputs),
+            Expr::And(a, b) => a.matches(inputs) && b.matches(inputs),
+            Expr::Or(a, b) => a.matches(inputs) || b.matches(inputs),
+            Expr::Compare(a, op, b) => match (a.value(inputs), b.value(inputs)) {
+                (Value::Number(a), Value::Number(b)) => match op {
+                    Op::Eq => a == b,
+                    Op::Ne => a != b,
+                    Op::Lt => a < b,
+                    Op::Le => a <= b,
+                    Op::Gt => a > b,
+                    Op::Ge => a >= b,
+                },
+                // Enum names are matched like the user would type them
+                (Value::Text(a), Value::Text(b)) => a.eq_ignore_ascii_case(&b) == (*op == Op::Eq),
+                (a, b) => (a == b) == (*op == Op::Eq),
+            },
+            e => e.value(inputs) == Value::Bool(true),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Text(String),
+    Ident(String),
+    Symbol(&'static str),
+}
+
+const SYMBOLS: [&str; 11] = ["&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")"];
+
+fn tokenize(query: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = query.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(symbol) = SYMBOLS.into_iter().find(|s| rest.starts_with(s)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if c == '"' {
+            let end = rest[1..]
+                .find('"')
+                .ok_or_else(|| format!("Unterminated string: {rest}"))?;
+            tokens.push(Token::Text(rest[1..end + 1].to_string()));
+            rest = &rest[end + 2..];
+        } else if c.is_ascii_digit() || c == '-' || c == '.' {
+            let end = rest[1..]
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .map_or(rest.len(), |e| e + 1);
+            let number = rest[..end]
+                .parse()
+                .map_err(|_| format!("Not a number: {}", &rest[..end]))?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            return Err(format!("Unexpected character '{c}' in: {rest}"));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn eat(&mut self, symbol: &'static str) -> bool {
+        let matches = self.tokens.get(self.position) == Some(&Token::Symbol(symbol));
+        self.position += matches as usize;
+        matches
+    }
+
+    fn condition(&mut self) -> Result<Expr, String> {
+        let expr = self.or()?;
+        if expr.ty() != Type::Bool {
+            return Err(s!("Expected a condition, like speed > 10 or frozen"));
+        }
+        Ok(expr)
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.condition_operand()?)));
+        }
+        self.compare()
+    }
+
+    fn condition_operand(&mut self) -> Result<Expr, String> {
+        let expr = self.not()?;
+        if expr.ty() != Type::Bool {
+            return Err(s!("! only works on conditions"));
+        }
+        Ok(expr)
+    }
+
+    fn compare(&mut self) -> Result<Expr, String> {
+        let left = self.primary()?;
+        let op = [
+            ("==", Op::Eq),
+            ("!=", Op::Ne),
+            ("<=", Op::Le),
+            (">=", Op::Ge),
+            ("<", Op::Lt),
+            (">", Op::Gt),
+        ]
+        .into_iter()
+        .find(|(symbol, _)| self.eat(symbol));
+        let Some((symbol, op)) = op else {
+            return Ok(left);
+        };
+        let right = self.primary()?;
+        if left.ty() != right.ty() {
+            return Err(format!(
+                "Can't compare {:?} with {:?}",
+                left.ty(),
+                right.ty()
+            ));
+        }
+        if !matches!(op, Op::Eq | Op::Ne) && left.ty() != Type::Number {
+            return Err(format!("{symbol} only works on numbers"));
+        }
+        Ok(Expr::Compare(Box::new(left), op, Box::new(right)))
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self
+            .tokens
+            .get(self.position)
+            .cloned()
+            .ok_or_else(|| s!("Unexpected end of the query"))?;
+        self.position += 1;
+        match token {
+            Token::Number(n) => Ok(Expr::Literal(Value::Number(n))),
+            Token::Text(t) => Ok(Expr::Literal(Value::Text(t))),
+            Token::Ident(name) if name == "true" || name == "false" => {
+                Ok(Expr::Literal(Value::Bool(name == "true")))
+            }
+            Token::Ident(name) => FIELDS
+                .iter()
+                .position(|f| f.name == name)
+                .map(Expr::Field)
+                .ok_or_else(|| format!("Unknown field {name}, known are {}", field_names())),
+            Token::Symbol("(") => {
+                let expr = self.or()?;
+                if !self.eat(")") {
+                    return Err(s!("Missing )"));
+                }
+                Ok(expr)
+            }
+            Token::Symbol(symbol) => Err(format!("Unexpected {symbol}")),
+        }
+    }
+}
+
+/// A condition on the inputs of a single sample.
+#[derive(Debug, Clone)]
+pub struct Query(Expr);
+
+/// Parses a query like `speed > 40 && hook_state == "Grabbed"`.
+pub fn parse(query: &str) -> Result<Query, String> {
+    let mut parser = Parser {
+        tokens: tokenize(query)?,
+        position: 0,
+    };
+    let expr = parser.condition()?;
+    if let Some(token) = parser.tokens.get(parser.position) {
+        return Err(format!("Unexpected {token:?} after the query"));
+    }
+    Ok(Query(expr))
+}
+
+/// Samples in a row that matched.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Match {
+    pub start: Timestamp,
+    pub end: Timestamp,
+    pub samples: usize,
+}
+
+/// The stretches of samples the query matched.
+pub fn run(query: &Query, inputs: &[Inputs], timeline: &Timeline) -> Vec<Match> {
+    let mut matches: Vec<Match> = Vec::new();
+    let mut previous = false;
+    for input in inputs {
+        let matched = query.0.matches(input);
+        if matched {
+            match matches.last_mut() {
+                Some(last) if previous => {
+                    last.end = timeline.timestamp(input.tick);
+                    last.samples += 1;
+                }
+                _ => matches.push(Match {
+                    start: timeline.timestamp(input.tick),
+                    end: timeline.timestamp(input.tick),
+                    samples: 1,
+                }),
+            }
+        }
+        previous = matched;
+    }
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::{
+        ActiveWeapon, Direction, Emote, HookState, Position, Velocity, VelocityPrecision,
+    };
+
+    fn sample(tick: i32, vel_x: f32, hook_state: HookState, frozen: bool) -> Inputs {
+        let velocity = |x: f32| Velocity {
+            x: VelocityPrecision::from_num(x),
+            y: VelocityPrecision::ZERO,
+        };
+        Inputs {
+            tick,
+            pos: Position::from_tiles(10.0, 20.0),
+            vel: velocity(vel_x),
+            angle: Default::default(),
+            direction: Direction::Right,
+            hook_state,
+            hook_tick: 0,
+            hook_pos: Position::from_tiles(0.0, 0.0),
+            hook_direction: velocity(0.0),
+            health: 10,
+            armor: 0,
+            ammo_count: 0,
+            weapon: ActiveWeapon::Hammer,
+            emote: Emote::Normal,
+            attack_tick: 0,
+            freeze_end: if frozen { tick + 150 } else { 0 },
+            jumps: 2,
+            tele_checkpoint: 0,
+            strong_weak_id: 0,
+            jumped_total: 0,
+            ninja_activation_tick: 0,
+            target: Position::from_tiles(3.0, 4.0),
+            score: 0,
+        }
+    }
+
+    fn matches(query: &str, input: &Inputs) -> bool {
+        parse(query).unwrap().0.matches(input)
+    }
+
+    fn error(query: &str) -> String {
+        parse(query).unwrap_err()
+    }
+
+    #[test]
+    fn compares_numbers() {
+        let input = sample(100, 12.0, HookState::Idle, false);
+        assert!(matches("speed == 12", &input));
+        assert!(matches("speed != 11", &input));
+        assert!(matches("speed > 11.5", &input));
+        assert!(matches("speed >= 12", &input));
+        assert!(matches("speed < 12.5", &input));
+        assert!(matches("speed <= 12", &input));
+        assert!(!matches("speed < 12", &input));
+        assert!(matches("x == 10 && y == 20", &input));
+        assert!(matches("vel_x > -1", &input));
+        assert!(matches("target_distance == 5", &input));
+    }
+
+    #[test]
+    fn compares_text_like_it_is_typed() {
+        let input = sample(100, 0.0, HookState::Grabbed, false);
+        assert!(matches(r#"hook_state == "Grabbed""#, &input));
+        assert!(matches(r#"hook_state == "grabbed""#, &input));
+        assert!(matches(r#"hook_state != "Flying""#, &input));
+        assert!(matches(r#""Right" == direction"#, &input));
+    }
+
+    #[test]
+    fn uses_booleans_as_conditions() {
+        let frozen = sample(100, 0.0, HookState::Idle, true);
+        let free = sample(100, 0.0, HookState::Idle, false);
+        assert!(matches("frozen", &frozen));
+        assert!(!matches("frozen", &free));
+        assert!(matches("!frozen", &free));
+        assert!(matches("frozen == true", &frozen));
+        assert!(matches("!!frozen", &frozen));
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let input = sample(100, 0.0, HookState::Idle, false);
+        // Read as `true || (false && false)`
+        assert!(matches("health == 10 || jumps == 0 && frozen", &input));
+        assert!(!matches("(health == 10 || jumps == 0) && frozen", &input));
+        // `!` only applies to the condition right after it
+        assert!(matches("!frozen && health == 10", &input));
+        assert!(!matches("!(frozen || health == 10)", &input));
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        assert_eq!(error(r#"speed > "fast""#), "Can't compare Number with Text");
+        assert_eq!(
+            error(r#"hook_state < "Grabbed""#),
+            "< only works on numbers"
+        );
+        assert_eq!(
+            error("speed"),
+            "Expected a condition, like speed > 10 or frozen"
+        );
+        assert_eq!(error("!speed"), "! only works on conditions");
+        assert_eq!(error("speed >"), "Unexpected end of the query");
+        assert_eq!(error(""), "Unexpected end of the query");
+        assert_eq!(error("(speed > 1"), "Missing )");
+        assert_eq!(error("speed > )"), "Unexpected )");
+        assert_eq!(
+            error("speed > 1 frozen"),
+            r#"Unexpected Ident("frozen") after the query"#
+        );
+        assert_eq!(
+            error(r#"weapon == "Hammer"#),
+            r#"Unterminated string: "Hammer"#
+        );
+        assert_eq!(error("speed > 1.2.3"), "Not a number: 1.2.3");
+        assert_eq!(error("speed > 1 # x"), "Unexpected character '#' in: # x");
+        assert_eq!(
+            error("sped > 1"),
+            format!("Unknown field sped, known are {}", field_names())
+        );
+    }
+
+    #[test]
+    fn groups_matches_in_a_row() {
+        let inputs: Vec<Inputs> = [0.0, 20.0, 30.0, 5.0, 25.0, 0.0]
+            .into_iter()
+            .enumerate()
+            .map(|(tick, speed)| sample(tick as i32, speed, HookState::Idle, false))
+            .collect();
+        let matches = run(&parse("speed > 10").unwrap(), &inputs, &Timeline::default());
+        let samples: Vec<usize> = matches.iter().map(|m| m.samples).collect();
+        assert_eq!(samples, [2, 1]);
+    }
+}
@@ -0,0 +1,124 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{
+    changes::InputChanges,
+    data::{Direction, Inputs},
+    events::{self, EventKind},
+    zoom::percentile,
+};
+
+/// Default hook length of 380 units, in tiles.
+const HOOK_RANGE: f32 = 11.875;
+/// Anything later isn't a reaction to the stimulus anymore.
+const MAX_REACTION_SECONDS: f32 = 1.0;
+/// Humans don't react this fast consistently.
+const FAST_REACTION_MS: f32 = 100.0;
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct ReactionTimes {
+    pub count: usize,
+    /// All times are in milliseconds from the stimulus to the reaction
+    pub average: f32,
+    pub median: f32,
+    pub p10: f32,
+    /// Fraction of the reactions faster than 100ms
+    pub fast_fraction: f32,
+}
+
+impl ReactionTimes {
+    fn new(mut times: Vec<f32>) -> Self {
+        if times.is_empty() {
+            return Self::default();
+        }
+        times.sort_by(f32::total_cmp);
+        let count = times.len() as f32;
+        Self {
+            count: times.len(),
+            average: times.iter().sum::<f32>() / count,
+            median: percentile(&times, 0.5),
+            p10: percentile(&times, 0.1),
+            fast_fraction: times.iter().filter(|t| **t < FAST_REACTION_MS).count() as f32 / count,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct ReactionStats {
+    /// From another tee coming into hook range to the next hook press
+    pub hook: ReactionTimes,
+    /// From unfreezing to the first direction, hook or jump input
+    pub unfreeze: ReactionTimes,
+}
+
+fn sample_at(samples: &[Inputs], tick: i32) -> Option<&Inputs> {
+    samples
+        .get(samples.partition_point(|i| i.tick < tick))
+        .filter(|i| i.tick == tick)
+}
+
+/// Milliseconds from the stimulus to the first later sample the reaction is seen in.
+fn reaction(
+    inputs: &[Inputs],
+    stimulus: i32,
+    tick_rate: i32,
+    reacted: impl Fn(&Inputs, &Inputs) -> bool,
+) -> Option<f32> {
+    let start = inputs.partition_point(|i| i.tick <= stimulus).max(1);
+    let limit = stimulus + (MAX_REACTION_SECONDS * tick_rate as f32) as i32;
+    let tick = inputs[start..]
+        .iter()
+        .zip(&inputs[start - 1..])
+        .take_while(|(i, _)| i.tick <= limit)
+        .find(|(i, previous)| reacted(previous, i))?
+        .0
+        .tick;
+    Some((tick - stimulus) as f32 * 1000.0 / tick_rate as f32)
+}
+
+/// How fast the player reacts to things happening, as far as the snapshots show it. Times
+/// are only as precise as the snapshots, a tick apart at best.
+pub fn calculate_reaction_stats(
+    inputs: &[Inputs],
+    others: &[&InputChanges],
+    tick_rate: i32,
+) -> ReactionStats {
+    let in_range = |sample: &Inputs| {
+        others.iter().any(|other| {
+            other
+                .position_at(sample.tick)
+                .is_some_and(|other| sample.pos.distance(other) <= HOOK_RANGE)
+        })
+    };
+    let hook = inputs
+        .windows(2)
+        .filter(|w| !w[1].hook_state.pressed() && in_range(&w[1]) && !in_range(&w[0]))
+        .filter_map(|w| {
+            reaction(inputs, w[1].tick, tick_rate, |previous, i| {
+                i.hook_state.pressed() && !previous.hook_state.pressed()
+            })
+        })
+        .collect();
+
+    // Keys held through the freeze act on the unfreeze tick, that isn't a reaction
+    let unfreeze = events::detect_events(inputs, tick_rate)
+        .into_iter()
+        .filter(|e| e.kind == EventKind::FreezeEnd)
+        .filter(|e| {
+            sample_at(inputs, e.tick)
+                .is_some_and(|i| i.direction == Direction::None && !i.hook_state.pressed())
+        })
+        .filter_map(|e| {
+            reaction(inputs, e.tick, tick_rate, |previous, i| {
+                i.direction != previous.direction
+                    || i.hook_state.pressed() != previous.hook_state.pressed()
+                    || i.jumped_total > previous.jumped_total
+            })
+        })
+        .collect();
+
+    ReactionStats {
+        hook: ReactionTimes::new(hook),
+        unfreeze: ReactionTimes::new(unfreeze),
+    }
+}
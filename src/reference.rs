@@ -0,0 +1,79 @@
+use std::{collections::BTreeMap, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+use crate::distribution;
+
+/// The metrics a reference set is built for, the ones that mean little without context.
+pub const METRICS: [&str; 9] = [
+    "direction_change_rate_average",
+    "direction_change_rate_median",
+    "direction_change_rate_max",
+    "hook_state_change_rate_average",
+    "hook_state_change_rate_median",
+    "hook_state_change_rate_max",
+    "aim_angular_speed_average",
+    "rehook.frequency",
+    "rehook.max_sustained_rate",
+];
+
+/// The values of players known to be legitimate, to place the values of other players in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Reference {
+    /// Number of players, every player of every demo counts separately
+    pub players: usize,
+    /// Sorted values of every metric
+    pub metrics: BTreeMap<String, Vec<f32>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Placement {
+    pub metric: &'static str,
+    pub value: f32,
+    /// Share of the reference values at or below the value, the percentile it is at
+    pub percentile: f32,
+}
+
+impl Reference {
+    /// Collects the metrics from the serialized stats of the players.
+    pub fn build(stats: &[serde_json::Value]) -> Self {
+        let metrics = METRICS
+            .iter()
+            .map(|metric| {
+                let mut values: Vec<f32> = stats
+                    .iter()
+                    .filter_map(|s| distribution::lookup(s, metric))
+                    .collect();
+                values.sort_by(f32::total_cmp);
+                (metric.to_string(), values)
+            })
+            .collect();
+        Self {
+            players: stats.len(),
+            metrics,
+        }
+    }
+
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        serde_json::from_str(&std::fs::read_to_string(path)?)
+            .with_context(|| format!("Couldn't read reference set {path:?}"))
+    }
+
+    /// Where the stats of a player fall in the reference set, for every metric it has values for.
+    pub fn placements(&self, stats: &serde_json::Value) -> Vec<Placement> {
+        METRICS
+            .iter()
+            .filter_map(|metric| {
+                let values = self.metrics.get(*metric).filter(|v| !v.is_empty())?;
+                let value = distribution::lookup(stats, metric)?;
+                let below = values.partition_point(|v| *v <= value);
+                Some(Placement {
+                    metric,
+                    value,
+                    percentile: below as f32 / values.len() as f32 * 100.0,
+                })
+            })
+            .collect()
+    }
+}
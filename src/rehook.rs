@@ -0,0 +1,124 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::data::Inputs;
+
+/// Hook presses further apart than this aren't part of a rehook chain.
+const MAX_CYCLE_SECONDS: f32 = 1.0;
+/// Fewer cycles in a row are just hooking twice, not a rhythm.
+const MIN_CHAIN_CYCLES: usize = 3;
+/// The rate has to be held at least this long to count as sustained.
+const SUSTAINED_SECONDS: f32 = 2.0;
+/// Servers usually only send every second tick, so cycle lengths are only this precise.
+const PERIOD_TOLERANCE_TICKS: i32 = 2;
+/// Holding the exact same rhythm for this long is beyond what humans can do.
+const MACRO_SECONDS: f32 = 60.0;
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct RehookStats {
+    /// Hook-release-hook cycles that are part of a chain
+    pub cycles: usize,
+    pub chains: usize,
+    /// Average rehooks per second within chains
+    pub frequency: f32,
+    /// Standard deviation of the cycle length divided by its mean, lower is more regular
+    pub period_variation: f32,
+    /// Highest rehook rate held for at least two seconds, in rehooks per second
+    pub max_sustained_rate: f32,
+    /// Longest stretch where every cycle had the same length, in seconds
+    pub longest_periodic_seconds: f32,
+    pub probable_macro: bool,
+}
+
+/// Lengths of the cycles between consecutive hook presses, in ticks, split into chains that
+/// each start with the tick of their first press.
+pub fn chains(inputs: &[Inputs], tick_rate: i32) -> Vec<(i32, Vec<i32>)> {
+    let presses: Vec<i32> = inputs
+        .windows(2)
+        .filter(|w| !w[0].hook_state.pressed() && w[1].hook_state.pressed())
+        .map(|w| w[1].tick)
+        .collect();
+    let max_cycle = (MAX_CYCLE_SECONDS * tick_rate as f32) as i32;
+
+    let mut chains = Vec::new();
+    let mut chain = (0, Vec::new());
+    for w in presses.windows(2) {
+        let period = w[1] - w[0];
+        if period <= max_cycle {
+            if chain.1.is_empty() {
+                chain.0 = w[0];
+            }
+            chain.1.push(period);
+        } else if !chain.1.is_empty() {
+            chains.push(std::mem::take(&mut chain));
+        }
+    }
+    chains.push(chain);
+    chains.retain(|(_, c)| c.len() >= MIN_CHAIN_CYCLES);
+    chains
+}
+
+fn max_sustained_rate(chain: &[i32], tick_rate: i32) -> f32 {
+    let sustained = (SUSTAINED_SECONDS * tick_rate as f32) as i32;
+    let mut max: f32 = 0.0;
+    for start in 0..chain.len() {
+        let mut ticks = 0;
+        for (cycles, period) in chain[start..].iter().enumerate() {
+            ticks += period;
+            if ticks >= sustained {
+                max = max.max((cycles + 1) as f32 * tick_rate as f32 / ticks as f32);
+                break;
+            }
+        }
+    }
+    max
+}
+
+/// Longest stretch of cycles within the tolerance of the first one, in ticks.
+fn longest_periodic(chain: &[i32]) -> i32 {
+    let mut longest = 0;
+    let mut start = 0;
+    let mut ticks = 0;
+    for (i, period) in chain.iter().enumerate() {
+        if (period - chain[start]).abs() > PERIOD_TOLERANCE_TICKS {
+            start = i;
+            ticks = 0;
+        }
+        ticks += period;
+        longest = longest.max(ticks);
+    }
+    longest
+}
+
+pub fn calculate_rehook_stats(inputs: &[Inputs], tick_rate: i32) -> RehookStats {
+    let chains: Vec<Vec<i32>> = chains(inputs, tick_rate)
+        .into_iter()
+        .map(|(_, c)| c)
+        .collect();
+    let periods: Vec<f32> = chains.iter().flatten().map(|p| *p as f32).collect();
+    if periods.is_empty() {
+        return RehookStats::default();
+    }
+
+    let mean = periods.iter().sum::<f32>() / periods.len() as f32;
+    let variance = periods.iter().map(|p| (p - mean).powi(2)).sum::<f32>() / periods.len() as f32;
+    let longest_periodic_seconds = chains
+        .iter()
+        .map(|c| longest_periodic(c))
+        .max()
+        .unwrap_or_default() as f32
+        / tick_rate as f32;
+
+    RehookStats {
+        cycles: periods.len(),
+        chains: chains.len(),
+        frequency: tick_rate as f32 / mean,
+        period_variation: variance.sqrt() / mean,
+        max_sustained_rate: chains
+            .iter()
+            .map(|c| max_sustained_rate(c, tick_rate))
+            .fold(0.0, f32::max),
+        longest_periodic_seconds,
+        probable_macro: longest_periodic_seconds >= MACRO_SECONDS,
+    }
+}
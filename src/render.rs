@@ -0,0 +1,263 @@
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{bail, Context};
+use tiny_skia::{Color, FillRule, Paint, PathBuilder, Pixmap, Rect, Stroke, Transform};
+
+use crate::{
+    data::{Direction, HookState, Inputs},
+    interpolate,
+    map::{self, Map},
+};
+
+/// Radius of a tee in tiles
+const TEE_RADIUS: f32 = 0.45;
+
+pub struct RenderOptions {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub tick_rate: i32,
+    /// How many tiles are visible vertically
+    pub tiles_visible: f32,
+    pub ffmpeg: String,
+    pub output: PathBuf,
+    /// First and last tick of the video, the whole time the player is in the demo if none
+    pub ticks: Option<(i32, i32)>,
+    /// Move the tees smoothly between the snapshots instead of from one to the next
+    pub interpolate: bool,
+}
+
+fn paint(r: u8, g: u8, b: u8, a: u8) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    paint
+}
+
+fn tile_color(tile: u8) -> Option<Paint<'static>> {
+    match tile {
+        map::TILE_SOLID => Some(paint(120, 120, 120, 255)),
+        map::TILE_DEATH => Some(paint(200, 40, 40, 255)),
+        map::TILE_NOHOOK => Some(paint(60, 60, 60, 255)),
+        map::TILE_FREEZE => Some(paint(90, 140, 230, 160)),
+        map::TILE_START => Some(paint(80, 200, 80, 160)),
+        map::TILE_FINISH => Some(paint(230, 200, 60, 160)),
+        _ => None,
+    }
+}
+
+/// The game layer of the map scaled so its longer side is `size` pixels, as picture of its own
+/// or as background to draw on. Where several tiles fall on one pixel, the last one that isn't
+/// air is drawn, so thin walls don't disappear.
+pub fn thumbnail(map: &Map, size: u32) -> anyhow::Result<Pixmap> {
+    let (width, height) = (map.game.width, map.game.height);
+    let scale = size as f32 / width.max(height).max(1) as f32;
+    let mut pixmap = Pixmap::new(
+        ((width as f32 * scale).ceil() as u32).max(1),
+        ((height as f32 * scale).ceil() as u32).max(1),
+    )
+    .context("Invalid thumbnail size")?;
+    pixmap.fill(Color::from_rgba8(30, 30, 40, 255));
+    let side = scale.max(1.0);
+    for ty in 0..height as i32 {
+        for tx in 0..width as i32 {
+            let Some(mut paint) = tile_color(map.game.get(tx, ty)) else {
+                continue;
+            };
+            paint.anti_alias = false;
+            let (x, y) = ((tx as f32 * scale).floor(), (ty as f32 * scale).floor());
+            if let Some(rect) = Rect::from_xywh(x, y, side, side) {
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+        }
+    }
+    Ok(pixmap)
+}
+
+/// Returns the most recent sample at or before the tick, advancing the cursor.
+fn sample_at(
+    samples: &[Inputs],
+    cursor: &mut usize,
+    time: f64,
+    options: &RenderOptions,
+) -> Option<Inputs> {
+    while *cursor + 1 < samples.len() && samples[*cursor + 1].tick as f64 <= time {
+        *cursor += 1;
+    }
+    let sample = samples.get(*cursor).filter(|s| s.tick as f64 <= time)?;
+    if options.interpolate {
+        Some(interpolate::at(samples, *cursor, time, options.tick_rate).inputs)
+    } else {
+        Some(sample.clone())
+    }
+}
+
+fn circle(pixmap: &mut Pixmap, x: f32, y: f32, radius: f32, paint: &Paint) {
+    if let Some(path) = PathBuilder::from_circle(x, y, radius) {
+        pixmap.fill_path(&path, paint, FillRule::Winding, Transform::identity(), None);
+    }
+}
+
+fn draw_overlay(pixmap: &mut Pixmap, input: &Inputs) {
+    let size = pixmap.height() as f32 / 16.0;
+    let (x, y) = (size, pixmap.height() as f32 - size * 1.5);
+    let active = paint(255, 255, 255, 230);
+    let inactive = paint(255, 255, 255, 60);
+
+    let arrow = |pixmap: &mut Pixmap, x: f32, dir: f32, pressed: bool| {
+        let mut pb = PathBuilder::new();
+        pb.move_to(x + dir * size, y);
+        pb.line_to(x, y - size / 2.0);
+        pb.line_to(x, y + size / 2.0);
+        pb.close();
+        if let Some(path) = pb.finish() {
+            let paint = if pressed { &active } else { &inactive };
+            pixmap.fill_path(&path, paint, FillRule::Winding, Transform::identity(), None);
+        }
+    };
+    arrow(pixmap, x + size, -1.0, input.direction == Direction::Left);
+    arrow(
+        pixmap,
+        x + size * 1.5,
+        1.0,
+        input.direction == Direction::Right,
+    );
+
+    let hook = if input.hook_state.pressed() {
+        &active
+    } else {
+        &inactive
+    };
+    circle(pixmap, x + size * 3.5, y, size / 2.0, hook);
+}
+
+pub fn render(
+    map: Option<&Map>,
+    inputs: &HashMap<String, Vec<Inputs>>,
+    focus: &str,
+    options: &RenderOptions,
+) -> anyhow::Result<()> {
+    let Some(focused) = inputs.get(focus) else {
+        bail!("Player {focus} not found in demo");
+    };
+    let (Some(first), Some(last)) = (focused.first(), focused.last()) else {
+        bail!("No data for {focus}");
+    };
+    let (first, last) = match options.ticks {
+        Some((start, end)) => (start.max(first.tick), end.min(last.tick)),
+        None => (first.tick, last.tick),
+    };
+    if last < first {
+        bail!("{focus} isn't in the demo at the given ticks");
+    }
+
+    let mut ffmpeg = Command::new(&options.ffmpeg)
+        .args([
+            "-y",
+            "-loglevel",
+            "error",
+            "-f",
+            "rawvideo",
+            "-pix_fmt",
+            "rgba",
+        ])
+        .args(["-s", &format!("{}x{}", options.width, options.height)])
+        .args(["-r", &options.fps.to_string(), "-i", "-"])
+        .args(["-pix_fmt", "yuv420p"])
+        .arg(&options.output)
+        .stdin(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Couldn't start {}", options.ffmpeg))?;
+    let mut stdin = ffmpeg.stdin.take().context("Couldn't write to ffmpeg")?;
+
+    let mut pixmap = Pixmap::new(options.width, options.height).context("Invalid video size")?;
+    let scale = options.height as f32 / options.tiles_visible;
+    let mut cursors: HashMap<&str, usize> = HashMap::new();
+    let mut focus_cursor = 0;
+
+    let frames = ((last - first) as f32 / options.tick_rate as f32 * options.fps as f32) as usize;
+    for frame in 0..=frames {
+        let mut time = first as f64 + frame as f64 * options.tick_rate as f64 / options.fps as f64;
+        if !options.interpolate {
+            time = time.floor();
+        }
+        let Some(camera) = sample_at(focused, &mut focus_cursor, time, options) else {
+            continue;
+        };
+        let (camera_x, camera_y) = camera.pos.tiles();
+        let screen = |x: f32, y: f32| {
+            (
+                (x - camera_x) * scale + options.width as f32 / 2.0,
+                (y - camera_y) * scale + options.height as f32 / 2.0,
+            )
+        };
+
+        pixmap.fill(Color::from_rgba8(30, 30, 40, 255));
+
+        if let Some(map) = map {
+            let half_width = options.width as f32 / scale / 2.0;
+            let half_height = options.tiles_visible / 2.0;
+            for ty in (camera_y - half_height).floor() as i32..=(camera_y + half_height) as i32 {
+                for tx in (camera_x - half_width).floor() as i32..=(camera_x + half_width) as i32 {
+                    let Some(paint) = tile_color(map.game.get(tx, ty)) else {
+                        continue;
+                    };
+                    let (x, y) = screen(tx as f32, ty as f32);
+                    if let Some(rect) = Rect::from_xywh(x, y, scale, scale) {
+                        pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+                    }
+                }
+            }
+        }
+
+        for (name, samples) in inputs {
+            let cursor = cursors.entry(name).or_default();
+            let Some(sample) = sample_at(samples, cursor, time, options) else {
+                continue;
+            };
+            let (x, y) = sample.pos.tiles();
+            let (x, y) = screen(x, y);
+
+            if matches!(sample.hook_state, HookState::Flying | HookState::Grabbed) {
+                let (hx, hy) = sample.hook_pos.tiles();
+                let (hx, hy) = screen(hx, hy);
+                let mut pb = PathBuilder::new();
+                pb.move_to(x, y);
+                pb.line_to(hx, hy);
+                if let Some(path) = pb.finish() {
+                    let stroke = Stroke {
+                        width: scale / 8.0,
+                        ..Default::default()
+                    };
+                    let color = if sample.hook_state == HookState::Grabbed {
+                        paint(240, 240, 240, 255)
+                    } else {
+                        paint(160, 160, 160, 255)
+                    };
+                    pixmap.stroke_path(&path, &color, &stroke, Transform::identity(), None);
+                }
+            }
+
+            let color = if name == focus {
+                paint(250, 170, 60, 255)
+            } else {
+                paint(180, 180, 200, 200)
+            };
+            circle(&mut pixmap, x, y, TEE_RADIUS * scale, &color);
+        }
+
+        draw_overlay(&mut pixmap, &camera);
+        stdin.write_all(pixmap.data())?;
+    }
+
+    drop(stdin);
+    if !ffmpeg.wait()?.success() {
+        bail!("ffmpeg failed to encode the video");
+    }
+    Ok(())
+}
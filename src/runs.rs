@@ -0,0 +1,147 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{calculate_direction_change_stats, data::Inputs, timeline::Timeline};
+
+/// Jumping further than this between two samples is treated as a teleport, in tiles.
+const TELEPORT_DISTANCE: f32 = 10.0;
+/// How close to a known spawn point a teleport has to end to count as a respawn, in tiles.
+const SPAWN_RADIUS: f32 = 2.0;
+/// The server only resends a tee every three seconds if its movement is predictable, so
+/// anything above that means the tee wasn't in the snapshots in between.
+const ABSENCE_SECONDS: i32 = 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum RunEnd {
+    /// The score changed, which is how DDNet reports a new finish time
+    Finish,
+    /// The tee vanished or was teleported back to spawn
+    Death,
+    /// The demo ended before the run did
+    DemoEnd,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RunStats {
+    pub start_tick: i32,
+    /// Time since the start of the demo as mm:ss.ms
+    pub start_time: String,
+    pub end_tick: i32,
+    pub end_time: String,
+    pub duration: f32,
+    pub end: RunEnd,
+    pub distance: f32,
+    pub direction_changes: usize,
+    pub hook_changes: usize,
+    pub direction_change_rate_average: f32,
+    pub hook_state_change_rate_average: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Runs {
+    pub runs: Vec<RunStats>,
+    /// Index of the fastest finished run
+    pub best_run: Option<usize>,
+}
+
+/// Splits a player's timeline into runs, returning the sample range and how each run ended.
+/// Samples after a finish and before the next spawn are not part of any run.
+pub fn segment_runs(inputs: &[Inputs], tick_rate: i32) -> Vec<(std::ops::Range<usize>, RunEnd)> {
+    let Some(first) = inputs.first() else {
+        return Vec::new();
+    };
+
+    let mut spawns = vec![first];
+    let mut segments = Vec::new();
+    let mut start = Some(0);
+
+    for (i, w) in inputs.windows(2).enumerate() {
+        let (prev, cur) = (&w[0], &w[1]);
+        let absent = cur.tick - prev.tick > ABSENCE_SECONDS * tick_rate;
+        let respawned = prev.pos.distance(&cur.pos) > TELEPORT_DISTANCE
+            && spawns
+                .iter()
+                .any(|s| s.pos.distance(&cur.pos) < SPAWN_RADIUS);
+
+        if absent || respawned {
+            if let Some(start) = start {
+                segments.push((start..i + 1, RunEnd::Death));
+            }
+            if absent {
+                spawns.push(cur);
+            }
+            start = Some(i + 1);
+        } else if cur.score != prev.score {
+            if let Some(start) = start.take() {
+                segments.push((start..i + 2, RunEnd::Finish));
+            }
+        }
+    }
+
+    if let Some(start) = start {
+        segments.push((start..inputs.len(), RunEnd::DemoEnd));
+    }
+
+    segments
+}
+
+fn run_stats(inputs: &[Inputs], end: RunEnd, timeline: &Timeline) -> RunStats {
+    let mut direction_changes = Vec::new();
+    let mut hook_changes = Vec::new();
+    for w in inputs.windows(2) {
+        if w[0].direction != w[1].direction {
+            direction_changes.push(w[1].tick);
+        }
+        if w[0].hook_state.pressed() != w[1].hook_state.pressed() {
+            hook_changes.push(w[1].tick);
+        }
+    }
+    let direction_stats = calculate_direction_change_stats(direction_changes, timeline.tick_rate);
+    let hook_stats = calculate_direction_change_stats(hook_changes, timeline.tick_rate);
+
+    let start_tick = inputs.first().map(|i| i.tick).unwrap_or_default();
+    let end_tick = inputs.last().map(|i| i.tick).unwrap_or_default();
+    RunStats {
+        start_tick,
+        start_time: timeline.timestamp(start_tick).time,
+        end_tick,
+        end_time: timeline.timestamp(end_tick).time,
+        duration: (end_tick - start_tick) as f32 / timeline.tick_rate as f32,
+        end,
+        distance: inputs
+            .windows(2)
+            .map(|w| w[0].pos.distance(&w[1].pos))
+            .sum(),
+        direction_changes: direction_stats.overall_changes,
+        hook_changes: hook_stats.overall_changes,
+        direction_change_rate_average: direction_stats.average,
+        hook_state_change_rate_average: hook_stats.average,
+    }
+}
+
+pub fn calculate_runs(inputs: &[Inputs], timeline: &Timeline) -> Runs {
+    let runs: Vec<RunStats> = segment_runs(inputs, timeline.tick_rate)
+        .into_iter()
+        .map(|(range, end)| run_stats(&inputs[range], end, timeline))
+        .collect();
+
+    let best_run = runs
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.end == RunEnd::Finish)
+        .min_by_key(|(_, r)| r.end_tick - r.start_tick)
+        .map(|(i, _)| i);
+
+    Runs { runs, best_run }
+}
+
+/// Keeps only the samples of the fastest finished run.
+pub fn best_run(mut inputs: Vec<Inputs>, tick_rate: i32) -> Option<Vec<Inputs>> {
+    let (range, _) = segment_runs(&inputs, tick_rate)
+        .into_iter()
+        .filter(|(_, end)| *end == RunEnd::Finish)
+        .min_by_key(|(range, _)| inputs[range.end - 1].tick - inputs[range.start].tick)?;
+    inputs.truncate(range.end);
+    inputs.drain(..range.start);
+    Some(inputs)
+}
@@ -0,0 +1,526 @@
+use serde_json::{Map, Value};
+use stringlit::s;
+
+/// One step of a path, applied to the result of the step before.
+#[derive(Debug, Clone)]
+enum Step {
+    Field(String),
+    /// Negative indices count from the end
+    Index(i64),
+    /// `@`, the value itself
+    Current,
+    /// `[*]`, the rest of the path is applied to every element
+    Project,
+    /// `*`, the rest of the path is applied to every value. Unlike JMESPath the keys are kept,
+    /// so that stats stay next to the player they belong to.
+    Values,
+    /// `[]`, like `[*]` but lists in the list are merged into it first
+    Flatten,
+    /// `[?condition]`, like `[*]` for the elements the condition holds for
+    Filter(Condition),
+    /// `{name: expression, ...}`
+    Hash(Vec<(String, Select)>),
+    /// `[expression, ...]`
+    MultiList(Vec<Select>),
+    Literal(Value),
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+    Truthy(Select),
+    Compare(Select, &'static str, Select),
+    Not(Box<Condition>),
+    And(Box<Condition>, Box<Condition>),
+    Or(Box<Condition>, Box<Condition>),
+}
+
+/// A JMESPath-like expression to shape structured output with, `a | b` applies `b` to the
+/// result of `a`.
+#[derive(Debug, Clone)]
+pub struct Select(Vec<Vec<Step>>);
+
+/// Like JMESPath, empty and missing values are false.
+fn truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+        Value::Number(_) => true,
+    }
+}
+
+impl Condition {
+    fn holds(&self, value: &Value) -> bool {
+        match self {
+            Condition::Truthy(select) => truthy(&select.apply_to(value)),
+            Condition::Compare(left, op, right) => {
+                let (left, right) = (left.apply_to(value), right.apply_to(value));
+                match (*op, left.as_f64(), right.as_f64()) {
+                    // `1 == 1.0`, which json values only are as numbers
+                    ("==", Some(l), Some(r)) => l == r,
+                    ("!=", Some(l), Some(r)) => l != r,
+                    ("==", _, _) => left == right,
+                    ("!=", _, _) => left != right,
+                    ("<", Some(l), Some(r)) => l < r,
+                    ("<=", Some(l), Some(r)) => l <= r,
+                    (">", Some(l), Some(r)) => l > r,
+                    (">=", Some(l), Some(r)) => l >= r,
+                    _ => false,
+                }
+            }
+            Condition::Not(c) => !c.holds(value),
+            Condition::And(a, b) => a.holds(value) && b.holds(value),
+            Condition::Or(a, b) => a.holds(value) || b.holds(value),
+        }
+    }
+}
+
+fn apply(value: &Value, steps: &[Step]) -> Value {
+    let Some((step, rest)) = steps.split_first() else {
+        return value.clone();
+    };
+    // Projections drop what the rest of the path found nothing in
+    let project = |items: Vec<&Value>| {
+        Value::Array(
+            items
+                .into_iter()
+                .map(|item| apply(item, rest))
+                .filter(|item| !item.is_null())
+                .collect(),
+        )
+    };
+    match (step, value) {
+        (Step::Field(name), Value::Object(map)) => {
+            apply(map.get(name).unwrap_or(&Value::Null), rest)
+        }
+        (Step::Index(index), Value::Array(items)) => {
+            let index = if *index < 0 {
+                items.len() as i64 + index
+            } else {
+                *index
+            };
+            usize::try_from(index)
+                .ok()
+                .and_then(|i| items.get(i))
+                .map_or(Value::Null, |item| apply(item, rest))
+        }
+        (Step::Project, Value::Array(items)) => project(items.iter().collect()),
+        (Step::Values, Value::Object(map)) => Value::Object(
+            map.iter()
+                .map(|(key, item)| (key.clone(), apply(item, rest)))
+                .filter(|(_, item)| !item.is_null())
+                .collect(),
+        ),
+        (Step::Flatten, Value::Array(items)) => project(
+            items
+                .iter()
+                .flat_map(|item| match item {
+                    Value::Array(inner) => inner.iter().collect(),
+                    item => vec![item],
+                })
+                .collect(),
+        ),
+        (Step::Filter(condition), Value::Array(items)) => {
+            project(items.iter().filter(|item| condition.holds(item)).collect())
+        }
+        (Step::Hash(fields), value) if !value.is_null() => {
+            let map: Map<String, Value> = fields
+                .iter()
+                .map(|(key, select)| (key.clone(), select.apply_to(value)))
+                .collect();
+            apply(&Value::Object(map), rest)
+        }
+        (Step::MultiList(selects), value) if !value.is_null() => {
+            let items = selects.iter().map(|s| s.apply_to(value)).collect();
+            apply(&Value::Array(items), rest)
+        }
+        (Step::Literal(literal), _) => apply(literal, rest),
+        (Step::Current, value) => apply(value, rest),
+        _ => Value::Null,
+    }
+}
+
+impl Select {
+    fn apply_to(&self, value: &Value) -> Value {
+        self.0
+            .iter()
+            .fold(value.clone(), |value, steps| apply(&value, steps))
+    }
+
+    pub fn apply(&self, value: Value) -> Value {
+        self.apply_to(&value)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Ident(String),
+    Number(i64),
+    Literal(Value),
+    Symbol(&'static str),
+}
+
+/// Longer symbols first, so that `[*]` isn't read as `[` and `*`.
+const SYMBOLS: [&str; 24] = [
+    "[?", "[*]", "[]", "&&", "||", "==", "!=", "<=", ">=", "<", ">", "!", "|", ".", "*", "[", "]",
+    "{", "}", ",", ":", "@", "(", ")",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+    while let Some(c) = rest.chars().next() {
+        if let Some(symbol) = SYMBOLS.into_iter().find(|s| rest.starts_with(s)) {
+            tokens.push(Token::Symbol(symbol));
+            rest = &rest[symbol.len()..];
+        } else if matches!(c, '"' | '\'' | '`') {
+            let end = rest[1..]
+                .find(c)
+                .ok_or_else(|| format!("Unterminated {c} in: {rest}"))?;
+            let text = &rest[1..end + 1];
+            tokens.push(match c {
+                '"' => Token::Ident(text.to_string()),
+                '\'' => Token::Literal(Value::String(text.to_string())),
+                _ => Token::Literal(
+                    serde_json::from_str(text).map_err(|e| format!("Bad literal `{text}`: {e}"))?,
+                ),
+            });
+            rest = &rest[end + 2..];
+        } else if c.is_ascii_digit() || c == '-' {
+            let end = rest[1..]
+                .find(|c: char| !c.is_ascii_digit())
+                .map_or(rest.len(), |e| e + 1);
+            let number = rest[..end]
+                .parse()
+                .map_err(|_| format!("Not a number: {}", &rest[..end]))?;
+            tokens.push(Token::Number(number));
+            rest = &rest[end..];
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let end = rest
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            return Err(format!("Unexpected character '{c}' in: {rest}"));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, symbol: &'static str) -> bool {
+        let matches = self.peek() == Some(&Token::Symbol(symbol));
+        self.position += matches as usize;
+        matches
+    }
+
+    fn expect(&mut self, symbol: &'static str) -> Result<(), String> {
+        if self.eat(symbol) {
+            Ok(())
+        } else {
+            Err(format!("Expected {symbol}"))
+        }
+    }
+
+    fn next(&mut self) -> Result<Token, String> {
+        let token = self
+            .peek()
+            .cloned()
+            .ok_or_else(|| s!("Unexpected end of the expression"))?;
+        self.position += 1;
+        Ok(token)
+    }
+
+    fn select(&mut self) -> Result<Select, String> {
+        let mut pipes = vec![self.path()?];
+        while self.eat("|") {
+            pipes.push(self.path()?);
+        }
+        Ok(Select(pipes))
+    }
+
+    fn path(&mut self) -> Result<Vec<Step>, String> {
+        let mut steps = vec![self.first_step()?];
+        loop {
+            if self.eat(".") {
+                steps.push(self.dotted_step()?);
+            } else if let Some(step) = self.bracket_step()? {
+                steps.push(step);
+            } else {
+                return Ok(steps);
+            }
+        }
+    }
+
+    fn first_step(&mut self) -> Result<Step, String> {
+        if let Some(step) = self.bracket_step()? {
+            return Ok(step);
+        }
+        if self.eat("@") {
+            return Ok(Step::Current);
+        }
+        if let Some(Token::Literal(value)) = self.peek().cloned() {
+            self.position += 1;
+            return Ok(Step::Literal(value));
+        }
+        self.dotted_step()
+    }
+
+    /// What can follow a dot: a field, `*`, a hash or a list.
+    fn dotted_step(&mut self) -> Result<Step, String> {
+        if self.eat("*") {
+            return Ok(Step::Values);
+        }
+        if self.eat("{") {
+            let mut fields = Vec::new();
+            loop {
+                let Token::Ident(key) = self.next()? else {
+                    return Err(s!("Expected a key in {}"));
+                };
+                self.expect(":")?;
+                fields.push((key, self.select()?));
+                if !self.eat(",") {
+                    break;
+                }
+            }
+            self.expect("}")?;
+            return Ok(Step::Hash(fields));
+        }
+        if self.eat("[") {
+            return self.list();
+        }
+        match self.next()? {
+            Token::Ident(name) => Ok(Step::Field(name)),
+            token => Err(format!("Unexpected {token:?}")),
+        }
+    }
+
+    fn list(&mut self) -> Result<Step, String> {
+        let mut selects = vec![self.select()?];
+        while self.eat(",") {
+            selects.push(self.select()?);
+        }
+        self.expect("]")?;
+        Ok(Step::MultiList(selects))
+    }
+
+    fn bracket_step(&mut self) -> Result<Option<Step>, String> {
+        if self.eat("[*]") {
+            return Ok(Some(Step::Project));
+        }
+        if self.eat("[]") {
+            return Ok(Some(Step::Flatten));
+        }
+        if self.eat("[?") {
+            let condition = self.or()?;
+            self.expect("]")?;
+            return Ok(Some(Step::Filter(condition)));
+        }
+        if !self.eat("[") {
+            return Ok(None);
+        }
+        if let Some(Token::Number(index)) = self.peek().cloned() {
+            self.position += 1;
+            self.expect("]")?;
+            return Ok(Some(Step::Index(index)));
+        }
+        self.list().map(Some)
+    }
+
+    fn or(&mut self) -> Result<Condition, String> {
+        let mut condition = self.and()?;
+        while self.eat("||") {
+            condition = Condition::Or(Box::new(condition), Box::new(self.and()?));
+        }
+        Ok(condition)
+    }
+
+    fn and(&mut self) -> Result<Condition, String> {
+        let mut condition = self.not()?;
+        while self.eat("&&") {
+            condition = Condition::And(Box::new(condition), Box::new(self.not()?));
+        }
+        Ok(condition)
+    }
+
+    fn not(&mut self) -> Result<Condition, String> {
+        if self.eat("!") {
+            return Ok(Condition::Not(Box::new(self.not()?)));
+        }
+        if self.eat("(") {
+            let condition = self.or()?;
+            self.expect(")")?;
+            return Ok(condition);
+        }
+        let left = self.operand()?;
+        let op = ["==", "!=", "<=", ">=", "<", ">"]
+            .into_iter()
+            .find(|op| self.eat(op));
+        Ok(match op {
+            Some(op) => Condition::Compare(left, op, self.operand()?),
+            None => Condition::Truthy(left),
+        })
+    }
+
+    /// A path, or a number which JMESPath would want in backticks.
+    fn operand(&mut self) -> Result<Select, String> {
+        if let Some(Token::Number(number)) = self.peek().cloned() {
+            self.position += 1;
+            return Ok(Select(vec![vec![Step::Literal(number.into())]]));
+        }
+        Ok(Select(vec![self.path()?]))
+    }
+}
+
+/// Parses a subset of JMESPath. Supported are fields, `"quoted fields"`, indices, `[*]`,
+/// `*`, `[]`, filters with `[?...]`, `{name: ...}`, `[..., ...]`, `'raw strings'`,
+/// `` `json` `` literals and pipes. Functions, slices and `&expression` are not, `*` keeps
+/// the keys and numbers in filters don't need backticks.
+pub fn parse(expression: &str) -> Result<Select, String> {
+    let mut parser = Parser {
+        tokens: tokenize(expression)?,
+        position: 0,
+    };
+    let select = parser.select()?;
+    if let Some(token) = parser.peek() {
+        return Err(format!("Unexpected {token:?} after the expression"));
+    }
+    Ok(select)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn select(expression: &str, value: Value) -> Value {
+        parse(expression).unwrap().apply(value)
+    }
+
+    fn error(expression: &str) -> String {
+        parse(expression).unwrap_err()
+    }
+
+    fn players() -> Value {
+        json!({
+            "a": {"median": 2.0, "runs": [{"time": 30}, {"time": 25}]},
+            "b": {"median": 5.0, "runs": [{"time": 40}]},
+        })
+    }
+
+    #[test]
+    fn follows_paths() {
+        assert_eq!(select("a.median", players()), json!(2.0));
+        assert_eq!(select("a.runs[0].time", players()), json!(30));
+        assert_eq!(select("a.runs[-1].time", players()), json!(25));
+        assert_eq!(select("a.runs[5]", players()), Value::Null);
+        assert_eq!(select("missing.median", players()), Value::Null);
+        assert_eq!(select(r#""a".median"#, players()), json!(2.0));
+        assert_eq!(select("@.b.median", players()), json!(5.0));
+    }
+
+    #[test]
+    fn projects_and_keeps_keys() {
+        assert_eq!(select("*.median", players()), json!({"a": 2.0, "b": 5.0}));
+        assert_eq!(select("a.runs[*].time", players()), json!([30, 25]));
+        assert_eq!(select("*.runs[].time | b", players()), json!([40]));
+        assert_eq!(
+            select("`[[1, 2], 3, [4]]`[]", Value::Null),
+            json!([1, 2, 3, 4])
+        );
+        assert_eq!(select("a.runs | [].time", players()), json!([30, 25]));
+    }
+
+    #[test]
+    fn filters() {
+        assert_eq!(select("a.runs[?time < `28`].time", players()), json!([25]));
+        assert_eq!(
+            select("a.runs[?time >= 25 && time != 30].time", players()),
+            json!([25])
+        );
+        assert_eq!(
+            select("a.runs[?!(time == 25)].time", players()),
+            json!([30])
+        );
+        assert_eq!(
+            select("a.runs[?time == 1 || time == 30].time", players()),
+            json!([30])
+        );
+        assert_eq!(select("a.runs[?missing].time", players()), json!([]));
+        assert_eq!(
+            select("a.runs[?time == `25.0`].time", players()),
+            json!([25])
+        );
+        assert_eq!(
+            select("a.runs[?time != `30.0`].time", players()),
+            json!([25])
+        );
+        assert_eq!(
+            select("`[\"a\", \"b\", 1]`[?@ == 'b']", Value::Null),
+            json!(["b"])
+        );
+    }
+
+    #[test]
+    fn builds_hashes_lists_and_literals() {
+        assert_eq!(
+            select("a.{m: median, first: runs[0].time}", players()),
+            json!({"m": 2.0, "first": 30})
+        );
+        assert_eq!(select("a.[median, 'x']", players()), json!([2.0, "x"]));
+        assert_eq!(select("`{\"n\": 1}`.n", Value::Null), json!(1));
+        assert_eq!(select("*.median | b", players()), json!(5.0));
+    }
+
+    #[test]
+    fn rejects_bad_expressions() {
+        assert_eq!(error(""), "Unexpected end of the expression");
+        assert_eq!(error("a.runs[0"), "Expected ]");
+        assert_eq!(error("a.{median}"), "Expected :");
+        assert_eq!(error("a.{1: median}"), "Expected a key in {}");
+        assert_eq!(error("a.'median"), "Unterminated ' in: 'median");
+        assert_eq!(
+            error("a | `{x`").split(':').next(),
+            Some("Bad literal `{x`")
+        );
+        assert_eq!(error("a.#"), "Unexpected character '#' in: #");
+        assert_eq!(error("a.]"), r#"Unexpected Symbol("]")"#);
+        assert_eq!(
+            error("a b"),
+            r#"Unexpected Ident("b") after the expression"#
+        );
+    }
+
+    #[test]
+    fn only_works_with_structured_formats() {
+        let args = |format: &str| {
+            crate::Args::try_parse_checked([
+                "demo_analyzer",
+                "--select",
+                "*.median",
+                "analyze",
+                "--format",
+                format,
+                "test.demo",
+            ])
+        };
+        let error = args("plain").err().unwrap();
+        assert_eq!(error.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(args("json").is_ok());
+        assert!(args("yaml").is_ok());
+    }
+}
@@ -0,0 +1,338 @@
+use std::{
+    collections::BTreeMap,
+    io::{Cursor, Read},
+    panic::{catch_unwind, AssertUnwindSafe},
+    path::Path,
+    sync::Mutex,
+    time::Instant,
+};
+
+use anyhow::Context;
+use stringlit::s;
+use tiny_http::{Header, Method, Request, Response, Server};
+use twsnap::compat::ddnet::DemoReader;
+
+use crate::{
+    jobs::JobStore,
+    names::NameFilter,
+    summary::{self, Summary},
+};
+
+/// The demo reader needs more stack than the default for spawned threads.
+pub const WORKER_STACK_SIZE: usize = 32 * 1024 * 1024;
+
+/// Uploads larger than this are refused, demos of even long sessions stay far below.
+const MAX_DEMO_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Upper bounds of the processing time histogram, in seconds.
+const LATENCY_BUCKETS: [f64; 9] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0];
+
+#[derive(Default)]
+struct Counters {
+    demos_analyzed: u64,
+    demos_failed: u64,
+    players_analyzed: u64,
+    players_flagged: u64,
+    flags: BTreeMap<&'static str, u64>,
+    latency_buckets: [u64; LATENCY_BUCKETS.len()],
+    latency_sum: f64,
+    latency_count: u64,
+}
+
+#[derive(Default)]
+pub struct Metrics(Mutex<Counters>);
+
+impl Metrics {
+    fn record(&self, result: Option<&Summary>, seconds: f64) {
+        let mut c = self.0.lock().unwrap();
+        for (bucket, bound) in c.latency_buckets.iter_mut().zip(LATENCY_BUCKETS) {
+            if seconds <= bound {
+                *bucket += 1;
+            }
+        }
+        c.latency_sum += seconds;
+        c.latency_count += 1;
+
+        let Some(summary) = result else {
+            c.demos_failed += 1;
+            return;
+        };
+        c.demos_analyzed += 1;
+        for player in summary.players.values() {
+            c.players_analyzed += 1;
+            if !player.flags.is_empty() {
+                c.players_flagged += 1;
+            }
+            for flag in &player.flags {
+                *c.flags.entry(flag).or_default() += 1;
+            }
+        }
+    }
+
+    /// Renders the metrics in the OpenMetrics text format.
+    pub fn render(&self) -> String {
+        let c = self.0.lock().unwrap();
+        let mut vec = Vec::new();
+        let mut counter = |name: &str, help: &str, value: u64| {
+            vec.push(format!("# TYPE tw_demo_analyzer_{name} counter"));
+            vec.push(format!("# HELP tw_demo_analyzer_{name} {help}"));
+            vec.push(format!("tw_demo_analyzer_{name}_total {value}"));
+        };
+        counter(
+            "demos_analyzed",
+            "Demos analyzed successfully",
+            c.demos_analyzed,
+        );
+        counter(
+            "demos_failed",
+            "Demos that couldn't be read",
+            c.demos_failed,
+        );
+        counter("players_analyzed", "Players analyzed", c.players_analyzed);
+        counter(
+            "players_flagged",
+            "Players with at least one flag",
+            c.players_flagged,
+        );
+
+        vec.push(s!("# TYPE tw_demo_analyzer_flags counter"));
+        vec.push(s!("# HELP tw_demo_analyzer_flags Players flagged, by flag"));
+        for flag in summary::FLAGS {
+            let value = c.flags.get(flag).copied().unwrap_or_default();
+            vec.push(format!(
+                "tw_demo_analyzer_flags_total{{flag=\"{flag}\"}} {value}"
+            ));
+        }
+
+        vec.push(s!("# TYPE tw_demo_analyzer_processing_seconds histogram"));
+        vec.push(s!(
+            "# HELP tw_demo_analyzer_processing_seconds Time spent analyzing a demo"
+        ));
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(c.latency_buckets) {
+            vec.push(format!(
+                "tw_demo_analyzer_processing_seconds_bucket{{le=\"{bound}\"}} {count}"
+            ));
+        }
+        vec.push(format!(
+            "tw_demo_analyzer_processing_seconds_bucket{{le=\"+Inf\"}} {}",
+            c.latency_count
+        ));
+        vec.push(format!(
+            "tw_demo_analyzer_processing_seconds_sum {}",
+            c.latency_sum
+        ));
+        vec.push(format!(
+            "tw_demo_analyzer_processing_seconds_count {}",
+            c.latency_count
+        ));
+        vec.push(s!("# EOF"));
+        vec.push(s!(""));
+        vec.join("\n")
+    }
+}
+
+/// Analyzes a demo that was uploaded as request body.
+pub fn analyze(
+    demo: Vec<u8>,
+    name: &str,
+    filter: &str,
+    metrics: &Metrics,
+) -> anyhow::Result<Summary> {
+    let start = Instant::now();
+    // A broken demo must not take the worker down with it
+    let result = catch_unwind(AssertUnwindSafe(|| {
+        DemoReader::new(Cursor::new(demo))
+            .context("Couldn't read demo")
+            .map(|reader| {
+                let info = summary::DemoInfo::new(name.to_string(), &reader);
+                let (stats, _) = crate::analyze(reader, &NameFilter::new(filter, false), None);
+                summary::summarize(info, &stats)
+            })
+    }))
+    .unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|m| m.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        Err(anyhow::anyhow!("Analysis crashed: {message}"))
+    });
+    metrics.record(result.as_ref().ok(), start.elapsed().as_secs_f64());
+    result
+}
+
+fn percent_decode(value: &str) -> String {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut rest = value.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        match b {
+            b'+' => bytes.push(b' '),
+            b'%' if rest.len() >= 2 => {
+                match u8::from_str_radix(std::str::from_utf8(&rest[..2]).unwrap_or(""), 16) {
+                    Ok(decoded) => {
+                        bytes.push(decoded);
+                        rest = &rest[2..];
+                    }
+                    Err(_) => bytes.push(b),
+                }
+            }
+            _ => bytes.push(b),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn query(url: &str, key: &str) -> Option<String> {
+    let (_, query) = url.split_once('?')?;
+    query
+        .split('&')
+        .filter_map(|p| p.split_once('='))
+        .find(|(k, _)| *k == key)
+        .map(|(_, v)| percent_decode(v))
+}
+
+fn json_response(status: u16, body: String) -> Response<Cursor<Vec<u8>>> {
+    Response::from_string(body)
+        .with_status_code(status)
+        .with_header(Header::from_bytes("Content-Type", "application/json").unwrap())
+}
+
+struct State {
+    metrics: Metrics,
+    jobs: JobStore,
+}
+
+fn error_response(status: u16, error: anyhow::Error) -> Response<Cursor<Vec<u8>>> {
+    json_response(
+        status,
+        serde_json::json!({ "error": format!("{error:#}") }).to_string(),
+    )
+}
+
+/// The demo sent as body, `None` if it is larger than `MAX_DEMO_BYTES`.
+fn read_demo(request: &mut Request) -> std::io::Result<Option<Vec<u8>>> {
+    if request
+        .body_length()
+        .is_some_and(|length| length as u64 > MAX_DEMO_BYTES)
+    {
+        return Ok(None);
+    }
+    let mut demo = Vec::new();
+    request
+        .as_reader()
+        .take(MAX_DEMO_BYTES + 1)
+        .read_to_end(&mut demo)?;
+    Ok((demo.len() as u64 <= MAX_DEMO_BYTES).then_some(demo))
+}
+
+fn too_large() -> Response<Cursor<Vec<u8>>> {
+    error_response(
+        413,
+        anyhow::anyhow!("Demos can be at most {} MiB", MAX_DEMO_BYTES / 1024 / 1024),
+    )
+}
+
+fn handle(mut request: Request, state: &State) -> std::io::Result<()> {
+    let path = request
+        .url()
+        .split('?')
+        .next()
+        .unwrap_or_default()
+        .to_string();
+    match (request.method(), path.as_str()) {
+        (Method::Get, "/metrics") => {
+            let content_type = "application/openmetrics-text; version=1.0.0; charset=utf-8";
+            let response = Response::from_string(state.metrics.render())
+                .with_header(Header::from_bytes("Content-Type", content_type).unwrap());
+            request.respond(response)
+        }
+        (Method::Post, "/analyze") => {
+            let name = query(request.url(), "name").unwrap_or_else(|| s!("upload.demo"));
+            let filter = query(request.url(), "filter").unwrap_or_default();
+            let Some(demo) = read_demo(&mut request)? else {
+                return request.respond(too_large());
+            };
+            let response = match analyze(demo, &name, &filter, &state.metrics) {
+                Ok(summary) => json_response(200, serde_json::to_string(&summary).unwrap()),
+                Err(e) => error_response(400, e),
+            };
+            request.respond(response)
+        }
+        (Method::Post, "/jobs") => {
+            let name = query(request.url(), "name").unwrap_or_else(|| s!("upload.demo"));
+            let filter = query(request.url(), "filter").unwrap_or_default();
+            let Some(demo) = read_demo(&mut request)? else {
+                return request.respond(too_large());
+            };
+            let response = match state.jobs.submit(&name, &filter, &demo) {
+                Ok(id) => json_response(202, serde_json::json!({ "id": id }).to_string()),
+                Err(e) => error_response(500, e),
+            };
+            request.respond(response)
+        }
+        (Method::Get, path) if path.starts_with("/jobs/") => {
+            let response = match path["/jobs/".len()..].parse() {
+                Ok(id) => match state.jobs.get(id) {
+                    Ok(Some(job)) => json_response(200, serde_json::to_string(&job).unwrap()),
+                    Ok(None) => error_response(404, anyhow::anyhow!("Unknown job {id}")),
+                    Err(e) => error_response(500, e),
+                },
+                Err(_) => error_response(400, anyhow::anyhow!("Invalid job id")),
+            };
+            request.respond(response)
+        }
+        _ => request.respond(Response::from_string("Not found").with_status_code(404)),
+    }
+}
+
+fn run_jobs(state: &State) -> anyhow::Result<()> {
+    loop {
+        let job = state.jobs.take()?;
+        let result = analyze(job.demo, &job.name, &job.filter, &state.metrics)
+            .map(|summary| serde_json::to_string(&summary).unwrap())
+            .map_err(|e| format!("{e:#}"));
+        state.jobs.finish(job.id, result)?;
+    }
+}
+
+/// Serves `POST /analyze`, which takes a demo as body and returns its summary,
+/// `POST /jobs` and `GET /jobs/<id>` to do the same in the background, and
+/// `GET /metrics` for monitoring.
+pub fn serve(
+    address: &str,
+    workers: usize,
+    job_workers: usize,
+    database: &Path,
+) -> anyhow::Result<()> {
+    let state = State {
+        metrics: Metrics::default(),
+        jobs: JobStore::open(database)?,
+    };
+    let server =
+        Server::http(address).map_err(|e| anyhow::anyhow!("Couldn't listen on {address}: {e}"))?;
+    crate::status::note!("Listening on http://{address}");
+    std::thread::scope(|scope| {
+        for _ in 0..workers.max(1) {
+            std::thread::Builder::new()
+                .stack_size(WORKER_STACK_SIZE)
+                .spawn_scoped(scope, || {
+                    for request in server.incoming_requests() {
+                        if let Err(e) = handle(request, &state) {
+                            eprintln!("Couldn't answer request: {e}");
+                        }
+                    }
+                })?;
+        }
+        for _ in 0..job_workers.max(1) {
+            std::thread::Builder::new()
+                .stack_size(WORKER_STACK_SIZE)
+                .spawn_scoped(scope, || {
+                    if let Err(e) = run_jobs(&state) {
+                        eprintln!("Job worker stopped: {e:#}");
+                    }
+                })?;
+        }
+        Ok(())
+    })
+}
@@ -0,0 +1,88 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::bookmarks::Bookmark;
+
+/// One thing the reviewer did. Everything but notes stores the new state, not the change,
+/// so stepping through a session only has to apply the steps in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Step {
+    /// Switched to a tab, `None` is the compare view
+    Tab {
+        tab: Option<usize>,
+    },
+    Player {
+        tab: usize,
+        player: String,
+    },
+    /// Zoomed or panned to this range of ticks
+    View {
+        tab: usize,
+        from: f64,
+        to: f64,
+    },
+    Bookmarks {
+        tab: usize,
+        bookmarks: Vec<Bookmark>,
+    },
+    Note {
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    /// Seconds since the recording started
+    pub seconds: f32,
+    #[serde(flatten)]
+    pub step: Step,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Session {
+    /// Titles of the tabs the session was recorded with
+    pub demos: Vec<String>,
+    pub entries: Vec<Entry>,
+}
+
+pub fn load(path: &Path) -> anyhow::Result<Session> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+/// Writes the session after every step, so nothing is lost when the visualizer is closed.
+pub struct Recorder {
+    path: PathBuf,
+    session: Session,
+    started: Instant,
+}
+
+impl Recorder {
+    pub fn new(path: PathBuf, demos: Vec<String>) -> Self {
+        Self {
+            path,
+            session: Session {
+                demos,
+                entries: Vec::new(),
+            },
+            started: Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn record(&mut self, step: Step) -> anyhow::Result<()> {
+        self.session.entries.push(Entry {
+            seconds: self.started.elapsed().as_secs_f32(),
+            step,
+        });
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.session)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,35 @@
+/// Resamples taken for a bootstrap confidence interval.
+const RESAMPLES: usize = 1000;
+/// Share of the resampled means outside the interval, on each side.
+const TAIL: f32 = 0.025;
+
+/// xorshift64, seeded with a constant so the same demo always gets the same interval.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+/// 95% bootstrap confidence interval of the mean of the values.
+pub fn mean_interval(values: &[usize]) -> (f32, f32) {
+    if values.is_empty() {
+        return (0.0, 0.0);
+    }
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let mut means: Vec<f32> = (0..RESAMPLES)
+        .map(|_| {
+            let sum: usize = (0..values.len())
+                .map(|_| values[rng.below(values.len())])
+                .sum();
+            sum as f32 / values.len() as f32
+        })
+        .collect();
+    means.sort_by(f32::total_cmp);
+    let at = |q: f32| means[((RESAMPLES - 1) as f32 * q).round() as usize];
+    (at(TAIL), at(1.0 - TAIL))
+}
@@ -0,0 +1,202 @@
+use std::f32::consts::TAU;
+
+use anyhow::{bail, Context};
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::Serialize;
+use tiny_skia::{Paint, Pixmap, Rect, Transform};
+
+use crate::data::{Direction, Inputs};
+
+/// Ticks per segment the spectrum is taken over, about five seconds at 50 ticks per second.
+const SEGMENT: usize = 256;
+/// Segments overlap by half.
+const HOP: usize = SEGMENT / 2;
+const PEAKS: usize = 3;
+/// Pixels per segment and per frequency bin in the spectrogram.
+const CELL: u32 = 3;
+/// The spectrogram shows powers down to this many decibels below the strongest one.
+const DYNAMIC_RANGE: f32 = 40.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Signal {
+    /// Left as -1, none as 0 and right as 1
+    Direction,
+    /// 1 while the hook is pressed, 0 otherwise
+    Hook,
+}
+
+impl Signal {
+    fn value(self, sample: &Inputs) -> f32 {
+        match self {
+            Signal::Direction => match sample.direction {
+                Direction::Left => -1.0,
+                Direction::None => 0.0,
+                Direction::Right => 1.0,
+            },
+            Signal::Hook => f32::from(u8::from(sample.hook_state.pressed())),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Peak {
+    /// In Hz
+    pub frequency: f32,
+    /// Fraction of the power of the whole spectrum in this frequency
+    pub power_share: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Spectrum {
+    /// Segments of 256 ticks the spectrum is averaged over, none if the demo is shorter
+    pub segments: usize,
+    /// The strongest local maxima, strongest first
+    pub peaks: Vec<Peak>,
+    /// Geometric over arithmetic mean of the power, near 1 for noise and near 0 for a few
+    /// sharp peaks
+    pub flatness: f32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct SpectralStats {
+    pub direction: Spectrum,
+    pub hook: Spectrum,
+}
+
+/// The signal once per tick, ticks without a sample keep the value of the one before.
+fn resample(inputs: &[Inputs], signal: Signal) -> Vec<f32> {
+    let (Some(first), Some(last)) = (inputs.first(), inputs.last()) else {
+        return Vec::new();
+    };
+    let mut values = Vec::with_capacity((last.tick - first.tick + 1).max(0) as usize);
+    let mut samples = inputs.iter().peekable();
+    let mut value = 0.0;
+    for tick in first.tick..=last.tick {
+        while let Some(sample) = samples.next_if(|s| s.tick <= tick) {
+            value = signal.value(sample);
+        }
+        values.push(value);
+    }
+    values
+}
+
+/// The power of the frequencies above zero up to half the tick rate, for every segment of
+/// the signal. Bin `k` is the frequency `(k + 1) * tick_rate / 256`.
+pub fn spectrogram(inputs: &[Inputs], signal: Signal) -> Vec<Vec<f32>> {
+    let values = resample(inputs, signal);
+    if values.len() < SEGMENT {
+        return Vec::new();
+    }
+    let angle = |i: usize| TAU * i as f32 / SEGMENT as f32;
+    let (cos, sin): (Vec<f32>, Vec<f32>) = (0..SEGMENT)
+        .map(|i| (angle(i).cos(), angle(i).sin()))
+        .unzip();
+    // Hann window against the leakage of the segment edges
+    let window: Vec<f32> = cos.iter().map(|c| 0.5 - 0.5 * c).collect();
+    (0..=values.len() - SEGMENT)
+        .step_by(HOP)
+        .map(|start| {
+            let segment = &values[start..start + SEGMENT];
+            let mean = segment.iter().sum::<f32>() / SEGMENT as f32;
+            let segment: Vec<f32> = segment
+                .iter()
+                .zip(&window)
+                .map(|(v, w)| (v - mean) * w)
+                .collect();
+            (1..=SEGMENT / 2)
+                .map(|k| {
+                    let (mut re, mut im) = (0.0, 0.0);
+                    for (n, v) in segment.iter().enumerate() {
+                        let i = k * n % SEGMENT;
+                        re += v * cos[i];
+                        im -= v * sin[i];
+                    }
+                    re * re + im * im
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn spectrum(inputs: &[Inputs], signal: Signal, tick_rate: i32) -> Spectrum {
+    let segments = spectrogram(inputs, signal);
+    let Some(bins) = segments.first().map(Vec::len) else {
+        return Spectrum::default();
+    };
+    let power: Vec<f32> = (0..bins)
+        .map(|k| segments.iter().map(|s| s[k]).sum::<f32>() / segments.len() as f32)
+        .collect();
+    let total: f32 = power.iter().sum();
+    if total <= 0.0 {
+        return Spectrum {
+            segments: segments.len(),
+            ..Spectrum::default()
+        };
+    }
+    let mean = total / bins as f32;
+    let log_mean = power
+        .iter()
+        .map(|p| p.max(f32::MIN_POSITIVE).ln())
+        .sum::<f32>()
+        / bins as f32;
+
+    let mut peaks: Vec<usize> = (0..bins)
+        .filter(|&k| k == 0 || power[k] >= power[k - 1])
+        .filter(|&k| k + 1 == bins || power[k] >= power[k + 1])
+        .collect();
+    peaks.sort_by(|a, b| power[*b].total_cmp(&power[*a]));
+    Spectrum {
+        segments: segments.len(),
+        peaks: peaks
+            .into_iter()
+            .take(PEAKS)
+            .map(|k| Peak {
+                frequency: (k + 1) as f32 * tick_rate as f32 / SEGMENT as f32,
+                power_share: power[k] / total,
+            })
+            .collect(),
+        flatness: log_mean.exp() / mean,
+    }
+}
+
+/// The spectra of the direction and hook inputs, macros repeat them at a fixed rate which
+/// shows up as sharp peaks.
+pub fn calculate_spectral_stats(inputs: &[Inputs], tick_rate: i32) -> SpectralStats {
+    SpectralStats {
+        direction: spectrum(inputs, Signal::Direction, tick_rate),
+        hook: spectrum(inputs, Signal::Hook, tick_rate),
+    }
+}
+
+/// The spectrogram as PNG, time from left to right and frequency from the bottom up, brighter
+/// is stronger.
+pub fn render(segments: &[Vec<f32>], player: &str) -> anyhow::Result<Vec<u8>> {
+    let Some(bins) = segments.first().map(Vec::len) else {
+        bail!("The inputs of {player} are shorter than a segment of {SEGMENT} ticks");
+    };
+    let max = segments
+        .iter()
+        .flatten()
+        .copied()
+        .fold(f32::MIN_POSITIVE, f32::max);
+    let mut pixmap = Pixmap::new(segments.len() as u32 * CELL, bins as u32 * CELL)
+        .context("Invalid image size")?;
+    pixmap.fill(tiny_skia::Color::BLACK);
+    for (x, segment) in segments.iter().enumerate() {
+        for (k, power) in segment.iter().enumerate() {
+            let decibels = 10.0 * (power.max(f32::MIN_POSITIVE) / max).log10();
+            let v = (1.0 + decibels / DYNAMIC_RANGE).clamp(0.0, 1.0);
+            let channel = |offset: f32| ((v * 3.0 - offset).clamp(0.0, 1.0) * 255.0) as u8;
+            let mut paint = Paint::default();
+            paint.set_color_rgba8(channel(0.0), channel(1.0), channel(2.0), 255);
+            let y = (bins - 1 - k) as u32 * CELL;
+            if let Some(rect) =
+                Rect::from_xywh((x as u32 * CELL) as f32, y as f32, CELL as f32, CELL as f32)
+            {
+                pixmap.fill_rect(rect, &paint, Transform::identity(), None);
+            }
+        }
+    }
+    Ok(pixmap.encode_png()?)
+}
@@ -0,0 +1,92 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+/// Exit codes scripts can branch on, listed in the help.
+pub const IO_ERROR: i32 = 1;
+pub const CORRUPT_DEMO: i32 = 2;
+pub const NO_PLAYERS: i32 = 3;
+pub const THRESHOLDS_EXCEEDED: i32 = 4;
+pub const USAGE: i32 = 5;
+
+pub const EXIT_CODES: &str = "Exit codes:
+  0  Success
+  1  IO or other error
+  2  The demo couldn't be read, it is corrupt or not a demo
+  3  No players matched the filter
+  4  A player exceeded the alert thresholds of live
+  5  The arguments are wrong, or don't fit the demo like a player that isn't in it";
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Prints to stderr unless `--quiet` is set, for messages a script doesn't need.
+macro_rules! note {
+    ($($arg:tt)*) => {
+        if !$crate::status::quiet() {
+            eprintln!($($arg)*);
+        }
+    };
+}
+pub(crate) use note;
+
+#[derive(Debug)]
+pub struct NoPlayersMatched;
+
+impl fmt::Display for NoPlayersMatched {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "No players matched the filter")
+    }
+}
+
+impl std::error::Error for NoPlayersMatched {}
+
+/// Arguments that are wrong or don't fit the demo, like a missing `--out`.
+#[derive(Debug)]
+pub struct Usage(pub String);
+
+impl fmt::Display for Usage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::error::Error for Usage {}
+
+pub fn code(error: &anyhow::Error) -> i32 {
+    if error.is::<Usage>() {
+        USAGE
+    } else if error.is::<NoPlayersMatched>() {
+        NO_PLAYERS
+    } else if error.chain().any(|e| {
+        e.is::<twsnap::compat::ddnet::ReadError>() || e.is::<libtw2_demo::ddnet::ReadError>()
+    }) {
+        CORRUPT_DEMO
+    } else {
+        IO_ERROR
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn usage_errors_have_their_own_code() {
+        assert_eq!(
+            code(&Usage(String::from("Rendering requires --out")).into()),
+            USAGE
+        );
+        assert_eq!(code(&NoPlayersMatched.into()), NO_PLAYERS);
+        assert_eq!(code(&anyhow::anyhow!("No samples to compare")), IO_ERROR);
+        assert!(EXIT_CODES.contains(&format!("  {USAGE}  ")));
+    }
+}
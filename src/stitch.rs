@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+
+use crate::{
+    data::Inputs,
+    timeline::{Pause, Timeline, MIN_PAUSE_TICKS},
+};
+
+/// A demo that is part of a stitched session.
+pub struct Part {
+    /// Recording time from the demo header, in seconds since the epoch
+    pub recorded: Option<i64>,
+    pub inputs: HashMap<String, Vec<Inputs>>,
+    pub timeline: Timeline,
+}
+
+/// Joins demos of one session into a single timeline, in the order given. Every demo is moved
+/// to where it was recorded relative to the first one. Demos without a usable recording time,
+/// or that would overlap the previous one, continue right after the previous demo instead.
+/// The time between two demos becomes a pause of the timeline.
+pub fn stitch(parts: Vec<Part>) -> (HashMap<String, Vec<Inputs>>, Timeline) {
+    let mut parts = parts.into_iter();
+    let Some(first) = parts.next() else {
+        return Default::default();
+    };
+    let mut timeline = first.timeline;
+    let start_tick = timeline.start_tick;
+    let mut inputs = first.inputs;
+    let last_tick = |inputs: &HashMap<String, Vec<Inputs>>| {
+        inputs
+            .values()
+            .filter_map(|i| i.last())
+            .map(|i| i.tick)
+            .max()
+            .unwrap_or(start_tick)
+    };
+
+    for part in parts {
+        let end = last_tick(&inputs);
+        let start = match (first.recorded, part.recorded) {
+            (Some(first), Some(recorded)) => {
+                timeline.start_tick + ((recorded - first) * timeline.tick_rate as i64) as i32
+            }
+            _ => end + 1,
+        };
+        let offset = start.max(end + 1) - part.timeline.start_tick;
+        let start = part.timeline.start_tick + offset;
+        if start - end >= MIN_PAUSE_TICKS {
+            timeline.pauses.push(Pause {
+                from: end,
+                to: start,
+            });
+        }
+        timeline
+            .pauses
+            .extend(part.timeline.pauses.iter().map(|p| Pause {
+                from: p.from + offset,
+                to: p.to + offset,
+            }));
+        for (name, mut samples) in part.inputs {
+            samples.iter_mut().for_each(|s| s.shift_ticks(offset));
+            inputs.entry(name).or_default().extend(samples);
+        }
+    }
+    (inputs, timeline)
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+
+use twsnap::{
+    compat::ddnet::{DemoChunk, DemoReader},
+    Snap,
+};
+
+use crate::{
+    data::{ActiveWeapon, Inputs},
+    tee::{TeeChange, TeeTracker},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    /// The player showed up in the snapshots, at the start or after having left
+    Joined,
+    Left,
+    Spawn,
+    /// The tee is gone while the player stays, which is also what joining the spectators
+    /// looks like
+    Death,
+    FreezeStart,
+    FreezeEnd,
+    /// First time a weapon the tee doesn't spawn with was held since it spawned
+    WeaponPickup(ActiveWeapon),
+}
+
+impl From<TeeChange> for EventKind {
+    fn from(change: TeeChange) -> Self {
+        match change {
+            TeeChange::FreezeStart => EventKind::FreezeStart,
+            TeeChange::FreezeEnd => EventKind::FreezeEnd,
+            TeeChange::WeaponPickup(weapon) => EventKind::WeaponPickup(weapon),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Event<'a> {
+    pub tick: i32,
+    pub player: &'a str,
+    pub kind: EventKind,
+}
+
+#[derive(Default)]
+struct PlayerState {
+    present: bool,
+    /// The last snapshot the player was in
+    snapshot: usize,
+    alive: bool,
+    tee: TeeTracker,
+}
+
+type TickCallback<'a> = Box<dyn FnMut(&str, &Inputs) + 'a>;
+type EventCallback<'a> = Box<dyn FnMut(&Event) + 'a>;
+
+/// Goes through a demo once and hands every sample and event to the callbacks as it is read,
+/// for statistics that don't need all inputs at once. Only the previous state of each player
+/// is kept.
+pub struct DemoAnalyzer<'a> {
+    reader: DemoReader,
+    on_tick: Vec<TickCallback<'a>>,
+    on_event: Vec<EventCallback<'a>>,
+}
+
+impl<'a> DemoAnalyzer<'a> {
+    pub fn new(reader: DemoReader) -> Self {
+        Self {
+            reader,
+            on_tick: Vec::new(),
+            on_event: Vec::new(),
+        }
+    }
+
+    /// Called with the name and the inputs of every player with a tee in every snapshot.
+    pub fn on_tick(mut self, callback: impl FnMut(&str, &Inputs) + 'a) -> Self {
+        self.on_tick.push(Box::new(callback));
+        self
+    }
+
+    /// Called for every event, before the inputs of the snapshot it happened in.
+    pub fn on_event(mut self, callback: impl FnMut(&Event) + 'a) -> Self {
+        self.on_event.push(Box::new(callback));
+        self
+    }
+
+    /// Reads the demo to the end. Like the rest of the analyzer, a broken demo is read up to
+    /// where it breaks.
+    pub fn run(mut self) {
+        let mut players: HashMap<String, PlayerState> = HashMap::new();
+        let mut snap = Snap::default();
+        let mut snapshot = 0;
+        while let Ok(Some(chunk)) = self.reader.next_chunk(&mut snap) {
+            let DemoChunk::Snapshot(tick) = chunk else {
+                continue;
+            };
+            snapshot += 1;
+            for (_, p) in snap.players.iter() {
+                // Only allocate for players not seen before
+                if !players.contains_key(p.name.as_str()) {
+                    players.insert(p.name.to_string(), PlayerState::default());
+                }
+                let name = p.name.as_str();
+                let state = players.get_mut(name).unwrap();
+                let mut emit = |tick, kind| {
+                    let event = Event {
+                        tick,
+                        player: name,
+                        kind,
+                    };
+                    self.on_event.iter_mut().for_each(|f| f(&event));
+                };
+                state.snapshot = snapshot;
+                if !state.present {
+                    state.present = true;
+                    emit(tick, EventKind::Joined);
+                }
+                let Some(tee) = &p.tee else {
+                    if state.alive {
+                        state.alive = false;
+                        emit(tick, EventKind::Death);
+                    }
+                    continue;
+                };
+                let inputs = Inputs::from((p, tee));
+                if !state.alive {
+                    state.alive = true;
+                    state.tee = TeeTracker::default();
+                    emit(inputs.tick, EventKind::Spawn);
+                }
+                state
+                    .tee
+                    .update(&inputs, |change| emit(inputs.tick, change.into()));
+                self.on_tick.iter_mut().for_each(|f| f(name, &inputs));
+            }
+            for (name, state) in &mut players {
+                if state.present && state.snapshot != snapshot {
+                    state.present = false;
+                    state.alive = false;
+                    let event = Event {
+                        tick,
+                        player: name,
+                        kind: EventKind::Left,
+                    };
+                    self.on_event.iter_mut().for_each(|f| f(&event));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::BTreeMap;
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use twsnap::compat::ddnet::DemoReader;
+
+use crate::{zoom::percentile, CombinedStats};
+
+/// Version of the summary format. The keys of the summary are guaranteed to stay the same
+/// within a version, so this only gets bumped when one has to be renamed or removed.
+pub const SUMMARY_VERSION: u32 = 1;
+
+/// Players this many median absolute deviations above the median of a metric are outliers.
+const OUTLIER_SCORE: f32 = 3.0;
+/// How many outliers the roll-up lists, the highest scores first.
+const MAX_OUTLIERS: usize = 5;
+/// Scales the median absolute deviation to the standard deviation of a normal distribution.
+const MAD_SCALE: f32 = 1.4826;
+
+pub const FLAGS: [&str; 4] = ["fast_fire", "double_click", "dyncam", "zoom"];
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct DemoInfo {
+    pub file: String,
+    pub map: String,
+    /// Recording time from the demo header
+    pub timestamp: String,
+    pub length_seconds: i32,
+}
+
+impl DemoInfo {
+    pub fn new(file: String, reader: &DemoReader) -> Self {
+        Self {
+            file,
+            map: reader.map_name().to_string(),
+            timestamp: reader.timestamp().to_string(),
+            length_seconds: reader.length(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct PlayerSummary {
+    pub direction_change_rate_average: f32,
+    pub direction_change_rate_max: usize,
+    pub hook_state_change_rate_average: f32,
+    pub hook_state_change_rate_max: usize,
+    pub aim_angular_speed_average: f32,
+    pub aim_linear_segment_fraction: f32,
+    pub target_distance_average: f32,
+    pub fast_fire: usize,
+    pub double_clicks: usize,
+    pub runs: usize,
+    pub best_run_seconds: Option<f32>,
+    /// Any of [`FLAGS`]
+    pub flags: Vec<&'static str>,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Summary {
+    pub version: u32,
+    pub demo: DemoInfo,
+    pub players: BTreeMap<String, PlayerSummary>,
+}
+
+fn summarize_player(stats: &CombinedStats) -> PlayerSummary {
+    let fast_fire = stats.attacks.values().map(|a| a.fast_fire.len()).sum();
+    let double_clicks = stats.attacks.values().map(|a| a.double_clicks.len()).sum();
+
+    let mut flags = Vec::new();
+    if fast_fire > 0 {
+        flags.push("fast_fire");
+    }
+    if double_clicks > 0 {
+        flags.push("double_click");
+    }
+    if stats.target_distance.probable_dyncam {
+        flags.push("dyncam");
+    }
+    if stats.target_distance.probable_zoom {
+        flags.push("zoom");
+    }
+
+    PlayerSummary {
+        direction_change_rate_average: stats.direction_change_rate_average,
+        direction_change_rate_max: stats.direction_change_rate_max,
+        hook_state_change_rate_average: stats.hook_state_change_rate_average,
+        hook_state_change_rate_max: stats.hook_state_change_rate_max,
+        aim_angular_speed_average: stats.aim_angular_speed_average,
+        aim_linear_segment_fraction: stats.aim_linear_segment_fraction,
+        target_distance_average: stats.target_distance.average,
+        fast_fire,
+        double_clicks,
+        runs: stats.runs.runs.len(),
+        best_run_seconds: stats.runs.best_run.map(|i| stats.runs.runs[i].duration),
+        flags,
+    }
+}
+
+pub fn summarize<'a>(
+    demo: DemoInfo,
+    stats: impl IntoIterator<Item = (&'a String, &'a CombinedStats)>,
+) -> Summary {
+    Summary {
+        version: SUMMARY_VERSION,
+        demo,
+        players: stats
+            .into_iter()
+            .map(|(name, s)| (name.clone(), summarize_player(s)))
+            .collect(),
+    }
+}
+
+/// How a metric is spread over the players of a demo.
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct Spread {
+    pub min: f32,
+    pub median: f32,
+    pub mean: f32,
+    pub max: f32,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Outlier {
+    pub player: String,
+    pub metric: &'static str,
+    pub value: f32,
+    /// Median absolute deviations above the median of the demo
+    pub score: f32,
+}
+
+/// One object per demo without the details of the players, for dashboards with a row per demo.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct RollUp {
+    pub version: u32,
+    pub demo: DemoInfo,
+    pub players: usize,
+    /// Ticks from the first to the last sample of any player
+    pub total_ticks: i32,
+    /// Time played of all players together
+    pub player_seconds: f32,
+    /// Players with any of [`FLAGS`]
+    pub flagged_players: usize,
+    pub direction_change_rate_average: Spread,
+    pub direction_change_rate_max: Spread,
+    pub hook_state_change_rate_average: Spread,
+    pub hook_state_change_rate_max: Spread,
+    pub aim_angular_speed_average: Spread,
+    pub outliers: Vec<Outlier>,
+}
+
+fn spread(mut values: Vec<f32>) -> Spread {
+    if values.is_empty() {
+        return Spread::default();
+    }
+    values.sort_by(f32::total_cmp);
+    Spread {
+        min: values[0],
+        median: percentile(&values, 0.5),
+        mean: values.iter().sum::<f32>() / values.len() as f32,
+        max: values[values.len() - 1],
+    }
+}
+
+/// Players far above the others in the metric. Nothing stands out when most players have the
+/// same value, like with only two players.
+fn outliers(metric: &'static str, values: &[(&String, f32)]) -> Vec<Outlier> {
+    let mut sorted: Vec<f32> = values.iter().map(|(_, v)| *v).collect();
+    sorted.sort_by(f32::total_cmp);
+    let Some(median) = (!sorted.is_empty()).then(|| percentile(&sorted, 0.5)) else {
+        return Vec::new();
+    };
+    let mut deviations: Vec<f32> = sorted.iter().map(|v| (v - median).abs()).collect();
+    deviations.sort_by(f32::total_cmp);
+    let deviation = percentile(&deviations, 0.5) * MAD_SCALE;
+    if deviation == 0.0 {
+        return Vec::new();
+    }
+    values
+        .iter()
+        .map(|(name, value)| Outlier {
+            player: (*name).clone(),
+            metric,
+            value: *value,
+            score: (value - median) / deviation,
+        })
+        .filter(|o| o.score >= OUTLIER_SCORE)
+        .collect()
+}
+
+pub fn roll_up<'a>(
+    demo: DemoInfo,
+    stats: impl IntoIterator<Item = (&'a String, &'a CombinedStats)>,
+) -> RollUp {
+    let stats: Vec<(&String, &CombinedStats)> = stats.into_iter().collect();
+    let presence = stats.iter().flat_map(|(_, s)| &s.presence);
+    let first = presence.clone().map(|p| p.joined.tick).min();
+    let last = presence.map(|p| p.left.tick).max();
+
+    let mut found = Vec::new();
+    let mut metric = |metric: &'static str, value: fn(&CombinedStats) -> f32| {
+        let values: Vec<(&String, f32)> = stats.iter().map(|(n, s)| (*n, value(s))).collect();
+        found.extend(outliers(metric, &values));
+        spread(values.into_iter().map(|(_, v)| v).collect())
+    };
+    let direction_change_rate_average = metric("direction_change_rate_average", |s| {
+        s.direction_change_rate_average
+    });
+    let direction_change_rate_max = metric("direction_change_rate_max", |s| {
+        s.direction_change_rate_max as f32
+    });
+    let hook_state_change_rate_average = metric("hook_state_change_rate_average", |s| {
+        s.hook_state_change_rate_average
+    });
+    let hook_state_change_rate_max = metric("hook_state_change_rate_max", |s| {
+        s.hook_state_change_rate_max as f32
+    });
+    let aim_angular_speed_average =
+        metric("aim_angular_speed_average", |s| s.aim_angular_speed_average);
+    found.sort_by(|a, b| b.score.total_cmp(&a.score));
+    found.truncate(MAX_OUTLIERS);
+
+    RollUp {
+        version: SUMMARY_VERSION,
+        demo,
+        players: stats.len(),
+        total_ticks: first.zip(last).map_or(0, |(first, last)| last - first),
+        player_seconds: stats.iter().map(|(_, s)| s.time_played).sum(),
+        flagged_players: stats
+            .iter()
+            .filter(|(_, s)| !summarize_player(s).flags.is_empty())
+            .count(),
+        direction_change_rate_average,
+        direction_change_rate_max,
+        hook_state_change_rate_average,
+        hook_state_change_rate_max,
+        aim_angular_speed_average,
+        outliers: found,
+    }
+}
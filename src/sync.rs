@@ -0,0 +1,138 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::Serialize;
+
+use crate::{
+    data::{ActiveWeapon, Inputs},
+    tricks,
+};
+
+/// The partner has to stay this close during a hammerfly, on average, in tiles.
+const PARTNER_DISTANCE: f32 = 4.0;
+/// Fewer shot pairs don't say anything about the timing.
+const MIN_PAIRS: usize = 10;
+/// Timing that varies less than this is beyond human precision, in ticks. The attack tick
+/// is exact, so unlike positions this isn't limited by the snapshot rate.
+const SUPERHUMAN_DEVIATION_TICKS: f32 = 0.5;
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct HammerflySync {
+    pub players: [String; 2],
+    pub segments: usize,
+    /// Shots of one player that were matched with a shot of the partner
+    pub pairs: usize,
+    /// Mean offset of the partner's shots as fraction of the hammer interval, 0 means both
+    /// hammer at the same time, ±0.5 means they alternate
+    pub relative_phase: f32,
+    pub phase_variance: f32,
+    /// Standard deviation of the offset between the partners' shots, in ticks
+    pub offset_deviation: f32,
+    /// Standard deviation of the time between consecutive shots of the same player, in ticks
+    pub interval_deviation: f32,
+    pub superhuman: bool,
+}
+
+#[derive(Default)]
+struct Samples {
+    segments: usize,
+    offsets: Vec<f32>,
+    phases: Vec<f32>,
+    intervals: Vec<f32>,
+}
+
+fn mean(values: &[f32]) -> f32 {
+    values.iter().sum::<f32>() / values.len().max(1) as f32
+}
+
+fn variance(values: &[f32]) -> f32 {
+    let mean = mean(values);
+    values.iter().map(|v| (v - mean).powi(2)).sum::<f32>() / values.len().max(1) as f32
+}
+
+/// The sample of the player closest to the tick.
+fn at(inputs: &[Inputs], tick: i32) -> Option<&Inputs> {
+    let i = inputs.partition_point(|i| i.tick < tick);
+    inputs.get(i).or(inputs.last())
+}
+
+fn partner<'a>(
+    inputs: &'a HashMap<String, Vec<Inputs>>,
+    name: &str,
+    segment: &[Inputs],
+) -> Option<&'a str> {
+    inputs
+        .iter()
+        .filter(|(other, _)| other.as_str() != name)
+        .filter_map(|(other, other_inputs)| {
+            let distances: Vec<f32> = segment
+                .iter()
+                .map(|s| Some(s.pos.distance(&at(other_inputs, s.tick)?.pos)))
+                .collect::<Option<_>>()?;
+            Some((other.as_str(), mean(&distances)))
+        })
+        .filter(|(_, distance)| *distance <= PARTNER_DISTANCE)
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(other, _)| other)
+}
+
+/// Compares the hammer timing of every player with the partner they hammerflied with.
+pub fn hammerfly_sync(inputs: &HashMap<String, Vec<Inputs>>, tick_rate: i32) -> Vec<HammerflySync> {
+    let mut samples: BTreeMap<[String; 2], Samples> = BTreeMap::new();
+    for (name, player_inputs) in inputs {
+        for range in tricks::hammerflies(player_inputs, tick_rate) {
+            let segment = &player_inputs[range.clone()];
+            let Some(partner) = partner(inputs, name, segment) else {
+                continue;
+            };
+            let (start, end) = (segment[0].tick, segment[segment.len() - 1].tick);
+            let own: Vec<i32> = tricks::shots(player_inputs, ActiveWeapon::Hammer)
+                .into_iter()
+                .filter(|i| range.contains(i))
+                .map(|i| player_inputs[i].attack_tick)
+                .collect();
+            let theirs: Vec<i32> = tricks::shots(&inputs[partner], ActiveWeapon::Hammer)
+                .into_iter()
+                .map(|i| inputs[partner][i].attack_tick)
+                .filter(|t| (start..=end).contains(t))
+                .collect();
+            let intervals: Vec<f32> = own.windows(2).map(|w| (w[1] - w[0]) as f32).collect();
+            let period = mean(&intervals);
+
+            let mut key = [name.clone(), partner.to_string()];
+            key.sort();
+            let entry = samples.entry(key).or_default();
+            entry.segments += 1;
+            entry.intervals.extend(&intervals);
+            for shot in &own {
+                let Some(offset) = theirs.iter().map(|t| t - shot).min_by_key(|o| o.abs()) else {
+                    continue;
+                };
+                if (offset.abs() as f32) <= period / 2.0 {
+                    entry.offsets.push(offset as f32);
+                    entry.phases.push(offset as f32 / period);
+                }
+            }
+        }
+    }
+
+    samples
+        .into_iter()
+        .map(|(players, s)| {
+            let offset_deviation = variance(&s.offsets).sqrt();
+            let interval_deviation = variance(&s.intervals).sqrt();
+            HammerflySync {
+                players,
+                segments: s.segments,
+                pairs: s.offsets.len(),
+                relative_phase: mean(&s.phases),
+                phase_variance: variance(&s.phases),
+                offset_deviation,
+                interval_deviation,
+                superhuman: s.offsets.len() >= MIN_PAIRS
+                    && offset_deviation < SUPERHUMAN_DEVIATION_TICKS
+                    || s.intervals.len() >= MIN_PAIRS
+                        && interval_deviation < SUPERHUMAN_DEVIATION_TICKS,
+            }
+        })
+        .collect()
+}
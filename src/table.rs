@@ -0,0 +1,75 @@
+use crate::{
+    data::{Direction, Inputs},
+    timeline::{TimeFormat, Timeline},
+};
+
+pub const COLUMNS: [&str; 13] = [
+    "Tick",
+    "Time",
+    "Direction",
+    "Hook",
+    "Aim",
+    "X",
+    "Y",
+    "Vel X",
+    "Vel Y",
+    "Weapon",
+    "Fire",
+    "Jumps",
+    "Frozen",
+];
+
+pub type Row = [String; COLUMNS.len()];
+
+/// The exact values of one sample.
+pub fn row(input: &Inputs, timeline: &Timeline, time_format: TimeFormat) -> Row {
+    let direction = match input.direction {
+        Direction::Left => "Left",
+        Direction::None => "-",
+        Direction::Right => "Right",
+    };
+    [
+        input.tick.to_string(),
+        time_format.format(timeline.seconds(input.tick)),
+        direction.to_string(),
+        format!("{:?}", input.hook_state),
+        format!("{:.1}°", input.angle.to_num::<f32>().to_degrees()),
+        format!("{:.2}", input.pos.x.to_num::<f32>()),
+        format!("{:.2}", input.pos.y.to_num::<f32>()),
+        format!("{:.3}", input.vel.x.to_num::<f32>()),
+        format!("{:.3}", input.vel.y.to_num::<f32>()),
+        format!("{:?}", input.weapon),
+        if input.attack_tick == input.tick {
+            "yes"
+        } else {
+            ""
+        }
+        .to_string(),
+        input.jumped_total.to_string(),
+        if input.freeze_end != 0 { "yes" } else { "" }.to_string(),
+    ]
+}
+
+/// The rows aligned under the column names, for the terminal.
+pub fn format_table(rows: &[Row]) -> String {
+    let mut widths = COLUMNS.map(|c| c.chars().count());
+    for row in rows {
+        for (width, value) in widths.iter_mut().zip(row) {
+            *width = (*width).max(value.chars().count());
+        }
+    }
+    let line = |values: &mut dyn Iterator<Item = &str>| {
+        values
+            .zip(widths)
+            .map(|(value, width)| format!("{value:>width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+    let mut lines = vec![line(&mut COLUMNS.into_iter())];
+    for row in rows {
+        lines.push(line(&mut row.iter().map(String::as_str)));
+    }
+    lines.join("\n")
+}
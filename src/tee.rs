@@ -0,0 +1,47 @@
+use std::collections::BTreeSet;
+
+use crate::data::{ActiveWeapon, Inputs};
+
+/// What can be told from one sample of a tee compared to the ones before it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TeeChange {
+    FreezeStart,
+    FreezeEnd,
+    /// First time a weapon the tee doesn't spawn with was held since it spawned
+    WeaponPickup(ActiveWeapon),
+}
+
+/// Follows a tee sample by sample from its spawn on, replaced by a new one on the next spawn.
+pub struct TeeTracker {
+    frozen: bool,
+    held: BTreeSet<ActiveWeapon>,
+}
+
+impl Default for TeeTracker {
+    fn default() -> Self {
+        Self {
+            frozen: false,
+            // Every tee spawns with hammer and pistol
+            held: BTreeSet::from([ActiveWeapon::Hammer, ActiveWeapon::Pistol]),
+        }
+    }
+}
+
+impl TeeTracker {
+    /// Takes the next sample and hands what changed with it to `change`.
+    pub fn update(&mut self, input: &Inputs, mut change: impl FnMut(TeeChange)) {
+        // The freeze end tick is only set while the tee is frozen
+        let frozen = input.freeze_end != 0;
+        if frozen != self.frozen {
+            self.frozen = frozen;
+            change(if frozen {
+                TeeChange::FreezeStart
+            } else {
+                TeeChange::FreezeEnd
+            });
+        }
+        if self.held.insert(input.weapon) {
+            change(TeeChange::WeaponPickup(input.weapon));
+        }
+    }
+}
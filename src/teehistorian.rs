@@ -0,0 +1,267 @@
+use std::{
+    collections::HashMap,
+    io::{self, Read},
+    thread,
+    time::Duration,
+};
+
+use anyhow::bail;
+
+/// Every teehistorian file starts with this UUID.
+const MAGIC: [u8; 16] = [
+    0x69, 0x9d, 0xb1, 0x7b, 0x8e, 0xfb, 0x34, 0xff, 0xb1, 0xd8, 0xda, 0x6f, 0x60, 0xc1, 0x5d, 0xd1,
+];
+
+const FINISH: i32 = -1;
+const TICK_SKIP: i32 = -2;
+const PLAYER_NEW: i32 = -3;
+const PLAYER_OLD: i32 = -4;
+const INPUT_DIFF: i32 = -5;
+const INPUT_NEW: i32 = -6;
+const MESSAGE: i32 = -7;
+const JOIN: i32 = -8;
+const DROP: i32 = -9;
+const CONSOLE_COMMAND: i32 = -10;
+const EX: i32 = -11;
+
+/// Game messages that carry the player name, as sent by 0.6 clients.
+const MSG_CL_STARTINFO: i32 = 20;
+const MSG_CL_CHANGEINFO: i32 = 21;
+
+/// How often a followed file is checked for new data.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The parts of the input a client sent that the live stats look at.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Input {
+    pub direction: i32,
+    pub hook: bool,
+}
+
+impl From<[i32; 10]> for Input {
+    /// From the fields of the input in network order.
+    fn from(v: [i32; 10]) -> Self {
+        Self {
+            direction: v[0],
+            hook: v[5] != 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum Event {
+    /// The server moved on to this tick
+    Tick(i32),
+    Join(i32),
+    Drop(i32),
+    Name {
+        client: i32,
+        name: String,
+    },
+    Input {
+        client: i32,
+        input: Input,
+    },
+    /// The server closed the file
+    Finish,
+}
+
+/// Waits for more data at the end of the file instead of stopping, for files that are still
+/// being written.
+pub struct Follow<R> {
+    inner: R,
+    follow: bool,
+}
+
+impl<R> Follow<R> {
+    pub fn new(inner: R, follow: bool) -> Self {
+        Self { inner, follow }
+    }
+}
+
+impl<R: Read> Read for Follow<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.inner.read(buf)? {
+                0 if self.follow && !buf.is_empty() => thread::sleep(POLL_INTERVAL),
+                n => return Ok(n),
+            }
+        }
+    }
+}
+
+/// Reads the packed integers used by the teehistorian format: six bits and the sign in the
+/// first byte, seven bits in every following one.
+fn read_int(r: &mut impl Read) -> io::Result<i32> {
+    let mut byte = [0];
+    r.read_exact(&mut byte)?;
+    let sign = byte[0] & 0x40 != 0;
+    let mut value = (byte[0] & 0x3f) as i32;
+    let mut shift = 6;
+    while byte[0] & 0x80 != 0 {
+        if shift > 27 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "integer too long",
+            ));
+        }
+        r.read_exact(&mut byte)?;
+        value |= ((byte[0] & 0x7f) as i32) << shift;
+        shift += 7;
+    }
+    Ok(if sign { !value } else { value })
+}
+
+fn read_string(r: &mut impl Read) -> io::Result<String> {
+    let mut bytes = Vec::new();
+    let mut byte = [0];
+    loop {
+        r.read_exact(&mut byte)?;
+        if byte[0] == 0 {
+            return Ok(String::from_utf8_lossy(&bytes).into_owned());
+        }
+        bytes.push(byte[0]);
+    }
+}
+
+fn read_bytes(r: &mut impl Read) -> io::Result<Vec<u8>> {
+    let size = read_int(r)?;
+    let mut data = vec![0; size.max(0) as usize];
+    r.read_exact(&mut data)?;
+    Ok(data)
+}
+
+/// The player name if the message is one of the client info messages.
+fn message_name(mut data: &[u8]) -> Option<String> {
+    let msg = read_int(&mut data).ok()?;
+    let (system, id) = (msg & 1 != 0, msg >> 1);
+    if system || !matches!(id, MSG_CL_STARTINFO | MSG_CL_CHANGEINFO) {
+        return None;
+    }
+    read_string(&mut data).ok()
+}
+
+/// Reads the events of a teehistorian file, as written by DDNet servers.
+pub struct Teehistorian<R> {
+    reader: R,
+    pub header: serde_json::Value,
+    tick: i32,
+    /// Player records are written in client order, a lower client starts the next tick
+    last_client: i32,
+    inputs: HashMap<i32, [i32; 10]>,
+}
+
+impl<R: Read> Teehistorian<R> {
+    pub fn new(mut reader: R) -> anyhow::Result<Self> {
+        let mut magic = [0; 16];
+        reader.read_exact(&mut magic)?;
+        if magic != MAGIC {
+            bail!("Not a teehistorian file");
+        }
+        let header = serde_json::from_str(&read_string(&mut reader)?)?;
+        Ok(Self {
+            reader,
+            header,
+            tick: 0,
+            last_client: -1,
+            inputs: HashMap::new(),
+        })
+    }
+
+    fn player_record(&mut self, client: i32) -> Option<Event> {
+        let next_tick = client <= self.last_client;
+        self.last_client = client;
+        next_tick.then(|| {
+            self.tick += 1;
+            Event::Tick(self.tick)
+        })
+    }
+
+    fn read_input(&mut self) -> io::Result<[i32; 10]> {
+        let mut input = [0; 10];
+        for value in &mut input {
+            *value = read_int(&mut self.reader)?;
+        }
+        Ok(input)
+    }
+
+    pub fn next_event(&mut self) -> io::Result<Event> {
+        loop {
+            let event = match read_int(&mut self.reader)? {
+                client if client >= 0 => {
+                    // Position difference, only the ordering matters here
+                    read_int(&mut self.reader)?;
+                    read_int(&mut self.reader)?;
+                    self.player_record(client)
+                }
+                FINISH => Some(Event::Finish),
+                TICK_SKIP => {
+                    self.tick += read_int(&mut self.reader)? + 1;
+                    self.last_client = -1;
+                    Some(Event::Tick(self.tick))
+                }
+                PLAYER_NEW => {
+                    let client = read_int(&mut self.reader)?;
+                    read_int(&mut self.reader)?;
+                    read_int(&mut self.reader)?;
+                    self.player_record(client)
+                }
+                PLAYER_OLD => {
+                    let client = read_int(&mut self.reader)?;
+                    self.player_record(client)
+                }
+                kind @ (INPUT_DIFF | INPUT_NEW) => {
+                    let client = read_int(&mut self.reader)?;
+                    let mut input = self.read_input()?;
+                    if kind == INPUT_DIFF {
+                        let previous = self.inputs.get(&client).copied().unwrap_or_default();
+                        for (value, previous) in input.iter_mut().zip(previous) {
+                            *value = value.wrapping_add(previous);
+                        }
+                    }
+                    self.inputs.insert(client, input);
+                    Some(Event::Input {
+                        client,
+                        input: input.into(),
+                    })
+                }
+                MESSAGE => {
+                    let client = read_int(&mut self.reader)?;
+                    let data = read_bytes(&mut self.reader)?;
+                    message_name(&data).map(|name| Event::Name { client, name })
+                }
+                JOIN => Some(Event::Join(read_int(&mut self.reader)?)),
+                DROP => {
+                    let client = read_int(&mut self.reader)?;
+                    read_string(&mut self.reader)?;
+                    self.inputs.remove(&client);
+                    Some(Event::Drop(client))
+                }
+                CONSOLE_COMMAND => {
+                    read_int(&mut self.reader)?;
+                    read_int(&mut self.reader)?;
+                    read_string(&mut self.reader)?;
+                    for _ in 0..read_int(&mut self.reader)? {
+                        read_string(&mut self.reader)?;
+                    }
+                    None
+                }
+                EX => {
+                    let mut uuid = [0; 16];
+                    self.reader.read_exact(&mut uuid)?;
+                    read_bytes(&mut self.reader)?;
+                    None
+                }
+                kind => {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unknown teehistorian chunk {kind}"),
+                    ))
+                }
+            };
+            if let Some(event) = event {
+                return Ok(event);
+            }
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::{data::Inputs, timeline::Timeline};
+
+#[derive(Debug, Clone, Copy, Serialize, JsonSchema)]
+pub struct PathPoint {
+    /// Seconds since the start of the demo
+    pub t: f32,
+    /// Position in tiles, y counts downwards like in the game
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Distance of the point to the segment from `start` to `end`. Unlike the distance to the
+/// line through them this keeps turnarounds, where the tee moves back along the same line.
+fn distance(point: &PathPoint, start: &PathPoint, end: &PathPoint) -> f32 {
+    let (dx, dy) = (end.x - start.x, end.y - start.y);
+    let length = dx * dx + dy * dy;
+    let along = if length == 0.0 {
+        0.0
+    } else {
+        (((point.x - start.x) * dx + (point.y - start.y) * dy) / length).clamp(0.0, 1.0)
+    };
+    (point.x - start.x - along * dx).hypot(point.y - start.y - along * dy)
+}
+
+/// Ramer–Douglas–Peucker, keeps the fewest points that leave no dropped point further than
+/// `tolerance` tiles from the simplified path.
+pub fn simplify(points: &[PathPoint], tolerance: f32) -> Vec<PathPoint> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let farthest = (start + 1..end)
+            .map(|i| (i, distance(&points[i], &points[start], &points[end])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((i, _)) = farthest.filter(|(_, d)| *d > tolerance) {
+            keep[i] = true;
+            ranges.push((start, i));
+            ranges.push((i, end));
+        }
+    }
+    points
+        .iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(p, _)| *p)
+        .collect()
+}
+
+/// The positions of the player, one point per sample.
+pub fn trace(inputs: &[Inputs], timeline: &Timeline) -> Vec<PathPoint> {
+    inputs
+        .iter()
+        .map(|i| {
+            let (x, y) = i.pos.tiles();
+            PathPoint {
+                t: timeline.seconds(i.tick),
+                x,
+                y,
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,251 @@
+use std::{collections::HashMap, sync::Arc};
+
+use fluent_bundle::FluentArgs;
+
+use minijinja::{
+    context,
+    value::{Kwargs, ValueKind},
+    Environment, Error, ErrorKind, Value,
+};
+use serde::Serialize;
+
+use crate::{
+    color::{self, Palette},
+    i18n::{Lang, Translations},
+    numbers::NumberFormat,
+    reference::Placement,
+    timeline::{self, TimeFormat, Timeline},
+};
+
+/// The layout of the plain report, a starting point for `--template`. Templates are
+/// minijinja, with these filters on top of the builtins: `fixed` and `decimal` for two
+/// decimals, `precise` for three, `round`, `fixed_percent` and `percent` for fractions,
+/// `clock` for seconds, `time` for ticks, `left` and `right` to pad to a width, `banner`
+/// and `section` for the headers, `alert(condition)` and `warning` for colors. `tr("id",
+/// name=value)` looks up a string of the report in i18n/ for `--lang`, with the named
+/// arguments filled in.
+pub const DEFAULT: &str = include_str!("../templates/report.txt");
+
+/// How the plain report is written, the same for the built-in layout and `--template`.
+#[derive(Debug, Clone, Copy)]
+pub struct ReportOptions<'a> {
+    pub timeline: &'a Timeline,
+    pub time_format: TimeFormat,
+    pub lang: Lang,
+    pub numbers: NumberFormat,
+    pub palette: Palette,
+}
+
+fn number(value: &Value) -> Result<f32, Error> {
+    f64::try_from(value.clone()).map(|n| n as f32).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("{value} isn't a number"),
+        )
+    })
+}
+
+/// The value as the report writes it, `true` and `false` in lower case like the json.
+fn text(value: &Value) -> String {
+    if value.kind() == ValueKind::Bool {
+        value.is_true().to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+fn environment(options: ReportOptions) -> Environment<'static> {
+    let ReportOptions {
+        timeline,
+        time_format,
+        lang,
+        numbers: _,
+        palette,
+    } = options;
+    let mut env = Environment::new();
+    env.set_trim_blocks(true);
+    env.set_lstrip_blocks(true);
+    env.set_formatter(|out, state, value| {
+        if value.kind() == ValueKind::Bool {
+            out.write_str(&text(value)).map_err(Error::from)
+        } else {
+            minijinja::escape_formatter(out, state, value)
+        }
+    });
+    env.add_filter("fixed", |v: Value| Ok(format!("{:0>5.2}", number(&v)?)));
+    env.add_filter("decimal", |v: Value| Ok(format!("{:.2}", number(&v)?)));
+    env.add_filter("precise", |v: Value| Ok(format!("{:.3}", number(&v)?)));
+    env.add_filter("round", |v: Value| Ok(format!("{:.0}", number(&v)?)));
+    env.add_filter("percent", |v: Value| {
+        Ok(format!("{:.2}", number(&v)? * 100.0))
+    });
+    env.add_filter("fixed_percent", |v: Value| {
+        Ok(format!("{:0>5.2}", number(&v)? * 100.0))
+    });
+    env.add_filter("clock", |v: Value| Ok(timeline::clock(number(&v)?, false)));
+    let timeline = timeline.clone();
+    env.add_filter("time", move |tick: i32| {
+        time_format.format(timeline.seconds(tick))
+    });
+    env.add_filter("left", |v: Value, width: usize| {
+        format!("{:<width$}", text(&v))
+    });
+    env.add_filter("right", |v: Value, width: usize| {
+        format!("{:>width$}", text(&v))
+    });
+    env.add_filter("banner", |v: Value| {
+        format!("{:=^44}", format!(" {} ", text(&v)))
+    });
+    env.add_filter("section", |v: Value| {
+        format!("{:-^44}", format!(" {} ", text(&v)))
+    });
+    env.add_filter("alert", move |v: Value, condition: Value| {
+        palette.alert(text(&v), condition.is_true())
+    });
+    env.add_filter("warning", move |v: Value| palette.warning(text(&v)));
+    let translations = Arc::new(Translations::new(lang));
+    env.add_function("tr", move |id: String, kwargs: Kwargs| {
+        let mut args = FluentArgs::new();
+        for name in kwargs.args() {
+            args.set(name, text(&kwargs.get::<Value>(name)?));
+        }
+        Ok::<_, Error>(translations.tr(&id, &args))
+    });
+    env
+}
+
+/// Renders the stats of every player, sorted by name. Each player has the `name`, the `stats`
+/// as in the json output and the `placements` in the `--reference` set. The thresholds the
+/// report colors values from are under `limits`.
+pub fn render(
+    template: &str,
+    demo: &impl Serialize,
+    stats: &HashMap<String, impl Serialize>,
+    placements: &HashMap<String, Vec<Placement>>,
+    options: ReportOptions,
+) -> anyhow::Result<String> {
+    let mut players: Vec<_> = stats.iter().collect();
+    players.sort_by_key(|(name, _)| *name);
+    let players: Vec<Value> = players
+        .into_iter()
+        .map(|(name, stats)| {
+            context! {
+                name,
+                stats => Value::from_serialize(stats),
+                placements => Value::from_serialize(placements.get(name)),
+            }
+        })
+        .collect();
+    let limits = context! {
+        max_change_rate => color::MAX_CHANGE_RATE,
+        bot_probability => color::BOT_PROBABILITY,
+        reference_percentile => color::REFERENCE_PERCENTILE,
+    };
+    let context = context! {
+        demo => Value::from_serialize(demo),
+        players,
+        limits,
+    };
+    let report = environment(options).render_str(template, context)?;
+    Ok(report
+        .split('\n')
+        .map(|line| {
+            // the banners stay as they are, they have the player name in them
+            let line = if line.starts_with('=') {
+                line.to_string()
+            } else {
+                options.numbers.text(line)
+            };
+            options.palette.header(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+    use stringlit::s;
+
+    use super::*;
+    use crate::color::ColorChoice;
+
+    fn options(timeline: &Timeline) -> ReportOptions<'_> {
+        ReportOptions {
+            timeline,
+            time_format: TimeFormat::Seconds,
+            lang: Lang::En,
+            numbers: NumberFormat::default(),
+            palette: Palette::new(ColorChoice::Never, true),
+        }
+    }
+
+    #[test]
+    fn pads_and_writes_values_like_the_report() {
+        let timeline = Timeline::default();
+        let stats = HashMap::from([(s!("a"), json!({ "run": 7, "duration": 1.5, "macro": true }))]);
+        let template = "{% for player in players %}#{{ player.stats.run | left(3) }} \
+            {{ player.stats.duration | decimal | right(8) }}s {{ player.stats.macro }} \
+            {{ player.stats.macro | left(6) }}|{% endfor %}";
+        let report = render(template, &(), &stats, &HashMap::new(), options(&timeline)).unwrap();
+        assert_eq!(report, "#7       1.50s true true  |");
+    }
+
+    #[test]
+    fn translates_formats_and_colors() {
+        let timeline = Timeline::default();
+        let stats = HashMap::from([(s!("a"), json!({ "weapons": 12345, "duration": 2.5 }))]);
+        let placements = HashMap::from([(
+            s!("a"),
+            vec![Placement {
+                metric: "direction_change_rate_max",
+                value: 20.5,
+                percentile: 99.5,
+            }],
+        )]);
+        let template = "{% for player in players %}{% set stats = player.stats %}\
+            {{ tr(\"end\") | banner }}\n\
+            {{ tr(\"pickup-weapons\", count=stats.weapons) }}\n\
+            {{ tr(\"insufficient-sample\", seconds=stats.duration | decimal) | warning }}\n\
+            {% for p in player.placements %}{{ p.value | decimal | alert(p.percentile >= limits.reference_percentile) }}{% endfor %}\
+            {% endfor %}";
+        let options = ReportOptions {
+            lang: Lang::De,
+            numbers: NumberFormat {
+                decimal_comma: true,
+                thousands_separator: Some('.'),
+            },
+            palette: Palette::new(ColorChoice::Always, true),
+            ..options(&timeline)
+        };
+        let report = render(template, &(), &stats, &placements, options).unwrap();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                "\x1b[1m=================== ENDE ===================\x1b[0m",
+                "Waffen . : 12.345",
+                "\x1b[33mZu wenig Daten, nur 2,50s\x1b[0m",
+                "\x1b[31m20,50\x1b[0m",
+            ]
+        );
+    }
+
+    #[test]
+    fn default_template_reports_an_insufficient_sample() {
+        let timeline = Timeline::default();
+        let stats = HashMap::from([(
+            s!("player"),
+            json!({ "insufficient_sample": true, "duration": 3.25 }),
+        )]);
+        let report = render(DEFAULT, &(), &stats, &HashMap::new(), options(&timeline)).unwrap();
+        assert_eq!(
+            report.trim_end(),
+            "================== player ==================\n\n\
+            Insufficient sample, only 3.25s of data\n\n\
+            ============================================\n\
+            =================== END ====================\n\
+            ============================================"
+        );
+    }
+}
@@ -0,0 +1,195 @@
+use clap::ValueEnum;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crate::data::DEFAULT_TICK_RATE;
+
+/// How times are shown in reports and on axes.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimeFormat {
+    /// mm:ss.ms
+    #[default]
+    Clock,
+    /// Seconds since the start of the demo
+    Seconds,
+}
+
+impl TimeFormat {
+    pub fn format(&self, seconds: f32) -> String {
+        match self {
+            TimeFormat::Clock => clock(seconds, true),
+            TimeFormat::Seconds => format!("{seconds:.2}s"),
+        }
+    }
+
+    /// Whole seconds only, for axis labels.
+    pub fn format_short(&self, seconds: f32) -> String {
+        match self {
+            TimeFormat::Clock => clock(seconds, false),
+            TimeFormat::Seconds => format!("{}s", seconds as i32),
+        }
+    }
+}
+
+pub fn clock(seconds: f32, millis: bool) -> String {
+    let sign = if seconds < 0.0 { "-" } else { "" };
+    let total = (seconds.abs() * 1000.0).round() as u64;
+    let (minutes, seconds, ms) = (total / 60_000, total / 1000 % 60, total % 1000);
+    if millis {
+        format!("{sign}{minutes:02}:{seconds:02}.{ms:03}")
+    } else {
+        format!("{sign}{minutes:02}:{seconds:02}")
+    }
+}
+
+/// Snapshots at least this many ticks apart are a pause rather than lag, half a second at the
+/// default tick rate.
+pub const MIN_PAUSE_TICKS: i32 = DEFAULT_TICK_RATE / 2;
+
+/// What to do with the pauses of a demo.
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PauseMode {
+    /// Keep the ticks of the server, times include the pauses
+    #[default]
+    Keep,
+    /// Cut the pauses out, the ticks after a pause follow right after the one before it
+    Compress,
+}
+
+/// Where the snapshots jump ahead, because the recorder was paused, the server skipped ticks
+/// or the demos of a stitched session were recorded apart.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+pub struct Pause {
+    /// The last snapshot before the pause
+    pub from: i32,
+    /// The first snapshot after it
+    pub to: i32,
+}
+
+/// Where a demo starts and how fast it ticks, to turn ticks into times.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Timeline {
+    /// Tick of the first snapshot
+    pub start_tick: i32,
+    pub tick_rate: i32,
+    pub pauses: Vec<Pause>,
+    /// When the first snapshot was recorded, in UTC milliseconds since the epoch. Only set
+    /// with `--wallclock`, it comes from the demo header rather than the demo itself.
+    #[serde(skip)]
+    pub recorded: Option<i64>,
+}
+
+impl Default for Timeline {
+    fn default() -> Self {
+        Self {
+            start_tick: 0,
+            tick_rate: DEFAULT_TICK_RATE,
+            pauses: Vec::new(),
+            recorded: None,
+        }
+    }
+}
+
+impl Timeline {
+    /// Seconds since the start of the demo.
+    pub fn seconds(&self, tick: i32) -> f32 {
+        (tick - self.start_tick) as f32 / self.tick_rate as f32
+    }
+
+    /// The tick with the pauses before it cut out, each pause shrinks to a single tick.
+    pub fn compressed_tick(&self, tick: i32) -> i32 {
+        let cut: i32 = self
+            .pauses
+            .iter()
+            .filter(|p| p.to <= tick)
+            .map(|p| p.to - p.from - 1)
+            .sum();
+        tick - cut
+    }
+
+    /// Cuts the pauses out of the timeline, after the ticks of the samples were passed through
+    /// `compressed_tick`. The pauses are kept at the tick they were cut at.
+    pub fn compress(&mut self) {
+        let pauses = self
+            .pauses
+            .iter()
+            .map(|p| {
+                let from = self.compressed_tick(p.from);
+                Pause { from, to: from + 1 }
+            })
+            .collect();
+        self.pauses = pauses;
+    }
+
+    /// The real time of the tick, if the recording time is known.
+    pub fn wallclock(&self, tick: i32) -> Option<String> {
+        let millis = (self.seconds(tick) * 1000.0).round() as i64;
+        self.recorded.map(|recorded| format_utc(recorded + millis))
+    }
+
+    pub fn timestamp(&self, tick: i32) -> Timestamp {
+        Timestamp {
+            tick,
+            time: clock(self.seconds(tick), true),
+            wallclock: self.wallclock(tick),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, JsonSchema)]
+pub struct Timestamp {
+    pub tick: i32,
+    /// Time since the start of the demo as mm:ss.ms
+    pub time: String,
+    /// UTC time as RFC 3339, only with `--wallclock`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wallclock: Option<String>,
+}
+
+/// Parses the recording time in the demo header, `YYYY-MM-DD_HH-MM-SS` with any separators,
+/// into seconds since the epoch.
+pub fn parse_timestamp(timestamp: &str) -> Option<i64> {
+    let parts: Vec<i64> = timestamp
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|p| !p.is_empty())
+        .map(|p| p.parse().ok())
+        .collect::<Option<_>>()?;
+    let [year, month, day, hour, minute, second] = parts[..] else {
+        return None;
+    };
+    // Days since the epoch of the civil date, from Howard Hinnant's date algorithms
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    let days = era * 146097 + day_of_era - 719468;
+    Some(days * 86400 + hour * 3600 + minute * 60 + second)
+}
+
+/// Formats milliseconds since the epoch as RFC 3339 UTC time, like `2024-05-01T18:30:00.120Z`.
+pub fn format_utc(millis: i64) -> String {
+    let (days, millis) = (millis.div_euclid(86_400_000), millis.rem_euclid(86_400_000));
+    // The civil date of the days since the epoch, the inverse of `parse_timestamp`
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}.{:03}Z",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
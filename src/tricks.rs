@@ -0,0 +1,178 @@
+use std::{collections::BTreeMap, ops::RangeInclusive};
+
+use serde::Serialize;
+
+use crate::{
+    data::{ActiveWeapon, HookState, Inputs},
+    map::Map,
+    runs,
+    timeline::{Timeline, Timestamp},
+};
+
+/// Shots further apart than this don't belong to the same flight.
+const MAX_SHOT_GAP_SECONDS: f32 = 1.0;
+/// Hammerflies and rocketflies need at least this many shots in a row.
+const MIN_FLIGHT_SHOTS: usize = 3;
+/// How far a flight has to go up, in tiles.
+const MIN_FLIGHT_RISE: f32 = 3.0;
+/// Vertical speed right after a ground jump, the jump impulse is 13.2, in units per tick.
+const JUMP_VELOCITY: f32 = -10.0;
+/// Half the size of a tee and how far below its center the ground is checked, in tiles,
+/// as used by the game's ground check.
+const FOOT_OFFSET_X: f32 = 14.0 / 32.0;
+const FOOT_OFFSET_Y: f32 = 19.0 / 32.0;
+/// Running tops out at 10 units per tick, only hooking gets a tee this fast.
+const SPEEDFLY_SPEED: f32 = 20.0;
+/// How long the speed has to keep increasing while hooked to count as speedfly.
+const SPEEDFLY_SECONDS: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+pub enum Trick {
+    /// Hammering the hooked partner repeatedly to fly up together
+    Hammerfly,
+    /// Shooting grenades downwards repeatedly to fly up
+    Rocketfly,
+    /// Jumping while standing on the very edge of a block, only detected with the map
+    EdgeJump,
+    /// Building up speed far beyond running speed by hooking
+    Speedfly,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrickOccurrence {
+    pub trick: Trick,
+    pub start: Timestamp,
+    pub end: Timestamp,
+    /// Index of the run as reported by the run segmentation, `None` between runs
+    pub run: Option<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Tricks {
+    pub counts: BTreeMap<Trick, usize>,
+    pub occurrences: Vec<TrickOccurrence>,
+}
+
+/// Indices of the samples where the player fired the weapon.
+pub fn shots(inputs: &[Inputs], weapon: ActiveWeapon) -> Vec<usize> {
+    (1..inputs.len())
+        .filter(|i| {
+            let (prev, cur) = (&inputs[i - 1], &inputs[*i]);
+            cur.weapon == weapon && cur.attack_tick > 0 && cur.attack_tick != prev.attack_tick
+        })
+        .collect()
+}
+
+/// Groups shots of the weapon that are close enough together into flights, returning the
+/// samples from the first to the last shot.
+fn flights(
+    inputs: &[Inputs],
+    weapon: ActiveWeapon,
+    tick_rate: i32,
+    shot_matches: impl Fn(&Inputs) -> bool,
+) -> Vec<RangeInclusive<usize>> {
+    let max_gap = (MAX_SHOT_GAP_SECONDS * tick_rate as f32) as i32;
+    shots(inputs, weapon)
+        .chunk_by(|a, b| inputs[*b].attack_tick - inputs[*a].attack_tick <= max_gap)
+        .filter(|shots| shots.len() >= MIN_FLIGHT_SHOTS)
+        .filter(|shots| shots.iter().all(|i| shot_matches(&inputs[*i])))
+        .map(|shots| shots[0]..=shots[shots.len() - 1])
+        .filter(|range| {
+            let rise = inputs[*range.start()].pos.y - inputs[*range.end()].pos.y;
+            rise.to_num::<f32>() >= MIN_FLIGHT_RISE
+        })
+        .collect()
+}
+
+pub fn hammerflies(inputs: &[Inputs], tick_rate: i32) -> Vec<RangeInclusive<usize>> {
+    flights(inputs, ActiveWeapon::Hammer, tick_rate, |i| {
+        i.hook_state == HookState::Grabbed
+    })
+}
+
+fn grounded_feet(map: &Map, input: &Inputs) -> usize {
+    let (x, y) = (input.pos.x.to_num::<f32>(), input.pos.y.to_num::<f32>());
+    [x - FOOT_OFFSET_X, x + FOOT_OFFSET_X]
+        .into_iter()
+        .filter(|x| map.is_solid(*x, y + FOOT_OFFSET_Y))
+        .count()
+}
+
+fn edge_jumps(inputs: &[Inputs], map: &Map) -> Vec<RangeInclusive<usize>> {
+    (1..inputs.len())
+        .filter(|i| {
+            let (prev, cur) = (&inputs[i - 1], &inputs[*i]);
+            let jumped =
+                prev.vel.y.to_num::<f32>() >= 0.0 && cur.vel.y.to_num::<f32>() <= JUMP_VELOCITY;
+            let on_edge = grounded_feet(map, prev) == 1
+                && !map.is_solid(
+                    prev.pos.x.to_num(),
+                    prev.pos.y.to_num::<f32>() + FOOT_OFFSET_Y,
+                );
+            jumped && on_edge
+        })
+        .map(|i| i - 1..=i)
+        .collect()
+}
+
+fn speedflies(inputs: &[Inputs], tick_rate: i32) -> Vec<RangeInclusive<usize>> {
+    let min_ticks = (SPEEDFLY_SECONDS * tick_rate as f32) as i32;
+    let speed = |i: &Inputs| i.vel.x.to_num::<f32>().hypot(i.vel.y.to_num::<f32>());
+    let mut flies = Vec::new();
+    let mut start = 0;
+    for i in 1..=inputs.len() {
+        let accelerating = i < inputs.len()
+            && inputs[i].hook_state == HookState::Grabbed
+            && speed(&inputs[i]) > speed(&inputs[i - 1]);
+        if accelerating {
+            continue;
+        }
+        let end = i - 1;
+        if inputs[end].tick - inputs[start].tick >= min_ticks
+            && speed(&inputs[end]) >= SPEEDFLY_SPEED
+        {
+            flies.push(start..=end);
+        }
+        start = i;
+    }
+    flies
+}
+
+pub fn detect_tricks(inputs: &[Inputs], map: Option<&Map>, timeline: &Timeline) -> Tricks {
+    let tick_rate = timeline.tick_rate;
+    let hammerflies = hammerflies(inputs, tick_rate);
+    // Aiming down means the target is below the tee rather than beside it
+    let rocketflies = flights(inputs, ActiveWeapon::Grenade, tick_rate, |i| {
+        let (x, y) = (i.target.x.to_num::<f32>(), i.target.y.to_num::<f32>());
+        y > x.abs()
+    });
+    let edge_jumps = map.map(|m| edge_jumps(inputs, m)).unwrap_or_default();
+    let speedflies = speedflies(inputs, tick_rate);
+
+    let runs = runs::segment_runs(inputs, tick_rate);
+    let mut occurrences: Vec<TrickOccurrence> = [
+        (Trick::Hammerfly, hammerflies),
+        (Trick::Rocketfly, rocketflies),
+        (Trick::EdgeJump, edge_jumps),
+        (Trick::Speedfly, speedflies),
+    ]
+    .into_iter()
+    .flat_map(|(trick, ranges)| ranges.into_iter().map(move |r| (trick, r)))
+    .map(|(trick, range)| TrickOccurrence {
+        trick,
+        start: timeline.timestamp(inputs[*range.start()].tick),
+        end: timeline.timestamp(inputs[*range.end()].tick),
+        run: runs.iter().position(|(run, _)| run.contains(range.start())),
+    })
+    .collect();
+    occurrences.sort_by_key(|o| (o.start.tick, o.trick));
+
+    let mut counts = BTreeMap::new();
+    for occurrence in &occurrences {
+        *counts.entry(occurrence.trick).or_default() += 1;
+    }
+    Tricks {
+        counts,
+        occurrences,
+    }
+}
@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Modifier, Style},
+    text::Line,
+    widgets::{Block, List, ListState, Row, Sparkline, Table},
+    DefaultTerminal, Frame,
+};
+
+use crate::{
+    chart,
+    data::Inputs,
+    timeline::{TimeFormat, Timeline},
+    CombinedStats,
+};
+
+type Metric = (&'static str, fn(&CombinedStats) -> String);
+
+/// The metrics that can be toggled with the number keys.
+const METRICS: [Metric; 9] = [
+    ("Overall Changes", |s| s.overall_changes.to_string()),
+    ("Direction Rate Avg", |s| {
+        format!("{:.2}/s", s.direction_change_rate_average)
+    }),
+    ("Direction Rate Max", |s| {
+        format!("{}/s", s.direction_change_rate_max)
+    }),
+    ("Hook Rate Avg", |s| {
+        format!("{:.2}/s", s.hook_state_change_rate_average)
+    }),
+    ("Hook Rate Max", |s| {
+        format!("{}/s", s.hook_state_change_rate_max)
+    }),
+    ("Aim Speed", |s| {
+        format!("{:.2} rad/s", s.aim_angular_speed_average)
+    }),
+    ("Linear Aim", |s| {
+        format!("{:.2}%", s.aim_linear_segment_fraction * 100.0)
+    }),
+    ("Target Distance", |s| {
+        format!("{:.2} tiles", s.target_distance.average)
+    }),
+    ("Runs", |s| s.runs.runs.len().to_string()),
+];
+
+struct Player {
+    name: String,
+    stats: CombinedStats,
+    direction_changes: Vec<u64>,
+    hook_changes: Vec<u64>,
+}
+
+struct App {
+    players: Vec<Player>,
+    list: ListState,
+    visible: [bool; METRICS.len()],
+    /// First second shown in the sparklines
+    offset: usize,
+    time_format: TimeFormat,
+}
+
+impl App {
+    fn selected(&self) -> Option<&Player> {
+        self.players.get(self.list.selected()?)
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, help] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [players, details] =
+            Layout::horizontal([Constraint::Length(24), Constraint::Min(0)]).areas(main);
+        let [table, direction, hook] = Layout::vertical([
+            Constraint::Min(0),
+            Constraint::Length(6),
+            Constraint::Length(6),
+        ])
+        .areas(details);
+
+        let list = List::new(self.players.iter().map(|p| p.name.as_str()))
+            .block(Block::bordered().title(" Players "))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, players, &mut self.list);
+
+        let Some(player) = self.selected() else {
+            return;
+        };
+        let rows = METRICS
+            .iter()
+            .zip(self.visible)
+            .filter(|(_, visible)| *visible)
+            .map(|((name, value), _)| Row::new([name.to_string(), value(&player.stats)]));
+        let table_widget = Table::new(rows, [Constraint::Length(20), Constraint::Min(0)])
+            .block(Block::bordered().title(format!(" {} ", player.name)));
+        frame.render_widget(table_widget, table);
+
+        let width = direction.width.saturating_sub(2) as usize;
+        let window = |data: &[u64]| {
+            let start = self.offset.min(data.len());
+            data[start..(start + width).min(data.len())].to_vec()
+        };
+        let seconds = format!(
+            "{} - {}",
+            self.time_format.format_short(self.offset as f32),
+            self.time_format.format_short((self.offset + width) as f32)
+        );
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(format!(" Direction Changes per Second {seconds} ")))
+                .data(window(&player.direction_changes)),
+            direction,
+        );
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(format!(" Hook Changes per Second {seconds} ")))
+                .data(window(&player.hook_changes)),
+            hook,
+        );
+
+        frame.render_widget(
+            Line::from("q: quit  up/down: player  left/right: scroll time  1-9: toggle metric"),
+            help,
+        );
+    }
+
+    fn run(mut self, terminal: &mut DefaultTerminal) -> std::io::Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            match key.code {
+                KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                KeyCode::Down | KeyCode::Char('j') => self.list.select_next(),
+                KeyCode::Up | KeyCode::Char('k') => self.list.select_previous(),
+                KeyCode::Right | KeyCode::Char('l') => self.offset += 10,
+                KeyCode::Left | KeyCode::Char('h') => self.offset = self.offset.saturating_sub(10),
+                KeyCode::Char(c @ '1'..='9') => {
+                    let index = c as usize - '1' as usize;
+                    self.visible[index] = !self.visible[index];
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub fn run(
+    stats: HashMap<String, CombinedStats>,
+    inputs: &HashMap<String, Vec<Inputs>>,
+    timeline: &Timeline,
+    time_format: TimeFormat,
+) -> anyhow::Result<()> {
+    let Timeline {
+        start_tick,
+        tick_rate,
+        ..
+    } = *timeline;
+    let end_tick = inputs
+        .values()
+        .flat_map(|i| i.last())
+        .map(|i| i.tick)
+        .max()
+        .unwrap_or_default();
+    let seconds = ((end_tick - start_tick) / tick_rate) as usize + 1;
+    let per_second = |name: &str, changed: fn(&Inputs, &Inputs) -> usize| {
+        inputs
+            .get(name)
+            .map(|i| chart::changes_per_second(i, start_tick, seconds, tick_rate, changed))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|c| c as u64)
+            .collect()
+    };
+
+    let mut players: Vec<Player> = stats
+        .into_iter()
+        .map(|(name, stats)| Player {
+            direction_changes: per_second(&name, chart::direction_changed),
+            hook_changes: per_second(&name, chart::hook_changed),
+            name,
+            stats,
+        })
+        .collect();
+    players.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let app = App {
+        players,
+        list: ListState::default().with_selected(Some(0)),
+        visible: [true; METRICS.len()],
+        offset: 0,
+        time_format,
+    };
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal);
+    ratatui::restore();
+    Ok(result?)
+}
@@ -0,0 +1,178 @@
+use std::{collections::BTreeMap, fs::File, io::BufReader, path::Path};
+
+use libtw2_demo::ddnet::{Chunk, DemoReader};
+use libtw2_gamenet_ddnet::{
+    msg::{game::SvTuneParams, Game},
+    Protocol as DDNet,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+use stringlit::s;
+
+use crate::timeline::{TimeFormat, Timeline, Timestamp};
+
+/// Tune parameters sent by the server, which decide what movement is physically possible.
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct Tuning {
+    pub at: Timestamp,
+    /// The parameters that changed since the last tuning, all of them for the first one
+    pub params: BTreeMap<String, f32>,
+}
+
+macro_rules! params {
+    ($tune:expr, $($field:ident),* $(,)?) => {
+        [$((stringify!($field), $tune.$field.to_float())),*]
+    };
+}
+
+fn params(t: &SvTuneParams) -> BTreeMap<String, f32> {
+    params!(
+        t,
+        ground_control_speed,
+        ground_control_accel,
+        ground_friction,
+        ground_jump_impulse,
+        air_jump_impulse,
+        air_control_speed,
+        air_control_accel,
+        air_friction,
+        hook_length,
+        hook_fire_speed,
+        hook_drag_accel,
+        hook_drag_speed,
+        gravity,
+        velramp_start,
+        velramp_range,
+        velramp_curvature,
+        gun_curvature,
+        gun_speed,
+        gun_lifetime,
+        shotgun_curvature,
+        shotgun_speed,
+        shotgun_speeddiff,
+        shotgun_lifetime,
+        grenade_curvature,
+        grenade_speed,
+        grenade_lifetime,
+        laser_reach,
+        laser_bounce_delay,
+        laser_bounce_num,
+        laser_bounce_cost,
+        laser_damage,
+        player_collision,
+        player_hooking,
+        jetpack_strength,
+        shotgun_strength,
+        explosion_strength,
+        hammer_strength,
+        hook_duration,
+        hammer_fire_delay,
+        gun_fire_delay,
+        shotgun_fire_delay,
+        grenade_fire_delay,
+        laser_fire_delay,
+        ninja_fire_delay,
+        hammer_hit_fire_delay,
+        ground_elasticity_x,
+        ground_elasticity_y,
+    )
+    .into_iter()
+    .map(|(name, value)| (name.to_string(), value))
+    .collect()
+}
+
+/// The value of the parameter in effect at the tick, none before the server sent it.
+pub fn value_at(tunes: &[Tuning], name: &str, tick: i32) -> Option<f32> {
+    tunes
+        .iter()
+        .take_while(|tuning| tuning.at.tick <= tick)
+        .filter_map(|tuning| tuning.params.get(name))
+        .last()
+        .copied()
+}
+
+/// Every tuning the server sent in the demo. Net messages aren't part of the snapshots, so the
+/// demo is read a second time without converting them.
+pub fn read_tunes(path: &Path, tick_rate: Option<i32>) -> anyhow::Result<(Vec<Tuning>, Timeline)> {
+    let mut reader: DemoReader<DDNet> =
+        DemoReader::new(BufReader::new(File::open(path)?), &mut warn::Ignore)?;
+    let length = reader.inner().length();
+    let mut ticks: Option<(i32, i32)> = None;
+    let mut current = BTreeMap::new();
+    let mut sent = Vec::new();
+    while let Ok(Some(chunk)) = reader.next_chunk(&mut warn::Ignore) {
+        match chunk {
+            Chunk::Tick(tick) => {
+                ticks = Some((ticks.map_or(tick, |(first, _)| first), tick));
+            }
+            Chunk::Message(Game::SvTuneParams(tune)) => {
+                let tune = params(&tune);
+                let changed: BTreeMap<String, f32> = tune
+                    .iter()
+                    .filter(|(name, value)| current.get(*name) != Some(*value))
+                    .map(|(name, value)| (name.clone(), *value))
+                    .collect();
+                if !changed.is_empty() {
+                    // Tunings sent on connect come before the first tick
+                    sent.push((ticks.map(|(_, last)| last), changed));
+                }
+                current = tune;
+            }
+            _ => {}
+        }
+    }
+
+    let timeline = crate::timeline(length, ticks, tick_rate);
+    let tunes = sent
+        .into_iter()
+        .map(|(tick, params)| Tuning {
+            at: timeline.timestamp(tick.unwrap_or(timeline.start_tick)),
+            params,
+        })
+        .collect();
+    Ok((tunes, timeline))
+}
+
+pub fn plain_report(tunes: &[Tuning], timeline: &Timeline, format: TimeFormat) -> String {
+    let mut vec = Vec::new();
+    if tunes.is_empty() {
+        vec.push(s!("No tunings in the demo"));
+    }
+    for tuning in tunes {
+        vec.push(format!(
+            "{:-^44}",
+            format!(" {} ", format.format(timeline.seconds(tuning.at.tick)))
+        ));
+        for (name, value) in &tuning.params {
+            vec.push(format!("{name:<22}: {value}"));
+        }
+        vec.push(s!(""));
+    }
+    vec.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuning(tick: i32, params: &[(&str, f32)]) -> Tuning {
+        Tuning {
+            at: Timeline::default().timestamp(tick),
+            params: params.iter().map(|(n, v)| (n.to_string(), *v)).collect(),
+        }
+    }
+
+    #[test]
+    fn looks_up_the_value_in_effect() {
+        let tunes = [
+            tuning(10, &[("gravity", 0.5), ("grenade_speed", 1000.0)]),
+            tuning(20, &[("gravity", 0.25)]),
+        ];
+        assert_eq!(value_at(&tunes, "gravity", 5), None);
+        assert_eq!(value_at(&tunes, "gravity", 10), Some(0.5));
+        assert_eq!(value_at(&tunes, "gravity", 25), Some(0.25));
+        // Later tunings only have what changed
+        assert_eq!(value_at(&tunes, "grenade_speed", 25), Some(1000.0));
+        assert_eq!(value_at(&tunes, "hook_length", 25), None);
+    }
+}
@@ -1,150 +1,1634 @@
-use std::{collections::HashMap, process::exit};
+use std::{
+    collections::{BTreeMap, HashMap},
+    f64::consts::PI,
+    path::{Path, PathBuf},
+    process::exit,
+};
 
-use eframe::egui::{self, ComboBox, Key};
+use eframe::egui::{self, Button, Color32, ComboBox, Key};
 use egui_dropdown::DropDownBox;
-use egui_plot::{Bar, BarChart, GridMark, Line, Plot, PlotPoints};
+use egui_plot::{
+    Bar, BarChart, GridMark, Legend, Line, MarkerShape, Plot, PlotBounds, PlotPoints, PlotUi,
+    Points, Polygon, VLine,
+};
 use stringlit::s;
 
-use crate::data::{self, Inputs};
+use anyhow::Context;
 
-#[derive(Default)]
-pub struct MyApp {
+use crate::{
+    attack,
+    bookmarks::{self, Bookmark},
+    crosshair,
+    data::{self, ActiveWeapon, HookState, Inputs},
+    events::{self, EventKind, GameEvent},
+    keymap::{Action, Keymap},
+    pickups::{self, PickupKind},
+    session::{Recorder, Session, Step},
+    table,
+    timeline::{TimeFormat, Timeline},
+    ExtractionOutputFormat,
+};
+
+/// Who a player is playing with, taken from the snapshots.
+#[derive(Debug, Clone, Default)]
+pub struct PlayerInfo {
+    pub clan: String,
+    /// DDNet team, 0 if not in one
+    pub team: u32,
+    /// Body color of the skin, if the player set a custom one
+    pub color: Option<[u8; 3]>,
+}
+
+impl PlayerInfo {
+    fn color(&self) -> Option<Color32> {
+        self.color.map(|[r, g, b]| Color32::from_rgb(r, g, b))
+    }
+}
+
+/// The skin color of the player in the compare view, players without a custom color would
+/// all look the same so they get one of the compare colors.
+fn compare_color(tab: &Tab, index: usize) -> Color32 {
+    tab.player_color()
+        .unwrap_or(COMPARE_COLORS[index % COMPARE_COLORS.len()])
+}
+
+/// Body color of the default skin, for players without a custom color.
+const DEFAULT_SKIN_COLOR: Color32 = Color32::from_rgb(0xb8, 0x7a, 0x4e);
+
+/// A dot in the body color of the player, to tell them apart at a glance.
+fn color_swatch(ui: &mut egui::Ui, color: Option<Color32>) {
+    let size = ui.text_style_height(&egui::TextStyle::Body);
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(size, size), egui::Sense::hover());
+    let color = color.unwrap_or(DEFAULT_SKIN_COLOR);
+    ui.painter()
+        .circle_filled(rect.center(), size / 2.0 - 1.0, color);
+}
+
+/// One line of the player list.
+struct PlayerRow {
+    name: String,
+    info: PlayerInfo,
+    direction_rate: f32,
+    direction_max: usize,
+    hook_rate: f32,
+    hook_max: usize,
+}
+
+impl PlayerRow {
+    fn new(name: &str, inputs: &[Inputs], info: PlayerInfo, tick_rate: i32) -> Self {
+        let direction = crate::calculate_direction_change_stats(
+            crate::change_ticks(inputs, |i| i.direction),
+            tick_rate,
+        );
+        let hook = crate::calculate_direction_change_stats(
+            crate::change_ticks(inputs, |i| i.hook_state.pressed()),
+            tick_rate,
+        );
+        Self {
+            name: name.to_owned(),
+            info,
+            direction_rate: direction.average,
+            direction_max: direction.max,
+            hook_rate: hook.average,
+            hook_max: hook.max,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PlayerSort {
+    Name,
+    DirectionRate,
+    DirectionMax,
+    HookRate,
+    HookMax,
+}
+
+impl PlayerSort {
+    const ALL: [PlayerSort; 5] = [
+        PlayerSort::Name,
+        PlayerSort::DirectionRate,
+        PlayerSort::DirectionMax,
+        PlayerSort::HookRate,
+        PlayerSort::HookMax,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            PlayerSort::Name => "Name",
+            PlayerSort::DirectionRate => "Direction change rate",
+            PlayerSort::DirectionMax => "Max direction changes",
+            PlayerSort::HookRate => "Hook change rate",
+            PlayerSort::HookMax => "Max hook changes",
+        }
+    }
+}
+
+/// The filter and order of the player list.
+struct PlayerList {
+    rows: Vec<PlayerRow>,
+    search: String,
+    clan: Option<String>,
+    team: Option<u32>,
+    sort: PlayerSort,
+}
+
+impl PlayerList {
+    fn sort(&mut self) {
+        let key = |r: &PlayerRow| match self.sort {
+            PlayerSort::Name => 0.0,
+            PlayerSort::DirectionRate => r.direction_rate,
+            PlayerSort::DirectionMax => r.direction_max as f32,
+            PlayerSort::HookRate => r.hook_rate,
+            PlayerSort::HookMax => r.hook_max as f32,
+        };
+        // Highest first, the most suspicious players are the interesting ones
+        self.rows.sort_by(|a, b| {
+            key(b)
+                .total_cmp(&key(a))
+                .then_with(|| a.name.to_lowercase().cmp(&b.name.to_lowercase()))
+        });
+    }
+
+    fn matches(&self, row: &PlayerRow) -> bool {
+        row.name
+            .to_lowercase()
+            .contains(&self.search.to_lowercase())
+            && self.clan.as_ref().is_none_or(|c| *c == row.info.clan)
+            && self.team.is_none_or(|t| t == row.info.team)
+    }
+}
+
+/// Colors of the demos in the compare view, repeated when there are more tabs.
+const COMPARE_COLORS: [Color32; 6] = [
+    Color32::from_rgb(0x4e, 0x9a, 0xf1),
+    Color32::from_rgb(0xf1, 0x8f, 0x4e),
+    Color32::from_rgb(0x5c, 0xc8, 0x6a),
+    Color32::from_rgb(0xe0, 0x5a, 0x8a),
+    Color32::from_rgb(0xb5, 0x8c, 0xf0),
+    Color32::from_rgb(0xe0, 0xc8, 0x4a),
+];
+
+/// Event markers sit at the top edge of every plot, bookmarks at the bottom edge, this far
+/// inside as a share of the plot height.
+const MARKER_MARGIN: f64 = 0.08;
+const BOOKMARK_COLOR: Color32 = Color32::from_rgb(0xf0, 0xf0, 0xf0);
+/// How much is shown around a bookmark when jumping to it, in seconds.
+const BOOKMARK_JUMP_SECONDS: f64 = 5.0;
+/// Samples shown before and after the cursor in the table.
+const TABLE_ROWS: usize = 10;
+/// The rows of the pickups plot, from the bottom.
+const PICKUP_KINDS: [PickupKind; 4] = [
+    PickupKind::Shield,
+    PickupKind::Heart,
+    PickupKind::Ammo,
+    PickupKind::Weapon,
+];
+/// Pauses and tick jumps in the demo are shaded.
+const PAUSE_COLOR: Color32 = Color32::from_rgba_premultiplied(0x40, 0x40, 0x40, 0x40);
+const CURSOR_COLOR: Color32 = Color32::from_rgb(0xf0, 0xe0, 0x40);
+const HOOK_FLYING_COLOR: Color32 = Color32::from_rgb(0xa0, 0xa0, 0xa0);
+const HOOK_GRABBED_COLOR: Color32 = Color32::from_rgb(0xf0, 0x70, 0x40);
+/// At most this many hook lines are drawn in the map, longer ranges only draw every nth.
+const MAP_HOOK_LINES: usize = 2000;
+/// The rows of the input strip, from the bottom.
+const STRIP_ROWS: [(&str, Color32); 5] = [
+    ("Jump", Color32::from_rgb(0x5c, 0xc8, 0x6a)),
+    ("Fire", Color32::from_rgb(0xe0, 0x5a, 0x8a)),
+    ("Hook", HOOK_GRABBED_COLOR),
+    ("Right", Color32::from_rgb(0xf1, 0x8f, 0x4e)),
+    ("Left", Color32::from_rgb(0x4e, 0x9a, 0xf1)),
+];
+/// Height of the input strip in points, the other plots share the rest.
+const STRIP_HEIGHT: f32 = 90.0;
+
+fn event_color(kind: EventKind) -> Color32 {
+    match kind {
+        EventKind::Death => Color32::from_rgb(0xe0, 0x4a, 0x4a),
+        EventKind::Finish => Color32::from_rgb(0x5c, 0xc8, 0x6a),
+        EventKind::FreezeStart => Color32::from_rgb(0x6a, 0xb8, 0xf0),
+        EventKind::FreezeEnd => Color32::from_rgb(0xa0, 0xa0, 0xa0),
+        EventKind::WeaponPickup(_) => Color32::from_rgb(0xe0, 0xc8, 0x4a),
+    }
+}
+
+/// One demo with its own player selection and zoom.
+pub struct Tab {
+    pub title: String,
+    /// The demo bookmarks are exported next to
+    pub demo: PathBuf,
     pub names: Vec<String>,
     pub inputs: HashMap<String, Vec<Inputs>>,
-    pub filter: String,
-    pub selected: SelectedFilter,
+    pub player: String,
+    pub channels: Channels,
+    pub show_events: bool,
+    pub show_table: bool,
+    pub show_map: bool,
+    pub show_crosshair: bool,
+    /// Range of ticks the plots show, what gets exported
+    visible: Option<(f64, f64)>,
+    export_path: String,
+    export_format: ExtractionOutputFormat,
+    export_status: String,
+    pub events: HashMap<String, Vec<GameEvent>>,
+    pub timeline: Timeline,
+    /// Whether the selected player is shown in the compare view
+    pub compare: bool,
+    pub bookmarks: Vec<Bookmark>,
+    current_bookmark: Option<usize>,
+    /// Range of ticks the plots move to in the next frame
+    jump_to: Option<(f64, f64)>,
+    status: String,
+    /// Tick moved by the keyboard navigation
+    cursor: Option<i32>,
+    /// Whether the plot should scroll to the cursor in the next frame
+    follow_cursor: bool,
+    players: PlayerList,
 }
 
-#[derive(PartialEq, Eq, Default)]
-pub enum SelectedFilter {
-    #[default]
-    ShowBoth,
-    ShowHooks,
-    ShowDirections,
+impl Tab {
+    pub fn new(
+        title: String,
+        demo: PathBuf,
+        inputs: HashMap<String, Vec<Inputs>>,
+        timeline: Timeline,
+        bookmarks: Vec<Bookmark>,
+        info: HashMap<String, PlayerInfo>,
+    ) -> Self {
+        let player = inputs
+            .iter()
+            .max_by_key(|i| i.1.len())
+            .map(|i| i.0.to_owned())
+            .unwrap_or_default();
+        let mut names: Vec<_> = inputs.keys().cloned().collect();
+        names.sort();
+        let events = inputs
+            .iter()
+            .map(|(n, i)| (n.clone(), events::detect_events(i, timeline.tick_rate)))
+            .collect();
+        let rows = inputs
+            .iter()
+            .map(|(n, i)| {
+                let info = info.get(n).cloned().unwrap_or_default();
+                PlayerRow::new(n, i, info, timeline.tick_rate)
+            })
+            .collect();
+        let mut players = PlayerList {
+            rows,
+            search: String::new(),
+            clan: None,
+            team: None,
+            sort: PlayerSort::Name,
+        };
+        players.sort();
+        let export_path = format!("{}.selection.json", demo.display());
+        Self {
+            title,
+            demo,
+            names,
+            inputs,
+            player,
+            channels: Channels::default(),
+            show_events: true,
+            show_table: false,
+            show_map: false,
+            show_crosshair: false,
+            visible: None,
+            export_path,
+            export_format: ExtractionOutputFormat::Json,
+            export_status: String::new(),
+            events,
+            timeline,
+            compare: true,
+            bookmarks,
+            current_bookmark: None,
+            jump_to: None,
+            status: String::new(),
+            cursor: None,
+            follow_cursor: false,
+            players,
+        }
+    }
 }
 
-impl eframe::App for MyApp {
-    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        if ctx.input(|i| i.key_down(Key::Escape)) {
-            exit(0);
+/// What the plots of a tab show, every combination is possible.
+pub struct Channels {
+    pub directions: bool,
+    pub hooks: bool,
+    pub fire: bool,
+    pub jumps: bool,
+    pub pickups: bool,
+    pub speed: bool,
+    pub velocity_x: bool,
+    pub velocity_y: bool,
+    pub aim: bool,
+    pub aim_target: bool,
+    /// Every input as a row of colored spans, compact enough for long demos
+    pub strip: bool,
+}
+
+impl Default for Channels {
+    fn default() -> Self {
+        Self {
+            directions: true,
+            hooks: true,
+            fire: false,
+            jumps: false,
+            pickups: false,
+            speed: false,
+            velocity_x: false,
+            velocity_y: false,
+            aim: false,
+            aim_target: false,
+            strip: false,
         }
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.vertical(|ui| {
-                ui.label("Player name:");
-                ui.add_enabled(
-                    self.names.len() > 1,
-                    DropDownBox::from_iter(
-                        &self.names,
-                        "test_dropbox",
-                        &mut self.filter,
-                        |ui, text| ui.selectable_label(false, text),
-                    ),
+    }
+}
+
+impl Channels {
+    fn show(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal_wrapped(|ui| {
+            ui.checkbox(&mut self.directions, "Directions");
+            ui.checkbox(&mut self.hooks, "Hooks");
+            ui.checkbox(&mut self.fire, "Fire");
+            ui.checkbox(&mut self.jumps, "Jumps");
+            ui.checkbox(&mut self.pickups, "Pickups");
+            ui.checkbox(&mut self.speed, "Speed");
+            ui.checkbox(&mut self.velocity_x, "Velocity x");
+            ui.checkbox(&mut self.velocity_y, "Velocity y");
+            ui.checkbox(&mut self.aim, "Aim");
+            ui.checkbox(&mut self.aim_target, "Aim target");
+            ui.checkbox(&mut self.strip, "Input strip");
+        });
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy)]
+enum View {
+    Tab(usize),
+    Compare,
+}
+
+pub enum SessionMode {
+    Off,
+    Record(Recorder),
+    Replay(Session),
+}
+
+/// What was last recorded of a tab, steps are only written when something changed.
+#[derive(Default)]
+struct Recorded {
+    player: Option<String>,
+    view: Option<(f64, f64)>,
+    bookmarks: Option<Vec<Bookmark>>,
+}
+
+enum SessionState {
+    Off,
+    Record {
+        recorder: Recorder,
+        view: Option<View>,
+        tabs: Vec<Recorded>,
+        note: String,
+    },
+    Replay {
+        session: Session,
+        /// How many steps are applied
+        position: usize,
+    },
+}
+
+pub struct MyApp {
+    pub tabs: Vec<Tab>,
+    pub time_format: TimeFormat,
+    keymap: Keymap,
+    view: View,
+    session: SessionState,
+}
+
+impl MyApp {
+    pub fn new(
+        tabs: Vec<Tab>,
+        time_format: TimeFormat,
+        keymap: Keymap,
+        session: SessionMode,
+    ) -> Self {
+        let session = match session {
+            SessionMode::Off => SessionState::Off,
+            SessionMode::Record(recorder) => SessionState::Record {
+                recorder,
+                view: None,
+                tabs: tabs.iter().map(|_| Recorded::default()).collect(),
+                note: String::new(),
+            },
+            SessionMode::Replay(session) => SessionState::Replay {
+                session,
+                position: 0,
+            },
+        };
+        Self {
+            tabs,
+            time_format,
+            keymap,
+            view: View::Tab(0),
+            session,
+        }
+    }
+}
+
+/// The first sample after the cursor where the value differs from the sample before it.
+fn next_change<T: PartialEq>(
+    data: &[Inputs],
+    cursor: i32,
+    value: impl Fn(&Inputs) -> T,
+) -> Option<i32> {
+    let start = data.partition_point(|i| i.tick <= cursor).max(1);
+    (start..data.len())
+        .find(|&i| value(&data[i]) != value(&data[i - 1]))
+        .map(|i| data[i].tick)
+}
+
+fn previous_change<T: PartialEq>(
+    data: &[Inputs],
+    cursor: i32,
+    value: impl Fn(&Inputs) -> T,
+) -> Option<i32> {
+    let end = data.partition_point(|i| i.tick < cursor);
+    (1..end)
+        .rev()
+        .find(|&i| value(&data[i]) != value(&data[i - 1]))
+        .map(|i| data[i].tick)
+}
+
+fn direction_points(data: &[Inputs], x: impl Fn(i32) -> f64) -> PlotPoints {
+    data.iter()
+        .map(|t| {
+            [
+                x(t.tick),
+                match t.direction {
+                    data::Direction::Left => -1,
+                    data::Direction::None => 0,
+                    data::Direction::Right => 1,
+                } as f64,
+            ]
+        })
+        .collect()
+}
+
+/// The ranges of ticks the input is held, until the tick of the sample after the last one.
+fn held_spans(data: &[Inputs], held: impl Fn(&Inputs) -> bool) -> Vec<(i32, i32)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (i, sample) in data.iter().enumerate() {
+        match (start, held(sample)) {
+            (None, true) => start = Some(sample.tick),
+            (Some(from), false) => {
+                spans.push((from, sample.tick));
+                start = None;
+            }
+            _ => {}
+        }
+        if let (Some(from), None) = (start, data.get(i + 1)) {
+            spans.push((from, sample.tick + 1));
+        }
+    }
+    spans
+}
+
+fn hook_bars(data: &[Inputs], x: impl Fn(i32) -> f64) -> Vec<Bar> {
+    data.iter()
+        .map(|t| {
+            let hook = if t.hook_state.pressed() { 1.0 } else { 0.0 };
+            Bar::new(x(t.tick), hook)
+        })
+        .collect()
+}
+
+/// The plots stacked in a tab, all sharing the x axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum Channel {
+    Directions,
+    Hooks,
+    /// Shots and jumps
+    Actions,
+    Pickups,
+    Speed,
+    Aim,
+    /// Where the crosshair was relative to the tee
+    AimTarget,
+    /// All inputs in one compact plot
+    Strip,
+}
+
+fn grid_marks(values: &[f64]) -> Vec<GridMark> {
+    values
+        .iter()
+        .map(|&value| GridMark {
+            value,
+            step_size: 1.0,
+        })
+        .collect()
+}
+
+/// The plot of one channel with its y axis labeled, `seconds` turns x values into the time
+/// shown on the x axis.
+fn channel_plot<'a>(
+    id: impl std::hash::Hash,
+    channel: Channel,
+    time_format: TimeFormat,
+    seconds: impl Fn(f64) -> f32 + Copy + 'a,
+) -> Plot<'a> {
+    let plot = Plot::new(id)
+        .allow_scroll(false)
+        .x_axis_formatter(move |gm, _rng| time_format.format_short(seconds(gm.value)))
+        .label_formatter(move |name, value| {
+            let time = time_format.format(seconds(value.x));
+            let value = match channel {
+                Channel::Speed => format!("{:.1} tiles/s\n", value.y),
+                Channel::Aim => format!("{:.0}°\n", value.y.to_degrees()),
+                Channel::AimTarget => format!("{:.1} tiles\n", value.y),
+                Channel::Directions
+                | Channel::Hooks
+                | Channel::Actions
+                | Channel::Pickups
+                | Channel::Strip => String::new(),
+            };
+            if name.is_empty() {
+                format!("{value}{time}")
+            } else {
+                format!("{name}\n{value}{time}")
+            }
+        });
+    match channel {
+        Channel::Directions => plot
+            .include_y(-1.5)
+            .include_y(1.5)
+            .y_axis_formatter(|gm, _rng| match gm.value.round() as i32 {
+                -1 => s!("Left"),
+                1 => s!("Right"),
+                _ => s!("None"),
+            })
+            .y_grid_spacer(|_| grid_marks(&[-1.0, 0.0, 1.0])),
+        Channel::Hooks => plot
+            .include_y(-0.5)
+            .include_y(1.5)
+            .y_axis_formatter(|gm, _rng| {
+                if gm.value > 0.5 {
+                    s!("Hook")
+                } else {
+                    s!("Idle")
+                }
+            })
+            .y_grid_spacer(|_| grid_marks(&[0.0, 1.0])),
+        Channel::Actions => plot
+            .include_y(-0.5)
+            .include_y(1.5)
+            .legend(Legend::default())
+            .y_axis_formatter(|gm, _rng| {
+                if gm.value > 0.5 {
+                    s!("Fire")
+                } else {
+                    s!("Jump")
+                }
+            })
+            .y_grid_spacer(|_| grid_marks(&[0.0, 1.0])),
+        Channel::Pickups => plot
+            .include_y(-0.5)
+            .include_y(3.5)
+            .legend(Legend::default())
+            .y_axis_formatter(|gm, _rng| {
+                PICKUP_KINDS
+                    .get(gm.value.round() as usize)
+                    .map(|k| k.label().to_string())
+                    .unwrap_or_default()
+            })
+            .y_grid_spacer(|_| grid_marks(&[0.0, 1.0, 2.0, 3.0])),
+        Channel::Speed => plot.legend(Legend::default()),
+        Channel::Aim => plot
+            .include_y(-PI)
+            .include_y(PI)
+            .y_axis_formatter(|gm, _rng| format!("{:.0}°", gm.value.to_degrees())),
+        Channel::AimTarget => plot.legend(Legend::default()),
+        Channel::Strip => plot
+            .include_y(-0.5)
+            .include_y(STRIP_ROWS.len() as f64 - 0.5)
+            .y_axis_formatter(|gm, _rng| {
+                STRIP_ROWS
+                    .get(gm.value.round() as usize)
+                    .map(|(label, _)| label.to_string())
+                    .unwrap_or_default()
+            })
+            .y_grid_spacer(|_| grid_marks(&[0.0, 1.0, 2.0, 3.0, 4.0])),
+    }
+}
+
+impl Tab {
+    fn show(&mut self, ui: &mut egui::Ui, index: usize, time_format: TimeFormat, keymap: &Keymap) {
+        egui::SidePanel::left(egui::Id::new(("players", index)))
+            .show_inside(ui, |ui| self.show_players(ui, index));
+        egui::SidePanel::right(egui::Id::new(("bookmarks", index))).show_inside(ui, |ui| {
+            self.show_bookmarks(ui, time_format);
+            ui.separator();
+            Self::show_keys(ui, keymap);
+        });
+        egui::CentralPanel::default().show_inside(ui, |ui| self.show_plot(ui, index, time_format));
+    }
+
+    /// The custom skin color of the selected player.
+    fn player_color(&self) -> Option<Color32> {
+        let row = self.players.rows.iter().find(|r| r.name == self.player)?;
+        row.info.color()
+    }
+
+    fn jump(&mut self, bookmark: usize) {
+        self.current_bookmark = Some(bookmark);
+        let bookmark = &self.bookmarks[bookmark];
+        if self.inputs.contains_key(&bookmark.player) {
+            self.player = bookmark.player.clone();
+        }
+        let (tick, half) = (
+            bookmark.tick as f64,
+            BOOKMARK_JUMP_SECONDS * self.timeline.tick_rate as f64,
+        );
+        self.jump_to = Some((tick - half, tick + half));
+        self.cursor = Some(bookmark.tick);
+    }
+
+    fn navigate(&mut self, action: Action) {
+        if let Action::PreviousPlayer | Action::NextPlayer = action {
+            let n = self.names.len();
+            if n == 0 {
+                return;
+            }
+            let i = match (action, self.names.iter().position(|p| *p == self.player)) {
+                (Action::NextPlayer, Some(i)) => (i + 1) % n,
+                (_, Some(i)) => (i + n - 1) % n,
+                (_, None) => 0,
+            };
+            self.player = self.names[i].clone();
+            return;
+        }
+
+        let Some(data) = self.inputs.get(&self.player) else {
+            return;
+        };
+        let (Some(first), Some(last)) = (data.first(), data.last()) else {
+            return;
+        };
+        let cursor = self.cursor.unwrap_or(first.tick);
+        let second = self.timeline.tick_rate;
+        let target = match action {
+            Action::PreviousFrame => data[..data.partition_point(|i| i.tick < cursor)]
+                .last()
+                .map(|i| i.tick),
+            Action::NextFrame => data
+                .get(data.partition_point(|i| i.tick <= cursor))
+                .map(|i| i.tick),
+            Action::Back1s => Some(cursor - second),
+            Action::Forward1s => Some(cursor + second),
+            Action::Back10s => Some(cursor - 10 * second),
+            Action::Forward10s => Some(cursor + 10 * second),
+            Action::PreviousDirectionChange => previous_change(data, cursor, |i| i.direction),
+            Action::NextDirectionChange => next_change(data, cursor, |i| i.direction),
+            Action::PreviousHook => previous_change(data, cursor, |i| i.hook_state.pressed()),
+            Action::NextHook => next_change(data, cursor, |i| i.hook_state.pressed()),
+            Action::PreviousPlayer | Action::NextPlayer => unreachable!(),
+        };
+        if let Some(target) = target {
+            self.cursor = Some(target.clamp(first.tick, last.tick));
+            self.follow_cursor = true;
+        }
+    }
+
+    fn add_bookmark(&mut self, tick: i32) {
+        let bookmark = Bookmark {
+            player: self.player.clone(),
+            tick,
+            time: self.timeline.timestamp(tick).time,
+            label: format!("Bookmark {}", self.bookmarks.len() + 1),
+        };
+        let i = self.bookmarks.partition_point(|b| b.tick <= tick);
+        self.bookmarks.insert(i, bookmark);
+        self.current_bookmark = Some(i);
+    }
+
+    fn show_bookmarks(&mut self, ui: &mut egui::Ui, time_format: TimeFormat) {
+        ui.heading("Bookmarks");
+        ui.label("Click the plot to add one.");
+        let count = self.bookmarks.len();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(count > 0, Button::new("Previous")).clicked() {
+                self.jump(
+                    self.current_bookmark
+                        .map_or(count - 1, |i| (i + count - 1) % count),
                 );
-            });
-            let mut reset = false;
-            ui.vertical(|ui| {
-                ComboBox::from_label("filter")
-                    .selected_text(format!(
-                        "{}",
-                        match self.selected {
-                            SelectedFilter::ShowBoth => "Both",
-                            SelectedFilter::ShowHooks => "Hooks",
-                            SelectedFilter::ShowDirections => "Directions",
-                        }
-                    ))
-                    .show_ui(ui, |ui| {
-                        ui.selectable_value(&mut self.selected, SelectedFilter::ShowHooks, "Hooks");
-                        ui.selectable_value(
-                            &mut self.selected,
-                            SelectedFilter::ShowDirections,
-                            "Directions",
+            }
+            if ui.add_enabled(count > 0, Button::new("Next")).clicked() {
+                self.jump(self.current_bookmark.map_or(0, |i| (i + 1) % count));
+            }
+        });
+
+        let (mut jump, mut remove) = (None, None);
+        egui::ScrollArea::vertical()
+            .max_height(ui.available_height() - 60.0)
+            .show(ui, |ui| {
+                for (i, bookmark) in self.bookmarks.iter_mut().enumerate() {
+                    ui.separator();
+                    ui.horizontal(|ui| {
+                        let text = format!(
+                            "{} {}",
+                            time_format.format(self.timeline.seconds(bookmark.tick)),
+                            bookmark.player
                         );
-                        ui.selectable_value(&mut self.selected, SelectedFilter::ShowBoth, "Both");
+                        if ui
+                            .selectable_label(self.current_bookmark == Some(i), text)
+                            .clicked()
+                        {
+                            jump = Some(i);
+                        }
+                        if ui.small_button("Delete").clicked() {
+                            remove = Some(i);
+                        }
                     });
-                reset = ui.button("Reset").clicked();
+                    ui.text_edit_singleline(&mut bookmark.label);
+                }
+            });
+        if let Some(i) = jump {
+            self.jump(i);
+        }
+        if let Some(i) = remove {
+            self.bookmarks.remove(i);
+            self.current_bookmark = None;
+        }
+
+        ui.separator();
+        if ui.button("Export JSON").clicked() {
+            self.status = match bookmarks::save(&self.demo, &self.bookmarks) {
+                Ok(path) => format!("Saved to {}", path.display()),
+                Err(e) => format!("Couldn't save the bookmarks: {e}"),
+            };
+        }
+        ui.label(&self.status);
+    }
+
+    fn show_players(&mut self, ui: &mut egui::Ui, index: usize) {
+        ui.heading("Players");
+        let list = &mut self.players;
+        ui.horizontal(|ui| {
+            ui.label("Search:");
+            ui.text_edit_singleline(&mut list.search);
+        });
+
+        let mut clans: Vec<_> = list.rows.iter().map(|r| r.info.clan.clone()).collect();
+        clans.sort();
+        clans.dedup();
+        ComboBox::from_id_source(("clan", index))
+            .selected_text(match &list.clan {
+                Some(clan) => format!("Clan: {clan}"),
+                None => s!("Clan: all"),
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut list.clan, None, "All");
+                for clan in clans {
+                    let label = if clan.is_empty() {
+                        s!("(none)")
+                    } else {
+                        clan.clone()
+                    };
+                    ui.selectable_value(&mut list.clan, Some(clan), label);
+                }
             });
 
-            if let Some(data) = self.inputs.get(&self.filter) {
-                let direction_data: PlotPoints = data
-                    .iter()
-                    .map(|t| {
-                        [
-                            t.tick as f64,
-                            match t.direction {
-                                data::Direction::Left => -1,
-                                data::Direction::None => 0,
-                                data::Direction::Right => 1,
-                            } as f64,
-                        ]
-                    })
-                    .collect();
+        let mut teams: Vec<_> = list.rows.iter().map(|r| r.info.team).collect();
+        teams.sort();
+        teams.dedup();
+        ComboBox::from_id_source(("team", index))
+            .selected_text(match list.team {
+                Some(team) => format!("Team: {team}"),
+                None => s!("Team: all"),
+            })
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut list.team, None, "All");
+                for team in teams {
+                    ui.selectable_value(&mut list.team, Some(team), team.to_string());
+                }
+            });
+
+        let sort = list.sort;
+        ComboBox::from_id_source(("sort", index))
+            .selected_text(format!("Sort: {}", sort.label()))
+            .show_ui(ui, |ui| {
+                for option in PlayerSort::ALL {
+                    ui.selectable_value(&mut list.sort, option, option.label());
+                }
+            });
+        if list.sort != sort {
+            list.sort();
+        }
+
+        ui.separator();
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new(("player_list", index))
+                .striped(true)
+                .show(ui, |ui| {
+                    ui.strong("Name");
+                    ui.strong("Clan");
+                    ui.strong("Team");
+                    ui.strong("Dir/s");
+                    ui.strong("Hook/s");
+                    ui.end_row();
+                    for row in list.rows.iter().filter(|r| list.matches(r)) {
+                        let selected = row.name == self.player;
+                        ui.horizontal(|ui| {
+                            color_swatch(ui, row.info.color());
+                            if ui.selectable_label(selected, &row.name).clicked() {
+                                self.player = row.name.clone();
+                            }
+                        });
+                        ui.label(&row.info.clan);
+                        ui.label(row.info.team.to_string());
+                        ui.label(format!("{:.2} ({})", row.direction_rate, row.direction_max))
+                            .on_hover_text("Average per second (most in one second)");
+                        ui.label(format!("{:.2} ({})", row.hook_rate, row.hook_max))
+                            .on_hover_text("Average per second (most in one second)");
+                        ui.end_row();
+                    }
+                });
+        });
+    }
+
+    fn show_keys(ui: &mut egui::Ui, keymap: &Keymap) {
+        ui.collapsing("Keys", |ui| {
+            egui::Grid::new("keys").show(ui, |ui| {
+                for (action, keys) in keymap.names() {
+                    ui.label(action.label());
+                    ui.label(keys.join(", "));
+                    ui.end_row();
+                }
+            });
+        });
+    }
+
+    fn show_plot(&mut self, ui: &mut egui::Ui, index: usize, time_format: TimeFormat) {
+        ui.vertical(|ui| {
+            ui.label("Player name:");
+            ui.add_enabled(
+                self.names.len() > 1,
+                DropDownBox::from_iter(
+                    &self.names,
+                    ("player_dropbox", index),
+                    &mut self.player,
+                    |ui, text| ui.selectable_label(false, text),
+                ),
+            );
+        });
+        let mut reset = false;
+        ui.vertical(|ui| {
+            self.channels.show(ui);
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut self.show_events, "Events");
+                ui.checkbox(&mut self.show_table, "Table");
+                ui.checkbox(&mut self.show_map, "Map");
+                ui.checkbox(&mut self.show_crosshair, "Crosshair");
+            });
+            reset = ui.button("Reset").clicked();
+        });
+        ui.horizontal(|ui| {
+            let format = self.export_format;
+            ComboBox::from_id_source(("export_format", index))
+                .selected_text(match format {
+                    ExtractionOutputFormat::Csv => "CSV",
+                    _ => "JSON",
+                })
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(
+                        &mut self.export_format,
+                        ExtractionOutputFormat::Json,
+                        "JSON",
+                    );
+                    ui.selectable_value(
+                        &mut self.export_format,
+                        ExtractionOutputFormat::Csv,
+                        "CSV",
+                    );
+                });
+            if self.export_format != format {
+                let extension = match self.export_format {
+                    ExtractionOutputFormat::Csv => "csv",
+                    _ => "json",
+                };
+                self.export_path = Path::new(&self.export_path)
+                    .with_extension(extension)
+                    .to_string_lossy()
+                    .into_owned();
+            }
+            ui.text_edit_singleline(&mut self.export_path);
+            if ui.button("Export selection…").clicked() {
+                self.export_status = match self.export_selection() {
+                    Ok(samples) => format!("Wrote {samples} samples"),
+                    Err(e) => format!("Couldn't export the selection: {e}"),
+                };
+            }
+            ui.label(&self.export_status);
+        });
+        let sample = self
+            .cursor
+            .zip(self.inputs.get(&self.player))
+            .and_then(|(cursor, data)| {
+                data.get(data.partition_point(|i| i.tick < cursor))
+                    .filter(|i| i.tick == cursor)
+            });
+        if let (Some(cursor), Some(sample)) = (self.cursor, sample) {
+            ui.label(format!(
+                "{}: {:?}, hook {:?}",
+                time_format.format(self.timeline.seconds(cursor)),
+                sample.direction,
+                sample.hook_state
+            ));
+        }
 
-                let hook_data: Vec<Bar> = data
+        if self.show_table {
+            egui::TopBottomPanel::bottom(egui::Id::new(("table", index)))
+                .resizable(true)
+                .show_inside(ui, |ui| self.show_table(ui, index, time_format));
+        }
+        if self.show_map {
+            egui::SidePanel::right(egui::Id::new(("map", index)))
+                .resizable(true)
+                .default_width(ui.available_width() / 3.0)
+                .show_inside(ui, |ui| self.show_map(ui, index));
+        }
+        if self.show_crosshair {
+            egui::SidePanel::right(egui::Id::new(("crosshair", index)))
+                .resizable(true)
+                .default_width(ui.available_width() / 3.0)
+                .show_inside(ui, |ui| self.show_crosshair(ui, index));
+        }
+
+        let Some(data) = self.inputs.get(&self.player) else {
+            return;
+        };
+        let channels = self.channels();
+        let spacing = ui.spacing().item_spacing.y * (channels.len() as f32 - 1.0).max(0.0);
+        let strip = if self.channels.strip {
+            STRIP_HEIGHT
+        } else {
+            0.0
+        };
+        let plots = channels.len() - usize::from(self.channels.strip);
+        let height = (ui.available_height() - spacing - strip) / plots.max(1) as f32;
+        let timeline = self.timeline.clone();
+        let seconds = |x: f64| timeline.seconds(x.round() as i32);
+        let link = egui::Id::new(("linked", index));
+        let jump_to = self.jump_to.take();
+        let follow_cursor = std::mem::take(&mut self.follow_cursor);
+        let (mut clicked, mut visible) = (None, None);
+        for (i, channel) in channels.into_iter().enumerate() {
+            // Every tab has its own plot ids, so each keeps its own zoom
+            let plot = channel_plot((index, channel), channel, time_format, seconds)
+                .height(if channel == Channel::Strip {
+                    strip
+                } else {
+                    height
+                })
+                .link_axis(link, true, false)
+                .link_cursor(link, true, false);
+            let plot = if reset { plot.reset() } else { plot };
+            let response = plot.show(ui, |plot_ui| {
+                self.draw_channel(plot_ui, channel, data);
+                self.draw_markers(plot_ui);
+                // The x axes are linked, moving the first plot moves all of them
+                if i == 0 {
+                    self.move_view(plot_ui, jump_to, follow_cursor);
+                }
+                (plot_ui.pointer_coordinate(), plot_ui.plot_bounds())
+            });
+            let (pointer, bounds) = response.inner;
+            if response.response.clicked() {
+                clicked = pointer.or(clicked);
+            }
+            visible = Some((bounds.min()[0], bounds.max()[0]));
+        }
+        self.visible = visible;
+        if let Some(pointer) = clicked {
+            self.add_bookmark(pointer.x.round() as i32);
+        }
+    }
+
+    /// The path of the selected player in the range the plots show, with the hook as a line
+    /// from the tee to where it is, colored by whether it is flying or grabbed.
+    fn show_map(&self, ui: &mut egui::Ui, index: usize) {
+        let Some(data) = self.inputs.get(&self.player) else {
+            return;
+        };
+        let (from, to) = self.visible.unwrap_or((f64::MIN, f64::MAX));
+        let data = &data[data.partition_point(|i| (i.tick as f64) < from)
+            ..data.partition_point(|i| (i.tick as f64) <= to)];
+        // The game counts y downwards
+        let point = |p: &data::Position| [p.x.to_num::<f64>(), -p.y.to_num::<f64>()];
+        let hooks: Vec<&Inputs> = data.iter().filter(|i| i.hook_state.pressed()).collect();
+        let step = hooks.len().div_ceil(MAP_HOOK_LINES).max(1);
+        let cursor = self.cursor.and_then(|cursor| {
+            data.get(data.partition_point(|i| i.tick < cursor))
+                .filter(|i| i.tick == cursor)
+        });
+        Plot::new(("map", index))
+            .data_aspect(1.0)
+            .allow_scroll(false)
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                let path: PlotPoints = data.iter().map(|i| point(&i.pos)).collect();
+                plot_ui.line(Line::new(path).name("Path"));
+                for sample in hooks.into_iter().step_by(step) {
+                    let (name, color) = if sample.hook_state == HookState::Grabbed {
+                        ("Hook grabbed", HOOK_GRABBED_COLOR)
+                    } else {
+                        ("Hook flying", HOOK_FLYING_COLOR)
+                    };
+                    let line = vec![point(&sample.pos), point(&sample.hook_pos)];
+                    plot_ui.line(Line::new(line).color(color).name(name));
+                }
+                if let Some(sample) = cursor {
+                    plot_ui.points(
+                        Points::new(vec![point(&sample.pos)])
+                            .color(CURSOR_COLOR)
+                            .radius(4.0)
+                            .name("Cursor"),
+                    );
+                }
+            });
+    }
+
+    /// Where the selected player aimed relative to their tee in the range the plots show, and
+    /// where the other players were at the same ticks.
+    fn show_crosshair(&self, ui: &mut egui::Ui, index: usize) {
+        let (from, to) = self.visible.unwrap_or((f64::MIN, f64::MAX));
+        let ticks =
+            from.ceil().max(i32::MIN as f64) as i32..=to.floor().min(i32::MAX as f64) as i32;
+        let trace = crosshair::trace(&self.inputs, &self.player, ticks);
+        // The game counts y downwards
+        let points = |points: Vec<[f32; 2]>| -> PlotPoints {
+            points
+                .into_iter()
+                .map(|[x, y]| [x as f64, -y as f64])
+                .collect()
+        };
+        Plot::new(("crosshair", index))
+            .data_aspect(1.0)
+            .allow_scroll(false)
+            .legend(Legend::default())
+            .show(ui, |plot_ui| {
+                plot_ui.points(
+                    Points::new(points(trace.others))
+                        .color(HOOK_FLYING_COLOR)
+                        .name("Other players"),
+                );
+                plot_ui.points(Points::new(points(trace.targets)).name("Target"));
+                plot_ui.points(
+                    Points::new(vec![[0.0, 0.0]])
+                        .color(CURSOR_COLOR)
+                        .radius(4.0)
+                        .name("Tee"),
+                );
+            });
+    }
+
+    /// Writes the samples of the selected player that are currently visible.
+    fn export_selection(&self) -> anyhow::Result<usize> {
+        let data = self
+            .inputs
+            .get(&self.player)
+            .context("No player selected")?;
+        let (from, to) = self.visible.unwrap_or((f64::MIN, f64::MAX));
+        let selection: Vec<Inputs> = data
+            .iter()
+            .filter(|i| (from..=to).contains(&(i.tick as f64)))
+            .cloned()
+            .collect();
+        let samples = selection.len();
+        let selection = HashMap::from([(self.player.clone(), selection)]);
+        crate::write_output(
+            Some(PathBuf::from(&self.export_path)),
+            false,
+            crate::serialize(
+                &selection,
+                self.export_format,
+                true,
+                crate::numbers::NumberFormat::default(),
+                &crate::keys::Keys::default(),
+            ),
+        )?;
+        Ok(samples)
+    }
+
+    /// The samples around the cursor with their exact values, clicking one moves the cursor.
+    fn show_table(&mut self, ui: &mut egui::Ui, index: usize, time_format: TimeFormat) {
+        let Some(data) = self.inputs.get(&self.player) else {
+            return;
+        };
+        let cursor = self
+            .cursor
+            .unwrap_or_else(|| data.first().map_or(0, |i| i.tick));
+        let at = data.partition_point(|i| i.tick < cursor);
+        let range = at.saturating_sub(TABLE_ROWS)..(at + TABLE_ROWS + 1).min(data.len());
+        let mut clicked = None;
+        egui::ScrollArea::both().show(ui, |ui| {
+            egui::Grid::new(("table", index))
+                .striped(true)
+                .show(ui, |ui| {
+                    for column in table::COLUMNS {
+                        ui.strong(column);
+                    }
+                    ui.end_row();
+                    for input in &data[range] {
+                        let [tick, values @ ..] = table::row(input, &self.timeline, time_format);
+                        if ui.selectable_label(input.tick == cursor, tick).clicked() {
+                            clicked = Some(input.tick);
+                        }
+                        for value in values {
+                            ui.label(value);
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+        if let Some(tick) = clicked {
+            self.cursor = Some(tick);
+            self.follow_cursor = true;
+        }
+    }
+
+    fn channels(&self) -> Vec<Channel> {
+        let c = &self.channels;
+        [
+            (Channel::Directions, c.directions),
+            (Channel::Hooks, c.hooks),
+            (Channel::Actions, c.fire || c.jumps),
+            (Channel::Pickups, c.pickups),
+            (Channel::Speed, c.speed || c.velocity_x || c.velocity_y),
+            (Channel::Aim, c.aim),
+            (Channel::AimTarget, c.aim_target),
+            (Channel::Strip, c.strip),
+        ]
+        .into_iter()
+        .filter(|(_, shown)| *shown)
+        .map(|(channel, _)| channel)
+        .collect()
+    }
+
+    fn draw_channel(&self, plot_ui: &mut PlotUi, channel: Channel, data: &[Inputs]) {
+        let tick = |t: i32| t as f64;
+        match channel {
+            Channel::Directions => {
+                plot_ui.line(Line::new(direction_points(data, tick)).name("Direction"))
+            }
+            Channel::Hooks => plot_ui.bar_chart(BarChart::new(hook_bars(data, tick)).name("Hook")),
+            Channel::Speed => {
+                // Velocities are in tiles per tick
+                let per_second = self.timeline.tick_rate as f64;
+                let points = |value: fn(&Inputs) -> f64| -> PlotPoints {
+                    data.iter()
+                        .map(|i| [i.tick as f64, value(i) * per_second])
+                        .collect()
+                };
+                if self.channels.speed {
+                    plot_ui.line(
+                        Line::new(points(|i| {
+                            i.vel.x.to_num::<f64>().hypot(i.vel.y.to_num::<f64>())
+                        }))
+                        .name("Speed"),
+                    );
+                }
+                if self.channels.velocity_x {
+                    plot_ui.line(Line::new(points(|i| i.vel.x.to_num::<f64>())).name("Velocity x"));
+                }
+                if self.channels.velocity_y {
+                    plot_ui.line(Line::new(points(|i| i.vel.y.to_num::<f64>())).name("Velocity y"));
+                }
+            }
+            Channel::Actions => {
+                if self.channels.fire {
+                    let mut shots: BTreeMap<ActiveWeapon, Vec<[f64; 2]>> = BTreeMap::new();
+                    for a in attack::attacks(data) {
+                        shots
+                            .entry(a.weapon)
+                            .or_default()
+                            .push([a.tick as f64, 1.0]);
+                    }
+                    for (weapon, points) in shots {
+                        plot_ui.points(Points::new(points).name(format!("{weapon:?}")).radius(3.0));
+                    }
+                }
+                if self.channels.jumps {
+                    // Counts every jump since the tee last touched the ground
+                    let jumps: Vec<[f64; 2]> = data
+                        .windows(2)
+                        .filter(|w| w[1].jumped_total > w[0].jumped_total)
+                        .map(|w| [w[1].tick as f64, 0.0])
+                        .collect();
+                    plot_ui.points(Points::new(jumps).name("Jump").radius(3.0));
+                }
+            }
+            Channel::Pickups => {
+                // Weapon pickups are named after the weapon
+                let mut points: BTreeMap<String, Vec<[f64; 2]>> = BTreeMap::new();
+                for p in pickups::pickups(data) {
+                    let y = PICKUP_KINDS.iter().position(|k| *k == p.kind).unwrap_or(0);
+                    let name = match p.kind {
+                        PickupKind::Weapon => format!("{:?}", p.weapon),
+                        kind => kind.label().to_string(),
+                    };
+                    points
+                        .entry(name)
+                        .or_default()
+                        .push([p.tick as f64, y as f64]);
+                }
+                for (name, points) in points {
+                    plot_ui.points(Points::new(points).name(name).radius(3.0));
+                }
+            }
+            Channel::Aim => {
+                let aim: PlotPoints = data
                     .iter()
-                    .map(|t| {
-                        let hook = match t.hook_state {
-                            data::HookState::Retracted => 0.0,
-                            data::HookState::Idle => 0.0,
-                            data::HookState::RetractStart => 0.0,
-                            data::HookState::Retracting => 0.0,
-                            data::HookState::RetractEnd => 0.0,
-                            data::HookState::Flying => 0.5,
-                            data::HookState::Grabbed => 0.5,
-                        };
-                        Bar::new(t.tick as f64, hook)
-                    })
+                    .map(|t| [t.tick as f64, t.angle.to_num::<f64>()])
                     .collect();
+                plot_ui.line(Line::new(aim).name("Aim"));
+            }
+            Channel::AimTarget => {
+                let target = |axis: fn(&Inputs) -> f32| -> PlotPoints {
+                    data.iter()
+                        .map(|t| [t.tick as f64, axis(t) as f64])
+                        .collect()
+                };
+                plot_ui.line(Line::new(target(|t| t.target.tiles().0)).name("Target x"));
+                plot_ui.line(Line::new(target(|t| t.target.tiles().1)).name("Target y"));
+            }
+            Channel::Strip => {
+                // Shots and jumps only last a tick, they are drawn one tick wide
+                let ticks = |ticks: Vec<i32>| ticks.into_iter().map(|t| (t, t + 1)).collect();
+                let rows: [Vec<(i32, i32)>; 5] = [
+                    ticks(
+                        data.windows(2)
+                            .filter(|w| w[1].jumped_total > w[0].jumped_total)
+                            .map(|w| w[1].tick)
+                            .collect(),
+                    ),
+                    ticks(attack::attacks(data).iter().map(|a| a.tick).collect()),
+                    held_spans(data, |i| i.hook_state.pressed()),
+                    held_spans(data, |i| i.direction == data::Direction::Right),
+                    held_spans(data, |i| i.direction == data::Direction::Left),
+                ];
+                for (row, (spans, (label, color))) in rows.iter().zip(STRIP_ROWS).enumerate() {
+                    let (bottom, top) = (row as f64 - 0.4, row as f64 + 0.4);
+                    for &(from, to) in spans {
+                        let (from, to) = (from as f64, to as f64);
+                        plot_ui.polygon(
+                            Polygon::new(vec![
+                                [from, bottom],
+                                [to, bottom],
+                                [to, top],
+                                [from, top],
+                            ])
+                            .name(label)
+                            .fill_color(color)
+                            .stroke((1.0, color))
+                            .allow_hover(false),
+                        );
+                    }
+                }
+            }
+        }
+    }
 
-                let directions = Line::new(direction_data);
-                let hooks = BarChart::new(hook_data);
-                let plot = Plot::new("direction_plot")
-                    .allow_scroll(false)
-                    .y_axis_formatter(|gm, _rng| {
-                        if gm.value < 0.0 {
-                            s!("Left")
-                        } else if gm.value > 0.0 {
-                            if gm.value > 0.4 && gm.value < 0.6 {
-                                s!("Hook")
-                            } else {
-                                s!("Right")
-                            }
-                        } else {
-                            s!("Idle")
+    /// Events, bookmarks and the cursor, drawn on every plot.
+    fn draw_markers(&self, plot_ui: &mut PlotUi) {
+        let bounds = plot_ui.plot_bounds();
+        let margin = bounds.height() * MARKER_MARGIN;
+        let (top, bottom) = (bounds.max()[1] - margin, bounds.min()[1] + margin);
+        for pause in &self.timeline.pauses {
+            let (from, to) = (pause.from as f64, pause.to as f64);
+            let (min, max) = (bounds.min()[1], bounds.max()[1]);
+            plot_ui.polygon(
+                Polygon::new(vec![[from, min], [to, min], [to, max], [from, max]])
+                    .name("Pause")
+                    .fill_color(PAUSE_COLOR)
+                    .stroke((1.0, PAUSE_COLOR))
+                    .allow_hover(false),
+            );
+        }
+        if self.show_events {
+            for event in self.events.get(&self.player).into_iter().flatten() {
+                let color = event_color(event.kind);
+                plot_ui.vline(VLine::new(event.tick as f64).color(color.gamma_multiply(0.5)));
+                plot_ui.points(
+                    Points::new([event.tick as f64, top])
+                        .name(event.kind.label())
+                        .color(color)
+                        .radius(4.0),
+                );
+            }
+        }
+        for bookmark in self.bookmarks.iter().filter(|b| b.player == self.player) {
+            let x = bookmark.tick as f64;
+            plot_ui.vline(VLine::new(x).color(BOOKMARK_COLOR.gamma_multiply(0.5)));
+            plot_ui.points(
+                Points::new([x, bottom])
+                    .name(&bookmark.label)
+                    .color(BOOKMARK_COLOR)
+                    .shape(MarkerShape::Diamond)
+                    .radius(5.0),
+            );
+        }
+        if let Some(cursor) = self.cursor {
+            plot_ui.vline(VLine::new(cursor as f64).color(CURSOR_COLOR));
+        }
+    }
+
+    /// Shows the range that was jumped to, or centers the view on the cursor if it left it.
+    fn move_view(&self, plot_ui: &mut PlotUi, jump_to: Option<(f64, f64)>, follow_cursor: bool) {
+        let bounds = plot_ui.plot_bounds();
+        let range = |from: f64, to: f64| {
+            PlotBounds::from_min_max([from, bounds.min()[1]], [to, bounds.max()[1]])
+        };
+        if let Some((from, to)) = jump_to {
+            plot_ui.set_plot_bounds(range(from, to));
+        } else if let Some(cursor) = self.cursor.filter(|_| follow_cursor) {
+            let (x, half) = (cursor as f64, bounds.width() / 2.0);
+            if !(bounds.min()[0]..=bounds.max()[0]).contains(&x) {
+                plot_ui.set_plot_bounds(range(x - half, x + half));
+            }
+        }
+    }
+}
+
+impl MyApp {
+    /// The selected player of every included tab on the same plots, aligned at the start of
+    /// their demos.
+    fn show_compare(&mut self, ui: &mut egui::Ui) {
+        ui.label("Shows the selected player of every checked demo, from the start of the demo.");
+        ui.horizontal_wrapped(|ui| {
+            for (i, tab) in self.tabs.iter_mut().enumerate() {
+                let label = format!("{}: {}", tab.title, tab.player);
+                color_swatch(ui, Some(compare_color(tab, i)));
+                ui.checkbox(&mut tab.compare, label);
+            }
+        });
+        let time_format = self.time_format;
+        let height = (ui.available_height() - ui.spacing().item_spacing.y) / 2.0;
+        let link = egui::Id::new("compare_linked");
+        for channel in [Channel::Directions, Channel::Hooks] {
+            let plot = channel_plot(("compare_plot", channel), channel, time_format, |x| {
+                x as f32
+            })
+            .legend(Legend::default())
+            .height(height)
+            .link_axis(link, true, false)
+            .link_cursor(link, true, false);
+            plot.show(ui, |plot_ui| {
+                for (i, tab) in self.tabs.iter().enumerate() {
+                    let Some(data) = tab.inputs.get(&tab.player).filter(|_| tab.compare) else {
+                        continue;
+                    };
+                    let name = format!("{}: {}", tab.title, tab.player);
+                    let color = compare_color(tab, i);
+                    let seconds = |t: i32| tab.timeline.seconds(t) as f64;
+                    match channel {
+                        Channel::Hooks => plot_ui.bar_chart(
+                            BarChart::new(hook_bars(data, seconds))
+                                .width(1.0 / tab.timeline.tick_rate as f64)
+                                .name(&name)
+                                .color(color),
+                        ),
+                        _ => plot_ui.line(
+                            Line::new(direction_points(data, seconds))
+                                .name(&name)
+                                .color(color),
+                        ),
+                    }
+                }
+            });
+        }
+    }
+}
+
+impl MyApp {
+    /// Writes what changed since the last frame into the session.
+    fn record(&mut self, ctx: &egui::Context) {
+        let SessionState::Record {
+            recorder,
+            view,
+            tabs,
+            ..
+        } = &mut self.session
+        else {
+            return;
+        };
+        // Only the result of dragging and typing is interesting
+        if ctx.input(|i| i.pointer.any_down()) || ctx.wants_keyboard_input() {
+            return;
+        }
+        let mut steps = Vec::new();
+        if *view != Some(self.view) {
+            *view = Some(self.view);
+            let tab = match self.view {
+                View::Tab(i) => Some(i),
+                View::Compare => None,
+            };
+            steps.push(Step::Tab { tab });
+        }
+        for (i, (tab, recorded)) in self.tabs.iter().zip(tabs).enumerate() {
+            if recorded.player.as_ref() != Some(&tab.player) {
+                recorded.player = Some(tab.player.clone());
+                steps.push(Step::Player {
+                    tab: i,
+                    player: tab.player.clone(),
+                });
+            }
+            if let Some((from, to)) = tab.visible.filter(|v| recorded.view != Some(*v)) {
+                recorded.view = Some((from, to));
+                steps.push(Step::View { tab: i, from, to });
+            }
+            if recorded.bookmarks.as_ref() != Some(&tab.bookmarks) {
+                recorded.bookmarks = Some(tab.bookmarks.clone());
+                steps.push(Step::Bookmarks {
+                    tab: i,
+                    bookmarks: tab.bookmarks.clone(),
+                });
+            }
+        }
+        for step in steps {
+            if let Err(e) = recorder.record(step) {
+                eprintln!("Couldn't write the session: {e}");
+            }
+        }
+    }
+
+    fn apply(&mut self, step: &Step) {
+        match step {
+            Step::Tab { tab } => {
+                self.view = match tab {
+                    Some(tab) if *tab < self.tabs.len() => View::Tab(*tab),
+                    _ => View::Compare,
+                }
+            }
+            Step::Player { tab, player } => {
+                if let Some(tab) = self.tabs.get_mut(*tab) {
+                    tab.player = player.clone();
+                }
+            }
+            Step::View { tab, from, to } => {
+                if let Some(tab) = self.tabs.get_mut(*tab) {
+                    tab.jump_to = Some((*from, *to));
+                }
+            }
+            Step::Bookmarks { tab, bookmarks } => {
+                if let Some(tab) = self.tabs.get_mut(*tab) {
+                    tab.bookmarks = bookmarks.clone();
+                    tab.current_bookmark = None;
+                }
+            }
+            Step::Note { .. } => {}
+        }
+    }
+
+    /// Applies the first steps of the replayed session, from the start so going back works.
+    fn replay_to(&mut self, position: usize) {
+        let SessionState::Replay { session, .. } = &self.session else {
+            return;
+        };
+        let steps: Vec<Step> = session.entries[..position]
+            .iter()
+            .map(|e| e.step.clone())
+            .collect();
+        for step in &steps {
+            self.apply(step);
+        }
+        if let SessionState::Replay { position: p, .. } = &mut self.session {
+            *p = position;
+        }
+    }
+
+    fn describe(&self, step: &Step) -> String {
+        let title = |tab: usize| self.tabs.get(tab).map_or("?", |t| t.title.as_str());
+        match step {
+            Step::Tab { tab: Some(tab) } => format!("Switched to {}", title(*tab)),
+            Step::Tab { tab: None } => s!("Switched to the compare view"),
+            Step::Player { tab, player } => format!("{}: selected {player}", title(*tab)),
+            Step::View { tab, from, to } => {
+                let time = |tick: f64| match self.tabs.get(*tab) {
+                    Some(t) => self.time_format.format(t.timeline.seconds(tick as i32)),
+                    None => tick.to_string(),
+                };
+                format!(
+                    "{}: looked at {} to {}",
+                    title(*tab),
+                    time(*from),
+                    time(*to)
+                )
+            }
+            Step::Bookmarks { tab, bookmarks } => {
+                format!("{}: {} bookmarks", title(*tab), bookmarks.len())
+            }
+            Step::Note { text } => format!("Note: {text}"),
+        }
+    }
+
+    fn show_session(&mut self, ui: &mut egui::Ui) {
+        let mut replay_to = None;
+        match &mut self.session {
+            SessionState::Off => {}
+            SessionState::Record { recorder, note, .. } => {
+                ui.horizontal(|ui| {
+                    ui.label(format!("Recording to {}", recorder.path().display()));
+                    ui.separator();
+                    ui.text_edit_singleline(note);
+                    if ui
+                        .add_enabled(!note.is_empty(), Button::new("Add note"))
+                        .clicked()
+                    {
+                        let text = std::mem::take(note);
+                        if let Err(e) = recorder.record(Step::Note { text }) {
+                            eprintln!("Couldn't write the session: {e}");
                         }
-                    })
-                    .y_grid_spacer(|_| {
-                        vec![
-                            GridMark {
-                                value: -1.0,
-                                step_size: 1.0,
-                            },
-                            GridMark {
-                                value: 0.0,
-                                step_size: 1.0,
-                            },
-                            GridMark {
-                                value: 0.5,
-                                step_size: 0.5,
-                            },
-                            GridMark {
-                                value: 1.0,
-                                step_size: 1.0,
-                            },
-                        ]
-                    })
-                    .x_axis_formatter(|gm, _rng| format!("{}s", (gm.value / 50.0) as usize));
-                let plot = if reset { plot.reset() } else { plot };
-                plot.show(ui, |plot_ui| match self.selected {
-                    SelectedFilter::ShowBoth => {
-                        plot_ui.line(directions);
-                        plot_ui.bar_chart(hooks)
                     }
-                    SelectedFilter::ShowHooks => {
-                        plot_ui.line(directions);
+                });
+            }
+            SessionState::Replay { session, position } => {
+                let (position, count) = (*position, session.entries.len());
+                let current = position.checked_sub(1).map(|i| session.entries[i].clone());
+                let note = session.entries[..position]
+                    .iter()
+                    .rev()
+                    .find_map(|e| match &e.step {
+                        Step::Note { text } => Some(text.clone()),
+                        _ => None,
+                    });
+                ui.horizontal(|ui| {
+                    if ui
+                        .add_enabled(position > 0, Button::new("Previous"))
+                        .clicked()
+                    {
+                        replay_to = Some(position - 1);
+                    }
+                    if ui
+                        .add_enabled(position < count, Button::new("Next"))
+                        .clicked()
+                    {
+                        replay_to = Some(position + 1);
+                    }
+                    ui.label(format!("Step {position}/{count}"));
+                    if let Some(entry) = &current {
+                        ui.separator();
+                        ui.label(format!(
+                            "{:.1}s: {}",
+                            entry.seconds,
+                            self.describe(&entry.step)
+                        ));
+                    }
+                });
+                if let Some(note) = note {
+                    ui.label(format!("Note: {note}"));
+                }
+            }
+        }
+        if let Some(position) = replay_to {
+            self.replay_to(position);
+        }
+    }
+}
+
+impl eframe::App for MyApp {
+    fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        if ctx.input(|i| i.key_down(Key::Escape)) {
+            exit(0);
+        }
+        // Typing a bookmark label shouldn't move the cursor
+        if !ctx.wants_keyboard_input() {
+            if let View::Tab(i) = self.view {
+                for action in self.keymap.pressed(ctx) {
+                    self.tabs[i].navigate(action);
+                }
+            }
+        }
+        if !matches!(self.session, SessionState::Off) {
+            egui::TopBottomPanel::top("session").show(ctx, |ui| self.show_session(ui));
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            if self.tabs.len() > 1 {
+                ui.horizontal(|ui| {
+                    for (i, tab) in self.tabs.iter().enumerate() {
+                        ui.selectable_value(&mut self.view, View::Tab(i), &tab.title);
                     }
-                    SelectedFilter::ShowDirections => plot_ui.bar_chart(hooks),
+                    ui.separator();
+                    ui.selectable_value(&mut self.view, View::Compare, "Compare tabs");
                 });
+                ui.separator();
+            }
+            match self.view {
+                View::Tab(i) => self.tabs[i].show(ui, i, self.time_format, &self.keymap),
+                View::Compare => self.show_compare(ui),
             }
         });
+        self.record(ctx);
     }
 }
@@ -0,0 +1,265 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use schemars::JsonSchema;
+use serde::Serialize;
+use stringlit::s;
+use twsnap::{compat::ddnet::DemoReader, flags::GameFlags, Events, Position, Snap};
+
+use crate::{aim::wrap_angle, names::NameFilter};
+
+/// A carried flag sits on the tee of its carrier. Distances are in tiles.
+const FLAG_CARRY_DISTANCE: f32 = 0.25;
+/// A flag that moves further than this between two snapshots was taken back to its stand.
+const FLAG_RESET_DISTANCE: f32 = 6.25;
+/// Turning the aim by this much within `SNAP_TICKS` before a shot is an aim snap.
+const SNAP_RADIANS: f32 = 0.5;
+const SNAP_TICKS: i32 = 3;
+/// Damage this long after a shot can come from it, grenades fly for a while.
+const HIT_TICKS: i32 = 25;
+/// Damage this close to where a shot was fired hurt the shooter, like a grenade jump.
+const SELF_DAMAGE_DISTANCE: f32 = 0.875;
+/// Damage this close to the aim direction of a shot counts as a hit of it.
+const HIT_RADIANS: f32 = 0.15;
+/// Vanilla CTF points, subtracted from the score to estimate the kills.
+const GRAB_SCORE: i32 = 1;
+const CAPTURE_SCORE: i32 = 5;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, JsonSchema)]
+pub enum GameMode {
+    Dm,
+    Tdm,
+    Ctf,
+}
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct VanillaStats {
+    pub flag_grabs: usize,
+    pub flag_captures: usize,
+    /// Kill messages aren't in the demo, these are the score gains without the flag points
+    pub kills: i32,
+    pub deaths: usize,
+    pub kills_per_death: f32,
+    pub shots: usize,
+    /// Shots followed by damage in the direction they were aimed at
+    pub hits: usize,
+    /// Shots right after the aim turned sharply
+    pub snap_shots: usize,
+    pub snap_hits: usize,
+    /// Average ticks from the start of the aim snap to the shot
+    pub snap_to_shot_ticks: f32,
+}
+
+#[derive(Debug, Clone, Serialize, JsonSchema)]
+pub struct VanillaReport {
+    pub mode: GameMode,
+    pub players: BTreeMap<String, VanillaStats>,
+}
+
+struct Shot {
+    tick: i32,
+    pos: (f32, f32),
+    angle: f32,
+    /// Ticks since the start of the aim snap before it
+    snap: Option<i32>,
+    hit: bool,
+}
+
+#[derive(Default)]
+struct PlayerState {
+    score: Option<i32>,
+    score_gained: i32,
+    last_attack: i32,
+    /// Recent aim angles, for the snap detection
+    aim: VecDeque<(i32, f32)>,
+    shots: Vec<Shot>,
+    stats: VanillaStats,
+}
+
+#[derive(Default)]
+struct FlagState {
+    pos: Option<(f32, f32)>,
+    carrier: Option<String>,
+}
+
+fn point(pos: Position) -> (f32, f32) {
+    (pos.x.to_num(), pos.y.to_num())
+}
+
+fn distance(a: (f32, f32), b: (f32, f32)) -> f32 {
+    (a.0 - b.0).hypot(a.1 - b.1)
+}
+
+/// Reads the flags, deaths, damage and aim of every player from the snapshots.
+pub fn read_vanilla(mut reader: DemoReader, filter: &NameFilter) -> VanillaReport {
+    let mut ticks = None;
+    let mut mode = GameMode::Dm;
+    let mut players: HashMap<String, PlayerState> = HashMap::new();
+    let mut flags: HashMap<u8, FlagState> = HashMap::new();
+    let mut snap = Snap::default();
+    while let Ok(Some(chunk)) = reader.next_chunk(&mut snap) {
+        crate::track_ticks(&mut ticks, chunk);
+        let Some((_, tick)) = ticks else {
+            continue;
+        };
+        if let Some((_, info)) = snap.game_infos.iter().next() {
+            mode = if info.flags.contains(GameFlags::FLAGS) {
+                GameMode::Ctf
+            } else if info.flags.contains(GameFlags::TEAMS) {
+                GameMode::Tdm
+            } else {
+                GameMode::Dm
+            };
+        }
+
+        let mut positions = Vec::new();
+        let mut uids = HashMap::new();
+        for (_, p) in snap.players.iter() {
+            let name = p.name.to_string();
+            uids.insert(p.uid, name.clone());
+            let state = players.entry(name.clone()).or_default();
+            if let Some(score) = state.score {
+                state.score_gained += (p.score - score).max(0);
+            }
+            state.score = Some(p.score);
+            let Some(tee) = &p.tee else {
+                continue;
+            };
+            let pos = point(tee.pos);
+            let angle: f32 = tee.angle.to_num();
+            positions.push((name, pos));
+
+            state.aim.push_back((tick, angle));
+            while state
+                .aim
+                .front()
+                .is_some_and(|(t, _)| tick - t > SNAP_TICKS)
+            {
+                state.aim.pop_front();
+            }
+            let attack = tee.attack_tick.snap_tick();
+            if attack > 0 && attack != state.last_attack {
+                let snap = state
+                    .aim
+                    .iter()
+                    .filter(|(_, a)| wrap_angle(angle - a).abs() > SNAP_RADIANS)
+                    .map(|(t, _)| tick - t)
+                    .max();
+                state.shots.push(Shot {
+                    tick,
+                    pos,
+                    angle,
+                    snap,
+                    hit: false,
+                });
+            }
+            state.last_attack = attack;
+        }
+
+        let mut died = HashSet::new();
+        for event in &snap.events {
+            match event {
+                Events::Death(death) => {
+                    if let Some(name) = uids.get(&death.player) {
+                        died.insert(name.clone());
+                        players.entry(name.clone()).or_default().stats.deaths += 1;
+                    }
+                }
+                Events::DamageIndicator(damage) => {
+                    let at = point(damage.pos);
+                    // The shot aimed most closely at the damage gets the hit
+                    let shot = players
+                        .values_mut()
+                        .flat_map(|p| p.shots.iter_mut().rev())
+                        .filter(|s| !s.hit && tick - s.tick <= HIT_TICKS)
+                        .filter(|s| distance(s.pos, at) > SELF_DAMAGE_DISTANCE)
+                        .map(|s| {
+                            let towards = (at.1 - s.pos.1).atan2(at.0 - s.pos.0);
+                            (wrap_angle(towards - s.angle).abs(), s)
+                        })
+                        .filter(|(off, _)| *off < HIT_RADIANS)
+                        .min_by(|a, b| a.0.total_cmp(&b.0));
+                    if let Some((_, shot)) = shot {
+                        shot.hit = true;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (_, flag) in snap.pvp_flags.iter() {
+            let pos = point(flag.pos);
+            let state = flags.entry(flag.pvp_team as u8).or_default();
+            let reset = state
+                .pos
+                .is_some_and(|last| distance(last, pos) > FLAG_RESET_DISTANCE);
+            if reset {
+                // Taken back to the stand while the carrier lived, so it was captured
+                if let Some(carrier) = state.carrier.take().filter(|c| !died.contains(c)) {
+                    players.entry(carrier).or_default().stats.flag_captures += 1;
+                }
+            }
+            let carrier = positions
+                .iter()
+                .find(|(_, p)| distance(*p, pos) <= FLAG_CARRY_DISTANCE)
+                .map(|(name, _)| name.clone());
+            if let Some(carrier) = &carrier {
+                if state.carrier.as_ref() != Some(carrier) {
+                    players.entry(carrier.clone()).or_default().stats.flag_grabs += 1;
+                }
+            }
+            state.carrier = carrier;
+            state.pos = Some(pos);
+        }
+    }
+
+    let players = players
+        .into_iter()
+        .filter(|(name, _)| filter.matches(name))
+        .map(|(name, state)| {
+            let mut stats = state.stats;
+            let flag_score =
+                stats.flag_grabs as i32 * GRAB_SCORE + stats.flag_captures as i32 * CAPTURE_SCORE;
+            stats.kills = (state.score_gained - flag_score).max(0);
+            stats.kills_per_death = stats.kills as f32 / stats.deaths.max(1) as f32;
+            stats.shots = state.shots.len();
+            stats.hits = state.shots.iter().filter(|s| s.hit).count();
+            let snaps: Vec<&Shot> = state.shots.iter().filter(|s| s.snap.is_some()).collect();
+            stats.snap_shots = snaps.len();
+            stats.snap_hits = snaps.iter().filter(|s| s.hit).count();
+            if !snaps.is_empty() {
+                let ticks: i32 = snaps.iter().filter_map(|s| s.snap).sum();
+                stats.snap_to_shot_ticks = ticks as f32 / snaps.len() as f32;
+            }
+            (name, stats)
+        })
+        .collect();
+    VanillaReport { mode, players }
+}
+
+pub fn plain_report(report: &VanillaReport) -> String {
+    let mut vec = Vec::new();
+    vec.push(format!("Mode: {:?}", report.mode));
+    for (name, stats) in &report.players {
+        vec.push(s!(""));
+        vec.push(format!("{:=^44}", format!(" {name} ")));
+        vec.push(s!(""));
+        if report.mode == GameMode::Ctf {
+            vec.push(format!("Flag Grabs ..... : {}", stats.flag_grabs));
+            vec.push(format!("Flag Captures .. : {}", stats.flag_captures));
+        }
+        vec.push(format!("Kills .......... : {} (from score)", stats.kills));
+        vec.push(format!("Deaths ......... : {}", stats.deaths));
+        vec.push(format!("K/D ............ : {:.2}", stats.kills_per_death));
+        vec.push(format!("Shots .......... : {}", stats.shots));
+        vec.push(format!("Hits ........... : {}", stats.hits));
+        vec.push(format!(
+            "Snap Shots ..... : {} ({} hit)",
+            stats.snap_shots, stats.snap_hits
+        ));
+        vec.push(format!(
+            "Snap to Shot ... : {:.2} ticks",
+            stats.snap_to_shot_ticks
+        ));
+    }
+    vec.join("\n")
+}
@@ -0,0 +1,78 @@
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use crate::data::Inputs;
+
+/// Default `cl_mouse_max_distance` without dynamic camera, in tiles (400 units).
+const DEFAULT_MAX_DISTANCE: f32 = 12.5;
+/// Default `cl_dyncam_max_distance`, in tiles (1000 units).
+const DYNCAM_MAX_DISTANCE: f32 = 31.25;
+const BUCKET_SIZE: f32 = 2.0;
+const BUCKETS: usize = 20;
+
+#[derive(Debug, Clone, Default, Serialize, JsonSchema)]
+pub struct TargetDistanceStats {
+    /// All distances are in tiles
+    pub average: f32,
+    pub median: f32,
+    pub p90: f32,
+    pub max: f32,
+    /// Sample counts in buckets of two tiles, the last bucket contains everything beyond
+    pub histogram: Vec<usize>,
+    pub beyond_default_range_fraction: f32,
+    pub beyond_dyncam_range_fraction: f32,
+    /// Fraction of samples at the single most common distance, while being out of the default range
+    pub constant_long_range_fraction: f32,
+    pub probable_dyncam: bool,
+    pub probable_zoom: bool,
+}
+
+pub fn percentile(sorted: &[f32], p: f32) -> f32 {
+    sorted[((sorted.len() - 1) as f32 * p).round() as usize]
+}
+
+pub fn calculate_target_distance_stats(inputs: &[Inputs]) -> TargetDistanceStats {
+    let mut distances: Vec<f32> = inputs
+        .iter()
+        .map(|i| i.target.x.to_num::<f32>().hypot(i.target.y.to_num::<f32>()))
+        .collect();
+    if distances.is_empty() {
+        return TargetDistanceStats::default();
+    }
+    distances.sort_by(f32::total_cmp);
+
+    let count = distances.len() as f32;
+    let fraction =
+        |f: &dyn Fn(f32) -> bool| distances.iter().filter(|d| f(**d)).count() as f32 / count;
+
+    let mut histogram = vec![0; BUCKETS];
+    for d in &distances {
+        histogram[((d / BUCKET_SIZE) as usize).min(BUCKETS - 1)] += 1;
+    }
+
+    // Mouse input is noisy, so a player sitting on the exact same long distance for
+    // a large part of the demo is unusual. The distances are sorted, so equal values are
+    // next to each other.
+    let constant_long_range = distances
+        .chunk_by(|a, b| a == b)
+        .filter(|c| c[0] > DEFAULT_MAX_DISTANCE)
+        .map(|c| c.len())
+        .max()
+        .unwrap_or_default();
+
+    let beyond_default_range_fraction = fraction(&|d| d > DEFAULT_MAX_DISTANCE);
+    let beyond_dyncam_range_fraction = fraction(&|d| d > DYNCAM_MAX_DISTANCE);
+
+    TargetDistanceStats {
+        average: distances.iter().sum::<f32>() / count,
+        median: percentile(&distances, 0.5),
+        p90: percentile(&distances, 0.9),
+        max: *distances.last().unwrap(),
+        histogram,
+        beyond_default_range_fraction,
+        beyond_dyncam_range_fraction,
+        constant_long_range_fraction: constant_long_range as f32 / count,
+        probable_dyncam: beyond_default_range_fraction > 0.05,
+        probable_zoom: beyond_dyncam_range_fraction > 0.05,
+    }
+}